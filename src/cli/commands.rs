@@ -1,10 +1,15 @@
 //! CLI command implementations
 
-use crate::codegen;
+use crate::codegen::{self, GenWriter};
 use crate::diff;
-use crate::parser::{self, IntentStore};
+use crate::model::{IntentDocument, IntentKind, IntentSummary};
+use crate::parser::{self, DependencyNode, GenTarget, IntentConfig, IntentStore};
+use crate::simulate;
 use crate::validation;
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::time::Duration;
 
 /// Exit codes as defined in the spec
 pub mod exit_codes {
@@ -14,11 +19,48 @@ pub mod exit_codes {
     pub const GENERATION_MISMATCH: i32 = 3;
     pub const PATCH_CONFLICT: i32 = 4;
     pub const OPEN_OBLIGATIONS: i32 = 5;
+    pub const BREAKING_CHANGES: i32 = 6;
 }
 
 /// Create a new intent file
-pub fn cmd_new(kind: &str, name: &str, json_output: bool) -> Result<i32> {
-    match parser::create_new_intent(kind, name) {
+pub fn cmd_new(kind: &str, name: &str, dry_run: bool, model_dir: &Path, json_output: bool) -> Result<i32> {
+    if dry_run {
+        return match parser::plan_new_intent(kind, name, model_dir) {
+            Ok(new_intent) => {
+                if json_output {
+                    println!(
+                        "{}",
+                        serde_json::json!({
+                            "success": true,
+                            "dry_run": true,
+                            "path": new_intent.path.display().to_string(),
+                            "content": new_intent.content
+                        })
+                    );
+                } else {
+                    println!("Dry run - would create: {}", new_intent.path.display());
+                    print!("{}", new_intent.content);
+                }
+                Ok(exit_codes::SUCCESS)
+            }
+            Err(e) => {
+                if json_output {
+                    println!(
+                        "{}",
+                        serde_json::json!({
+                            "success": false,
+                            "error": e.to_string()
+                        })
+                    );
+                } else {
+                    eprintln!("Error: {}", e);
+                }
+                Ok(exit_codes::GENERAL_ERROR)
+            }
+        };
+    }
+
+    match parser::create_new_intent(kind, name, model_dir) {
         Ok(path) => {
             if json_output {
                 println!(
@@ -51,9 +93,15 @@ pub fn cmd_new(kind: &str, name: &str, json_output: bool) -> Result<i32> {
 }
 
 /// List all intents
-pub fn cmd_list(kind_filter: Option<&str>, json_output: bool) -> Result<i32> {
-    let store = IntentStore::load_from_default_path()?;
-    let intents = store.list(kind_filter);
+pub fn cmd_list(
+    kind_filter: Option<&str>,
+    tag_filter: Option<&str>,
+    model_dir: &Path,
+    json_output: bool,
+) -> Result<i32> {
+    let config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+    let intents = store.list(kind_filter, tag_filter);
 
     if json_output {
         println!("{}", serde_json::to_string_pretty(&intents)?);
@@ -71,11 +119,100 @@ pub fn cmd_list(kind_filter: Option<&str>, json_output: bool) -> Result<i32> {
     Ok(exit_codes::SUCCESS)
 }
 
+/// Count intents by kind and by tag; an intent with several tags counts
+/// toward each of them
+pub fn cmd_stats(model_dir: &Path, json_output: bool) -> Result<i32> {
+    let config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+
+    let mut kinds: std::collections::BTreeMap<String, usize> = Default::default();
+    let mut tags: std::collections::BTreeMap<&str, usize> = Default::default();
+    let mut untagged = 0;
+    for doc in store.iter() {
+        *kinds.entry(doc.kind.to_string()).or_default() += 1;
+        for tag in &doc.tags {
+            *tags.entry(tag).or_default() += 1;
+        }
+        if doc.tags.is_empty() {
+            untagged += 1;
+        }
+    }
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "total": store.len(),
+                "kinds": kinds,
+                "tags": tags,
+                "untagged": untagged,
+            }))?
+        );
+    } else {
+        println!("{:<20} COUNT", "KIND");
+        for (kind, count) in &kinds {
+            println!("{:<20} {}", kind, count);
+        }
+        println!("\n{:<20} COUNT", "TAG");
+        for (tag, count) in &tags {
+            println!("{:<20} {}", tag, count);
+        }
+        println!("{:<20} {}", "(untagged)", untagged);
+        println!("\nTotal: {} intents", store.len());
+    }
+    Ok(exit_codes::SUCCESS)
+}
+
 /// Show details of an intent
-pub fn cmd_show(name: &str, json_output: bool) -> Result<i32> {
-    let store = IntentStore::load_from_default_path()?;
+pub fn cmd_show(
+    name: &str,
+    deps_tree: bool,
+    depth: Option<usize>,
+    impact: bool,
+    schema: bool,
+    model_dir: &Path,
+    json_output: bool,
+) -> Result<i32> {
+    let config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+
+    let found = match lookup_intent(&store, name, json_output)? {
+        Lookup::Found(doc) => Some(doc),
+        Lookup::NotFound => None,
+        Lookup::Ambiguous => return Ok(exit_codes::GENERAL_ERROR),
+    };
 
-    match store.find_by_name(name) {
+    match found {
+        Some(doc) if deps_tree => {
+            let tree = store
+                .dependency_tree(&doc.id, depth)
+                .expect("intent was just found in the store");
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&tree)?);
+            } else {
+                print_dependency_tree(&tree, 0);
+            }
+            Ok(exit_codes::SUCCESS)
+        }
+        Some(doc) if schema => {
+            if doc.kind != IntentKind::Type {
+                anyhow::bail!("--schema needs a Type intent; '{}' is of kind {}", doc.name, doc.kind);
+            }
+            let schema = codegen::type_json_schema_document(&store, doc);
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+            Ok(exit_codes::SUCCESS)
+        }
+        Some(doc) if impact => {
+            let report = store
+                .impact(&doc.id)
+                .expect("intent was just found in the store");
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print_impact(&report);
+            }
+            Ok(exit_codes::SUCCESS)
+        }
         Some(doc) => {
             if json_output {
                 println!("{}", serde_json::to_string_pretty(&doc)?);
@@ -122,9 +259,252 @@ pub fn cmd_show(name: &str, json_output: bool) -> Result<i32> {
     }
 }
 
+fn print_impact(report: &parser::ImpactReport) {
+    println!("Impact of {} '{}':", report.kind, report.name);
+    let sections = [
+        ("Direct dependents", Some(&report.direct)),
+        ("Transitive dependents", Some(&report.transitive)),
+        ("Exposed by endpoints", report.endpoints.as_ref()),
+    ];
+    for (title, list) in sections {
+        let Some(list) = list else {
+            continue;
+        };
+        println!("\n{}: {}", title, list.len());
+        for summary in list {
+            println!("  - {} ({})", summary.name, summary.kind);
+        }
+    }
+}
+
+fn print_dependency_tree(node: &DependencyNode, level: usize) {
+    let marker = if node.cycle {
+        " [cycle]"
+    } else if node.truncated {
+        " [...]"
+    } else {
+        ""
+    };
+    if level == 0 {
+        println!("{} ({:?}){}", node.name, node.kind, marker);
+    } else {
+        println!("{}- {} ({:?}){}", "  ".repeat(level), node.name, node.kind, marker);
+    }
+
+    for dep in &node.dependencies {
+        print_dependency_tree(dep, level + 1);
+    }
+}
+
+/// What a `show`, `delete` or `rename` name refers to
+enum Lookup<'a> {
+    Found(&'a IntentDocument),
+    NotFound,
+    /// Several intents share the bare name; already reported
+    Ambiguous,
+}
+
+/// Find the intent `name` refers to. `Kind:Name` picks one of several
+/// intents sharing a name; a bare name that matches several is reported with
+/// the qualified candidates.
+fn lookup_intent<'a>(store: &'a IntentStore, name: &str, json_output: bool) -> Result<Lookup<'a>> {
+    let found = match name.split_once(':') {
+        Some((kind, intent_name)) => {
+            let Some(kind) = IntentKind::from_str(kind) else {
+                anyhow::bail!("Unknown intent kind '{}' in '{}'", kind, name);
+            };
+            store.get_by_kind_name(kind, intent_name)
+        }
+        None => {
+            let candidates = store.find_all_by_name(name);
+            if candidates.len() > 1 {
+                let qualified: Vec<String> = candidates
+                    .iter()
+                    .map(|doc| format!("{}:{}", doc.kind, doc.name))
+                    .collect();
+                if json_output {
+                    println!(
+                        "{}",
+                        serde_json::json!({
+                            "error": format!("Ambiguous intent name: {}", name),
+                            "candidates": qualified
+                        })
+                    );
+                } else {
+                    eprintln!(
+                        "Ambiguous intent name '{}' matches {} intents; pick one with <Kind>:<Name>:",
+                        name,
+                        candidates.len()
+                    );
+                    for candidate in &qualified {
+                        eprintln!("  {}", candidate);
+                    }
+                }
+                return Ok(Lookup::Ambiguous);
+            }
+            candidates.into_iter().next()
+        }
+    };
+    Ok(found.map_or(Lookup::NotFound, Lookup::Found))
+}
+
+/// Delete an intent, refusing if other intents depend on it
+pub fn cmd_delete(
+    name: &str,
+    force: bool,
+    dry_run: bool,
+    model_dir: &Path,
+    json_output: bool,
+) -> Result<i32> {
+    let config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+
+    let doc = match lookup_intent(&store, name, json_output)? {
+        Lookup::Found(doc) => doc,
+        Lookup::NotFound => {
+            if json_output {
+                println!(
+                    "{}",
+                    serde_json::json!({
+                        "success": false,
+                        "error": format!("Intent not found: {}", name)
+                    })
+                );
+            } else {
+                eprintln!("Intent not found: {}", name);
+            }
+            return Ok(exit_codes::GENERAL_ERROR);
+        }
+        Lookup::Ambiguous => return Ok(exit_codes::GENERAL_ERROR),
+    };
+
+    let id = doc.id;
+    let Some(file) = doc.source_file.clone() else {
+        anyhow::bail!("{} '{}' has no source file to delete", doc.kind, doc.name);
+    };
+    let dependents: Vec<IntentSummary> = store
+        .get_dependents(&id)
+        .into_iter()
+        .map(IntentSummary::from)
+        .collect();
+    let blocked = !dependents.is_empty() && !force;
+
+    if !dry_run && !blocked {
+        std::fs::remove_file(&file)?;
+    }
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::json!({
+                "success": !blocked,
+                "deleted": !dry_run && !blocked,
+                "dry_run": dry_run,
+                "file": file,
+                "dependents": dependents
+            })
+        );
+    } else {
+        if !dependents.is_empty() {
+            println!("Depended on by:");
+            for dep in &dependents {
+                println!("  - {} ({})", dep.name, dep.kind);
+            }
+        }
+        if blocked {
+            eprintln!(
+                "Refusing to delete '{}': {} intents depend on it (use --force to delete anyway)",
+                name,
+                dependents.len()
+            );
+        } else if dry_run {
+            println!("Dry run - would delete: {}", file);
+        } else {
+            println!("Deleted: {}", file);
+        }
+    }
+
+    if blocked {
+        Ok(exit_codes::PATCH_CONFLICT)
+    } else {
+        Ok(exit_codes::SUCCESS)
+    }
+}
+
+/// Rename an intent and rewrite the references to it in dependent intents
+pub fn cmd_rename(
+    old_name: &str,
+    new_name: &str,
+    dry_run: bool,
+    model_dir: &Path,
+    json_output: bool,
+) -> Result<i32> {
+    let config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+
+    let plan = match lookup_intent(&store, old_name, json_output)? {
+        Lookup::Found(doc) => store.plan_rename(&doc.id, new_name),
+        Lookup::NotFound => Err(anyhow::anyhow!("Intent not found: {}", old_name)),
+        Lookup::Ambiguous => return Ok(exit_codes::GENERAL_ERROR),
+    };
+    let plan = match plan {
+        Ok(plan) => plan,
+        Err(err) => {
+            if json_output {
+                println!(
+                    "{}",
+                    serde_json::json!({
+                        "success": false,
+                        "error": err.to_string()
+                    })
+                );
+            } else {
+                eprintln!("{}", err);
+            }
+            return Ok(exit_codes::GENERAL_ERROR);
+        }
+    };
+
+    if !dry_run {
+        plan.apply()?;
+    }
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::json!({
+                "success": true,
+                "dry_run": dry_run,
+                "rename": plan
+            })
+        );
+    } else {
+        let verb = if dry_run { "Dry run - would rename" } else { "Renamed" };
+        println!("{} {} '{}' to '{}'", verb, plan.kind, plan.from, plan.to);
+        if plan.new_file != plan.file {
+            println!("  {} -> {}", plan.file, plan.new_file);
+        }
+        for path in &plan.updated {
+            println!("  updated {}", path);
+        }
+    }
+
+    Ok(exit_codes::SUCCESS)
+}
+
 /// Format intent files
-pub fn cmd_fmt(check: bool, file: Option<&str>, json_output: bool) -> Result<i32> {
-    let results = parser::format_intent_files(file, check)?;
+pub fn cmd_fmt(
+    check: bool,
+    all: bool,
+    file: Option<&str>,
+    model_dir: &Path,
+    json_output: bool,
+) -> Result<i32> {
+    let config = IntentConfig::load()?;
+    let mut results = parser::format_intent_files(file, check, model_dir, &config)?;
+    if all {
+        results.extend(parser::format_json_files(&codegen::LOCK_FILES, check)?);
+    }
 
     let needs_formatting: Vec<_> = results.iter().filter(|r| r.changed).collect();
 
@@ -167,25 +547,122 @@ pub fn cmd_fmt(check: bool, file: Option<&str>, json_output: bool) -> Result<i32
     }
 }
 
+/// Upgrade all intent files to the newest schema version
+pub fn cmd_migrate_schema(dry_run: bool, model_dir: &Path, json_output: bool) -> Result<i32> {
+    let config = IntentConfig::load()?;
+    let report = parser::migrate_intent_files(model_dir, &config, dry_run)?;
+
+    if json_output {
+        let mut output = serde_json::to_value(&report)?;
+        output["dry_run"] = serde_json::json!(dry_run);
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if report.migrated.is_empty() {
+        println!(
+            "All {} intent files are at schema_version {}.",
+            report.unchanged, report.version
+        );
+    } else {
+        let verb = if dry_run { "Would migrate" } else { "Migrated" };
+        println!(
+            "{} {} files to schema_version {} ({} already current):",
+            verb,
+            report.migrated.len(),
+            report.version,
+            report.unchanged
+        );
+        for file in &report.migrated {
+            println!("  {} ({} -> {})", file.path, file.from, file.to);
+        }
+    }
+
+    Ok(exit_codes::SUCCESS)
+}
+
 /// Validate intent files
-pub fn cmd_validate(json_output: bool) -> Result<i32> {
-    let store = IntentStore::load_from_default_path()?;
-    let result = validation::validate_all(&store)?;
+pub fn cmd_validate(
+    json_schema_validate: bool,
+    baseline: Option<&Path>,
+    write_baseline: bool,
+    show_info: bool,
+    fail_on_info: bool,
+    model_dir: &Path,
+    output: ValidateOutput,
+) -> Result<i32> {
+    let json_output = output == ValidateOutput::Json;
+    let mut config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+    config.validation.schema |= json_schema_validate;
+    let mut result = validation::validate_with_config(&store, &config)?;
+
+    let mut suppressed = 0;
+    if let Some(path) = baseline {
+        if write_baseline {
+            let baseline = validation::Baseline::from_result(&result);
+            baseline.save(path)?;
+            if json_output {
+                println!(
+                    "{}",
+                    serde_json::json!({
+                        "success": true,
+                        "baseline": path,
+                        "diagnostics": baseline.signatures.len()
+                    })
+                );
+            } else {
+                println!(
+                    "Wrote baseline with {} diagnostics to {}",
+                    baseline.signatures.len(),
+                    path.display()
+                );
+            }
+            return Ok(exit_codes::SUCCESS);
+        }
+        suppressed = validation::Baseline::load(path)?.filter(&mut result);
+    }
+
+    // Infos are hidden unless asked for (or they can fail the run)
+    let hidden_infos = if show_info || fail_on_info {
+        0
+    } else {
+        std::mem::take(&mut result.infos).len()
+    };
+    let failed_on_info = fail_on_info && !result.infos.is_empty();
 
     if json_output {
-        println!("{}", serde_json::to_string_pretty(&result)?);
+        print_versioned_json(VALIDATE_SCHEMA, &result)?;
+    } else if output == ValidateOutput::Junit {
+        print!("{}", super::validation_junit(&store, &result));
     } else {
+        if suppressed > 0 {
+            println!("{} baselined diagnostics suppressed.", suppressed);
+        }
         if result.errors.is_empty() {
-            println!(
-                "Validation passed. {} intents validated.",
-                store.len()
-            );
+            if failed_on_info {
+                println!(
+                    "Validation failed with {} info diagnostics (--fail-on-info).",
+                    result.infos.len()
+                );
+            } else {
+                println!(
+                    "Validation passed. {} intents validated.",
+                    store.len()
+                );
+            }
             if !result.warnings.is_empty() {
                 println!("\nWarnings ({}):", result.warnings.len());
                 for w in &result.warnings {
                     println!("  [{}] {}", w.code, w.message);
                 }
             }
+            if !result.infos.is_empty() {
+                println!("\nInfo ({}):", result.infos.len());
+                for i in &result.infos {
+                    println!("  [{}] {}", i.code, i.message);
+                }
+            }
+            if hidden_infos > 0 {
+                println!("\n{} info diagnostics hidden (use --show-info to list them)", hidden_infos);
+            }
         } else {
             println!("Validation failed with {} errors:", result.errors.len());
             for e in &result.errors {
@@ -198,19 +675,89 @@ pub fn cmd_validate(json_output: bool) -> Result<i32> {
         }
     }
 
-    if result.errors.is_empty() {
+    if result.errors.is_empty() && !failed_on_info {
         Ok(exit_codes::SUCCESS)
     } else {
         Ok(exit_codes::VALIDATION_ERROR)
     }
 }
 
+/// Check naming conventions. Violations are warnings, so they never fail
+/// the command.
+pub fn cmd_lint(model_dir: &Path, json_output: bool) -> Result<i32> {
+    let config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+    let result = validation::lint_with_config(&store, &config)?;
+
+    if json_output {
+        print_versioned_json(VALIDATE_SCHEMA, &result)?;
+    } else if result.warnings.is_empty() {
+        println!("No naming issues in {} intents.", store.len());
+    } else {
+        println!("Naming issues ({}):", result.warnings.len());
+        for w in &result.warnings {
+            println!("  [{}] {}", w.code, w.message);
+        }
+    }
+
+    Ok(exit_codes::SUCCESS)
+}
+
+/// Report formats `intent validate` can print
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidateOutput {
+    Human,
+    Json,
+    /// JUnit XML for CI test dashboards
+    Junit,
+}
+
+/// Report formats `intent diff` can print
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOutput {
+    Human,
+    Json,
+    /// Markdown table and details for PR comments
+    Markdown,
+}
+
+/// Report formats `intent gen` can print
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenOutput {
+    Human,
+    Json,
+    /// The generation manifest (files, hashes and source intents) as JSON
+    Manifest,
+}
+
+/// How `intent gen` runs
+#[derive(Debug, Clone, Copy)]
+pub struct GenOptions {
+    pub check: bool,
+    pub dry_run: bool,
+    pub prune: bool,
+    pub locks: codegen::LockFiles,
+    /// Overrides `[generation] target`
+    pub target: Option<GenTarget>,
+}
+
 /// Generate Rust code
-pub fn cmd_gen(check: bool, json_output: bool) -> Result<i32> {
-    let store = IntentStore::load_from_default_path()?;
+pub fn cmd_gen(
+    options: GenOptions,
+    filter: &codegen::GenFilter,
+    model_dir: &Path,
+    output: GenOutput,
+) -> Result<i32> {
+    let GenOptions { check, dry_run, prune, locks, target } = options;
+    let json_output = output != GenOutput::Human;
+    let mut config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+    if let Some(target) = target {
+        config.generation.target = target;
+    }
 
     // First validate
-    let validation_result = validation::validate_all(&store)?;
+    let validation_result = validation::validate_with_config(&store, &config)?;
     if !validation_result.errors.is_empty() {
         if json_output {
             println!(
@@ -227,25 +774,95 @@ pub fn cmd_gen(check: bool, json_output: bool) -> Result<i32> {
         return Ok(exit_codes::VALIDATION_ERROR);
     }
 
-    let result = codegen::generate_all(&store, check)?;
+    // An unfiltered check can skip regeneration when nothing it depends on
+    // changed since the manifest was written
+    let unchanged = if check && !dry_run && !filter.is_active() {
+        codegen::check_unchanged_sources(&store, &config, &codegen::FsWriter)?
+    } else {
+        None
+    };
+    let result = match unchanged {
+        Some(result) => result,
+        None => codegen::generate_with_config(
+            &store,
+            &config,
+            check || dry_run,
+            filter,
+            prune,
+            locks,
+            &mut codegen::FsWriter,
+        )?,
+    };
 
-    if json_output {
+    if dry_run {
+        print_gen_dry_run(&result, json_output)?;
+        return Ok(exit_codes::SUCCESS);
+    }
+
+    if check {
+        warn_missing_lock_files()?;
+    }
+
+    if output == GenOutput::Manifest {
+        println!("{}", serde_json::to_string_pretty(&result.manifest)?);
+        if check && !result.matches {
+            for (f, reason) in result.files.iter().filter_map(|f| Some((f, f.reason?))) {
+                eprintln!("Mismatch: {} ({})", f.path, reason);
+            }
+        }
+    } else if json_output {
         println!("{}", serde_json::to_string_pretty(&result)?);
     } else {
         if check {
-            if result.matches {
+            if result.sources_unchanged {
+                println!(
+                    "Generated code matches. Sources unchanged since the manifest was written; {} files verified.",
+                    result.files.len()
+                );
+            } else if result.matches {
                 println!("Generated code matches. {} files checked.", result.files.len());
             } else {
-                println!("Generated code does not match:");
-                for f in result.files.iter().filter(|f| !f.matches) {
-                    println!("  {} ({})", f.path, f.reason);
+                let drift = &result.drift;
+                println!(
+                    "Generated code does not match: {} modified, {} missing, {} new, {} orphaned",
+                    drift.modified, drift.missing, drift.new, drift.orphaned
+                );
+                for (f, reason) in result.files.iter().filter_map(|f| Some((f, f.reason?))) {
+                    println!("  {} ({})", f.path, reason);
+                }
+                for path in &result.orphaned {
+                    println!("  {} ({})", path, codegen::DriftKind::Orphaned);
                 }
             }
         } else {
-            println!("Generated {} files:", result.files.len());
-            for f in &result.files {
+            let written: Vec<_> = result.files.iter().filter(|f| f.written).collect();
+            println!(
+                "Generated {} files: {} written, {} unchanged",
+                result.files.len(),
+                written.len(),
+                result.files.len() - written.len()
+            );
+            for f in written {
                 println!("  {}", f.path);
             }
+            if !result.pruned.is_empty() {
+                println!("Pruned {} orphaned files:", result.pruned.len());
+                for path in &result.pruned {
+                    println!("  {}", path);
+                }
+            }
+            if !result.orphaned.is_empty() {
+                println!(
+                    "{} orphaned files are no longer generated (use --prune to remove)",
+                    result.orphaned.len()
+                );
+            }
+            if result.workspace_updated {
+                println!("Added gen to the workspace members in Cargo.toml");
+            }
+        }
+        for path in &result.unformatted {
+            eprintln!("Warning: {} is not valid Rust and was written unformatted", path);
         }
     }
 
@@ -256,12 +873,216 @@ pub fn cmd_gen(check: bool, json_output: bool) -> Result<i32> {
     }
 }
 
-/// Show semantic diff
-pub fn cmd_diff(base: &str, json_output: bool) -> Result<i32> {
-    let result = diff::compute_semantic_diff(base)?;
+/// Quiet period after the last file event before a watch cycle runs
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Validate and regenerate once at startup, then again whenever an intent
+/// file under `model_dir` changes, until interrupted. Bursts of events
+/// (editors often write a file several times) are debounced into one cycle.
+pub fn cmd_watch(json_output: bool, model_dir: &Path) -> Result<i32> {
+    use notify::{RecursiveMode, Watcher};
+
+    let (events, changes) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(events)?;
+    watcher
+        .watch(model_dir, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", model_dir.display()))?;
+    if !json_output {
+        println!("Watching {} for changes (Ctrl-C to stop)", model_dir.display());
+    }
+
+    watch_cycle(json_output, model_dir);
+    while let Ok(event) = changes.recv() {
+        let mut changed = touches_intent_file(&event);
+        while let Ok(event) = changes.recv_timeout(WATCH_DEBOUNCE) {
+            changed |= touches_intent_file(&event);
+        }
+        if changed {
+            watch_cycle(json_output, model_dir);
+        }
+    }
+
+    Ok(exit_codes::SUCCESS)
+}
+
+/// Whether a watch event created, changed or removed an intent file; reads
+/// and generated output are ignored
+fn touches_intent_file(event: &notify::Result<notify::Event>) -> bool {
+    let Ok(event) = event else {
+        return false;
+    };
+    !event.kind.is_access()
+        && event
+            .paths
+            .iter()
+            .any(|path| path.to_string_lossy().ends_with(parser::INTENT_EXTENSION))
+}
+
+/// What one `intent watch` cycle did
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+enum WatchCycle {
+    Generated { files: usize, changed: usize, warnings: usize },
+    /// Validation failed, so nothing was written
+    Invalid { validation_errors: Vec<crate::model::StructuredError> },
+    Failed { error: String },
+}
+
+/// One validate + gen pass of `intent watch`, reported as a single line.
+/// Like `intent gen`, nothing is written when validation fails.
+fn watch_cycle(json_output: bool, model_dir: &Path) {
+    let cycle = run_watch_cycle(model_dir).unwrap_or_else(|e| WatchCycle::Failed {
+        error: format!("{:#}", e),
+    });
 
     if json_output {
-        println!("{}", serde_json::to_string_pretty(&result)?);
+        match serde_json::to_string(&cycle) {
+            Ok(line) => println!("{}", line),
+            Err(e) => eprintln!("Error: {}", e),
+        }
+        return;
+    }
+
+    let time = chrono::Local::now().format("%H:%M:%S");
+    match cycle {
+        WatchCycle::Generated { files, changed, warnings } => println!(
+            "[{}] Generated {} files ({} changed, {} warnings)",
+            time, files, changed, warnings
+        ),
+        WatchCycle::Invalid { validation_errors } => {
+            println!(
+                "[{}] Validation failed with {} errors; nothing generated",
+                time,
+                validation_errors.len()
+            );
+            for e in &validation_errors {
+                println!("  [{}] {}", e.code, e.message);
+            }
+        }
+        WatchCycle::Failed { error } => println!("[{}] Error: {}", time, error),
+    }
+}
+
+fn run_watch_cycle(model_dir: &Path) -> Result<WatchCycle> {
+    let config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+    let validation_result = validation::validate_with_config(&store, &config)?;
+    if !validation_result.errors.is_empty() {
+        return Ok(WatchCycle::Invalid {
+            validation_errors: validation_result.errors,
+        });
+    }
+
+    let result = codegen::generate_all(
+        &store,
+        false,
+        &codegen::GenFilter::default(),
+        false,
+        codegen::LockFiles::default(),
+        &mut codegen::FsWriter,
+    )?;
+    let summary = result.summary();
+    Ok(WatchCycle::Generated {
+        files: result.files.len(),
+        changed: summary.create + summary.update,
+        warnings: validation_result.warnings.len(),
+    })
+}
+
+/// Recompute the hashes of the generated files on disk and report those that
+/// differ from the recorded generation manifest
+pub fn cmd_verify_manifest(json_output: bool) -> Result<i32> {
+    let writer = codegen::FsWriter;
+    if writer.read_existing(codegen::MANIFEST_LOCK)?.is_none() {
+        anyhow::bail!(
+            "No generation manifest at {}; run `intent gen` first",
+            codegen::MANIFEST_LOCK
+        );
+    }
+    let manifest = codegen::load_manifest(&writer)?;
+    let mismatches = codegen::verify_manifest(&manifest, &writer)?;
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "matches": mismatches.is_empty(),
+                "checked": manifest.files.len(),
+                "mismatches": mismatches,
+            }))?
+        );
+    } else if mismatches.is_empty() {
+        println!("Manifest matches. {} files checked.", manifest.files.len());
+    } else {
+        println!("Generated files differ from the manifest:");
+        for m in &mismatches {
+            println!("  {} ({})", m.path, m.reason);
+        }
+    }
+
+    if mismatches.is_empty() {
+        Ok(exit_codes::SUCCESS)
+    } else {
+        Ok(exit_codes::GENERATION_MISMATCH)
+    }
+}
+
+/// Lock files are optional for code generation, but verify and check rely on them
+fn warn_missing_lock_files() -> Result<()> {
+    for path in codegen::missing_lock_files(&codegen::FsWriter)? {
+        eprintln!("Warning: lock file {} is missing (run `intent gen` to create it)", path);
+    }
+    Ok(())
+}
+
+fn print_gen_dry_run(result: &codegen::GenerationResult, json_output: bool) -> Result<()> {
+    let summary = result.summary();
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "dry_run": true,
+                "summary": summary,
+                "hash": result.hash,
+                "files": result.files,
+                "orphaned": result.orphaned,
+            }))?
+        );
+        return Ok(());
+    }
+
+    println!(
+        "Dry run: {} to create, {} to update, {} orphaned, {} unchanged",
+        summary.create, summary.update, summary.delete, summary.unchanged
+    );
+    for f in result.files.iter().filter(|f| !f.matches) {
+        let action = if f.status == codegen::FileStatus::Created { "create" } else { "update" };
+        println!("  {:<7} {}", action, f.path);
+    }
+    for path in &result.orphaned {
+        println!("  {:<7} {} (no longer generated)", "delete", path);
+    }
+
+    Ok(())
+}
+
+/// Show semantic diff
+pub fn cmd_diff(
+    base: &str,
+    timeout: Duration,
+    filter: &diff::DiffFilter,
+    model_dir: &Path,
+    output: DiffOutput,
+) -> Result<i32> {
+    let git = diff::GitRunner::new(timeout)
+        .with_progress(output == DiffOutput::Human && std::io::stderr().is_terminal());
+    let result = diff::compute_semantic_diff(base, model_dir, &git, filter)?;
+
+    if output == DiffOutput::Json {
+        print_versioned_json(DIFF_SCHEMA, &result)?;
+    } else if output == DiffOutput::Markdown {
+        print!("{}", diff::render_markdown(&result));
     } else {
         if result.changes.is_empty() {
             println!("No semantic changes detected.");
@@ -287,122 +1108,340 @@ pub fn cmd_diff(base: &str, json_output: bool) -> Result<i32> {
     Ok(exit_codes::SUCCESS)
 }
 
-/// Verify all (fmt + validate + gen --check + obligations)
-pub fn cmd_verify(json_output: bool) -> Result<i32> {
+/// Schema markers for versioned JSON output. Bump the version whenever the
+/// shape of `result` changes incompatibly.
+pub const DIFF_SCHEMA: &str = "intent-diff/1";
+pub const VALIDATE_SCHEMA: &str = "intent-validate/1";
+pub const VERIFY_SCHEMA: &str = "intent-verify/1";
+
+/// Print `{"schema": ..., "result": ...}` so consumers can detect format changes
+fn print_versioned_json(schema: &str, result: &impl serde::Serialize) -> Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({
+            "schema": schema,
+            "result": result,
+        }))?
+    );
+    Ok(())
+}
+
+/// Simulate a workflow against a JSON input file
+pub fn cmd_simulate(workflow: &str, input_file: &Path, model_dir: &Path, json_output: bool) -> Result<i32> {
+    let config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+    let content = std::fs::read_to_string(input_file)
+        .with_context(|| format!("Failed to read input file: {}", input_file.display()))?;
+    let input: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse input file: {}", input_file.display()))?;
+
+    let simulation = simulate::simulate_workflow(&store, workflow, input)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&simulation)?);
+    } else {
+        println!("Simulating workflow '{}':", simulation.workflow);
+        for step in &simulation.steps {
+            println!("\n{}", step.step);
+            for action in &step.actions {
+                println!("  {}", action);
+            }
+            println!("  context: {}", serde_json::Value::Object(step.context.clone()));
+        }
+        match (&simulation.raised, &simulation.output) {
+            (Some(error), _) => println!("\nRaised {}", error),
+            (None, Some(output)) => println!("\nOutput: {}", serde_json::to_string_pretty(output)?),
+            (None, None) => {}
+        }
+    }
+
+    Ok(exit_codes::SUCCESS)
+}
+
+/// Write the OpenAPI document for the model's endpoints to `out`
+pub fn cmd_openapi(out: &Path, model_dir: &Path, json_output: bool) -> Result<i32> {
+    let config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+    let mut document = codegen::generate_openapi(&store);
+
+    if !config.project.name.is_empty() {
+        document["info"]["title"] = serde_json::json!(config.project.name);
+    }
+    if !config.project.version.is_empty() {
+        document["info"]["version"] = serde_json::json!(config.project.version);
+    }
+
+    if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(out, format!("{}\n", serde_json::to_string_pretty(&document)?))
+        .with_context(|| format!("Failed to write {}", out.display()))?;
+
+    let paths = document["paths"].as_object().map_or(0, |p| p.len());
+    if json_output {
+        println!(
+            "{}",
+            serde_json::json!({
+                "success": true,
+                "path": out.display().to_string(),
+                "paths": paths
+            })
+        );
+    } else {
+        println!("Wrote {} ({} paths)", out.display(), paths);
+    }
+    Ok(exit_codes::SUCCESS)
+}
+
+/// Export the dependency graph: Graphviz DOT when `format` is `dot`,
+/// otherwise each intent's dependencies as JSON
+pub fn cmd_graph(format: &str, model_dir: &Path) -> Result<i32> {
+    let config = IntentConfig::load()?;
+    let store = IntentStore::load_from_path_with_config(model_dir, &config)?;
+    let (graph, _) = validation::resolve_references(&store);
+
+    if format == "dot" {
+        print!("{}", super::graph_dot(&store, &graph));
+    } else {
+        println!("{}", serde_json::to_string_pretty(&super::graph_json(&store, &graph))?);
+    }
+    Ok(exit_codes::SUCCESS)
+}
+
+/// Phases of `intent verify`, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyStep {
+    Fmt,
+    Validate,
+    Gen,
+    Obligations,
+}
+
+impl VerifyStep {
+    pub const ALL: [VerifyStep; 4] = [Self::Fmt, Self::Validate, Self::Gen, Self::Obligations];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Fmt => "fmt",
+            Self::Validate => "validate",
+            Self::Gen => "gen",
+            Self::Obligations => "obligations",
+        }
+    }
+}
+
+/// Verify all intents. `steps` selects the phases to run (all if empty);
+/// they always run in fmt, validate, gen, obligations order. With
+/// `diff_base`, the semantic diff against that ref runs last and changes at
+/// least as severe as `fail_on` fail the run; JSON reports then come in a
+/// versioned envelope holding the diff as well.
+pub fn cmd_verify(
+    steps: &[VerifyStep],
+    diff_base: Option<&str>,
+    fail_on: diff::DiffSeverity,
+    model_dir: &Path,
+    json_output: bool,
+) -> Result<i32> {
+    let runs = |step: VerifyStep| steps.is_empty() || steps.contains(&step);
+    let ran: Vec<VerifyStep> = VerifyStep::ALL.into_iter().filter(|s| runs(*s)).collect();
+    let print_report = |report: serde_json::Value| -> Result<()> {
+        if diff_base.is_some() {
+            print_versioned_json(VERIFY_SCHEMA, &report)
+        } else {
+            println!("{}", report);
+            Ok(())
+        }
+    };
+
+    let config = IntentConfig::load()?;
+
     // Step 1: Check formatting
-    let fmt_results = parser::format_intent_files(None, true)?;
-    let needs_formatting: Vec<_> = fmt_results.iter().filter(|r| r.changed).collect();
-    if !needs_formatting.is_empty() {
-        if json_output {
-            println!(
-                "{}",
-                serde_json::json!({
+    if runs(VerifyStep::Fmt) {
+        let fmt_results = parser::format_intent_files(None, true, model_dir, &config)?;
+        let needs_formatting: Vec<_> = fmt_results.iter().filter(|r| r.changed).collect();
+        if !needs_formatting.is_empty() {
+            if json_output {
+                print_report(serde_json::json!({
                     "success": false,
                     "step": "fmt",
                     "error": "Files need formatting",
                     "files": needs_formatting.iter().map(|r| &r.path).collect::<Vec<_>>()
-                })
-            );
-        } else {
-            eprintln!("Verification failed: {} files need formatting", needs_formatting.len());
+                }))?;
+            } else {
+                eprintln!("Verification failed: {} files need formatting", needs_formatting.len());
+            }
+            return Ok(exit_codes::GENERAL_ERROR);
         }
-        return Ok(exit_codes::GENERAL_ERROR);
     }
 
+    let store = if ran.iter().any(|s| *s != VerifyStep::Fmt) {
+        Some(IntentStore::load_from_path_with_config(model_dir, &config)?)
+    } else {
+        None
+    };
+
     // Step 2: Validate
-    let store = IntentStore::load_from_default_path()?;
-    let validation_result = validation::validate_all(&store)?;
-    if !validation_result.errors.is_empty() {
-        if json_output {
-            println!(
-                "{}",
-                serde_json::json!({
+    let mut validation_result = None;
+    if let (true, Some(store)) = (runs(VerifyStep::Validate), &store) {
+        let result = validation::validate_with_config(store, &config)?;
+        if !result.errors.is_empty() {
+            if json_output {
+                print_report(serde_json::json!({
                     "success": false,
                     "step": "validate",
-                    "errors": validation_result.errors
-                })
-            );
-        } else {
-            eprintln!(
-                "Verification failed: {} validation errors",
-                validation_result.errors.len()
-            );
+                    "errors": result.errors
+                }))?;
+            } else {
+                eprintln!("Verification failed: {} validation errors", result.errors.len());
+            }
+            return Ok(exit_codes::VALIDATION_ERROR);
         }
-        return Ok(exit_codes::VALIDATION_ERROR);
+        validation_result = Some(result);
     }
 
     // Step 3: Gen check
-    let gen_result = codegen::generate_all(&store, true)?;
-    if !gen_result.matches {
-        if json_output {
-            println!(
-                "{}",
-                serde_json::json!({
+    let mut gen_result = None;
+    if let (true, Some(store)) = (runs(VerifyStep::Gen), &store) {
+        let result = codegen::generate_all(
+            store,
+            true,
+            &codegen::GenFilter::default(),
+            false,
+            codegen::LockFiles::default(),
+            &mut codegen::FsWriter,
+        )?;
+        warn_missing_lock_files()?;
+        if !result.matches {
+            if json_output {
+                print_report(serde_json::json!({
                     "success": false,
                     "step": "gen",
                     "error": "Generated code does not match"
-                })
-            );
-        } else {
-            eprintln!("Verification failed: generated code does not match");
+                }))?;
+            } else {
+                eprintln!("Verification failed: generated code does not match");
+            }
+            return Ok(exit_codes::GENERATION_MISMATCH);
         }
-        return Ok(exit_codes::GENERATION_MISMATCH);
+        gen_result = Some(result);
     }
 
     // Step 4: Check obligations
-    let obligations = validation::check_obligations(&store)?;
-    let high_obligations: Vec<_> = obligations
-        .iter()
-        .filter(|o| o.severity == validation::ObligationSeverity::High && o.status == validation::ObligationStatus::Open)
-        .collect();
+    let mut obligations = None;
+    if let (true, Some(store)) = (runs(VerifyStep::Obligations), &store) {
+        let found = validation::check_obligations(store)?;
+        let high_obligations: Vec<_> = found
+            .iter()
+            .filter(|o| o.severity == validation::ObligationSeverity::High && o.status == validation::ObligationStatus::Open)
+            .collect();
 
-    if !high_obligations.is_empty() {
-        if json_output {
-            println!(
-                "{}",
-                serde_json::json!({
+        if !high_obligations.is_empty() {
+            if json_output {
+                print_report(serde_json::json!({
                     "success": false,
                     "step": "obligations",
                     "open_obligations": high_obligations
-                })
-            );
-        } else {
-            eprintln!(
-                "Verification failed: {} HIGH severity obligations are open",
-                high_obligations.len()
-            );
-            for o in &high_obligations {
-                eprintln!("  - {}", o.description);
+                }))?;
+            } else {
+                eprintln!(
+                    "Verification failed: {} HIGH severity obligations are open",
+                    high_obligations.len()
+                );
+                for o in &high_obligations {
+                    eprintln!("  - {}", o.description);
+                }
             }
+            return Ok(exit_codes::OPEN_OBLIGATIONS);
         }
-        return Ok(exit_codes::OPEN_OBLIGATIONS);
+        obligations = Some(found);
     }
 
+    // Step 5: Semantic diff against the base ref
+    let semantic_diff = match diff_base {
+        Some(base) => Some(diff::compute_semantic_diff(
+            base,
+            model_dir,
+            &diff::GitRunner::default(),
+            &diff::DiffFilter::default(),
+        )?),
+        None => None,
+    };
+    let blocking: Vec<_> = semantic_diff
+        .iter()
+        .flat_map(|result| &result.changes)
+        .filter(|change| change.severity >= fail_on)
+        .collect();
+
     if json_output {
-        println!(
-            "{}",
-            serde_json::json!({
-                "success": true,
-                "intents_validated": store.len(),
-                "files_generated": gen_result.files.len()
-            })
+        let mut output = serde_json::json!({
+            "success": blocking.is_empty(),
+            "steps": ran,
+        });
+        if let (Some(store), Some(result)) = (&store, &validation_result) {
+            output["intents_validated"] = serde_json::json!(store.len());
+            output["diagnostics"] = serde_json::json!(validation::severity_summary(result));
+        }
+        if let Some(result) = &gen_result {
+            output["files_generated"] = serde_json::json!(result.files.len());
+        }
+        if let (Some(base), Some(result)) = (diff_base, &semantic_diff) {
+            output["diff"] = serde_json::json!({
+                "base": base,
+                "fail_on": fail_on,
+                "result": result,
+            });
+        }
+        print_report(output)?;
+    } else if !blocking.is_empty() {
+        eprintln!(
+            "Verification failed: {} changes at {} severity or above since {}",
+            blocking.len(),
+            fail_on,
+            diff_base.unwrap_or_default()
         );
+        for change in &blocking {
+            eprintln!("  - [{}] {} - {}", change.severity, change.category, change.description);
+        }
     } else {
         println!("Verification passed.");
-        println!("  {} intents validated", store.len());
-        println!("  {} files generated", gen_result.files.len());
-        if !obligations.is_empty() {
+        if let (Some(store), Some(_)) = (&store, &validation_result) {
+            println!("  {} intents validated", store.len());
+        }
+        if let Some(result) = &gen_result {
+            println!("  {} files generated", result.files.len());
+        }
+        if let Some(obligations) = obligations.filter(|o| !o.is_empty()) {
             let open_count = obligations.iter().filter(|o| o.status == validation::ObligationStatus::Open).count();
             println!("  {} obligations ({} open)", obligations.len(), open_count);
         }
+        if let (Some(base), Some(result)) = (diff_base, &semantic_diff) {
+            println!(
+                "  {} semantic changes since {} (HIGH: {}, MEDIUM: {}, LOW: {}, INFO: {})",
+                result.changes.len(),
+                base,
+                result.high_count,
+                result.medium_count,
+                result.low_count,
+                result.info_count
+            );
+        }
+        if ran.len() < VerifyStep::ALL.len() {
+            let names: Vec<_> = ran.iter().map(|s| s.name()).collect();
+            println!("  steps run: {}", names.join(", "));
+        }
     }
 
-    Ok(exit_codes::SUCCESS)
+    if blocking.is_empty() {
+        Ok(exit_codes::SUCCESS)
+    } else {
+        Ok(exit_codes::BREAKING_CHANGES)
+    }
 }
 
 /// Apply a patch
-pub fn cmd_patch_apply(file: &str, dry_run: bool, json_output: bool) -> Result<i32> {
-    let result = parser::apply_patch(file, dry_run)?;
+pub fn cmd_patch_apply(file: &str, dry_run: bool, model_dir: &Path, json_output: bool) -> Result<i32> {
+    let result = parser::apply_patch(file, dry_run, model_dir)?;
 
     if json_output {
         println!("{}", serde_json::to_string_pretty(&result)?);
@@ -0,0 +1,150 @@
+//! Dependency graph export (Graphviz DOT or JSON) for architecture reviews
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write;
+
+use uuid::Uuid;
+
+use crate::model::{IntentDocument, IntentKind};
+use crate::parser::IntentStore;
+use crate::validation::ResolvedGraph;
+
+/// `kind:name`, the label of an intent's node
+fn node_label(doc: &IntentDocument) -> String {
+    format!("{}:{}", doc.kind, doc.name)
+}
+
+/// Each intent's label mapped to the sorted labels of the intents it
+/// depends on
+fn adjacency(store: &IntentStore, graph: &ResolvedGraph) -> BTreeMap<String, BTreeSet<String>> {
+    let mut adjacency = BTreeMap::new();
+    for doc in store.iter() {
+        let deps = graph
+            .dependencies
+            .get(&doc.id)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| store.get(id))
+            .map(node_label)
+            .collect();
+        adjacency.insert(node_label(doc), deps);
+    }
+    adjacency
+}
+
+/// Cycles as node labels, in reference order
+fn cycle_labels(store: &IntentStore, graph: &ResolvedGraph) -> Vec<Vec<String>> {
+    graph
+        .cycles()
+        .iter()
+        .map(|cycle| cycle.iter().filter_map(|id| store.get(id)).map(node_label).collect())
+        .collect()
+}
+
+/// The graph as `{ "nodes": { label: [dependency labels] }, "cycles": [[labels]] }`
+pub fn graph_json(store: &IntentStore, graph: &ResolvedGraph) -> serde_json::Value {
+    serde_json::json!({
+        "nodes": adjacency(store, graph),
+        "cycles": cycle_labels(store, graph),
+    })
+}
+
+/// Fill color of a node by intent kind
+fn kind_color(kind: IntentKind) -> &'static str {
+    match kind {
+        IntentKind::Type => "lightblue",
+        IntentKind::Enum => "lightcyan",
+        IntentKind::Workflow => "lightgoldenrod",
+        IntentKind::Service => "lightpink",
+        IntentKind::Endpoint => "palegreen",
+        IntentKind::Function | IntentKind::Pipeline => "lavender",
+        _ => "white",
+    }
+}
+
+/// The graph in Graphviz DOT: one filled node per intent, colored by kind,
+/// and an edge from each intent to each of its dependencies. Intents and
+/// references that form a cycle are drawn in red.
+pub fn graph_dot(store: &IntentStore, graph: &ResolvedGraph) -> String {
+    let cycles = graph.cycles();
+    let in_cycle: HashSet<Uuid> = cycles.iter().flatten().copied().collect();
+    let cycle_edges: HashSet<(Uuid, Uuid)> = cycles
+        .iter()
+        .flat_map(|cycle| cycle.iter().copied().zip(cycle.iter().copied().cycle().skip(1)))
+        .collect();
+
+    let mut intents: Vec<&IntentDocument> = store.iter().collect();
+    intents.sort_by_key(|doc| node_label(doc));
+
+    let mut dot = String::from("digraph intents {\n    rankdir=LR;\n    node [shape=box, style=filled];\n");
+    for doc in &intents {
+        let cycle_attrs = if in_cycle.contains(&doc.id) { ", color=red, penwidth=2" } else { "" };
+        let _ = writeln!(
+            dot,
+            "    {} [fillcolor={}{}];",
+            quoted(&node_label(doc)),
+            kind_color(doc.kind),
+            cycle_attrs
+        );
+    }
+    for doc in &intents {
+        let mut deps: Vec<&IntentDocument> = graph
+            .dependencies
+            .get(&doc.id)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| store.get(id))
+            .collect();
+        deps.sort_by_key(|dep| node_label(dep));
+        deps.dedup_by_key(|dep| dep.id);
+        for dep in deps {
+            let cycle_attrs = if cycle_edges.contains(&(doc.id, dep.id)) { " [color=red]" } else { "" };
+            let _ = writeln!(
+                dot,
+                "    {} -> {}{};",
+                quoted(&node_label(doc)),
+                quoted(&node_label(dep)),
+                cycle_attrs
+            );
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn quoted(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::resolve_references;
+    use serde_json::json;
+
+    #[test]
+    fn test_graph_marks_cycles() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        for (name, other) in [("Node", "Edge"), ("Edge", "Node")] {
+            store
+                .add(IntentDocument::with_spec(
+                    IntentKind::Type,
+                    name.to_string(),
+                    json!({ "fields": { "next": { "type": other, "required": true } } }),
+                ))
+                .unwrap();
+        }
+        let (graph, _) = resolve_references(&store);
+
+        let dot = graph_dot(&store, &graph);
+        assert!(dot.starts_with("digraph intents {"));
+        assert!(dot.contains("\"Endpoint:CreateRefund\" [fillcolor=palegreen];"));
+        assert!(dot.contains("\"Endpoint:CreateRefund\" -> \"Type:RefundRequest\";"));
+        assert!(dot.contains("\"Type:Edge\" [fillcolor=lightblue, color=red, penwidth=2];"));
+        assert!(dot.contains("\"Type:Edge\" -> \"Type:Node\" [color=red];"));
+
+        let json = graph_json(&store, &graph);
+        assert_eq!(json["nodes"]["Type:Node"], json!(["Type:Edge"]));
+        assert_eq!(json["cycles"].as_array().unwrap().len(), 1);
+    }
+}
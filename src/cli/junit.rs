@@ -0,0 +1,141 @@
+//! JUnit XML rendering of validation results for CI test dashboards
+
+use std::fmt::Write;
+
+use crate::model::{IntentDocument, StructuredError};
+use crate::parser::IntentStore;
+use crate::validation::ValidationResult;
+
+/// Render `result` as a JUnit XML report: one `<testcase>` per intent, a
+/// `<failure>` for its errors and warnings/infos as `<system-out>`.
+/// Diagnostics not tied to a loaded intent file go in a `(model)` test case.
+pub fn validation_junit(store: &IntentStore, result: &ValidationResult) -> String {
+    let mut intents: Vec<&IntentDocument> = store.iter().collect();
+    intents.sort_by(|a, b| (a.kind.to_string(), &a.name).cmp(&(b.kind.to_string(), &b.name)));
+
+    let unattached = |diagnostic: &&StructuredError| !intents.iter().any(|doc| belongs_to(diagnostic, doc));
+
+    let mut cases = Vec::new();
+    for doc in &intents {
+        cases.push(TestCase {
+            classname: doc.kind.to_string(),
+            name: doc.name.clone(),
+            errors: attached(&result.errors, doc),
+            output: [attached(&result.warnings, doc), attached(&result.infos, doc)].concat(),
+        });
+    }
+
+    let model_errors: Vec<_> = result.errors.iter().filter(unattached).collect();
+    let model_output: Vec<_> = result
+        .warnings
+        .iter()
+        .chain(&result.infos)
+        .filter(unattached)
+        .collect();
+    if !model_errors.is_empty() || !model_output.is_empty() {
+        cases.push(TestCase {
+            classname: "Model".to_string(),
+            name: "(model)".to_string(),
+            errors: model_errors,
+            output: model_output,
+        });
+    }
+
+    let failures = cases.iter().filter(|case| !case.errors.is_empty()).count();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"intent validate\" tests=\"{}\" failures=\"{}\" errors=\"0\">",
+        cases.len(),
+        failures
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"intent validate\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\">",
+        cases.len(),
+        failures
+    );
+    for case in &cases {
+        case.write(&mut xml);
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+fn belongs_to(diagnostic: &StructuredError, doc: &IntentDocument) -> bool {
+    diagnostic.location.as_ref().is_some_and(|loc| {
+        !loc.file.is_empty() && doc.source_file.as_deref() == Some(loc.file.as_str())
+    })
+}
+
+fn attached<'a>(list: &'a [StructuredError], doc: &IntentDocument) -> Vec<&'a StructuredError> {
+    list.iter().filter(|d| belongs_to(d, doc)).collect()
+}
+
+struct TestCase<'a> {
+    classname: String,
+    name: String,
+    errors: Vec<&'a StructuredError>,
+    output: Vec<&'a StructuredError>,
+}
+
+impl TestCase<'_> {
+    fn write(&self, xml: &mut String) {
+        let _ = write!(
+            xml,
+            "    <testcase classname=\"{}\" name=\"{}\"",
+            escape(&self.classname),
+            escape(&self.name)
+        );
+        if self.errors.is_empty() && self.output.is_empty() {
+            xml.push_str("/>\n");
+            return;
+        }
+        xml.push_str(">\n");
+
+        // JUnit allows a single <failure>; every error is listed in its body
+        if let Some(first) = self.errors.first() {
+            let message = if self.errors.len() == 1 {
+                first.message.clone()
+            } else {
+                format!("{} errors", self.errors.len())
+            };
+            let body: Vec<String> = self.errors.iter().map(|e| diagnostic_line(e)).collect();
+            let _ = writeln!(
+                xml,
+                "      <failure type=\"{}\" message=\"{}\">{}</failure>",
+                escape(&first.code),
+                escape(&message),
+                escape(&body.join("\n"))
+            );
+        }
+        if !self.output.is_empty() {
+            let body: Vec<String> = self.output.iter().map(|d| diagnostic_line(d)).collect();
+            let _ = writeln!(xml, "      <system-out>{}</system-out>", escape(&body.join("\n")));
+        }
+        xml.push_str("    </testcase>\n");
+    }
+}
+
+/// `[E005] message (file:path)`
+fn diagnostic_line(diagnostic: &StructuredError) -> String {
+    match &diagnostic.location {
+        Some(loc) => format!("[{}] {} ({}:{})", diagnostic.code, diagnostic.message, loc.file, loc.path),
+        None => format!("[{}] {}", diagnostic.code, diagnostic.message),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -1,5 +1,9 @@
 //! CLI commands for the intent engine
 
 mod commands;
+mod graph;
+mod junit;
 
 pub use commands::*;
+pub use graph::*;
+pub use junit::*;
@@ -0,0 +1,89 @@
+//! Client crates the generated effect modules are built on, chosen by the
+//! `[runtime]` section of `intent.toml`
+
+use crate::parser::RuntimeConfig;
+
+/// A client crate one kind of effect can be generated against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backend {
+    /// Name used in `[runtime]`, e.g. `db_client = "diesel"`
+    pub name: &'static str,
+    /// Commented `[dependencies]` lines for the generated Cargo.toml; empty
+    /// when the generated module needs no client crate
+    pub dependency: &'static str,
+}
+
+/// `http_client` backends; the first is the default
+pub const HTTP_BACKENDS: &[Backend] = &[Backend {
+    name: "reqwest",
+    dependency: r#"# HTTP client
+reqwest = { version = "0.11", features = ["json"] }
+"#,
+}];
+
+/// `db_client` backends; the first is the default
+pub const DB_BACKENDS: &[Backend] = &[
+    Backend {
+        name: "sqlx",
+        dependency: r#"# Database
+sqlx = { version = "0.7", features = ["runtime-tokio", "postgres"] }
+"#,
+    },
+    Backend {
+        name: "diesel",
+        dependency: r#"# Database
+diesel = { version = "2.1", features = ["postgres"] }
+diesel-async = { version = "0.4", features = ["postgres", "deadpool"] }
+"#,
+    },
+];
+
+/// `event_client` backends; the first is the default. The Kafka module only
+/// logs each event until a producer is wired in, so it has no dependency.
+pub const EVENT_BACKENDS: &[Backend] = &[Backend {
+    name: "kafka",
+    dependency: "",
+}];
+
+/// Check that each `[runtime]` client names a supported backend
+pub fn check_runtime_backends(runtime: &RuntimeConfig) -> anyhow::Result<()> {
+    for (key, name, backends) in [
+        ("http_client", &runtime.http_client, HTTP_BACKENDS),
+        ("db_client", &runtime.db_client, DB_BACKENDS),
+        ("event_client", &runtime.event_client, EVENT_BACKENDS),
+    ] {
+        if !backends.iter().any(|backend| backend.name == name) {
+            let supported: Vec<_> = backends.iter().map(|backend| backend.name).collect();
+            anyhow::bail!(
+                "Unsupported {} '{}' in [runtime]; supported: {}",
+                key,
+                name,
+                supported.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The backend called `name`, or the default one for a name
+/// `check_runtime_backends` would reject
+pub fn select_backend(backends: &'static [Backend], name: &str) -> &'static Backend {
+    backends
+        .iter()
+        .find(|backend| backend.name == name)
+        .unwrap_or(&backends[0])
+}
+
+/// `[dependencies]` lines for the configured HTTP, database and event
+/// clients, each group preceded by a blank line
+pub fn client_dependencies(runtime: &RuntimeConfig) -> String {
+    [
+        select_backend(HTTP_BACKENDS, &runtime.http_client),
+        select_backend(DB_BACKENDS, &runtime.db_client),
+        select_backend(EVENT_BACKENDS, &runtime.event_client),
+    ]
+    .iter()
+    .filter(|backend| !backend.dependency.is_empty())
+    .map(|backend| format!("\n{}", backend.dependency))
+    .collect()
+}
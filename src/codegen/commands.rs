@@ -1,6 +1,7 @@
 //! Code generation for Command intents
 
 use crate::parser::IntentStore;
+use super::naming::{to_ident, to_pascal_case};
 
 /// Generate Rust code for all Command intents (CLI structure)
 pub fn generate_commands(store: &IntentStore) -> CommandsOutput {
@@ -9,16 +10,23 @@ pub fn generate_commands(store: &IntentStore) -> CommandsOutput {
 
     let mod_rs = generate_commands_mod(&commands);
     let cli_rs = generate_cli_main(&commands);
-    let mut files = vec![CommandFile {
-        name: "cli.rs".to_string(),
-        content: cli_rs,
-    }];
+    let registry_rs = generate_registry(&commands);
+    let mut files = vec![
+        CommandFile {
+            name: "cli.rs".to_string(),
+            content: cli_rs,
+        },
+        CommandFile {
+            name: "registry.rs".to_string(),
+            content: registry_rs,
+        },
+    ];
 
     for doc in &commands {
         if let Ok(spec) = doc.as_command_spec() {
             let content = generate_command_handler(&doc.name, &spec);
             files.push(CommandFile {
-                name: format!("{}.rs", to_snake_case(&doc.name)),
+                name: format!("{}.rs", to_ident(&doc.name)),
                 content,
             });
         }
@@ -46,8 +54,9 @@ fn generate_commands_mod(commands: &[&crate::model::IntentDocument]) -> String {
     output.push_str("// DO NOT EDIT - changes will be overwritten\n\n");
 
     output.push_str("pub mod cli;\n");
+    output.push_str("pub mod registry;\n");
     for doc in commands {
-        output.push_str(&format!("pub mod {};\n", to_snake_case(&doc.name)));
+        output.push_str(&format!("pub mod {};\n", to_ident(&doc.name)));
     }
 
     output
@@ -115,7 +124,7 @@ fn generate_cli_main(commands: &[&crate::model::IntentDocument]) -> String {
     for doc in commands {
         if let Ok(spec) = doc.as_command_spec() {
             let variant_name = to_pascal_case(&spec.command);
-            let module_name = to_snake_case(&doc.name);
+            let module_name = to_ident(&doc.name);
             output.push_str(&format!(
                 "        Commands::{}(args) => super::{}::run(args, cli.format),\n",
                 variant_name, module_name
@@ -128,6 +137,81 @@ fn generate_cli_main(commands: &[&crate::model::IntentDocument]) -> String {
     output
 }
 
+/// Runtime registration of the Command intents into an existing clap app,
+/// for embedding them next to hand-written subcommands
+fn generate_registry(commands: &[&crate::model::IntentDocument]) -> String {
+    let specs: Vec<_> = commands
+        .iter()
+        .filter_map(|doc| Some((*doc, doc.as_command_spec().ok()?)))
+        .collect();
+
+    let mut output = String::new();
+
+    output.push_str("// @generated by intent-engine v2.0\n");
+    output.push_str("// DO NOT EDIT - changes will be overwritten\n\n");
+
+    output.push_str("use clap::{Args, FromArgMatches};\n\n");
+    output.push_str("use super::cli::OutputFormat;\n\n");
+
+    // Register function
+    output.push_str("/// Add every Command intent to `app` as a subcommand\n");
+    output.push_str("pub fn register(app: clap::Command) -> clap::Command {\n");
+    output.push_str("    app");
+    for (_, spec) in &specs {
+        let mut command = format!("clap::Command::new({:?})", spec.command);
+        if !spec.description.is_empty() {
+            command.push_str(&format!(".about({:?})", spec.description));
+        }
+        for alias in &spec.aliases {
+            command.push_str(&format!(".visible_alias({:?})", alias));
+        }
+        output.push_str(&format!(
+            "\n        .subcommand(super::cli::{}Args::augment_args({}))",
+            to_pascal_case(&spec.command),
+            command
+        ));
+    }
+    output.push_str("\n}\n\n");
+
+    // Handler lookup
+    output.push_str("/// Handler declared by the Command intent behind a subcommand\n");
+    output.push_str("pub fn handler_name(subcommand: &str) -> Option<&'static str> {\n");
+    output.push_str("    match subcommand {\n");
+    for (_, spec) in &specs {
+        output.push_str(&format!(
+            "        {:?} => Some({:?}),\n",
+            spec.command, spec.handler
+        ));
+    }
+    output.push_str("        _ => None,\n");
+    output.push_str("    }\n");
+    output.push_str("}\n\n");
+
+    // Dispatch function
+    output.push_str("/// Run the matched subcommand, or `None` if it isn't a Command intent\n");
+    output.push_str("pub fn dispatch(matches: &clap::ArgMatches, format: OutputFormat) -> Option<anyhow::Result<i32>> {\n");
+    output.push_str("    let (name, sub_matches) = matches.subcommand()?;\n");
+    output.push_str("    let result = match name {\n");
+    for (doc, spec) in &specs {
+        output.push_str(&format!(
+            "        {:?} => super::cli::{}Args::from_arg_matches(sub_matches)\n",
+            spec.command,
+            to_pascal_case(&spec.command)
+        ));
+        output.push_str("            .map_err(anyhow::Error::from)\n");
+        output.push_str(&format!(
+            "            .and_then(|args| super::{}::run(args, format)),\n",
+            to_ident(&doc.name)
+        ));
+    }
+    output.push_str("        _ => return None,\n");
+    output.push_str("    };\n");
+    output.push_str("    Some(result)\n");
+    output.push_str("}\n");
+
+    output
+}
+
 fn generate_args_struct(spec: &crate::model::CommandSpec) -> String {
     let mut output = String::new();
 
@@ -142,10 +226,8 @@ fn generate_args_struct(spec: &crate::model::CommandSpec) -> String {
             output.push_str(&format!("    /// {}\n", arg.description));
         }
 
-        // Argument attribute
-        if arg.positional {
-            output.push_str("    #[arg]\n");
-        } else {
+        // Argument attribute (clap treats unannotated fields as positional)
+        if !arg.positional {
             let mut attrs = Vec::new();
             if let Some(short) = &arg.short {
                 attrs.push(format!("short = '{}'", short));
@@ -243,33 +325,3 @@ fn generate_command_handler(name: &str, spec: &crate::model::CommandSpec) -> Str
     output
 }
 
-fn to_snake_case(s: &str) -> String {
-    let mut result = String::new();
-    for (i, c) in s.chars().enumerate() {
-        if c.is_uppercase() {
-            if i > 0 {
-                result.push('_');
-            }
-            result.push(c.to_ascii_lowercase());
-        } else {
-            result.push(c);
-        }
-    }
-    result
-}
-
-fn to_pascal_case(s: &str) -> String {
-    let mut result = String::new();
-    let mut capitalize_next = true;
-    for c in s.chars() {
-        if c == '_' || c == '-' {
-            capitalize_next = true;
-        } else if capitalize_next {
-            result.push(c.to_ascii_uppercase());
-            capitalize_next = false;
-        } else {
-            result.push(c);
-        }
-    }
-    result
-}
@@ -0,0 +1,178 @@
+//! Contract test generation for ContractTest intents
+
+use quote::{format_ident, quote};
+
+use crate::model::IntentKind;
+use crate::parser::{IntentConfig, IntentStore};
+
+use super::{service_base_url, to_ident};
+
+/// Generate `tests/contracts.rs`: one test per scenario, sending its request
+/// to the live service and checking the response against any of the
+/// scenario's expected responses. The tests are `#[ignore]`d so a plain
+/// `cargo test` stays offline.
+pub fn generate_contract_tests(store: &IntentStore, config: &IntentConfig) -> anyhow::Result<ContractTestsOutput> {
+    let mut contracts = store.contract_tests();
+    contracts.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut tests = Vec::new();
+    let mut intent_ids = Vec::new();
+    for doc in contracts {
+        let Ok(spec) = doc.as_contract_test_spec() else {
+            continue;
+        };
+        // Unknown services and operations are reported by validation
+        let Some(service) = store
+            .get_by_kind_name(IntentKind::Service, &spec.service)
+            .and_then(|service| service.as_service_spec().ok())
+        else {
+            continue;
+        };
+        let Some(operation) = service.operations.get(&spec.operation) else {
+            continue;
+        };
+        intent_ids.push(doc.id);
+
+        let (env_var, base_url) = service_base_url(config, &spec.service, &service)?;
+        let method = operation.method.to_string();
+        let path = &operation.path;
+        let ignore = format!("calls the live {} service", spec.service);
+
+        for scenario in &spec.scenarios {
+            let test_ident = format_ident!("{}_{}", to_ident(&doc.name), to_ident(&scenario.name));
+            let request = scenario.request.to_string();
+            let expected = scenario.response.candidates().iter().map(|response| {
+                let status = proc_macro2::Literal::u16_unsuffixed(response.status);
+                let mut headers: Vec<_> = response.headers.iter().collect();
+                headers.sort();
+                // Header names are case-insensitive; `HeaderMap` stores them lowercase
+                let header_names = headers.iter().map(|(name, _)| name.to_lowercase());
+                let header_values = headers.iter().map(|(_, value)| value);
+                let body = response.body.to_string();
+                quote! {
+                    Expected {
+                        status: #status,
+                        headers: &[#((#header_names, #header_values)),*],
+                        body: #body,
+                    }
+                }
+            });
+            tests.push(quote! {
+                #[tokio::test]
+                #[ignore = #ignore]
+                async fn #test_ident() {
+                    let base_url = std::env::var(#env_var).unwrap_or_else(|_| #base_url.to_string());
+                    check_contract(
+                        #method,
+                        format!("{}{}", base_url, #path),
+                        #request,
+                        &[#(#expected),*],
+                    )
+                    .await;
+                }
+            });
+        }
+    }
+
+    let tokens = quote! {
+        // @generated by intent-engine v1.0
+        // DO NOT EDIT — changes will be overwritten
+
+        use reqwest::header::HeaderMap;
+        use serde_json::Value;
+
+        /// One response a scenario accepts
+        struct Expected {
+            status: u16,
+            headers: &'static [(&'static str, &'static str)],
+            body: &'static str,
+        }
+
+        /// Send `request` and check the response matches at least one of `expected`
+        async fn check_contract(method: &str, url: String, request: &str, expected: &[Expected]) {
+            let request: Value = serde_json::from_str(request).expect("request is valid JSON");
+            let client = reqwest::Client::new();
+            let builder = match method {
+                "GET" => client.get(&url).query(&request),
+                "DELETE" => client.delete(&url).query(&request),
+                "PUT" => client.put(&url).json(&request),
+                "PATCH" => client.patch(&url).json(&request),
+                _ => client.post(&url).json(&request),
+            };
+            let response = builder
+                .send()
+                .await
+                .unwrap_or_else(|e| panic!("{} {} failed: {}", method, url, e));
+            let status = response.status().as_u16();
+            let headers = response.headers().clone();
+            let body: Value = response.json().await.unwrap_or(Value::Null);
+
+            let mismatches: Vec<String> = expected
+                .iter()
+                .filter_map(|expected| mismatch(expected, status, &headers, &body))
+                .collect();
+            assert!(
+                mismatches.len() < expected.len(),
+                "{} {} matched none of the expected responses:\n{}",
+                method,
+                url,
+                mismatches.join("\n")
+            );
+        }
+
+        /// Why the response doesn't match `expected`, or `None` if it does
+        fn mismatch(expected: &Expected, status: u16, headers: &HeaderMap, body: &Value) -> Option<String> {
+            if status != expected.status {
+                return Some(format!("status {} is not {}", status, expected.status));
+            }
+            for (name, value) in expected.headers {
+                let actual = headers.get(*name).and_then(|actual| actual.to_str().ok());
+                if actual != Some(*value) {
+                    return Some(format!("header '{}' is {:?}, not '{}'", name, actual, value));
+                }
+            }
+            let expected_body: Value = serde_json::from_str(expected.body).expect("body is valid JSON");
+            if !matches(&expected_body, body) {
+                return Some(format!("body {} does not match {}", body, expected_body));
+            }
+            None
+        }
+
+        /// Literals match exactly and `@uuid`, `@string`, `@int` and `@datetime`
+        /// any value of that kind; objects match when every expected key does
+        fn matches(expected: &Value, actual: &Value) -> bool {
+            match (expected, actual) {
+                (Value::String(matcher), _) if matcher == "@uuid" => actual
+                    .as_str()
+                    .is_some_and(|s| uuid::Uuid::parse_str(s).is_ok()),
+                (Value::String(matcher), _) if matcher == "@string" => actual.is_string(),
+                (Value::String(matcher), _) if matcher == "@int" => actual.is_i64() || actual.is_u64(),
+                (Value::String(matcher), _) if matcher == "@datetime" => actual
+                    .as_str()
+                    .is_some_and(|s| chrono::DateTime::parse_from_rfc3339(s).is_ok()),
+                (Value::Object(expected), Value::Object(actual)) => expected
+                    .iter()
+                    .all(|(key, value)| actual.get(key).is_some_and(|actual| matches(value, actual))),
+                (Value::Array(expected), Value::Array(actual)) => {
+                    expected.len() == actual.len()
+                        && expected.iter().zip(actual).all(|(expected, actual)| matches(expected, actual))
+                }
+                _ => expected == actual,
+            }
+        }
+
+        #(#tests)*
+    };
+
+    let file = syn::parse2(tokens).expect("Failed to parse tests/contracts.rs");
+    Ok(ContractTestsOutput {
+        contracts_rs: prettyplease::unparse(&file),
+        intent_ids,
+    })
+}
+
+pub struct ContractTestsOutput {
+    pub contracts_rs: String,
+    /// ContractTests the tests were generated from
+    pub intent_ids: Vec<uuid::Uuid>,
+}
@@ -1,9 +1,20 @@
 //! Crate generation (Cargo.toml and lib.rs)
 
-use crate::parser::{IntentConfig, IntentStore};
+use anyhow::Context;
+
+use crate::parser::{BytesEncoding, GenTarget, IntentConfig, IntentStore, ResponseFormat};
+
+use super::{client_dependencies, uses_bytes, GenWriter, GEN_DIR};
+
+/// The host project's manifest, which `[generation] workspace` edits
+pub const WORKSPACE_MANIFEST: &str = "Cargo.toml";
+
+const RUNTIME_DEPENDENCY: &str = r#"# Async runtime
+tokio = { version = "1.35", features = ["full"] }
+"#;
 
 /// Generate Cargo.toml content
-pub fn generate_cargo_toml(config: &IntentConfig) -> String {
+pub fn generate_cargo_toml(store: &IntentStore, config: &IntentConfig) -> String {
     let name = if config.project.name.is_empty() {
         "generated"
     } else {
@@ -17,6 +28,7 @@ pub fn generate_cargo_toml(config: &IntentConfig) -> String {
     };
 
     let edition = &config.generation.rust_edition;
+    let clients = client_dependencies(&config.runtime);
 
     format!(
         r#"# @generated by intent-engine v1.0
@@ -28,11 +40,9 @@ version = "{version}"
 edition = "{edition}"
 
 [dependencies]
-# Async runtime
-tokio = {{ version = "1.35", features = ["full"] }}
-
+{runtime_dependency}
 # Web framework
-axum = {{ version = "0.7", features = ["json"] }}
+{axum_dependency}
 
 # Serialization
 serde = {{ version = "1.0", features = ["derive"] }}
@@ -46,15 +56,162 @@ anyhow = "1.0"
 uuid = {{ version = "1.6", features = ["v4", "serde"] }}
 chrono = {{ version = "0.4", features = ["serde"] }}
 rust_decimal = {{ version = "1.33", features = ["serde"] }}
-
+{bytes_dependency}{format_dependencies}
+{client_dependencies}
+# Logging
+tracing = "0.1"
+{target_dependencies}{dev_dependencies}{features_section}{bin_section}"#,
+        // axum's default features pull in tokio's networking, which
+        // doesn't build for wasm32; the generated handlers only need `json`
+        axum_dependency = match config.generation.target {
+            GenTarget::Native => r#"axum = { version = "0.7", features = ["json"] }"#,
+            GenTarget::Wasm => r#"axum = { version = "0.7", default-features = false, features = ["json"] }"#,
+        },
+        runtime_dependency = match config.generation.target {
+            GenTarget::Native => RUNTIME_DEPENDENCY,
+            GenTarget::Wasm => "",
+        },
+        client_dependencies = match config.generation.target {
+            GenTarget::Native => clients.as_str(),
+            GenTarget::Wasm => "",
+        },
+        // Native-only crates move behind target cfg; wasm32 gets
+        // browser-compatible replacements
+        target_dependencies = match config.generation.target {
+            GenTarget::Native => String::new(),
+            GenTarget::Wasm => format!(
+                r#"
+[target.'cfg(not(target_arch = "wasm32"))'.dependencies]
+{RUNTIME_DEPENDENCY}{clients}
+[target.'cfg(target_arch = "wasm32")'.dependencies]
 # HTTP client
-reqwest = {{ version = "0.11", features = ["json"] }}
+gloo-net = {{ version = "0.5", default-features = false, features = ["http", "json"] }}
 
-# Database
-sqlx = {{ version = "0.7", features = ["runtime-tokio", "postgres"] }}
+# Retry backoff timers
+gloo-timers = {{ version = "0.3", features = ["futures"] }}
 
-# Logging
-tracing = "0.1"
+# Randomness for uuid v4 from the JS host
+getrandom = {{ version = "0.2", features = ["js"] }}
+"#
+            ),
+        },
+        // The generated contract tests call the live services directly
+        dev_dependencies = if store.contract_tests().is_empty() {
+            ""
+        } else {
+            r#"
+[dev-dependencies]
+reqwest = { version = "0.11", features = ["json"] }
+"#
+        },
+        // Only the `bytes` field helper in types.rs needs it
+        bytes_dependency = match config.generation.bytes_encoding {
+            _ if !uses_bytes(store) => "",
+            BytesEncoding::Base64 => r#"base64 = "0.22""#,
+            BytesEncoding::Hex => r#"hex = "0.4""#,
+        },
+        format_dependencies = config
+            .generation
+            .response_formats
+            .iter()
+            .filter_map(|format| match format {
+                ResponseFormat::Json => None,
+                ResponseFormat::Msgpack => Some("\nrmp-serde = \"1.1\""),
+                ResponseFormat::Cbor => Some("\nciborium = \"0.2\""),
+            })
+            .collect::<String>(),
+        features_section = if config.generation.conditional_derives.is_empty() {
+            String::new()
+        } else {
+            let features: String = config
+                .generation
+                .conditional_derives
+                .keys()
+                .map(|feature| format!("{} = []\n", feature))
+                .collect();
+            format!("\n[features]\n{}", features)
+        },
+        bin_section = if config.generation.emits_binary() {
+            format!(
+                r#"
+[[bin]]
+name = "{name}"
+path = "src/main.rs"
+"#
+            )
+        } else {
+            String::new()
+        }
+    )
+}
+
+/// Generate main.rs content for `[generation] binary = true`
+pub fn generate_main_rs(config: &IntentConfig) -> String {
+    let name = if config.project.name.is_empty() {
+        "generated"
+    } else {
+        &config.project.name
+    };
+    let lib_name = name.replace('-', "_");
+    let bind_address = &config.generation.bind_address;
+
+    format!(
+        r#"// @generated by intent-engine v1.0
+// DO NOT EDIT — changes will be overwritten
+
+use std::net::SocketAddr;
+
+use axum::routing::get;
+
+const DEFAULT_BIND_ADDRESS: &str = "{bind_address}";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {{
+    let addr: SocketAddr = std::env::var("BIND_ADDRESS")
+        .unwrap_or_else(|_| DEFAULT_BIND_ADDRESS.to_string())
+        .parse()?;
+
+    let app = {lib_name}::app().route("/health", get(health));
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("listening on {{}}", addr);
+
+    axum::serve(listener, app.into_make_service())
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    Ok(())
+}}
+
+async fn health() -> &'static str {{
+    "ok"
+}}
+
+async fn shutdown_signal() {{
+    let ctrl_c = async {{
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install SIGINT handler");
+    }};
+
+    #[cfg(unix)]
+    let terminate = async {{
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    }};
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {{
+        _ = ctrl_c => {{}}
+        _ = terminate => {{}}
+    }}
+
+    tracing::info!("shutdown signal received");
+}}
 "#
     )
 }
@@ -62,20 +219,32 @@ tracing = "0.1"
 /// Generate lib.rs content
 pub fn generate_lib_rs(store: &IntentStore) -> String {
     let has_types = !store.types().is_empty();
+    let has_enums = !store.enums().is_empty();
     let has_endpoints = !store.endpoints().is_empty();
     let has_workflows = !store.workflows().is_empty();
+    let has_functions = !store.functions().is_empty();
+    let has_services = !store.services().is_empty();
 
     let mut mods = vec![];
 
     if has_types {
         mods.push("pub mod types;");
     }
+    if has_enums {
+        mods.push("pub mod enums;");
+    }
     if has_endpoints {
         mods.push("pub mod endpoints;");
     }
     if has_workflows {
         mods.push("pub mod workflows;");
     }
+    if has_functions {
+        mods.push("pub mod functions;");
+    }
+    if has_services {
+        mods.push("pub mod services;");
+    }
 
     mods.push("pub mod effects;");
     mods.push("pub mod errors;");
@@ -107,46 +276,230 @@ pub fn app() -> axum::Router {
     )
 }
 
+/// With `[generation] workspace = true`, make `gen` a member of the root
+/// `Cargo.toml` workspace, creating the manifest or its `[workspace]` table
+/// if needed. The rest of an existing manifest is kept as written, and an
+/// existing member is left alone. Returns whether the manifest changed.
+pub fn generate_workspace_integration(config: &IntentConfig, writer: &mut dyn GenWriter) -> anyhow::Result<bool> {
+    if !config.generation.workspace {
+        return Ok(false);
+    }
+
+    let existing = writer.read_existing(WORKSPACE_MANIFEST)?;
+    let mut manifest: toml_edit::DocumentMut = existing
+        .as_deref()
+        .unwrap_or_default()
+        .parse()
+        .with_context(|| format!("Failed to parse {}", WORKSPACE_MANIFEST))?;
+
+    let workspace = manifest
+        .entry("workspace")
+        .or_insert(toml_edit::table())
+        .as_table_like_mut()
+        .with_context(|| format!("`workspace` in {} is not a table", WORKSPACE_MANIFEST))?;
+    let members = workspace
+        .entry("members")
+        .or_insert(toml_edit::value(toml_edit::Array::new()))
+        .as_array_mut()
+        .with_context(|| format!("`workspace.members` in {} is not an array", WORKSPACE_MANIFEST))?;
+
+    let is_member = members
+        .iter()
+        .any(|member| member.as_str().is_some_and(|m| m.trim_end_matches('/') == GEN_DIR));
+    if is_member {
+        return Ok(false);
+    }
+    members.push(GEN_DIR);
+    if existing.is_none() {
+        workspace.insert("resolver", toml_edit::value("2"));
+    }
+
+    writer.write(WORKSPACE_MANIFEST, &manifest.to_string())?;
+    Ok(true)
+}
+
 /// Full generation result
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct GenerationResult {
     pub matches: bool,
+    /// SHA256 over every generated file's path and hash, for cache keys
+    pub hash: String,
     pub files: Vec<GeneratedFile>,
+    /// Files in the previous manifest that this run no longer produces
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub orphaned: Vec<String>,
+    /// Orphaned files removed by `--prune`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pruned: Vec<String>,
+    /// Rust files written unformatted because they failed to parse
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unformatted: Vec<String>,
+    /// `--check` found the sources unchanged since the manifest was
+    /// written and the files on disk intact, so nothing was regenerated
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub sources_unchanged: bool,
+    /// `gen` was added to the root `Cargo.toml` workspace members
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub workspace_updated: bool,
+    /// How many files drifted from what generation produces, by kind
+    pub drift: DriftCounts,
+    /// The manifest this run wrote, or would write when only checking
+    #[serde(skip)]
+    pub manifest: super::GenManifest,
+}
+
+/// Counts of what a generation run would do to `gen/`
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct GenerationSummary {
+    pub create: usize,
+    pub update: usize,
+    pub unchanged: usize,
+    pub delete: usize,
+}
+
+/// Files per drift kind, so CI can tell hand-edited generated code
+/// (`modified`) from a forgotten `gen` (`missing`, `new`, `orphaned`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct DriftCounts {
+    pub modified: usize,
+    pub missing: usize,
+    pub orphaned: usize,
+    pub new: usize,
+}
+
+/// Why a file on disk differs from what generation produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DriftKind {
+    /// Content differs from the generated content
+    Modified,
+    /// Tracked by the manifest but gone from disk
+    Missing,
+    /// Still on disk, but no longer generated
+    Orphaned,
+    /// Generated for the first time
+    New,
+}
+
+impl std::fmt::Display for DriftKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DriftKind::Modified => write!(f, "modified"),
+            DriftKind::Missing => write!(f, "missing"),
+            DriftKind::Orphaned => write!(f, "orphaned"),
+            DriftKind::New => write!(f, "new"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct GeneratedFile {
     pub path: String,
     pub matches: bool,
-    pub reason: String,
+    /// How the file on disk differs; `None` when it matches
+    pub reason: Option<DriftKind>,
+    /// SHA256 of the generated content
+    pub hash: String,
+    pub status: FileStatus,
+    /// Whether this run wrote the file: false in check mode, and when the
+    /// file on disk already had the generated content
+    pub written: bool,
+}
+
+/// What generation does to a file relative to what is on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Created,
+    Updated,
+    Unchanged,
 }
 
 impl GenerationResult {
     pub fn new() -> Self {
         Self {
             matches: true,
+            hash: String::new(),
             files: Vec::new(),
+            orphaned: Vec::new(),
+            pruned: Vec::new(),
+            unformatted: Vec::new(),
+            sources_unchanged: false,
+            workspace_updated: false,
+            drift: DriftCounts::default(),
+            manifest: super::GenManifest::new(),
         }
     }
 
-    pub fn add_file(&mut self, path: String, content: &str, existing: Option<&str>) {
+    /// Summarize files to create, update and leave alone, plus orphans to delete
+    pub fn summary(&self) -> GenerationSummary {
+        let mut summary = GenerationSummary {
+            delete: self.orphaned.len(),
+            ..Default::default()
+        };
+        for file in &self.files {
+            match file.status {
+                FileStatus::Unchanged => summary.unchanged += 1,
+                FileStatus::Created => summary.create += 1,
+                FileStatus::Updated => summary.update += 1,
+            }
+        }
+        summary
+    }
+
+    /// Aggregate hash of the generated tree: independent of generation
+    /// order, and changes whenever any file's path or content does
+    pub fn tree_hash(&self) -> String {
+        let mut entries: Vec<_> = self
+            .files
+            .iter()
+            .map(|f| format!("{}\0{}\n", f.path, f.hash))
+            .collect();
+        entries.sort();
+        super::manifest::compute_hash(&entries.concat())
+    }
+
+    /// Count the drifted files by kind
+    pub fn drift_counts(&self) -> DriftCounts {
+        let mut counts = DriftCounts {
+            orphaned: self.orphaned.len(),
+            ..Default::default()
+        };
+        for file in &self.files {
+            match file.reason {
+                Some(DriftKind::Modified) => counts.modified += 1,
+                Some(DriftKind::Missing) => counts.missing += 1,
+                Some(DriftKind::Orphaned) => counts.orphaned += 1,
+                Some(DriftKind::New) => counts.new += 1,
+                None => {}
+            }
+        }
+        counts
+    }
+
+    /// Record a generated file against `existing`, its content on disk.
+    /// `tracked` says whether the previous manifest lists the file, which
+    /// makes an absent file missing rather than new.
+    pub fn add_file(&mut self, path: String, content: &str, existing: Option<&str>, tracked: bool) {
         let matches = existing.map_or(false, |e| e == content);
         if !matches {
             self.matches = false;
         }
 
-        let reason = if existing.is_none() {
-            "new file".to_string()
-        } else if matches {
-            "unchanged".to_string()
-        } else {
-            "modified".to_string()
+        let (reason, status) = match existing {
+            None if tracked => (Some(DriftKind::Missing), FileStatus::Created),
+            None => (Some(DriftKind::New), FileStatus::Created),
+            Some(_) if matches => (None, FileStatus::Unchanged),
+            Some(_) => (Some(DriftKind::Modified), FileStatus::Updated),
         };
 
         self.files.push(GeneratedFile {
             path,
             matches,
             reason,
+            hash: super::manifest::compute_hash(content),
+            status,
+            written: false,
         });
     }
 }
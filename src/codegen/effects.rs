@@ -4,7 +4,9 @@ use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
 use crate::parser::IntentStore;
-use crate::parser::IntentConfig;
+use crate::parser::{GenTarget, IntentConfig};
+
+use super::{select_backend, DB_BACKENDS};
 
 /// Generate effects module
 pub fn generate_effects(store: &IntentStore, config: &IntentConfig) -> EffectsOutput {
@@ -30,11 +32,11 @@ pub fn generate_effects(store: &IntentStore, config: &IntentConfig) -> EffectsOu
 
     // Generate http.rs
     let http_client = &config.runtime.http_client;
-    output.http_rs = generate_http_module(store, http_client);
+    output.http_rs = generate_http_module(store, http_client, config.generation.target);
 
     // Generate db.rs
-    let db_client = &config.runtime.db_client;
-    output.db_rs = generate_db_module(db_client);
+    let db_client = select_backend(DB_BACKENDS, &config.runtime.db_client).name;
+    output.db_rs = generate_db_module(db_client, config.generation.target);
 
     // Generate events.rs
     let event_client = &config.runtime.event_client;
@@ -43,7 +45,7 @@ pub fn generate_effects(store: &IntentStore, config: &IntentConfig) -> EffectsOu
     output
 }
 
-fn generate_http_module(store: &IntentStore, client: &str) -> String {
+fn generate_http_module(store: &IntentStore, client: &str, target: GenTarget) -> String {
     // Generate service clients
     let mut service_clients = Vec::new();
 
@@ -82,6 +84,17 @@ fn generate_http_module(store: &IntentStore, client: &str) -> String {
         });
     }
 
+    let (request_error, transport) = match target {
+        GenTarget::Native => (
+            quote! {
+                #[error("HTTP request failed: {0}")]
+                Request(#[from] reqwest::Error),
+            },
+            TokenStream::new(),
+        ),
+        GenTarget::Wasm => wasm_http_transport(),
+    };
+
     let http_tokens = quote! {
         // @generated by intent-engine v1.0
         // DO NOT EDIT — changes will be overwritten
@@ -90,13 +103,14 @@ fn generate_http_module(store: &IntentStore, client: &str) -> String {
 
         #[derive(Debug, Error)]
         pub enum HttpError {
-            #[error("HTTP request failed: {0}")]
-            Request(#[from] reqwest::Error),
+            #request_error
 
             #[error("HTTP status error: {0}")]
             StatusError(u16),
         }
 
+        #transport
+
         pub async fn call(
             service: &str,
             operation: &str,
@@ -116,12 +130,88 @@ fn generate_http_module(store: &IntentStore, client: &str) -> String {
     prettyplease::unparse(&file)
 }
 
-fn generate_db_module(client: &str) -> String {
+/// `HttpError::Request` and a `post_json` transport for a crate that also
+/// builds for `wasm32`: reqwest natively, `gloo-net` (the browser's fetch)
+/// on wasm
+fn wasm_http_transport() -> (TokenStream, TokenStream) {
+    let request_error = quote! {
+        #[cfg(not(target_arch = "wasm32"))]
+        #[error("HTTP request failed: {0}")]
+        Request(#[from] reqwest::Error),
+
+        #[cfg(target_arch = "wasm32")]
+        #[error("HTTP request failed: {0}")]
+        Request(#[from] gloo_net::Error),
+    };
+    let transport = quote! {
+        /// POST `request` as JSON to `url` and decode the JSON response
+        #[cfg(not(target_arch = "wasm32"))]
+        pub async fn post_json(
+            url: &str,
+            request: &impl serde::Serialize,
+        ) -> Result<serde_json::Value, HttpError> {
+            let response = reqwest::Client::new().post(url).json(request).send().await?;
+            if response.status().is_success() {
+                Ok(response.json().await?)
+            } else {
+                Err(HttpError::StatusError(response.status().as_u16()))
+            }
+        }
+
+        /// POST `request` as JSON to `url` and decode the JSON response
+        #[cfg(target_arch = "wasm32")]
+        pub async fn post_json(
+            url: &str,
+            request: &impl serde::Serialize,
+        ) -> Result<serde_json::Value, HttpError> {
+            let response = gloo_net::http::Request::post(url).json(request)?.send().await?;
+            if response.ok() {
+                Ok(response.json().await?)
+            } else {
+                Err(HttpError::StatusError(response.status()))
+            }
+        }
+    };
+    (request_error, transport)
+}
+
+fn generate_db_module(client: &str, target: GenTarget) -> String {
+    // The database crates are native-only; a wasm32 build has no pool and
+    // reaches the database through the host
+    let native_only = match target {
+        GenTarget::Native => quote! {},
+        GenTarget::Wasm => quote! { #[cfg(not(target_arch = "wasm32"))] },
+    };
+
+    // The pool each backend's queries run on
+    let pool = match client {
+        "diesel" => quote! {
+            #native_only
+            use diesel_async::pooled_connection::deadpool::Pool;
+            #native_only
+            use diesel_async::AsyncPgConnection;
+
+            #native_only
+            pub type DbPool = Pool<AsyncPgConnection>;
+        },
+        _ => quote! {
+            #native_only
+            use sqlx::PgPool;
+
+            #native_only
+            pub type DbPool = PgPool;
+        },
+    };
+    let read_todo = format!("Implement database read with {}", client);
+    let write_todo = format!("Implement database write with {}", client);
+    let delete_todo = format!("Implement database delete with {}", client);
+
     let db_tokens = quote! {
         // @generated by intent-engine v1.0
         // DO NOT EDIT — changes will be overwritten
 
         use thiserror::Error;
+        #pool
 
         #[derive(Debug, Error)]
         pub enum DbError {
@@ -136,18 +226,15 @@ fn generate_db_module(client: &str) -> String {
         where
             T: serde::de::DeserializeOwned,
         {
-            // Database read implementation using sqlx
-            todo!("Implement database read")
+            todo!(#read_todo)
         }
 
         pub async fn write(table: &str, data: &impl serde::Serialize) -> Result<(), DbError> {
-            // Database write implementation using sqlx
-            todo!("Implement database write")
+            todo!(#write_todo)
         }
 
         pub async fn delete(table: &str, query: &impl serde::Serialize) -> Result<(), DbError> {
-            // Database delete implementation using sqlx
-            todo!("Implement database delete")
+            todo!(#delete_todo)
         }
     };
 
@@ -3,11 +3,16 @@
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
-use crate::model::HttpMethod;
-use crate::parser::IntentStore;
+use crate::model::{
+    AuthzConfig, BackoffStrategy, EndpointSpec, EnumSpec, HttpMethod, IntentKind, RetryPolicy, ScopeRequirement,
+    VariantData,
+};
+use crate::parser::{GenTarget, IntentConfig, IntentStore, ResponseFormat};
+use super::naming::{to_ident, to_pascal_case, to_snake_case};
 
 /// Generate endpoints module
-pub fn generate_endpoints(store: &IntentStore) -> EndpointsOutput {
+pub fn generate_endpoints(store: &IntentStore, config: &IntentConfig) -> EndpointsOutput {
+    let negotiate = config.generation.negotiates_responses();
     let mut endpoints: Vec<_> = store.endpoints().into_iter().collect();
     endpoints.sort_by(|a, b| a.name.cmp(&b.name));
 
@@ -25,7 +30,7 @@ pub fn generate_endpoints(store: &IntentStore) -> EndpointsOutput {
             continue;
         };
 
-        let mod_name = to_snake_case(&doc.name);
+        let mod_name = to_ident(&doc.name);
         let mod_ident = format_ident!("{}", mod_name);
         let handler_ident = format_ident!("{}", mod_name);
 
@@ -34,16 +39,32 @@ pub fn generate_endpoints(store: &IntentStore) -> EndpointsOutput {
         });
 
         let path = &spec.path;
-        let route = match spec.method {
-            HttpMethod::Get => quote! { .route(#path, axum::routing::get(#mod_ident::#handler_ident)) },
-            HttpMethod::Post => quote! { .route(#path, axum::routing::post(#mod_ident::#handler_ident)) },
-            HttpMethod::Put => quote! { .route(#path, axum::routing::put(#mod_ident::#handler_ident)) },
-            HttpMethod::Patch => quote! { .route(#path, axum::routing::patch(#mod_ident::#handler_ident)) },
-            HttpMethod::Delete => quote! { .route(#path, axum::routing::delete(#mod_ident::#handler_ident)) },
+        let method_router = match spec.method {
+            HttpMethod::Get => quote! { axum::routing::get(#mod_ident::#handler_ident) },
+            HttpMethod::Post => quote! { axum::routing::post(#mod_ident::#handler_ident) },
+            HttpMethod::Put => quote! { axum::routing::put(#mod_ident::#handler_ident) },
+            HttpMethod::Patch => quote! { axum::routing::patch(#mod_ident::#handler_ident) },
+            HttpMethod::Delete => quote! { axum::routing::delete(#mod_ident::#handler_ident) },
         };
-        router_routes.push(route);
+        let authz_layer = spec.authz.as_ref().map(authz_layer_tokens);
+        router_routes.push(quote! { .route(#path, #method_router #authz_layer) });
     }
 
+    let authz = if endpoints
+        .iter()
+        .any(|doc| doc.as_endpoint_spec().is_ok_and(|spec| spec.authz.is_some()))
+    {
+        authz_middleware_tokens()
+    } else {
+        quote! {}
+    };
+
+    let negotiation = if negotiate {
+        negotiation_tokens(&config.generation.response_formats)
+    } else {
+        quote! {}
+    };
+
     let mod_rs_tokens = quote! {
         // @generated by intent-engine v1.0
         // DO NOT EDIT — changes will be overwritten
@@ -56,6 +77,10 @@ pub fn generate_endpoints(store: &IntentStore) -> EndpointsOutput {
             Router::new()
                 #(#router_routes)*
         }
+
+        #negotiation
+
+        #authz
     };
 
     let file = syn::parse2(mod_rs_tokens).expect("Failed to parse mod.rs");
@@ -67,12 +92,12 @@ pub fn generate_endpoints(store: &IntentStore) -> EndpointsOutput {
             continue;
         };
 
-        let mod_name = to_snake_case(&doc.name);
+        let mod_name = to_ident(&doc.name);
         let handler_ident = format_ident!("{}", mod_name);
         let input_type = format_ident!("{}", &spec.input);
         let output_type = format_ident!("{}", &spec.output);
-        let workflow_mod = format_ident!("{}", to_snake_case(&spec.workflow));
-        let workflow_fn = format_ident!("{}", to_snake_case(&spec.workflow));
+        let workflow_mod = format_ident!("{}", to_ident(&spec.workflow));
+        let workflow_fn = format_ident!("{}", to_ident(&spec.workflow));
         let error_type = format_ident!("{}Error", &doc.name);
 
         // Generate timeout middleware if specified
@@ -85,28 +110,165 @@ pub fn generate_endpoints(store: &IntentStore) -> EndpointsOutput {
             quote! {}
         };
 
-        let file_tokens = quote! {
-            // @generated by intent-engine v1.0
-            // DO NOT EDIT — changes will be overwritten
-            // source: #mod_name
+        // Retried workflow calls clone the input for each attempt
+        let retryable = retryable_pattern(store, &spec, &error_type);
+        let (workflow_call, retry_helper) = match (&spec.policies.retries, retryable) {
+            (Some(policy), Some(retryable)) => (
+                quote! {
+                    with_retry(|| #workflow_mod::#workflow_fn(input.clone()), is_retryable).await?
+                },
+                retry_helper_tokens(policy, spec.policies.timeout_ms, &retryable, config.generation.target),
+            ),
+            _ => (quote! { #workflow_mod::#workflow_fn(input).await? }, quote! {}),
+        };
+
+        // Enum outputs come from the enums module; a variant with a declared
+        // status sets the response status
+        let output_enum = store
+            .get_by_kind_name(IntentKind::Enum, &spec.output)
+            .and_then(|doc| doc.as_enum_spec().ok());
+        let (output_types, output_import) = match &output_enum {
+            Some(_) => (vec![], quote! { use crate::enums::#output_type; }),
+            // Already imported as the input
+            None if spec.output == spec.input => (vec![], quote! {}),
+            None => (vec![&output_type], quote! {}),
+        };
+        let status_match = output_enum
+            .as_ref()
+            .and_then(|enum_spec| variant_status_tokens(&output_type, enum_spec));
+
+        // With negotiation the handler also takes the request headers (before
+        // the body extractor) and wraps its output
+        let (headers_arg, response_type, response, negotiated_import) = if negotiate {
+            (
+                quote! { headers: HeaderMap, },
+                quote! { Negotiated<#output_type> },
+                quote! { Negotiated::new(&headers, result) },
+                quote! {
+                    use axum::http::HeaderMap;
+                    use super::Negotiated;
+                },
+            )
+        } else {
+            (
+                quote! {},
+                quote! { Json<#output_type> },
+                quote! { Json(result) },
+                quote! {},
+            )
+        };
+
+        let (response_type, response, status_binding, status_import) = match status_match {
+            Some(status_match) => (
+                quote! { (StatusCode, #response_type) },
+                quote! { (status, #response) },
+                quote! { let status = #status_match; },
+                quote! { use axum::http::StatusCode; },
+            ),
+            None => (response_type, response, quote! {}, quote! {}),
+        };
+
+        let file_tokens = if spec.partial {
+            // The current value is read from the input type's table, keyed by
+            // the path parameters; the handler merges the fields present in
+            // the patch.
+            let patch_type = format_ident!("{}Patch", &spec.input);
+            let table = to_snake_case(&spec.input);
+            let has_path_params = spec.path.split('/').any(|segment| segment.starts_with(':'));
+            let (key_arg, key, path_import) = if has_path_params {
+                (
+                    quote! { Path(key): Path<HashMap<String, String>>, },
+                    quote! { key },
+                    quote! {
+                        use axum::extract::Path;
+                        use std::collections::HashMap;
+                    },
+                )
+            } else {
+                (quote! {}, quote! { () }, quote! {})
+            };
+            quote! {
+                // @generated by intent-engine v1.0
+                // DO NOT EDIT — changes will be overwritten
+                // source: #mod_name
+
+                use axum::Json;
+                #path_import
+                #status_import
+                #negotiated_import
+                use crate::types::{#input_type, #patch_type #(, #output_types)*};
+                #output_import
+                use crate::effects::db;
+                use crate::workflows::#workflow_mod;
+                use crate::errors::#error_type;
+
+                pub async fn #handler_ident(
+                    #key_arg
+                    #headers_arg
+                    Json(patch): Json<#patch_type>,
+                ) -> Result<#response_type, #error_type> {
+                    #timeout_layer
+
+                    let mut input: #input_type = db::read(#table, &#key)
+                        .await
+                        .map_err(anyhow::Error::from)?;
+                    patch.apply(&mut input);
+
+                    let result = #workflow_call;
+                    #status_binding
+                    Ok(#response)
+                }
+
+                #retry_helper
+            }
+        } else {
+            // Requests leave out server-assigned fields, which start unset
+            let has_readonly = store
+                .get_by_kind_name(IntentKind::Type, &spec.input)
+                .and_then(|doc| doc.as_type_spec().ok())
+                .is_some_and(|input_spec| input_spec.has_readonly_fields());
+            let (body_type, body_import, into_input) = if has_readonly {
+                let body_type = format_ident!("{}Input", &spec.input);
+                (
+                    quote! { #body_type },
+                    quote! { , #body_type },
+                    quote! { let input: #input_type = input.into(); },
+                )
+            } else {
+                (quote! { #input_type }, quote! {}, quote! {})
+            };
+            quote! {
+                // @generated by intent-engine v1.0
+                // DO NOT EDIT — changes will be overwritten
+                // source: #mod_name
+
+                use axum::{extract::State, Json};
+                #status_import
+                #negotiated_import
+                use crate::types::{#input_type #body_import #(, #output_types)*};
+                #output_import
+                use crate::workflows::#workflow_mod;
+                use crate::errors::#error_type;
 
-            use axum::{extract::State, Json};
-            use crate::types::{#input_type, #output_type};
-            use crate::workflows::#workflow_mod;
-            use crate::errors::#error_type;
+                pub async fn #handler_ident(
+                    #headers_arg
+                    Json(input): Json<#body_type>,
+                ) -> Result<#response_type, #error_type> {
+                    #timeout_layer
 
-            pub async fn #handler_ident(
-                Json(input): Json<#input_type>,
-            ) -> Result<Json<#output_type>, #error_type> {
-                #timeout_layer
+                    #into_input
+                    let result = #workflow_call;
+                    #status_binding
+                    Ok(#response)
+                }
 
-                let result = #workflow_mod::#workflow_fn(input).await?;
-                Ok(Json(result))
+                #retry_helper
             }
         };
 
         let file = syn::parse2(file_tokens).expect("Failed to parse endpoint file");
         output.files.push(EndpointFile {
+            intent_id: doc.id,
             name: format!("{}.rs", mod_name),
             content: prettyplease::unparse(&file),
         });
@@ -115,39 +277,332 @@ pub fn generate_endpoints(store: &IntentStore) -> EndpointsOutput {
     output
 }
 
+/// `match` from each variant of the output enum to its response status, or
+/// `None` if no variant declares one. Undeclared variants answer 200 OK.
+fn variant_status_tokens(enum_ident: &proc_macro2::Ident, spec: &EnumSpec) -> Option<TokenStream> {
+    if spec.variants.iter().all(|v| v.status.is_none()) {
+        return None;
+    }
+
+    let arms = spec.variants.iter().map(|variant| {
+        let variant_ident = format_ident!("{}", variant.name);
+        let pattern = match &variant.data {
+            Some(VariantData::Named(_)) => quote! { #enum_ident::#variant_ident { .. } },
+            Some(VariantData::Tuple(_)) => quote! { #enum_ident::#variant_ident(..) },
+            None => quote! { #enum_ident::#variant_ident },
+        };
+        let status = proc_macro2::Literal::u16_unsuffixed(variant.status.unwrap_or(200));
+        quote! {
+            #pattern => StatusCode::from_u16(#status).expect("validated HTTP status"),
+        }
+    });
+
+    Some(quote! {
+        match &result {
+            #(#arms)*
+        }
+    })
+}
+
+/// Body of `is_retryable`, matching the workflow errors declared `retryable`:
+/// the endpoint's error type, or its shared error enum, downcast from the
+/// workflow's `anyhow::Error`. `None` if no declared error is retryable.
+fn retryable_pattern(
+    store: &IntentStore,
+    spec: &EndpointSpec,
+    error_type: &proc_macro2::Ident,
+) -> Option<TokenStream> {
+    let codes: Vec<&str> = spec
+        .errors
+        .iter()
+        .filter(|error| error.retryable)
+        .map(|error| error.code.as_str())
+        .collect();
+    if codes.is_empty() {
+        return None;
+    }
+
+    let shared = spec.error_enum.as_deref().and_then(|name| {
+        let enum_spec = store.get_by_kind_name(IntentKind::Enum, name)?.as_enum_spec().ok()?;
+        Some((format_ident!("{}", name), enum_spec))
+    });
+    let (error_type, variants): (_, Vec<_>) = match &shared {
+        Some((enum_ident, enum_spec)) => (
+            enum_ident,
+            codes
+                .iter()
+                .filter_map(|code| enum_spec.find_variant(code))
+                .map(|variant| format_ident!("{}", to_pascal_case(&variant.name)))
+                .collect(),
+        ),
+        None => (
+            error_type,
+            codes
+                .iter()
+                .map(|code| format_ident!("{}", to_pascal_case(code)))
+                .collect(),
+        ),
+    };
+    if variants.is_empty() {
+        return None;
+    }
+
+    Some(quote! {
+        match error.downcast_ref::<crate::errors::#error_type>() {
+            Some(#(crate::errors::#error_type::#variants)|*) => true,
+            _ => false,
+        }
+    })
+}
+
+/// `with_retry` for an endpoint with a retry policy. Delays grow from a base
+/// of a tenth of the endpoint's timeout (100ms without one), and are slept
+/// on tokio natively and on a browser timer on wasm32.
+fn retry_helper_tokens(
+    policy: &RetryPolicy,
+    timeout_ms: Option<u32>,
+    retryable: &TokenStream,
+    target: GenTarget,
+) -> TokenStream {
+    let max = proc_macro2::Literal::u32_unsuffixed(policy.max);
+    let base_ms =
+        proc_macro2::Literal::u64_unsuffixed(timeout_ms.map_or(100, |timeout| (u64::from(timeout) / 10).max(1)));
+    let (strategy, attempt, delay) = match policy.backoff {
+        BackoffStrategy::Constant => ("constant", quote! { _attempt }, quote! { BACKOFF_BASE_MS }),
+        BackoffStrategy::Linear => (
+            "linear",
+            quote! { attempt },
+            quote! { BACKOFF_BASE_MS * u64::from(attempt) },
+        ),
+        BackoffStrategy::Exponential => (
+            "exponential",
+            quote! { attempt },
+            quote! { BACKOFF_BASE_MS.saturating_mul(1 << (attempt - 1).min(16)) },
+        ),
+    };
+    let policy_doc = format!(" Retry policy: up to {} retries with {} backoff", policy.max, strategy);
+    let sleep = match target {
+        GenTarget::Native => quote! {
+            tokio::time::sleep(backoff(attempt)).await;
+        },
+        GenTarget::Wasm => quote! {
+            #[cfg(not(target_arch = "wasm32"))]
+            tokio::time::sleep(backoff(attempt)).await;
+            #[cfg(target_arch = "wasm32")]
+            gloo_timers::future::sleep(backoff(attempt)).await;
+        },
+    };
+
+    quote! {
+        #[doc = #policy_doc]
+        const MAX_RETRIES: u32 = #max;
+        const BACKOFF_BASE_MS: u64 = #base_ms;
+
+        /// Run `operation` until it succeeds, fails with an error `retryable`
+        /// rejects, or `MAX_RETRIES` retries have failed
+        async fn with_retry<T, F, Fut>(
+            mut operation: F,
+            retryable: impl Fn(&anyhow::Error) -> bool,
+        ) -> Result<T, anyhow::Error>
+        where
+            F: FnMut() -> Fut,
+            Fut: std::future::Future<Output = Result<T, anyhow::Error>>,
+        {
+            let mut attempt = 0;
+            loop {
+                match operation().await {
+                    Err(error) if attempt < MAX_RETRIES && retryable(&error) => {
+                        attempt += 1;
+                        #sleep
+                    }
+                    result => return result,
+                }
+            }
+        }
+
+        /// Whether a failed workflow call raised an error declared retryable
+        fn is_retryable(error: &anyhow::Error) -> bool {
+            #retryable
+        }
+
+        /// Delay before retry number `attempt`, counting from 1
+        fn backoff(#attempt: u32) -> std::time::Duration {
+            std::time::Duration::from_millis(#delay)
+        }
+    }
+}
+
+/// `.layer(...)` on an endpoint's method router that enforces its scopes
+fn authz_layer_tokens(authz: &AuthzConfig) -> TokenStream {
+    let scopes = authz.scope.as_slice();
+    let requirement = match authz.require {
+        ScopeRequirement::All => quote! { ScopeRequirement::All },
+        ScopeRequirement::Any => quote! { ScopeRequirement::Any },
+    };
+    quote! {
+        .layer(axum::middleware::from_fn(
+            |request: axum::extract::Request, next: axum::middleware::Next| {
+                require_scopes(&[#(#scopes),*], #requirement, request, next)
+            },
+        ))
+    }
+}
+
+/// Scope-checking middleware for endpoints with `authz`. The caller's scopes
+/// come from `GrantedScopes`, put in the request extensions by whatever
+/// authenticates the principal in front of the router.
+fn authz_middleware_tokens() -> TokenStream {
+    quote! {
+        /// Scopes granted to the authenticated caller, inserted into the
+        /// request extensions by the authentication layer
+        #[derive(Debug, Clone, Default)]
+        pub struct GrantedScopes(pub Vec<String>);
+
+        /// Whether an endpoint needs all of its scopes or any one of them
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum ScopeRequirement {
+            All,
+            Any,
+        }
+
+        /// Answer 401 when no scopes were granted and 403 when the granted
+        /// scopes don't meet `requirement`
+        pub async fn require_scopes(
+            required: &'static [&'static str],
+            requirement: ScopeRequirement,
+            request: axum::extract::Request,
+            next: axum::middleware::Next,
+        ) -> axum::response::Response {
+            use axum::response::IntoResponse;
+
+            let Some(granted) = request.extensions().get::<GrantedScopes>() else {
+                return axum::http::StatusCode::UNAUTHORIZED.into_response();
+            };
+            let holds = |scope: &&str| granted.0.iter().any(|g| g == scope);
+            let allowed = match requirement {
+                ScopeRequirement::All => required.iter().all(holds),
+                ScopeRequirement::Any => required.iter().any(holds),
+            };
+            if !allowed {
+                return axum::http::StatusCode::FORBIDDEN.into_response();
+            }
+            next.run(request).await
+        }
+    }
+}
+
+/// `Negotiated<T>` response wrapper, serializing per the `Accept` header with
+/// a JSON fallback; only the configured formats get a branch
+fn negotiation_tokens(formats: &[ResponseFormat]) -> TokenStream {
+    let mut variants = Vec::new();
+    let mut detects = Vec::new();
+    let mut encodes = Vec::new();
+
+    for format in formats {
+        match format {
+            ResponseFormat::Json => {}
+            ResponseFormat::Msgpack => {
+                variants.push(quote! { Msgpack, });
+                detects.push(quote! {
+                    if accept.contains("application/msgpack") || accept.contains("application/x-msgpack") {
+                        return ResponseFormat::Msgpack;
+                    }
+                });
+                encodes.push(quote! {
+                    ResponseFormat::Msgpack => match rmp_serde::to_vec_named(&self.value) {
+                        Ok(body) => ([(header::CONTENT_TYPE, "application/msgpack")], body).into_response(),
+                        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+                    },
+                });
+            }
+            ResponseFormat::Cbor => {
+                variants.push(quote! { Cbor, });
+                detects.push(quote! {
+                    if accept.contains("application/cbor") {
+                        return ResponseFormat::Cbor;
+                    }
+                });
+                encodes.push(quote! {
+                    ResponseFormat::Cbor => {
+                        let mut body = Vec::new();
+                        match ciborium::into_writer(&self.value, &mut body) {
+                            Ok(()) => ([(header::CONTENT_TYPE, "application/cbor")], body).into_response(),
+                            Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    quote! {
+        use axum::http::{header, HeaderMap, StatusCode};
+        use axum::response::{IntoResponse, Response};
+
+        /// Response formats the handlers can produce
+        #[derive(Debug, Clone, Copy)]
+        enum ResponseFormat {
+            Json,
+            #(#variants)*
+        }
+
+        impl ResponseFormat {
+            /// Pick a format from the `Accept` header, falling back to JSON
+            fn from_headers(headers: &HeaderMap) -> Self {
+                let accept = headers
+                    .get(header::ACCEPT)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default();
+                #(#detects)*
+                ResponseFormat::Json
+            }
+        }
+
+        /// Handler output serialized in the format the client asked for
+        pub struct Negotiated<T> {
+            format: ResponseFormat,
+            value: T,
+        }
+
+        impl<T> Negotiated<T> {
+            pub fn new(headers: &HeaderMap, value: T) -> Self {
+                Self {
+                    format: ResponseFormat::from_headers(headers),
+                    value,
+                }
+            }
+        }
+
+        impl<T: serde::Serialize> IntoResponse for Negotiated<T> {
+            fn into_response(self) -> Response {
+                match self.format {
+                    ResponseFormat::Json => axum::Json(self.value).into_response(),
+                    #(#encodes)*
+                }
+            }
+        }
+    }
+}
+
 pub struct EndpointsOutput {
     pub mod_rs: String,
     pub files: Vec<EndpointFile>,
 }
 
 pub struct EndpointFile {
+    pub intent_id: uuid::Uuid,
     pub name: String,
     pub content: String,
 }
 
-fn to_snake_case(s: &str) -> String {
-    let mut result = String::new();
-    for (i, c) in s.chars().enumerate() {
-        if c.is_uppercase() {
-            if i > 0 {
-                result.push('_');
-            }
-            result.push(c.to_lowercase().next().unwrap());
-        } else {
-            result.push(c);
-        }
-    }
-    result
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_to_snake_case() {
-        assert_eq!(to_snake_case("CreateRefund"), "create_refund");
-        assert_eq!(to_snake_case("HTTPHandler"), "h_t_t_p_handler");
-        assert_eq!(to_snake_case("test"), "test");
+    fn test_to_ident() {
+        assert_eq!(to_ident("CreateRefund"), "create_refund");
+        assert_eq!(to_ident("HTTPHandler"), "http_handler");
+        assert_eq!(to_ident("test"), "test");
     }
 }
@@ -1,114 +1,258 @@
 //! Code generation for Enum intents
 
-use crate::model::VariantData;
+use anyhow::Context;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+
+use crate::model::{EnumSpec, TypeRef, VariantData};
 use crate::parser::IntentStore;
 
+use super::types::type_ref_to_tokens;
+use super::{escape_ident, KeywordEscape};
+
+/// Output from enum generation
+pub struct EnumsOutput {
+    /// `enums.rs`, with one `pub enum` per Enum intent
+    pub enums_rs: String,
+    /// Enums the file was generated from
+    pub intent_ids: Vec<uuid::Uuid>,
+}
+
 /// Generate Rust code for all Enum intents
-pub fn generate_enums(store: &IntentStore) -> String {
-    let mut output = String::new();
+pub fn generate_enums(store: &IntentStore) -> anyhow::Result<EnumsOutput> {
+    let mut enums: Vec<_> = store
+        .enums()
+        .into_iter()
+        .filter_map(|doc| Some((doc, doc.as_enum_spec().ok()?)))
+        .collect();
+    enums.sort_by(|a, b| a.0.name.cmp(&b.0.name));
 
-    output.push_str("// @generated by intent-engine v2.0\n");
-    output.push_str("// DO NOT EDIT - changes will be overwritten\n\n");
+    // Variant data may name Types
+    let types_import = if store.types().is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            #[allow(unused_imports)]
+            use crate::types::*;
+        }
+    };
+    let items = enums
+        .iter()
+        .map(|(doc, spec)| {
+            enum_tokens(&doc.name, spec).map_err(|message| anyhow::anyhow!("Enum '{}': {}", doc.name, message))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
 
-    output.push_str("use serde::{Deserialize, Serialize};\n\n");
+    let tokens = quote! {
+        // @generated by intent-engine v2.0
+        // DO NOT EDIT - changes will be overwritten
 
-    let mut enums: Vec<_> = store.enums().into_iter().collect();
-    enums.sort_by(|a, b| a.name.cmp(&b.name));
+        use serde::{Deserialize, Serialize};
+        #types_import
 
-    for doc in enums {
-        if let Ok(spec) = doc.as_enum_spec() {
-            output.push_str(&generate_enum(&doc.name, &spec));
-            output.push('\n');
-        }
-    }
+        #(#items)*
+    };
 
-    output
+    let file = syn::parse2(tokens).context("Failed to parse enums.rs")?;
+    Ok(EnumsOutput {
+        enums_rs: prettyplease::unparse(&file),
+        intent_ids: enums.iter().map(|(doc, _)| doc.id).collect(),
+    })
 }
 
-fn generate_enum(name: &str, spec: &crate::model::EnumSpec) -> String {
-    let mut output = String::new();
+/// Derives, generic parameters and bounds, variant names and variant data
+/// of `spec` that are not valid Rust, as `(JSON path, message)` pairs.
+/// Validation reports these as E013; generation refuses the enum.
+pub fn check_enum_rust(spec: &EnumSpec) -> Vec<(String, String)> {
+    let mut errors = Vec::new();
+    let mut check = |path: String, parsed: Result<TokenStream, String>| {
+        if let Err(message) = parsed {
+            errors.push((path, message));
+        }
+    };
 
-    // Doc comment
-    if !spec.description.is_empty() {
-        output.push_str(&format!("/// {}\n", spec.description));
+    for (i, derive) in spec.derives.iter().enumerate() {
+        check(format!("$.spec.derives[{}]", i), parse_rust::<syn::Path>(derive, "derive"));
     }
+    for (i, param) in spec.generics.iter().enumerate() {
+        check(
+            format!("$.spec.generics[{}].name", i),
+            parse_rust::<syn::Ident>(&param.name, "generic parameter"),
+        );
+        for (j, bound) in param.bounds.iter().enumerate() {
+            check(
+                format!("$.spec.generics[{}].bounds[{}]", i, j),
+                parse_rust::<syn::TypeParamBound>(bound, "bound"),
+            );
+        }
+    }
+    for (i, variant) in spec.variants.iter().enumerate() {
+        // Empty names are reported as E002
+        if !variant.name.is_empty() {
+            check(
+                format!("$.spec.variants[{}].name", i),
+                parse_rust::<syn::Ident>(&variant.name, "variant name"),
+            );
+        }
+        match &variant.data {
+            None => {}
+            Some(VariantData::Named(fields)) => {
+                for (field_name, field_type) in fields {
+                    let path = format!("$.spec.variants[{}].data.{}", i, field_name);
+                    check(path.clone(), field_ident_tokens(field_name));
+                    check(path, variant_type_tokens(field_type));
+                }
+            }
+            Some(VariantData::Tuple(types)) => {
+                for (j, field_type) in types.iter().enumerate() {
+                    check(
+                        format!("$.spec.variants[{}].data[{}]", i, j),
+                        variant_type_tokens(field_type),
+                    );
+                }
+            }
+        }
+    }
+    errors
+}
 
-    // Derives
-    let derives = if spec.derives.is_empty() {
+/// The enum's Rust source; the error is the first string in `spec` that
+/// `check_enum_rust` reports
+fn enum_tokens(name: &str, spec: &EnumSpec) -> Result<TokenStream, String> {
+
+    let enum_ident = format_ident!("{}", name);
+    let description = (!spec.description.is_empty()).then(|| {
+        let doc = format!(" {}", spec.description);
+        quote! { #[doc = #doc] }
+    });
+
+    let derives: Vec<TokenStream> = if spec.derives.is_empty() {
         vec![
-            "Debug".to_string(),
-            "Clone".to_string(),
-            "PartialEq".to_string(),
-            "Eq".to_string(),
-            "Serialize".to_string(),
-            "Deserialize".to_string(),
+            quote! { Debug },
+            quote! { Clone },
+            quote! { PartialEq },
+            quote! { Eq },
+            quote! { Serialize },
+            quote! { Deserialize },
         ]
     } else {
-        spec.derives.clone()
+        spec.derives
+            .iter()
+            .map(|derive| parse_rust::<syn::Path>(derive, "derive"))
+            .collect::<Result<_, _>>()?
     };
-    output.push_str(&format!("#[derive({})]\n", derives.join(", ")));
 
-    // Serde tag attribute
-    if let Some(tag) = &spec.serde_tag {
-        output.push_str(&format!("#[serde(tag = \"{}\")]\n", tag));
-    }
+    let serde_tag = spec.serde_tag.as_ref().map(|tag| quote! { #[serde(tag = #tag)] });
 
-    // Generic parameters
     let generics = if spec.generics.is_empty() {
-        String::new()
+        quote! {}
     } else {
-        let params: Vec<String> = spec
+        let params = spec
             .generics
             .iter()
-            .map(|g| {
-                if g.bounds.is_empty() {
-                    g.name.clone()
+            .map(|param| {
+                let param_ident = parse_rust::<syn::Ident>(&param.name, "generic parameter")?;
+                if param.bounds.is_empty() {
+                    Ok(quote! { #param_ident })
                 } else {
-                    format!("{}: {}", g.name, g.bounds.join(" + "))
+                    let bounds = param
+                        .bounds
+                        .iter()
+                        .map(|bound| parse_rust::<syn::TypeParamBound>(bound, "bound"))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(quote! { #param_ident: #(#bounds)+* })
                 }
             })
-            .collect();
-        format!("<{}>", params.join(", "))
+            .collect::<Result<Vec<_>, String>>()?;
+        quote! { <#(#params),*> }
     };
 
-    output.push_str(&format!("pub enum {}{} {{\n", name, generics));
-
-    // Variants
-    for variant in &spec.variants {
-        // Doc comment
-        if !variant.description.is_empty() {
-            output.push_str(&format!("    /// {}\n", variant.description));
-        }
-
-        // Serde rename
-        if let Some(rename) = &variant.serde_rename {
-            output.push_str(&format!("    #[serde(rename = \"{}\")]\n", rename));
-        }
+    let variants = spec.variants.iter().map(|variant| {
+        let variant_ident = parse_rust::<syn::Ident>(&variant.name, "variant name")?;
+        let description = (!variant.description.is_empty()).then(|| {
+            let doc = format!(" {}", variant.description);
+            quote! { #[doc = #doc] }
+        });
+        let rename = variant
+            .serde_rename
+            .as_ref()
+            .map(|rename| quote! { #[serde(rename = #rename)] });
 
-        // Variant definition
-        match &variant.data {
-            None => {
-                output.push_str(&format!("    {},\n", variant.name));
-            }
+        let data = match &variant.data {
+            None => quote! {},
             Some(VariantData::Named(fields)) => {
-                output.push_str(&format!("    {} {{\n", variant.name));
-                for (field_name, field_type) in fields {
-                    output.push_str(&format!("        {}: {},\n", field_name, field_type));
-                }
-                output.push_str("    },\n");
+                let mut fields: Vec<_> = fields.iter().collect();
+                fields.sort();
+                let fields = fields
+                    .into_iter()
+                    .map(|(field_name, field_type)| {
+                        let field_ident = field_ident_tokens(field_name)?;
+                        let field_type = variant_type_tokens(field_type)?;
+                        Ok(quote! { #field_ident: #field_type })
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                quote! { { #(#fields),* } }
             }
             Some(VariantData::Tuple(types)) => {
-                output.push_str(&format!("    {}({}),\n", variant.name, types.join(", ")));
+                let types = types
+                    .iter()
+                    .map(|t| variant_type_tokens(t))
+                    .collect::<Result<Vec<_>, _>>()?;
+                quote! { ( #(#types),* ) }
             }
+        };
+
+        Ok(quote! {
+            #description
+            #rename
+            #variant_ident #data,
+        })
+    });
+    let variants = variants.collect::<Result<Vec<_>, String>>()?;
+
+    Ok(quote! {
+        #description
+        #[derive(#(#derives),*)]
+        #serde_tag
+        pub enum #enum_ident #generics {
+            #(#variants)*
         }
+    })
+}
+
+/// A variant field's type: intent types (`uuid`, `array<string>`, named
+/// Types, generic parameters) go through `TypeRef`; anything else must be a
+/// Rust type
+fn variant_type_tokens(type_name: &str) -> Result<TokenStream, String> {
+    match TypeRef::parse(type_name) {
+        Ok(type_ref)
+            if type_ref
+                .get_named_references()
+                .iter()
+                .all(|name| syn::parse_str::<syn::Ident>(name).is_ok()) =>
+        {
+            Ok(type_ref_to_tokens(&type_ref))
+        }
+        _ => parse_rust::<syn::Type>(type_name, "type"),
     }
+}
 
-    output.push_str("}\n");
+/// A named variant field, raw-escaped if it is a keyword
+fn field_ident_tokens(field_name: &str) -> Result<TokenStream, String> {
+    parse_rust::<syn::Ident>(&escape_ident(field_name, KeywordEscape::Raw), "field name")
+        .map_err(|_| format!("'{}' is not a valid Rust field name", field_name))
+}
 
-    output
+/// User-written Rust from the spec, parsed as a `T`
+fn parse_rust<T: syn::parse::Parse + ToTokens>(source: &str, what: &str) -> Result<TokenStream, String> {
+    syn::parse_str::<T>(source)
+        .map(|parsed| parsed.into_token_stream())
+        .map_err(|_| format!("'{}' is not a valid Rust {}", source, what))
 }
 
 /// Generate Rust code for a single Enum spec (for inline generation)
-pub fn generate_enum_inline(name: &str, spec: &crate::model::EnumSpec) -> String {
-    generate_enum(name, spec)
+pub fn generate_enum_inline(name: &str, spec: &EnumSpec) -> anyhow::Result<String> {
+    let tokens = enum_tokens(name, spec).map_err(|message| anyhow::anyhow!("Enum '{}': {}", name, message))?;
+    let file = syn::parse2(tokens).context("Failed to parse enum")?;
+    Ok(prettyplease::unparse(&file))
 }
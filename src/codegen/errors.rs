@@ -1,17 +1,33 @@
 //! Error types code generation
 
+use std::collections::BTreeSet;
+
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
-use crate::parser::IntentStore;
+use crate::model::{EndpointError, EnumSpec, IntentKind};
+use crate::parser::{IntentConfig, IntentStore};
+use super::naming::to_pascal_case;
 
 /// Generate errors.rs content
-pub fn generate_errors(store: &IntentStore) -> String {
+pub fn generate_errors(store: &IntentStore, config: &IntentConfig) -> String {
+    let comparable = config.generation.comparable_errors;
     let mut endpoints: Vec<_> = store.endpoints().into_iter().collect();
     endpoints.sort_by(|a, b| a.name.cmp(&b.name));
 
     let mut error_enums = Vec::new();
 
+    // Shared error code enums, emitted once each ahead of the endpoint errors
+    let shared: BTreeSet<String> = endpoints
+        .iter()
+        .filter_map(|doc| doc.as_endpoint_spec().ok()?.error_enum)
+        .collect();
+    for enum_name in &shared {
+        if let Some(spec) = shared_enum_spec(store, enum_name) {
+            error_enums.push(shared_enum_tokens(enum_name, &spec));
+        }
+    }
+
     for doc in &endpoints {
         let Ok(spec) = doc.as_endpoint_spec() else {
             continue;
@@ -19,6 +35,12 @@ pub fn generate_errors(store: &IntentStore) -> String {
 
         let error_name = format_ident!("{}Error", &doc.name);
 
+        if let Some(enum_spec) = spec.error_enum.as_deref().and_then(|name| shared_enum_spec(store, name)) {
+            let enum_name = spec.error_enum.as_deref().unwrap_or_default();
+            error_enums.push(enum_backed_error(&error_name, enum_name, &enum_spec, &spec.errors, comparable));
+            continue;
+        }
+
         // Build error variants
         let mut variants = Vec::new();
         let mut status_arms = Vec::new();
@@ -58,21 +80,25 @@ pub fn generate_errors(store: &IntentStore) -> String {
         }
 
         // Add anyhow error variant
-        variants.push(quote! {
-            #[error("internal error: {0}")]
-            Internal(#[from] anyhow::Error),
-        });
+        let InternalVariant {
+            derives,
+            variant,
+            from_impl,
+        } = internal_variant(&error_name, comparable);
+        variants.push(variant);
 
         status_arms.push(quote! {
             Self::Internal(_) => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
         });
 
         error_enums.push(quote! {
-            #[derive(Debug, thiserror::Error)]
+            #derives
             pub enum #error_name {
                 #(#variants)*
             }
 
+            #from_impl
+
             impl axum::response::IntoResponse for #error_name {
                 fn into_response(self) -> axum::response::Response {
                     let status = match &self {
@@ -102,16 +128,152 @@ pub fn generate_errors(store: &IntentStore) -> String {
     prettyplease::unparse(&file)
 }
 
-fn to_pascal_case(s: &str) -> String {
-    s.split('_')
-        .map(|word| {
-            let mut chars = word.chars();
-            match chars.next() {
-                None => String::new(),
-                Some(c) => c.to_uppercase().chain(chars.flat_map(|c| c.to_lowercase())).collect(),
+fn shared_enum_spec(store: &IntentStore, name: &str) -> Option<EnumSpec> {
+    store
+        .get_by_kind_name(IntentKind::Enum, name)?
+        .as_enum_spec()
+        .ok()
+}
+
+/// A shared error code enum: one unit variant per Enum variant, plus `code()`
+/// returning the code as written in the model
+fn shared_enum_tokens(name: &str, spec: &EnumSpec) -> TokenStream {
+    let enum_ident = format_ident!("{}", name);
+    let doc = format!(" Shared error codes from the `{}` enum", name);
+    let mut variants = Vec::new();
+    let mut code_arms = Vec::new();
+
+    for variant in &spec.variants {
+        let ident = format_ident!("{}", to_pascal_case(&variant.name));
+        let code = variant.serde_rename.as_deref().unwrap_or(&variant.name);
+        let display_msg = code.replace('_', " ").to_lowercase();
+        variants.push(quote! {
+            #[error(#display_msg)]
+            #ident,
+        });
+        code_arms.push(quote! { Self::#ident => #code, });
+    }
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+        pub enum #enum_ident {
+            #(#variants)*
+        }
+
+        impl #enum_ident {
+            /// The code as declared in the model
+            pub fn code(&self) -> &'static str {
+                match self {
+                    #(#code_arms)*
+                }
             }
-        })
-        .collect()
+        }
+    }
+}
+
+/// Endpoint error type wrapping a shared code enum; only the endpoint's
+/// declared codes get their own status, the rest are 500s
+fn enum_backed_error(
+    error_name: &proc_macro2::Ident,
+    enum_name: &str,
+    enum_spec: &EnumSpec,
+    errors: &[EndpointError],
+    comparable: bool,
+) -> TokenStream {
+    let enum_ident = format_ident!("{}", enum_name);
+    let mut status_arms = Vec::new();
+    let mut covered = BTreeSet::new();
+
+    for error in errors {
+        let Some(variant) = enum_spec.find_variant(&error.code) else {
+            continue;
+        };
+        if !covered.insert(variant.name.clone()) {
+            continue;
+        }
+        let ident = format_ident!("{}", to_pascal_case(&variant.name));
+        let status = error.status;
+        status_arms.push(quote! {
+            Self::Code(#enum_ident::#ident) => axum::http::StatusCode::from_u16(#status)
+                .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR),
+        });
+    }
+    if covered.len() < enum_spec.variants.len() {
+        status_arms.push(quote! {
+            Self::Code(_) => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        });
+    }
+
+    let InternalVariant {
+        derives,
+        variant,
+        from_impl,
+    } = internal_variant(error_name, comparable);
+
+    quote! {
+        #derives
+        pub enum #error_name {
+            #[error(transparent)]
+            Code(#[from] #enum_ident),
+            #variant
+        }
+
+        #from_impl
+
+        impl axum::response::IntoResponse for #error_name {
+            fn into_response(self) -> axum::response::Response {
+                let status = match &self {
+                    #(#status_arms)*
+                    Self::Internal(_) => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                };
+
+                let body = serde_json::json!({
+                    "error": self.to_string(),
+                });
+
+                (status, axum::Json(body)).into_response()
+            }
+        }
+    }
+}
+
+/// The derives and `Internal` variant of an endpoint error enum, plus the
+/// `From<anyhow::Error>` impl a comparable enum needs in place of `#[from]`
+struct InternalVariant {
+    derives: TokenStream,
+    variant: TokenStream,
+    from_impl: TokenStream,
+}
+
+/// `anyhow::Error` is neither `Clone` nor `PartialEq`, so a comparable error
+/// enum keeps only the error's message, with its context chain
+fn internal_variant(error_name: &proc_macro2::Ident, comparable: bool) -> InternalVariant {
+    if !comparable {
+        return InternalVariant {
+            derives: quote! { #[derive(Debug, thiserror::Error)] },
+            variant: quote! {
+                #[error("internal error: {0}")]
+                Internal(#[from] anyhow::Error),
+            },
+            from_impl: TokenStream::new(),
+        };
+    }
+
+    InternalVariant {
+        derives: quote! { #[derive(Debug, Clone, PartialEq, thiserror::Error)] },
+        variant: quote! {
+            #[error("internal error: {0}")]
+            Internal(String),
+        },
+        from_impl: quote! {
+            impl From<anyhow::Error> for #error_name {
+                fn from(error: anyhow::Error) -> Self {
+                    Self::Internal(format!("{:#}", error))
+                }
+            }
+        },
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,42 @@
+//! Canonical formatting pass over generated Rust sources
+
+/// Re-print `source` through `prettyplease` so every generated file has the
+/// same layout however it was assembled. The leading `//` header block is
+/// kept, since prettyplease drops plain comments. Returns `None` when
+/// `source` doesn't parse as a Rust file.
+pub fn format_with_rustfmt(source: &str) -> Option<String> {
+    let header: Vec<&str> = source
+        .lines()
+        .take_while(|line| line.starts_with("//") && !line.starts_with("///") && !line.starts_with("//!"))
+        .collect();
+    let file = syn::parse_file(source).ok()?;
+
+    let mut formatted = String::new();
+    if !header.is_empty() {
+        formatted.push_str(&header.join("\n"));
+        formatted.push_str("\n\n");
+    }
+    formatted.push_str(&prettyplease::unparse(&file));
+    Some(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_keeps_header_and_is_idempotent() {
+        let source = "// @generated by intent-engine v1.0\n// DO NOT EDIT\n\npub mod types;\n\n\n\npub fn app()->u8{ 1 }\n";
+        let formatted = format_with_rustfmt(source).unwrap();
+        assert_eq!(
+            formatted,
+            "// @generated by intent-engine v1.0\n// DO NOT EDIT\n\npub mod types;\npub fn app() -> u8 {\n    1\n}\n"
+        );
+        assert_eq!(format_with_rustfmt(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_format_rejects_invalid_rust() {
+        assert_eq!(format_with_rustfmt("pub fn broken( {"), None);
+    }
+}
@@ -1,38 +1,111 @@
 //! Code generation for Function intents
 
-use crate::model::{BinaryOp, Expression, Pattern, UnaryOp};
+use std::collections::HashSet;
+
+use crate::model::{BinaryOp, Expression, FunctionSpec, Pattern, TypeRef, UnaryOp};
 use crate::parser::IntentStore;
+use super::format::format_with_rustfmt;
+use super::naming::to_ident;
+
+/// Methods that take `&mut self`; a `let` binding used as their receiver is
+/// declared `mut`
+const MUTATING_METHODS: &[&str] = &[
+    "append", "clear", "dedup", "extend", "insert", "pop", "push", "push_str", "remove",
+    "retain", "reverse", "sort", "sort_by", "sort_by_key", "truncate",
+];
+
+/// Binding a `starts_with`/`ends_with` match arm gives the scrutinee
+const STRING_ARM_BINDING: &str = "matched";
+
+/// Output from function generation
+pub struct FunctionsOutput {
+    /// `functions.rs`, with one `pub fn` per Function intent
+    pub functions_rs: String,
+    /// Functions the file was generated from
+    pub intent_ids: Vec<uuid::Uuid>,
+}
 
-/// Generate Rust code for all Function intents
-pub fn generate_functions(store: &IntentStore) -> String {
-    let mut output = String::new();
+/// Generate Rust code for all Function intents. Each body is lowered from
+/// its expression tree; a body that raises or uses `?` returns
+/// `Result<_, FunctionError>` unless it already declares a `Result`.
+pub fn generate_functions(store: &IntentStore) -> FunctionsOutput {
+    let mut functions: Vec<_> = store
+        .functions()
+        .into_iter()
+        .filter_map(|doc| Some((doc, doc.as_function_spec().ok()?)))
+        .collect();
+    functions.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+
+    let names: HashSet<String> = functions.iter().map(|(doc, _)| doc.name.clone()).collect();
 
+    let mut output = String::new();
     output.push_str("// @generated by intent-engine v2.0\n");
     output.push_str("// DO NOT EDIT - changes will be overwritten\n\n");
+    if !store.types().is_empty() {
+        output.push_str("#[allow(unused_imports)]\nuse crate::types::*;\n");
+    }
+    if !store.enums().is_empty() {
+        output.push_str("#[allow(unused_imports)]\nuse crate::enums::*;\n");
+    }
+    output.push('\n');
+    if functions.iter().any(|(_, spec)| ReturnShape::of(spec).uses_function_error()) {
+        output.push_str(FUNCTION_ERROR);
+    }
+    for (doc, spec) in &functions {
+        output.push_str(&generate_function(&doc.name, spec, &names));
+        output.push('\n');
+    }
 
-    let mut functions: Vec<_> = store.functions().into_iter().collect();
-    functions.sort_by(|a, b| a.name.cmp(&b.name));
+    FunctionsOutput {
+        functions_rs: format_with_rustfmt(&output).unwrap_or(output),
+        intent_ids: functions.iter().map(|(doc, _)| doc.id).collect(),
+    }
+}
 
-    for doc in functions {
-        if let Ok(spec) = doc.as_function_spec() {
-            output.push_str(&generate_function(&doc.name, &spec));
-            output.push('\n');
+/// Error type of generated functions that raise or use `?`. Like
+/// `anyhow::Error` it doesn't implement `std::error::Error`, so `?` can
+/// convert any error into it.
+const FUNCTION_ERROR: &str = r#"/// Error raised by a generated function: an error code and optional message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionError {
+    pub code: &'static str,
+    pub message: Option<String>,
+}
+
+impl FunctionError {
+    pub fn new(code: &'static str) -> Self {
+        Self { code, message: None }
+    }
+
+    pub fn with_message(code: &'static str, message: impl std::fmt::Display) -> Self {
+        Self { code, message: Some(message.to_string()) }
+    }
+}
+
+impl std::fmt::Display for FunctionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {}", self.code, message),
+            None => f.write_str(self.code),
         }
     }
+}
 
-    output
+impl<E: std::error::Error> From<E> for FunctionError {
+    fn from(error: E) -> Self {
+        Self::with_message("INTERNAL_ERROR", error)
+    }
 }
 
-fn generate_function(name: &str, spec: &crate::model::FunctionSpec) -> String {
+"#;
+
+fn generate_function(name: &str, spec: &FunctionSpec, functions: &HashSet<String>) -> String {
     let mut output = String::new();
 
-    // Doc comment
-    if !spec.description.is_empty() {
-        output.push_str(&format!("/// {}\n", spec.description));
+    for line in spec.description.lines() {
+        output.push_str(&format!("/// {}\n", line));
     }
 
-    // Function signature
-    let fn_name = to_snake_case(name);
     let generics = if spec.generics.is_empty() {
         String::new()
     } else {
@@ -53,260 +126,552 @@ fn generate_function(name: &str, spec: &crate::model::FunctionSpec) -> String {
     let params: Vec<String> = spec
         .parameters
         .iter()
-        .map(|p| format!("{}: {}", p.name, p.param_type))
+        .map(|p| format!("{}: {}", p.name, rust_type(&p.param_type)))
         .collect();
 
+    let shape = ReturnShape::of(spec);
+    let lowering = Lowering {
+        functions,
+        wraps_ok: shape.wraps_ok,
+        converts_errors: shape.declares_result,
+    };
     output.push_str(&format!(
-        "pub fn {}{}({}) -> {} {{\n",
-        fn_name,
+        "pub fn {}{}({}) -> {} {{\n{}\n}}\n",
+        to_ident(name),
         generics,
         params.join(", "),
-        spec.returns.return_type
+        shape.return_type(),
+        lowering.body(&spec.body)
     ));
 
-    // Function body
-    output.push_str(&generate_expression(&spec.body, 1));
-    output.push_str("\n}\n");
-
     output
 }
 
-fn generate_expression(expr: &Expression, indent: usize) -> String {
-    let indent_str = "    ".repeat(indent);
-
-    match expr {
-        Expression::Literal { value } => match value {
-            serde_json::Value::Null => "None".to_string(),
-            serde_json::Value::Bool(b) => b.to_string(),
-            serde_json::Value::Number(n) => n.to_string(),
-            serde_json::Value::String(s) => {
-                // Check if it's a type reference like TypeRef::String
-                if s.contains("::") {
-                    s.clone()
-                } else {
-                    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
-                }
-            }
-            serde_json::Value::Array(arr) => {
-                let items: Vec<String> = arr
-                    .iter()
-                    .map(|v| {
-                        generate_expression(
-                            &Expression::Literal { value: v.clone() },
-                            indent,
-                        )
-                    })
-                    .collect();
-                format!("vec![{}]", items.join(", "))
-            }
-            serde_json::Value::Object(_) => "/* complex object */".to_string(),
-        },
-
-        Expression::Variable { name } => name.clone(),
+/// How a function's declared return type is generated
+struct ReturnShape {
+    declared: String,
+    raises: bool,
+    declares_result: bool,
+    /// The declared type is wrapped in `Result<_, FunctionError>`
+    wraps_ok: bool,
+}
 
-        Expression::Field { expr, name } => {
-            format!("{}.{}", generate_expression(expr, indent), name)
+impl ReturnShape {
+    fn of(spec: &FunctionSpec) -> Self {
+        // `?` also works on Options, but raising needs a Result
+        let declared = rust_type(&spec.returns.return_type);
+        let raises = contains(&spec.body, is_raise);
+        let fallible = raises || contains(&spec.body, |e| matches!(e, Expression::Try { .. }));
+        let declares_result = declared.starts_with("Result<");
+        let wraps_ok = fallible && !declares_result && (raises || !declared.starts_with("Option<"));
+        Self {
+            declared,
+            raises,
+            declares_result,
+            wraps_ok,
         }
+    }
 
-        Expression::Index { expr, index } => {
-            format!(
-                "{}[{}]",
-                generate_expression(expr, indent),
-                generate_expression(index, indent)
-            )
+    fn return_type(&self) -> String {
+        if self.wraps_ok {
+            format!("Result<{}, FunctionError>", self.declared)
+        } else {
+            self.declared.clone()
         }
+    }
 
-        Expression::Call { function, args } => {
-            let args_str: Vec<String> = args
-                .iter()
-                .map(|a| generate_expression(a, indent))
-                .collect();
-            format!("{}({})", function, args_str.join(", "))
-        }
+    /// Whether the generated function names `FunctionError`
+    fn uses_function_error(&self) -> bool {
+        self.wraps_ok || self.raises || self.declared.contains("FunctionError")
+    }
+}
 
-        Expression::Method { expr, name, args } => {
-            let args_str: Vec<String> = args
-                .iter()
-                .map(|a| generate_expression(a, indent))
-                .collect();
-            format!(
-                "{}.{}({})",
-                generate_expression(expr, indent),
-                name,
-                args_str.join(", ")
-            )
+/// A type as written in the intent: an intent type reference, or a Rust type
+fn rust_type(type_str: &str) -> String {
+    TypeRef::parse(type_str)
+        .map(|t| t.to_rust_type())
+        .unwrap_or_else(|_| type_str.to_string())
+}
+
+fn is_raise(expr: &Expression) -> bool {
+    matches!(expr, Expression::Raise { .. })
+}
+
+fn contains(expr: &Expression, mut predicate: impl FnMut(&Expression) -> bool) -> bool {
+    let mut found = false;
+    expr.walk(&mut |e| found |= predicate(e));
+    found
+}
+
+/// Lowers one function's body to Rust source
+struct Lowering<'a> {
+    /// Function intents, called by their generated names
+    functions: &'a HashSet<String>,
+    /// The declared return type was wrapped in `Result<_, FunctionError>`,
+    /// so returned values are wrapped in `Ok`
+    wraps_ok: bool,
+    /// The function declares its own `Result`; raised errors are converted
+    /// into its error type with `.into()`
+    converts_errors: bool,
+}
+
+impl Lowering<'_> {
+    /// The function body
+    fn body(&self, expr: &Expression) -> String {
+        self.block(expr, self.wraps_ok)
+    }
+
+    /// Contents of a `{ ... }` block whose value is `expr`. With `ok`, the
+    /// value is wrapped in `Ok`, pushed down into `if` and `match` branches.
+    fn block(&self, expr: &Expression, ok: bool) -> String {
+        match expr {
+            Expression::Let { bindings, body } => {
+                let mut lines = self.bindings(bindings, body);
+                lines.push(self.block(body, ok));
+                lines.join("\n")
+            }
+            Expression::Block { exprs } => match exprs.split_last() {
+                Some((last, init)) => {
+                    let mut lines: Vec<String> = init.iter().map(|e| self.statement(e)).collect();
+                    lines.push(self.block(last, ok));
+                    lines.join("\n")
+                }
+                None if ok => "Ok(())".to_string(),
+                None => String::new(),
+            },
+            Expression::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => self.if_else(cond, then_branch, else_branch, ok),
+            Expression::Match { on, arms } => self.match_arms(on, arms, ok),
+            Expression::Return { .. } | Expression::Raise { .. } => self.expression(expr),
+            _ if ok => format!("Ok({})", self.value(expr)),
+            _ => self.value(expr),
         }
+    }
 
-        Expression::Binary { op, left, right } => {
-            let op_str = match op {
-                BinaryOp::Add => "+",
-                BinaryOp::Sub => "-",
-                BinaryOp::Mul => "*",
-                BinaryOp::Div => "/",
-                BinaryOp::Mod => "%",
-                BinaryOp::Eq => "==",
-                BinaryOp::Ne => "!=",
-                BinaryOp::Lt => "<",
-                BinaryOp::Le => "<=",
-                BinaryOp::Gt => ">",
-                BinaryOp::Ge => ">=",
-                BinaryOp::And => "&&",
-                BinaryOp::Or => "||",
-                BinaryOp::Concat => "+", // String concatenation in Rust uses format!
-            };
-            format!(
-                "({} {} {})",
-                generate_expression(left, indent),
-                op_str,
-                generate_expression(right, indent)
-            )
+    fn if_else(&self, cond: &Expression, then_branch: &Expression, else_branch: &Expression, ok: bool) -> String {
+        let head = format!("if {} {{\n{}\n}}", self.expression(cond), self.block(then_branch, ok));
+        match else_branch {
+            Expression::Block { exprs } if exprs.is_empty() && !ok => head,
+            Expression::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => format!("{} else {}", head, self.if_else(cond, then_branch, else_branch, ok)),
+            _ => format!("{} else {{\n{}\n}}", head, self.block(else_branch, ok)),
         }
+    }
 
-        Expression::Unary { op, expr } => {
-            let op_str = match op {
-                UnaryOp::Not => "!",
-                UnaryOp::Neg => "-",
+    fn match_arms(&self, on: &Expression, arms: &[crate::model::MatchArm], ok: bool) -> String {
+        let mut output = format!("match {} {{\n", self.expression(on));
+        for arm in arms {
+            let (pattern, pattern_guard) = arm_pattern(&arm.pattern);
+            let guards: Vec<String> = pattern_guard
+                .into_iter()
+                .chain(arm.guard.as_ref().map(|g| self.expression(g)))
+                .collect();
+            let guard = if guards.is_empty() {
+                String::new()
+            } else {
+                format!(" if {}", guards.join(" && "))
             };
-            format!("{}{}", op_str, generate_expression(expr, indent))
+            let body = match *arm.body {
+                Expression::Let { .. } | Expression::Block { .. } => {
+                    format!("{{\n{}\n}}", self.block(&arm.body, ok))
+                }
+                _ => self.block(&arm.body, ok),
+            };
+            output.push_str(&format!("{}{} => {},\n", pattern, guard, body));
         }
+        output.push('}');
+        output
+    }
 
-        Expression::If {
-            cond,
-            then_branch,
-            else_branch,
-        } => {
-            format!(
-                "if {} {{\n{}{}\n{}}} else {{\n{}{}\n{}}}",
-                generate_expression(cond, indent),
-                indent_str,
-                generate_expression(then_branch, indent + 1),
-                "    ".repeat(indent.saturating_sub(1)),
-                indent_str,
-                generate_expression(else_branch, indent + 1),
-                "    ".repeat(indent.saturating_sub(1))
-            )
+    /// Contents of a block whose value is discarded, such as a loop body
+    fn statements(&self, expr: &Expression) -> String {
+        match expr {
+            Expression::Let { bindings, body } => {
+                let mut lines = self.bindings(bindings, body);
+                lines.push(self.statements(body));
+                lines.join("\n")
+            }
+            Expression::Block { exprs } => exprs
+                .iter()
+                .map(|e| self.statement(e))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => self.statement(expr),
         }
+    }
 
-        Expression::Match { on, arms } => {
-            let mut output = format!("match {} {{\n", generate_expression(on, indent));
-            for arm in arms {
-                output.push_str(&format!(
-                    "{}    {} => {},\n",
-                    "    ".repeat(indent.saturating_sub(1)),
-                    generate_pattern(&arm.pattern),
-                    generate_expression(&arm.body, indent + 1)
-                ));
-            }
-            output.push_str(&format!("{}}}", "    ".repeat(indent.saturating_sub(1))));
-            output
+    fn statement(&self, expr: &Expression) -> String {
+        match expr {
+            Expression::Let { .. } | Expression::Block { .. } => format!("{{\n{}\n}}", self.statements(expr)),
+            _ => format!("{};", self.expression(expr)),
         }
+    }
 
-        Expression::Let { bindings, body } => {
-            let mut output = String::new();
-            for binding in bindings {
+    fn bindings(&self, bindings: &[crate::model::LetBinding], body: &Expression) -> Vec<String> {
+        bindings
+            .iter()
+            .enumerate()
+            .map(|(i, binding)| {
+                let mutated = bindings[i + 1..]
+                    .iter()
+                    .any(|later| mutates(&later.value, &binding.name))
+                    || mutates(body, &binding.name);
                 let type_ann = binding
                     .type_annotation
                     .as_ref()
-                    .map(|t| format!(": {}", t))
+                    .map(|t| format!(": {}", rust_type(t)))
                     .unwrap_or_default();
-                output.push_str(&format!(
-                    "let {}{} = {};\n{}",
+                format!(
+                    "let {}{}{} = {};",
+                    if mutated { "mut " } else { "" },
                     binding.name,
                     type_ann,
-                    generate_expression(&binding.value, indent),
-                    indent_str
-                ));
+                    self.value(&binding.value)
+                )
+            })
+            .collect()
+    }
+
+    fn expression(&self, expr: &Expression) -> String {
+        match expr {
+            Expression::Literal { value } => literal(value),
+
+            Expression::Variable { name } => name.clone(),
+
+            Expression::Field { expr, name } => format!("{}.{}", self.receiver(expr), name),
+
+            Expression::Index { expr, index } => {
+                format!("{}[{}]", self.receiver(expr), self.expression(index))
             }
-            output.push_str(&generate_expression(body, indent));
-            output
-        }
 
-        Expression::For { var, iterable, body } => {
-            format!(
-                "for {} in {} {{\n{}{}\n{}}}",
+            // Arguments of other generated functions and of `Ok`/`Err`/`Some`
+            // are owned values; anything else is called as written
+            Expression::Call { function, args } if self.functions.contains(function) => {
+                format!("{}({})", to_ident(function), self.values(args))
+            }
+            Expression::Call { function, args } if matches!(function.as_str(), "Ok" | "Err" | "Some") => {
+                format!("{}({})", function, self.values(args))
+            }
+            Expression::Call { function, args } => format!("{}({})", function, self.list(args)),
+
+            Expression::Method { expr, name, args } => {
+                format!("{}.{}({})", self.receiver(expr), name, self.list(args))
+            }
+
+            Expression::Binary { op: BinaryOp::Concat, left, right } => format!(
+                "format!(\"{{}}{{}}\", {}, {})",
+                self.expression(left),
+                self.expression(right)
+            ),
+
+            Expression::Binary { op, left, right } => format!(
+                "{} {} {}",
+                self.operand(left, *op, false),
+                binary_operator(*op),
+                self.operand(right, *op, true)
+            ),
+
+            Expression::Unary { op, expr } => {
+                let op_str = match op {
+                    UnaryOp::Not => "!",
+                    UnaryOp::Neg => "-",
+                };
+                let operand = match **expr {
+                    Expression::Binary { op: BinaryOp::Concat, .. } => self.expression(expr),
+                    Expression::Binary { .. } => format!("({})", self.expression(expr)),
+                    _ => self.receiver(expr),
+                };
+                format!("{}{}", op_str, operand)
+            }
+
+            Expression::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => self.if_else(cond, then_branch, else_branch, false),
+
+            Expression::Match { on, arms } => self.match_arms(on, arms, false),
+
+            Expression::Let { .. } | Expression::Block { .. } => {
+                format!("{{\n{}\n}}", self.block(expr, false))
+            }
+
+            Expression::For { var, iterable, body } => format!(
+                "for {} in {} {{\n{}\n}}",
                 var,
-                generate_expression(iterable, indent),
-                indent_str,
-                generate_expression(body, indent + 1),
-                "    ".repeat(indent.saturating_sub(1))
-            )
-        }
+                self.expression(iterable),
+                self.statements(body)
+            ),
 
-        Expression::Return { value } => {
-            format!("return {}", generate_expression(value, indent))
-        }
+            Expression::Return { value } if self.wraps_ok => {
+                format!("return Ok({})", self.value(value))
+            }
+            Expression::Return { value } => format!("return {}", self.value(value)),
+
+            Expression::Raise { error, message } => {
+                let error = match message {
+                    Some(message) => format!(
+                        "FunctionError::with_message({}, {})",
+                        string_literal(error),
+                        self.expression(message)
+                    ),
+                    None => format!("FunctionError::new({})", string_literal(error)),
+                };
+                if self.converts_errors {
+                    format!("return Err({}.into())", error)
+                } else {
+                    format!("return Err({})", error)
+                }
+            }
 
-        Expression::Raise { error, message } => {
-            if let Some(msg) = message {
-                format!(
-                    "return Err({}({}))",
-                    error,
-                    generate_expression(msg, indent)
-                )
-            } else {
-                format!("return Err({})", error)
+            Expression::Struct { name, fields } => {
+                let mut fields: Vec<_> = fields.iter().collect();
+                fields.sort_by(|a, b| a.0.cmp(b.0));
+                let field_strs: Vec<String> = fields
+                    .into_iter()
+                    .map(|(k, v)| match v {
+                        Expression::Variable { name } if name == k => k.clone(),
+                        _ => format!("{}: {}", k, self.value(v)),
+                    })
+                    .collect();
+                format!("{} {{ {} }}", name, field_strs.join(", "))
             }
-        }
 
-        Expression::Block { exprs } => {
-            let stmts: Vec<String> = exprs
-                .iter()
-                .enumerate()
-                .map(|(i, e)| {
-                    let is_last = i == exprs.len() - 1;
-                    let expr_str = generate_expression(e, indent);
-                    if is_last {
-                        expr_str
-                    } else {
-                        format!("{};", expr_str)
-                    }
-                })
-                .collect();
-            stmts.join(&format!("\n{}", indent_str))
-        }
+            Expression::Array { elements } => format!("vec![{}]", self.values(elements)),
 
-        Expression::Struct { name, fields } => {
-            let field_strs: Vec<String> = fields
-                .iter()
-                .map(|(k, v)| format!("{}: {}", k, generate_expression(v, indent)))
-                .collect();
-            format!("{} {{ {} }}", name, field_strs.join(", "))
-        }
+            Expression::Tuple { elements } if elements.len() == 1 => {
+                format!("({},)", self.value(&elements[0]))
+            }
+            Expression::Tuple { elements } => format!("({})", self.values(elements)),
 
-        Expression::Array { elements } => {
-            let items: Vec<String> = elements
-                .iter()
-                .map(|e| generate_expression(e, indent))
-                .collect();
-            format!("vec![{}]", items.join(", "))
-        }
+            Expression::Closure { params, body } => {
+                format!("|{}| {}", params.join(", "), self.expression(body))
+            }
 
-        Expression::Tuple { elements } => {
-            let items: Vec<String> = elements
-                .iter()
-                .map(|e| generate_expression(e, indent))
-                .collect();
-            format!("({})", items.join(", "))
+            Expression::Try { expr } => format!("{}?", self.receiver(expr)),
+
+            Expression::UnwrapOr { expr, default } => format!(
+                "{}.unwrap_or({})",
+                self.receiver(expr),
+                self.value(default)
+            ),
+
+            Expression::Format { template, args } if args.is_empty() => {
+                format!("format!({})", string_literal(template))
+            }
+            Expression::Format { template, args } => {
+                format!("format!({}, {})", string_literal(template), self.list(args))
+            }
         }
+    }
+
+    fn list(&self, exprs: &[Expression]) -> String {
+        exprs
+            .iter()
+            .map(|e| self.expression(e))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 
-        Expression::Closure { params, body } => {
-            format!("|{}| {}", params.join(", "), generate_expression(body, indent))
+    /// `expr` where an owned value is expected, such as a returned value, a
+    /// binding or a struct field: a string literal becomes a `String`
+    fn value(&self, expr: &Expression) -> String {
+        match expr {
+            Expression::Literal {
+                value: serde_json::Value::String(s),
+            } => owned_string_literal(s),
+            _ => self.expression(expr),
         }
+    }
 
-        Expression::Try { expr } => {
-            format!("{}?", generate_expression(expr, indent))
+    fn values(&self, exprs: &[Expression]) -> String {
+        exprs
+            .iter()
+            .map(|e| self.value(e))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// `expr` in front of `.field`, `.method()`, `[index]` or `?`
+    fn receiver(&self, expr: &Expression) -> String {
+        let lowered = self.expression(expr);
+        let needs_parens = match expr {
+            Expression::Binary { op, .. } => *op != BinaryOp::Concat,
+            Expression::Literal { value } => value.as_f64().is_some_and(|n| n < 0.0),
+            Expression::Unary { .. }
+            | Expression::If { .. }
+            | Expression::Match { .. }
+            | Expression::For { .. }
+            | Expression::Return { .. }
+            | Expression::Raise { .. }
+            | Expression::Closure { .. } => true,
+            _ => false,
+        };
+        if needs_parens {
+            format!("({})", lowered)
+        } else {
+            lowered
         }
+    }
 
-        Expression::UnwrapOr { expr, default } => {
-            format!(
-                "{}.unwrap_or({})",
-                generate_expression(expr, indent),
-                generate_expression(default, indent)
-            )
+    /// An operand of `parent`, parenthesised where Rust's precedence would
+    /// otherwise regroup it
+    fn operand(&self, expr: &Expression, parent: BinaryOp, right: bool) -> String {
+        let lowered = self.expression(expr);
+        let needs_parens = match expr {
+            Expression::Binary { op: BinaryOp::Concat, .. } => false,
+            Expression::Binary { op, .. } => {
+                let (child, parent_prec) = (precedence(*op), precedence(parent));
+                child < parent_prec || (child == parent_prec && (right || is_comparison(parent)))
+            }
+            Expression::If { .. }
+            | Expression::Match { .. }
+            | Expression::For { .. }
+            | Expression::Return { .. }
+            | Expression::Raise { .. }
+            | Expression::Closure { .. }
+            | Expression::Let { .. }
+            | Expression::Block { .. } => true,
+            _ => false,
+        };
+        if needs_parens {
+            format!("({})", lowered)
+        } else {
+            lowered
         }
     }
 }
 
+fn binary_operator(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add | BinaryOp::Concat => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::Mod => "%",
+        BinaryOp::Eq => "==",
+        BinaryOp::Ne => "!=",
+        BinaryOp::Lt => "<",
+        BinaryOp::Le => "<=",
+        BinaryOp::Gt => ">",
+        BinaryOp::Ge => ">=",
+        BinaryOp::And => "&&",
+        BinaryOp::Or => "||",
+    }
+}
+
+fn precedence(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 5,
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Concat => 4,
+        BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 3,
+        BinaryOp::And => 2,
+        BinaryOp::Or => 1,
+    }
+}
+
+/// Comparisons don't chain in Rust
+fn is_comparison(op: BinaryOp) -> bool {
+    precedence(op) == 3
+}
+
+/// Whether `expr` calls a `&mut self` method on the variable `name`
+fn mutates(expr: &Expression, name: &str) -> bool {
+    contains(expr, |e| {
+        matches!(
+            e,
+            Expression::Method { expr, name: method, .. }
+                if MUTATING_METHODS.contains(&method.as_str())
+                    && matches!(&**expr, Expression::Variable { name: receiver } if receiver == name)
+        )
+    })
+}
+
+fn literal(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "None".to_string(),
+        serde_json::Value::Bool(b) => b.to_string(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::String(s) if is_variant_path(s) => s.clone(),
+        serde_json::Value::String(s) => string_literal(s),
+        serde_json::Value::Array(items) => array_literal(items),
+        serde_json::Value::Object(_) => format!("serde_json::json!({})", value),
+    }
+}
+
+/// An array of booleans, strings or numbers lowers to a `vec!`, with
+/// integers written as floats when any element is one. Any other array has
+/// no single Rust element type and stays JSON.
+fn array_literal(items: &[serde_json::Value]) -> String {
+    let elements: Vec<String> = if items.iter().all(serde_json::Value::is_boolean) {
+        items.iter().map(literal).collect()
+    } else if items.iter().all(serde_json::Value::is_string) {
+        items
+            .iter()
+            .filter_map(serde_json::Value::as_str)
+            .map(owned_string_literal)
+            .collect()
+    } else if items.iter().all(serde_json::Value::is_number) {
+        let floats = items.iter().any(serde_json::Value::is_f64);
+        items
+            .iter()
+            .map(|n| match n.as_f64() {
+                Some(f) if floats => format!("{:?}", f),
+                _ => n.to_string(),
+            })
+            .collect()
+    } else {
+        return format!("serde_json::json!({})", serde_json::Value::Array(items.to_vec()));
+    };
+    format!("vec![{}]", elements.join(", "))
+}
+
+/// A string such as `TypeRef::String` names an enum variant or associated
+/// constant rather than text
+fn is_variant_path(s: &str) -> bool {
+    let segments: Vec<&str> = s.split("::").collect();
+    segments.len() > 1
+        && segments[0].starts_with(|c: char| c.is_ascii_uppercase())
+        && segments.iter().all(|segment| {
+            segment.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+        })
+}
+
+fn string_literal(s: &str) -> String {
+    format!("{:?}", s)
+}
+
+/// A string literal as a `String`, unless it names a variant
+fn owned_string_literal(s: &str) -> String {
+    if is_variant_path(s) {
+        s.to_string()
+    } else {
+        format!("{}.to_string()", string_literal(s))
+    }
+}
+
+/// The arm's pattern, and the guard a string-prefix or -suffix match needs
+fn arm_pattern(pattern: &Pattern) -> (String, Option<String>) {
+    match pattern {
+        Pattern::StartsWith { prefix } => (
+            STRING_ARM_BINDING.to_string(),
+            Some(format!("{}.starts_with({})", STRING_ARM_BINDING, string_literal(prefix))),
+        ),
+        Pattern::EndsWith { suffix } => (
+            STRING_ARM_BINDING.to_string(),
+            Some(format!("{}.ends_with({})", STRING_ARM_BINDING, string_literal(suffix))),
+        ),
+        _ => (generate_pattern(pattern), None),
+    }
+}
+
 fn generate_pattern(pattern: &Pattern) -> String {
     match pattern {
         Pattern::Wildcard => "_".to_string(),
@@ -315,8 +680,8 @@ fn generate_pattern(pattern: &Pattern) -> String {
             serde_json::Value::Null => "None".to_string(),
             serde_json::Value::Bool(b) => b.to_string(),
             serde_json::Value::Number(n) => n.to_string(),
-            serde_json::Value::String(s) => format!("\"{}\"", s),
-            _ => "/* complex pattern */".to_string(),
+            serde_json::Value::String(s) => string_literal(s),
+            _ => "_".to_string(),
         },
         Pattern::Variant {
             enum_name,
@@ -334,10 +699,16 @@ fn generate_pattern(pattern: &Pattern) -> String {
             }
         }
         Pattern::Struct { name, fields } => {
-            let field_pats: Vec<String> = fields
-                .iter()
-                .map(|(k, v)| format!("{}: {}", k, generate_pattern(v)))
+            let mut fields: Vec<_> = fields.iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+            let mut field_pats: Vec<String> = fields
+                .into_iter()
+                .map(|(k, v)| match v {
+                    Pattern::Variable { name } if name == k => k.clone(),
+                    _ => format!("{}: {}", k, generate_pattern(v)),
+                })
                 .collect();
+            field_pats.push("..".to_string());
             format!("{} {{ {} }}", name, field_pats.join(", "))
         }
         Pattern::Tuple { elements } => {
@@ -347,16 +718,12 @@ fn generate_pattern(pattern: &Pattern) -> String {
         Pattern::Array { elements, rest } => {
             let mut pats: Vec<String> = elements.iter().map(generate_pattern).collect();
             if let Some(r) = rest {
-                pats.push(format!("{}@..", r));
+                pats.push(format!("{} @ ..", r));
             }
             format!("[{}]", pats.join(", "))
         }
-        Pattern::StartsWith { prefix } => {
-            format!("s if s.starts_with(\"{}\")", prefix)
-        }
-        Pattern::EndsWith { suffix } => {
-            format!("s if s.ends_with(\"{}\")", suffix)
-        }
+        // Only meaningful as a whole arm, see `arm_pattern`
+        Pattern::StartsWith { .. } | Pattern::EndsWith { .. } => "_".to_string(),
         Pattern::Some { binding } => format!("Some({})", binding),
         Pattern::None => "None".to_string(),
         Pattern::Ok { binding } => format!("Ok({})", binding),
@@ -367,18 +734,3 @@ fn generate_pattern(pattern: &Pattern) -> String {
         }
     }
 }
-
-fn to_snake_case(s: &str) -> String {
-    let mut result = String::new();
-    for (i, c) in s.chars().enumerate() {
-        if c.is_uppercase() {
-            if i > 0 {
-                result.push('_');
-            }
-            result.push(c.to_ascii_lowercase());
-        } else {
-            result.push(c);
-        }
-    }
-    result
-}
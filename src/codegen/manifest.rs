@@ -4,6 +4,8 @@ use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+use super::GenWriter;
+
 /// Generation manifest tracking all generated files
 ///
 /// Uses BTreeMap instead of HashMap to ensure deterministic JSON serialization
@@ -16,7 +18,10 @@ pub struct GenManifest {
     /// Generated files with their content hashes (sorted by path)
     pub files: BTreeMap<String, FileEntry>,
 
-    /// Intent hashes that contributed to generation (sorted by intent ID)
+    /// Canonical hashes of the intents that contributed to generation
+    /// (sorted by intent ID), plus the `intent.toml` and `intent-engine`
+    /// entries for the configuration and engine version
+    #[serde(default)]
     pub source_hashes: BTreeMap<String, String>,
 }
 
@@ -68,37 +73,62 @@ impl GenManifest {
     }
 }
 
+/// A file whose content on disk no longer matches its manifest entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestMismatch {
+    pub path: String,
+    /// `missing` or `modified`
+    pub reason: String,
+    pub expected: String,
+    /// Hash of the file on disk, if it exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<String>,
+}
+
+/// Recompute the hash of every file the manifest records and report those
+/// that are missing or differ from the recorded hash
+pub fn verify_manifest(manifest: &GenManifest, writer: &dyn GenWriter) -> anyhow::Result<Vec<ManifestMismatch>> {
+    let mut mismatches = Vec::new();
+    for (path, entry) in &manifest.files {
+        let actual = writer.read_existing(path)?.map(|content| compute_hash(&content));
+        let reason = match &actual {
+            None => "missing",
+            Some(hash) if *hash != entry.hash => "modified",
+            Some(_) => continue,
+        };
+        mismatches.push(ManifestMismatch {
+            path: path.clone(),
+            reason: reason.to_string(),
+            expected: entry.hash.clone(),
+            actual,
+        });
+    }
+    Ok(mismatches)
+}
+
 /// Compute SHA256 hash of content
-fn compute_hash(content: &str) -> String {
+pub(super) fn compute_hash(content: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(content.as_bytes());
     hex::encode(hasher.finalize())
 }
 
 /// Write manifest to lock file
-pub fn write_manifest(manifest: &GenManifest) -> anyhow::Result<()> {
+pub fn write_manifest(manifest: &GenManifest, writer: &mut dyn GenWriter) -> anyhow::Result<()> {
     let lock_path = ".intent/locks/gen-manifest.json";
 
-    // Create directory if needed
-    if let Some(parent) = std::path::Path::new(lock_path).parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-
     let content = serde_json::to_string_pretty(manifest)?;
-    std::fs::write(lock_path, content)?;
+    writer.write(lock_path, &content)?;
 
     Ok(())
 }
 
 /// Load manifest from lock file
-pub fn load_manifest() -> anyhow::Result<GenManifest> {
-    let lock_path = ".intent/locks/gen-manifest.json";
-
-    if !std::path::Path::new(lock_path).exists() {
+pub fn load_manifest(writer: &dyn GenWriter) -> anyhow::Result<GenManifest> {
+    let Some(content) = writer.read_existing(super::MANIFEST_LOCK)? else {
         return Ok(GenManifest::new());
-    }
+    };
 
-    let content = std::fs::read_to_string(lock_path)?;
     let manifest: GenManifest = serde_json::from_str(&content)?;
 
     Ok(manifest)
@@ -127,4 +157,21 @@ mod tests {
         assert!(manifest.check_file("test.rs", "fn main() {}"));
         assert!(!manifest.check_file("test.rs", "fn main() { }"));
     }
+
+    #[test]
+    fn test_verify_manifest() {
+        let mut writer = crate::codegen::MemoryWriter::new();
+        let mut manifest = GenManifest::new();
+        for path in ["gen/a.rs", "gen/b.rs", "gen/c.rs"] {
+            manifest.add_file(path, "fn main() {}", vec![]);
+        }
+        writer.write("gen/a.rs", "fn main() {}").unwrap();
+        writer.write("gen/b.rs", "fn main() { }").unwrap();
+
+        let mismatches = verify_manifest(&manifest, &writer).unwrap();
+        let found: Vec<_> = mismatches.iter().map(|m| (m.path.as_str(), m.reason.as_str())).collect();
+        assert_eq!(found, [("gen/b.rs", "modified"), ("gen/c.rs", "missing")]);
+        assert_eq!(mismatches[0].actual, Some(compute_hash("fn main() { }")));
+        assert_eq!(mismatches[1].actual, None);
+    }
 }
@@ -8,6 +8,13 @@ mod errors;
 mod crate_gen;
 mod trace;
 mod manifest;
+mod writer;
+mod naming;
+mod format;
+mod openapi;
+mod services;
+mod contracts;
+mod backends;
 
 // v2 Meta Kind code generation
 mod functions;
@@ -23,6 +30,13 @@ pub use errors::*;
 pub use crate_gen::*;
 pub use trace::*;
 pub use manifest::*;
+pub use writer::*;
+pub use naming::*;
+pub use format::*;
+pub use openapi::*;
+pub use services::*;
+pub use contracts::*;
+pub use backends::*;
 
 // v2 exports
 pub use functions::*;
@@ -30,31 +44,195 @@ pub use enums::*;
 pub use commands::*;
 pub use templates::*;
 
-use std::path::Path;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path};
 
-use crate::parser::{IntentConfig, IntentStore};
+use crate::model::{codes, IntentDocument, IntentError, IntentKind};
+use crate::parser::{hash_canonical, IntentConfig, IntentStore, TypesLayout};
 
 const GEN_DIR: &str = "gen";
 
-/// Generate all Rust code from intents
-pub fn generate_all(store: &IntentStore, check_only: bool) -> anyhow::Result<GenerationResult> {
+pub const MANIFEST_LOCK: &str = ".intent/locks/gen-manifest.json";
+const TRACE_MAP_LOCK: &str = ".intent/locks/trace-map.json";
+const OBLIGATIONS_LOCK: &str = ".intent/locks/obligations.json";
+
+/// Every lock file a gen run can write
+pub const LOCK_FILES: [&str; 3] = [MANIFEST_LOCK, TRACE_MAP_LOCK, OBLIGATIONS_LOCK];
+
+/// Which lock files a real gen run writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockFiles {
+    pub manifest: bool,
+    pub trace_map: bool,
+    pub obligations: bool,
+}
+
+impl Default for LockFiles {
+    fn default() -> Self {
+        Self {
+            manifest: true,
+            trace_map: true,
+            obligations: true,
+        }
+    }
+}
+
+impl LockFiles {
+    /// Generate code only (`gen --no-locks`)
+    pub fn none() -> Self {
+        Self {
+            manifest: false,
+            trace_map: false,
+            obligations: false,
+        }
+    }
+}
+
+/// Restricts a gen run to the files derived from some of the intents
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GenFilter {
+    /// Kinds to generate (`--only`); every kind when empty
+    pub kinds: Vec<IntentKind>,
+    /// Intent names to generate (`--name`); every name when empty
+    pub names: Vec<String>,
+    /// Tags to generate (`--tag`); intents with any of them are selected,
+    /// every intent when empty
+    pub tags: Vec<String>,
+}
+
+impl GenFilter {
+    /// Build a filter from `--only` kind names, `--name` intent names and
+    /// `--tag` tags
+    pub fn parse(kinds: &[String], names: Vec<String>, tags: Vec<String>) -> anyhow::Result<Self> {
+        let kinds = kinds
+            .iter()
+            .map(|kind| {
+                IntentKind::from_str(kind)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown intent kind '{}' in --only", kind))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { kinds, names, tags })
+    }
+
+    /// Whether the filter restricts anything
+    pub fn is_active(&self) -> bool {
+        !self.kinds.is_empty() || !self.names.is_empty() || !self.tags.is_empty()
+    }
+
+    /// Whether `doc` is one of the intents the filter selects
+    pub fn selects(&self, doc: &IntentDocument) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&doc.kind))
+            && (self.names.is_empty() || self.names.contains(&doc.name))
+            && (self.tags.is_empty() || doc.tags.iter().any(|tag| self.tags.contains(tag)))
+    }
+}
+
+/// Endpoints and Workflows whose names map to the same generated file
+/// (`CreateRefund` and `Create_Refund` both become `create_refund.rs`).
+/// Each group is the file, relative to `gen/src`, and its intents sorted
+/// by name.
+pub fn module_file_collisions(store: &IntentStore) -> Vec<(String, Vec<&IntentDocument>)> {
+    let mut files: BTreeMap<String, Vec<&IntentDocument>> = BTreeMap::new();
+    for (kind, dir) in [(IntentKind::Endpoint, "endpoints"), (IntentKind::Workflow, "workflows")] {
+        for doc in store.iter().filter(|doc| doc.kind == kind) {
+            files
+                .entry(format!("{}/{}.rs", dir, to_ident(&doc.name)))
+                .or_default()
+                .push(doc);
+        }
+    }
+
+    files
+        .into_iter()
+        .filter(|(_, docs)| docs.len() > 1)
+        .map(|(file, mut docs)| {
+            docs.sort_by(|a, b| a.name.cmp(&b.name));
+            (file, docs)
+        })
+        .collect()
+}
+
+/// Lock files that are not present, for commands that rely on them
+pub fn missing_lock_files(writer: &dyn GenWriter) -> anyhow::Result<Vec<&'static str>> {
+    let mut missing = Vec::new();
+    for path in LOCK_FILES {
+        if writer.read_existing(path)?.is_none() {
+            missing.push(path);
+        }
+    }
+    Ok(missing)
+}
+
+/// Generate all Rust code from intents. With `prune`, files recorded in the
+/// previous manifest that are no longer produced are removed from `gen/`.
+/// `locks` selects which lock files are written alongside the code.
+///
+/// An active `filter` limits the run (and a check) to files generated from
+/// the selected intents; manifest entries of every other file are kept as
+/// they were, and nothing is reported as orphaned or pruned.
+pub fn generate_all(
+    store: &IntentStore,
+    check_only: bool,
+    filter: &GenFilter,
+    prune: bool,
+    locks: LockFiles,
+    writer: &mut dyn GenWriter,
+) -> anyhow::Result<GenerationResult> {
     let config = IntentConfig::load()?;
+    generate_with_config(store, &config, check_only, filter, prune, locks, writer)
+}
+
+/// Generate all Rust code from intents using an explicit configuration
+pub fn generate_with_config(
+    store: &IntentStore,
+    config: &IntentConfig,
+    check_only: bool,
+    filter: &GenFilter,
+    prune: bool,
+    locks: LockFiles,
+    writer: &mut dyn GenWriter,
+) -> anyhow::Result<GenerationResult> {
     let mut result = GenerationResult::new();
     let mut manifest = GenManifest::new();
 
-    // Create gen directory if not checking
-    if !check_only {
-        std::fs::create_dir_all(format!("{}/src/endpoints", GEN_DIR))?;
-        std::fs::create_dir_all(format!("{}/src/workflows", GEN_DIR))?;
-        std::fs::create_dir_all(format!("{}/src/effects", GEN_DIR))?;
+    // One intent's file would silently overwrite the other's
+    if let Some((file, docs)) = module_file_collisions(store).into_iter().next() {
+        let names: Vec<String> = docs.iter().map(|doc| format!("'{}'", doc.name)).collect();
+        anyhow::bail!(
+            "{}s {} would generate the same file {}/src/{}; rename all but one",
+            docs[0].kind,
+            names.join(" and "),
+            GEN_DIR,
+            file
+        );
     }
 
+    let selected: Option<HashSet<String>> = filter.is_active().then(|| {
+        store
+            .iter()
+            .filter(|doc| filter.selects(doc))
+            .map(|doc| doc.id.to_string())
+            .collect()
+    });
+    if selected.as_ref().is_some_and(HashSet::is_empty) {
+        anyhow::bail!("No intents match the gen filter");
+    }
+    let mut previous = load_manifest(writer)?;
+    let mode = WriteMode {
+        store,
+        previous: &previous,
+        check_only,
+        format: config.generation.format,
+        selected: selected.as_ref(),
+    };
+
     // Generate Cargo.toml
-    let cargo_content = generate_cargo_toml(&config);
+    let cargo_content = generate_cargo_toml(store, config);
     write_or_check(
         &format!("{}/Cargo.toml", GEN_DIR),
         &cargo_content,
-        check_only,
+        mode,
+        writer,
         &mut result,
         &mut manifest,
         vec![],
@@ -65,42 +243,102 @@ pub fn generate_all(store: &IntentStore, check_only: bool) -> anyhow::Result<Gen
     write_or_check(
         &format!("{}/src/lib.rs", GEN_DIR),
         &lib_content,
-        check_only,
+        mode,
+        writer,
         &mut result,
         &mut manifest,
         vec![],
     )?;
 
-    // Generate types.rs
-    let types_content = generate_types(store);
+    // Generate main.rs
+    if config.generation.emits_binary() {
+        let main_content = generate_main_rs(config);
+        write_or_check(
+            &format!("{}/src/main.rs", GEN_DIR),
+            &main_content,
+            mode,
+            writer,
+            &mut result,
+            &mut manifest,
+            vec![],
+        )?;
+    }
+
+    // Generate types
     let type_ids: Vec<_> = store.types().iter().map(|d| d.id.to_string()).collect();
-    write_or_check(
-        &format!("{}/src/types.rs", GEN_DIR),
-        &types_content,
-        check_only,
-        &mut result,
-        &mut manifest,
-        type_ids,
-    )?;
+    match config.generation.types_layout {
+        TypesLayout::Single => {
+            let types_content = generate_types(store, config);
+            write_or_check(
+                &format!("{}/src/types.rs", GEN_DIR),
+                &types_content,
+                mode,
+                writer,
+                &mut result,
+                &mut manifest,
+                type_ids,
+            )?;
+        }
+        TypesLayout::PerType => {
+            let types_output = generate_types_per_type(store, config);
+            write_or_check(
+                &format!("{}/src/types/mod.rs", GEN_DIR),
+                &types_output.mod_rs,
+                mode,
+                writer,
+                &mut result,
+                &mut manifest,
+                type_ids,
+            )?;
+
+            for file in &types_output.files {
+                write_or_check(
+                    &format!("{}/src/types/{}", GEN_DIR, file.name),
+                    &file.content,
+                    mode,
+                    writer,
+                    &mut result,
+                    &mut manifest,
+                    vec![file.intent_id.to_string()],
+                )?;
+            }
+        }
+    }
+
+    // Generate enums
+    if !store.enums().is_empty() {
+        let enums_output = generate_enums(store)?;
+        write_or_check(
+            &format!("{}/src/enums.rs", GEN_DIR),
+            &enums_output.enums_rs,
+            mode,
+            writer,
+            &mut result,
+            &mut manifest,
+            enums_output.intent_ids.iter().map(|id| id.to_string()).collect(),
+        )?;
+    }
 
     // Generate errors.rs
-    let errors_content = generate_errors(store);
+    let errors_content = generate_errors(store, config);
     let endpoint_ids: Vec<_> = store.endpoints().iter().map(|d| d.id.to_string()).collect();
     write_or_check(
         &format!("{}/src/errors.rs", GEN_DIR),
         &errors_content,
-        check_only,
+        mode,
+        writer,
         &mut result,
         &mut manifest,
         endpoint_ids.clone(),
     )?;
 
     // Generate endpoints
-    let endpoints_output = generate_endpoints(store);
+    let endpoints_output = generate_endpoints(store, config);
     write_or_check(
         &format!("{}/src/endpoints/mod.rs", GEN_DIR),
         &endpoints_output.mod_rs,
-        check_only,
+        mode,
+        writer,
         &mut result,
         &mut manifest,
         endpoint_ids.clone(),
@@ -110,10 +348,11 @@ pub fn generate_all(store: &IntentStore, check_only: bool) -> anyhow::Result<Gen
         write_or_check(
             &format!("{}/src/endpoints/{}", GEN_DIR, file.name),
             &file.content,
-            check_only,
+            mode,
+            writer,
             &mut result,
             &mut manifest,
-            vec![], // Individual endpoint IDs would be tracked here
+            vec![file.intent_id.to_string()],
         )?;
     }
 
@@ -123,7 +362,8 @@ pub fn generate_all(store: &IntentStore, check_only: bool) -> anyhow::Result<Gen
     write_or_check(
         &format!("{}/src/workflows/mod.rs", GEN_DIR),
         &workflows_output.mod_rs,
-        check_only,
+        mode,
+        writer,
         &mut result,
         &mut manifest,
         workflow_ids.clone(),
@@ -133,19 +373,63 @@ pub fn generate_all(store: &IntentStore, check_only: bool) -> anyhow::Result<Gen
         write_or_check(
             &format!("{}/src/workflows/{}", GEN_DIR, file.name),
             &file.content,
-            check_only,
+            mode,
+            writer,
             &mut result,
             &mut manifest,
-            vec![],
+            vec![file.intent_id.to_string()],
+        )?;
+    }
+
+    // Generate functions
+    if !store.functions().is_empty() {
+        let functions_output = generate_functions(store);
+        write_or_check(
+            &format!("{}/src/functions.rs", GEN_DIR),
+            &functions_output.functions_rs,
+            mode,
+            writer,
+            &mut result,
+            &mut manifest,
+            functions_output.intent_ids.iter().map(|id| id.to_string()).collect(),
+        )?;
+    }
+
+    // Generate service clients
+    if !store.services().is_empty() {
+        let services_output = generate_service_clients(store, config)?;
+        write_or_check(
+            &format!("{}/src/services/mod.rs", GEN_DIR),
+            &services_output.mod_rs,
+            mode,
+            writer,
+            &mut result,
+            &mut manifest,
+            services_output.intent_ids.iter().map(|id| id.to_string()).collect(),
+        )?;
+    }
+
+    // Generate contract tests
+    if !store.contract_tests().is_empty() {
+        let contracts_output = generate_contract_tests(store, config)?;
+        write_or_check(
+            &format!("{}/tests/contracts.rs", GEN_DIR),
+            &contracts_output.contracts_rs,
+            mode,
+            writer,
+            &mut result,
+            &mut manifest,
+            contracts_output.intent_ids.iter().map(|id| id.to_string()).collect(),
         )?;
     }
 
     // Generate effects
-    let effects_output = generate_effects(store, &config);
+    let effects_output = generate_effects(store, config);
     write_or_check(
         &format!("{}/src/effects/mod.rs", GEN_DIR),
         &effects_output.mod_rs,
-        check_only,
+        mode,
+        writer,
         &mut result,
         &mut manifest,
         vec![],
@@ -153,7 +437,8 @@ pub fn generate_all(store: &IntentStore, check_only: bool) -> anyhow::Result<Gen
     write_or_check(
         &format!("{}/src/effects/http.rs", GEN_DIR),
         &effects_output.http_rs,
-        check_only,
+        mode,
+        writer,
         &mut result,
         &mut manifest,
         vec![],
@@ -161,7 +446,8 @@ pub fn generate_all(store: &IntentStore, check_only: bool) -> anyhow::Result<Gen
     write_or_check(
         &format!("{}/src/effects/db.rs", GEN_DIR),
         &effects_output.db_rs,
-        check_only,
+        mode,
+        writer,
         &mut result,
         &mut manifest,
         vec![],
@@ -169,53 +455,524 @@ pub fn generate_all(store: &IntentStore, check_only: bool) -> anyhow::Result<Gen
     write_or_check(
         &format!("{}/src/effects/events.rs", GEN_DIR),
         &effects_output.events_rs,
-        check_only,
+        mode,
+        writer,
         &mut result,
         &mut manifest,
         vec![],
     )?;
 
+    // A filtered run only regenerated the selected intents; the rest keep
+    // the hashes they were last generated from
+    if selected.is_some() {
+        manifest.source_hashes = std::mem::take(&mut previous.source_hashes);
+    }
+    for (id, hash) in source_hashes(store, config)? {
+        if selected.as_ref().is_none_or(|selected| selected.contains(&id)) {
+            manifest.add_source(&id, &hash);
+        }
+    }
+
+    // Previously generated files this run no longer produces
+    for (path, entry) in previous.files {
+        if manifest.files.contains_key(&path) {
+            continue;
+        }
+        if selected.is_some() {
+            // Not part of this filtered run
+            manifest.files.insert(path, entry);
+            continue;
+        }
+        if writer.read_existing(&path)?.is_none() {
+            continue;
+        }
+
+        if prune && !check_only && is_in_gen_dir(&path) {
+            writer.remove(&path)?;
+            result.pruned.push(path);
+        } else {
+            // Keep tracking it so a later `--prune` can still remove it
+            manifest.files.insert(path.clone(), entry);
+            result.orphaned.push(path);
+        }
+    }
+
     // Write lock files if not checking
     if !check_only {
         // Write manifest
-        write_manifest(&manifest)?;
+        if locks.manifest {
+            write_manifest(&manifest, writer)?;
+        }
 
         // Generate and write trace map
-        let trace = generate_trace_map(store);
-        write_trace_map(&trace)?;
+        if locks.trace_map {
+            let trace = generate_trace_map(store, config);
+            write_trace_map(&trace, writer)?;
+        }
 
         // Write obligations
-        let obligations = crate::validation::check_obligations(store)?;
-        crate::validation::write_obligations_lock(&obligations)?;
+        if locks.obligations {
+            let obligations = crate::validation::check_obligations(store)?;
+            crate::validation::write_obligations_lock(&obligations, writer)?;
+        }
+
+        result.workspace_updated = generate_workspace_integration(config, writer)?;
     }
 
+    result.manifest = manifest;
+    result.hash = result.tree_hash();
+    result.drift = result.drift_counts();
     Ok(result)
 }
 
+/// Canonical hash of every intent document keyed by ID, plus hashes of the
+/// configuration (`intent.toml`) and engine version (`intent-engine`),
+/// which change the output just as an intent does
+pub fn source_hashes(store: &IntentStore, config: &IntentConfig) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut hashes = BTreeMap::new();
+    for doc in store.iter() {
+        hashes.insert(doc.id.to_string(), hash_canonical(&serde_json::to_value(doc)?));
+    }
+    hashes.insert("intent.toml".to_string(), hash_canonical(&serde_json::to_value(config)?));
+    hashes.insert(
+        "intent-engine".to_string(),
+        hash_canonical(&serde_json::json!(env!("CARGO_PKG_VERSION"))),
+    );
+    Ok(hashes)
+}
+
+/// Fast path for `gen --check`: when the manifest's source hashes equal the
+/// current ones and every file it records is intact on disk, the generated
+/// code must still match, so report that without regenerating. `None`
+/// means a full check is needed.
+pub fn check_unchanged_sources(
+    store: &IntentStore,
+    config: &IntentConfig,
+    writer: &dyn GenWriter,
+) -> anyhow::Result<Option<GenerationResult>> {
+    let previous = load_manifest(writer)?;
+    if previous.source_hashes.is_empty()
+        || previous.source_hashes != source_hashes(store, config)?
+        || !verify_manifest(&previous, writer)?.is_empty()
+    {
+        return Ok(None);
+    }
+
+    let mut result = GenerationResult::new();
+    for (path, entry) in &previous.files {
+        result.files.push(GeneratedFile {
+            path: path.clone(),
+            matches: true,
+            reason: None,
+            hash: entry.hash.clone(),
+            status: FileStatus::Unchanged,
+            written: false,
+        });
+    }
+    result.sources_unchanged = true;
+    result.manifest = previous;
+    result.hash = result.tree_hash();
+    Ok(Some(result))
+}
+
+/// Only manifest entries inside the gen directory may ever be pruned
+fn is_in_gen_dir(path: &str) -> bool {
+    let path = Path::new(path);
+    path.starts_with(GEN_DIR)
+        && path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Reject an output path that is absolute or climbs out with `..`, which
+/// would let a write land outside the project. `intent` names where the
+/// path came from.
+pub fn check_output_path(path: &str, intent: &str) -> Result<(), IntentError> {
+    let escapes = path.starts_with(['/', '\\'])
+        || Path::new(path)
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        || path.split(['/', '\\']).any(|segment| segment == "..");
+    if escapes {
+        return Err(IntentError::Codegen {
+            code: codes::E009_INVALID_MAPPING,
+            message: format!(
+                "Output path '{}' of {} must be relative and must not contain '..'",
+                path, intent
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// How `write_or_check` treats each generated file
+#[derive(Debug, Clone, Copy)]
+struct WriteMode<'a> {
+    /// Looks up the intents a file is generated from, to name them in errors
+    store: &'a IntentStore,
+    /// Manifest of the last run, to tell a deleted file from a new one
+    previous: &'a GenManifest,
+    check_only: bool,
+    /// Run `.rs` files through `format_with_rustfmt` first
+    format: bool,
+    /// IDs of the intents a filtered run covers; files generated from none
+    /// of them are skipped
+    selected: Option<&'a HashSet<String>>,
+}
+
 fn write_or_check(
     path: &str,
     content: &str,
-    check_only: bool,
+    mode: WriteMode,
+    writer: &mut dyn GenWriter,
     result: &mut GenerationResult,
     manifest: &mut GenManifest,
     source_intents: Vec<String>,
 ) -> anyhow::Result<()> {
-    let existing = if Path::new(path).exists() {
-        Some(std::fs::read_to_string(path)?)
+    if let Some(selected) = mode.selected {
+        if !source_intents.iter().any(|id| selected.contains(id)) {
+            return Ok(());
+        }
+    }
+
+    let intent = source_intents
+        .iter()
+        .filter_map(|id| id.parse().ok())
+        .filter_map(|id| mode.store.get(&id))
+        .map(|doc| format!("{} '{}'", doc.kind, doc.name))
+        .next()
+        .unwrap_or_else(|| "the generated crate".to_string());
+    check_output_path(path, &intent)?;
+
+    // Files that don't parse are written as generated and reported
+    let formatted = if mode.format && path.ends_with(".rs") {
+        let formatted = format_with_rustfmt(content);
+        if formatted.is_none() {
+            result.unformatted.push(path.to_string());
+        }
+        formatted
     } else {
         None
     };
+    let content = formatted.as_deref().unwrap_or(content);
+    let existing = writer.read_existing(path)?;
 
-    result.add_file(path.to_string(), content, existing.as_deref());
+    let tracked = mode.previous.files.contains_key(path);
+    result.add_file(path.to_string(), content, existing.as_deref(), tracked);
     manifest.add_file(path, content, source_intents);
 
-    if !check_only {
-        // Create parent directory if needed
-        if let Some(parent) = Path::new(path).parent() {
-            std::fs::create_dir_all(parent)?;
+    // Identical files are left alone so their mtimes don't churn
+    if !mode.check_only && existing.as_deref() != Some(content) {
+        writer.write(path, content)?;
+        if let Some(file) = result.files.last_mut() {
+            file.written = true;
         }
-        std::fs::write(path, content)?;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::IntentKind;
+
+    #[test]
+    fn test_generate_into_memory() {
+        let store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        let mut writer = MemoryWriter::new();
+
+        let result = generate_all(&store, false, &GenFilter::default(), false, LockFiles::default(), &mut writer).unwrap();
+
+        assert!(!result.files.is_empty());
+        for file in &result.files {
+            assert!(writer.files.contains_key(&file.path), "missing {}", file.path);
+        }
+        assert!(writer.files.contains_key(".intent/locks/gen-manifest.json"));
+        assert!(writer.files.contains_key(".intent/locks/trace-map.json"));
+        assert!(writer.files.contains_key(".intent/locks/obligations.json"));
+
+        // A second run against the captured output matches exactly
+        let check = generate_all(&store, true, &GenFilter::default(), false, LockFiles::default(), &mut writer).unwrap();
+        assert!(check.matches);
+    }
+
+    /// Records the paths written through it
+    struct RecordingWriter {
+        inner: MemoryWriter,
+        written: Vec<String>,
+    }
+
+    impl GenWriter for RecordingWriter {
+        fn write(&mut self, path: &str, content: &str) -> anyhow::Result<()> {
+            self.written.push(path.to_string());
+            self.inner.write(path, content)
+        }
+
+        fn read_existing(&self, path: &str) -> anyhow::Result<Option<String>> {
+            self.inner.read_existing(path)
+        }
+
+        fn remove(&mut self, path: &str) -> anyhow::Result<()> {
+            self.inner.remove(path)
+        }
+    }
+
+    #[test]
+    fn test_unchanged_files_are_not_rewritten() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        let mut writer = RecordingWriter { inner: MemoryWriter::new(), written: Vec::new() };
+        let first = generate_all(&store, false, &GenFilter::default(), false, LockFiles::default(), &mut writer).unwrap();
+        assert!(first.files.iter().all(|f| f.written));
+
+        // Only the endpoint's own file changes when its path does
+        let mut endpoint = store.get_by_kind_name(IntentKind::Endpoint, "CreateRefund").unwrap().clone();
+        endpoint.spec["path"] = serde_json::json!("/refunds");
+        store.remove(&endpoint.id);
+        store.add(endpoint).unwrap();
+        writer.written.clear();
+        let second = generate_all(&store, false, &GenFilter::default(), false, LockFiles::default(), &mut writer).unwrap();
+        let written: Vec<_> = second.files.iter().filter(|f| f.written).map(|f| f.path.as_str()).collect();
+        assert!(!written.is_empty() && written.len() < second.files.len());
+        assert!(written.iter().all(|path| writer.written.iter().any(|w| w == path)));
+        assert!(writer.written.iter().all(|path| path.starts_with(".intent/locks/") || written.contains(&path.as_str())));
+        assert_eq!(second.files.len(), first.files.len());
+        assert_eq!(second.manifest.files.len(), first.manifest.files.len());
+    }
+
+    fn add_endpoint(store: &mut IntentStore, name: &str, path: &str) {
+        let mut doc = store.get_by_kind_name(IntentKind::Endpoint, "CreateRefund").unwrap().clone();
+        doc.id = uuid::Uuid::new_v4();
+        doc.name = name.to_string();
+        doc.spec["path"] = serde_json::json!(path);
+        store.add(doc).unwrap();
+    }
+
+    #[test]
+    fn test_colliding_endpoint_files_are_an_error() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        add_endpoint(&mut store, "Create_Refund", "/refund-again");
+
+        let collisions = module_file_collisions(&store);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].0, "endpoints/create_refund.rs");
+
+        let mut writer = MemoryWriter::new();
+        let error = generate_all(&store, false, &GenFilter::default(), false, LockFiles::none(), &mut writer)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Endpoints 'CreateRefund' and 'Create_Refund' would generate the same file gen/src/endpoints/create_refund.rs; rename all but one"
+        );
+        assert!(writer.files.is_empty());
+    }
+
+    #[test]
+    fn test_distinct_endpoint_names_generate_distinct_files() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        add_endpoint(&mut store, "CreateRefunds", "/refunds");
+        assert!(module_file_collisions(&store).is_empty());
+
+        let mut writer = MemoryWriter::new();
+        generate_all(&store, false, &GenFilter::default(), false, LockFiles::none(), &mut writer).unwrap();
+        assert!(writer.files.contains_key("gen/src/endpoints/create_refund.rs"));
+        assert!(writer.files.contains_key("gen/src/endpoints/create_refunds.rs"));
+    }
+
+    #[test]
+    fn test_generated_rust_is_formatter_stable() {
+        let store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        let mut writer = MemoryWriter::new();
+        let result = generate_all(&store, false, &GenFilter::default(), false, LockFiles::none(), &mut writer).unwrap();
+        assert!(result.unformatted.is_empty(), "{:?}", result.unformatted);
+
+        for (path, content) in writer.files.iter().filter(|(path, _)| path.ends_with(".rs")) {
+            assert_eq!(format_with_rustfmt(content).as_ref(), Some(content), "{} not stable", path);
+        }
+    }
+
+    #[test]
+    fn test_regeneration_closure_of_type() {
+        let store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        let mut writer = MemoryWriter::new();
+        generate_all(&store, false, &GenFilter::default(), false, LockFiles::default(), &mut writer).unwrap();
+        let manifest = load_manifest(&writer).unwrap();
+
+        let request = store.get_by_kind_name(IntentKind::Type, "RefundRequest").unwrap();
+        let closure = store.regeneration_closure(&request.id, &manifest);
+
+        let endpoint = store.get_by_kind_name(IntentKind::Endpoint, "CreateRefund").unwrap();
+        let workflow = store.get_by_kind_name(IntentKind::Workflow, "RefundWorkflow").unwrap();
+        for id in [request.id, endpoint.id, workflow.id] {
+            assert!(closure.affected_intents.contains(&id));
+        }
+        for path in [
+            "gen/src/types.rs",
+            "gen/src/endpoints/create_refund.rs",
+            "gen/src/workflows/refund_workflow.rs",
+        ] {
+            assert!(closure.affected_files.contains(path), "missing {}", path);
+        }
+        assert!(!closure.affected_files.contains("gen/src/effects/db.rs"));
+    }
+
+    #[test]
+    fn test_filtered_generation_keeps_other_manifest_entries() {
+        let store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        let mut writer = MemoryWriter::new();
+        generate_all(&store, false, &GenFilter::default(), false, LockFiles::default(), &mut writer).unwrap();
+        let before = load_manifest(&writer).unwrap();
+
+        writer.write("gen/src/types.rs", "// edited").unwrap();
+        let filter = GenFilter {
+            kinds: vec![IntentKind::Workflow],
+            names: vec!["RefundWorkflow".to_string()],
+            tags: vec![],
+        };
+        let result = generate_all(&store, false, &filter, false, LockFiles::default(), &mut writer).unwrap();
+
+        let paths: Vec<_> = result.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["gen/src/workflows/mod.rs", "gen/src/workflows/refund_workflow.rs"]);
+        assert!(result.orphaned.is_empty());
+        assert_eq!(writer.files["gen/src/types.rs"], "// edited");
+        assert_eq!(load_manifest(&writer).unwrap().files.keys().collect::<Vec<_>>(), before.files.keys().collect::<Vec<_>>());
+
+        // A filtered check only looks at the selected files
+        let check = generate_all(&store, true, &filter, false, LockFiles::default(), &mut writer).unwrap();
+        assert!(check.matches);
+
+        let nothing = GenFilter {
+            kinds: vec![],
+            names: vec!["Missing".to_string()],
+            tags: vec![],
+        };
+        assert!(generate_all(&store, true, &nothing, false, LockFiles::default(), &mut writer).is_err());
+    }
+
+    #[test]
+    fn test_prune_only_touches_gen_dir() {
+        let store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        let mut writer = MemoryWriter::new();
+        generate_all(&store, false, &GenFilter::default(), false, LockFiles::default(), &mut writer).unwrap();
+
+        // Stale entries: one generated file, two that must never be deleted
+        let mut manifest = load_manifest(&writer).unwrap();
+        for path in ["gen/src/endpoints/old.rs", "src/main.rs", "gen/../README.md"] {
+            writer.write(path, "// stale").unwrap();
+            manifest.add_file(path, "// stale", vec![]);
+        }
+        write_manifest(&manifest, &mut writer).unwrap();
+
+        let result = generate_all(&store, false, &GenFilter::default(), true, LockFiles::default(), &mut writer).unwrap();
+
+        assert_eq!(result.pruned, ["gen/src/endpoints/old.rs"]);
+        assert!(!writer.files.contains_key("gen/src/endpoints/old.rs"));
+        assert!(writer.files.contains_key("src/main.rs"));
+        assert!(writer.files.contains_key("gen/../README.md"));
+        assert!(!load_manifest(&writer)
+            .unwrap()
+            .files
+            .contains_key("gen/src/endpoints/old.rs"));
+    }
+
+    #[test]
+    fn test_template_output_paths_must_stay_in_project() {
+        let mut store = IntentStore::new();
+        let template = |name: &str, output_file: &str| {
+            IntentDocument::with_spec(
+                IntentKind::Template,
+                name.to_string(),
+                serde_json::json!({
+                    "input": "Vec<IntentDocument>",
+                    "output_file": output_file,
+                    "template": ["// {{version}}"]
+                }),
+            )
+        };
+        store.add(template("CustomTemplate", "gen/src/custom.rs")).unwrap();
+        let context = TemplateContext::default();
+
+        let outputs = generate_from_templates(&store, &context).unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].path, "gen/src/custom.rs");
+
+        store.add(template("EscapeTemplate", "../escape.rs")).unwrap();
+        let error = generate_from_templates(&store, &context).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Codegen error: Output path '../escape.rs' of Template 'EscapeTemplate' must be relative and must not contain '..'"
+        );
+
+        assert!(check_output_path("/etc/passwd", "Template 'T'").is_err());
+        assert!(check_output_path("gen/src/../../escape.rs", "Template 'T'").is_err());
+        assert!(check_output_path("gen/./src/lib.rs", "Template 'T'").is_ok());
+    }
+
+    #[test]
+    fn test_manifest_records_source_hashes_for_check_fast_path() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        let config = IntentConfig::default();
+        let mut writer = MemoryWriter::new();
+        generate_with_config(&store, &config, false, &GenFilter::default(), false, LockFiles::default(), &mut writer)
+            .unwrap();
+
+        let manifest = load_manifest(&writer).unwrap();
+        for doc in store.iter() {
+            assert!(manifest.source_hashes.contains_key(&doc.id.to_string()), "no hash for {}", doc.name);
+        }
+        assert!(manifest.source_hashes.contains_key("intent.toml"));
+
+        let unchanged = check_unchanged_sources(&store, &config, &writer).unwrap().unwrap();
+        assert!(unchanged.matches && unchanged.sources_unchanged);
+        assert_eq!(unchanged.files.len(), manifest.files.len());
+
+        // A hand-edited file needs the full check
+        let lib_rs = writer.files["gen/src/lib.rs"].clone();
+        writer.write("gen/src/lib.rs", "// edited").unwrap();
+        assert!(check_unchanged_sources(&store, &config, &writer).unwrap().is_none());
+        writer.write("gen/src/lib.rs", &lib_rs).unwrap();
+
+        // So does a changed intent or configuration
+        let mut other = config.clone();
+        other.generation.format = !other.generation.format;
+        assert!(check_unchanged_sources(&store, &other, &writer).unwrap().is_none());
+
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Type,
+                "RefundNote".to_string(),
+                serde_json::json!({ "fields": { "text": { "type": "string" } } }),
+            ))
+            .unwrap();
+        assert!(check_unchanged_sources(&store, &config, &writer).unwrap().is_none());
+        let check = generate_with_config(&store, &config, true, &GenFilter::default(), false, LockFiles::default(), &mut writer)
+            .unwrap();
+        assert!(!check.sources_unchanged);
+    }
+
+    #[test]
+    fn test_workspace_integration() {
+        let mut config = IntentConfig::default();
+        let mut writer = MemoryWriter::new();
+        assert!(!generate_workspace_integration(&config, &mut writer).unwrap());
+        assert!(writer.files.is_empty());
+
+        // No root manifest yet: a bare workspace
+        config.generation.workspace = true;
+        assert!(generate_workspace_integration(&config, &mut writer).unwrap());
+        assert_eq!(writer.files[WORKSPACE_MANIFEST], "[workspace]\nmembers = [\"gen\"]\nresolver = \"2\"\n");
+
+        // A package manifest gains a workspace table
+        writer.write(WORKSPACE_MANIFEST, "[package]\nname = \"host\"\n").unwrap();
+        assert!(generate_workspace_integration(&config, &mut writer).unwrap());
+        assert_eq!(
+            writer.files[WORKSPACE_MANIFEST],
+            "[package]\nname = \"host\"\n\n[workspace]\nmembers = [\"gen\"]\n"
+        );
+
+        // An existing member, however spelled, is kept as is
+        writer.write(WORKSPACE_MANIFEST, "workspace = { members = [\"gen/\"] }\n").unwrap();
+        assert!(!generate_workspace_integration(&config, &mut writer).unwrap());
+        assert_eq!(writer.files[WORKSPACE_MANIFEST], "workspace = { members = [\"gen/\"] }\n");
+    }
+}
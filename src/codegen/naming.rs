@@ -0,0 +1,164 @@
+//! Case conversion and identifier escaping shared by all generators
+
+/// Rust keywords that can't be used as plain identifiers
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+    "type", "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final",
+    "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// Keywords that are not allowed even as raw identifiers
+const NON_RAW_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+/// How `escape_ident` handles a name that is a Rust keyword
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeywordEscape {
+    /// `r#type` — keeps the serde name of struct fields unchanged
+    Raw,
+    /// `type_` — needed for module and file names
+    Suffix,
+}
+
+/// Split a name into words at `_`/`-`/other separators, lower-to-upper
+/// transitions, digit-to-upper transitions, and the end of an acronym
+/// (`HTTPServer` -> `HTTP`, `Server`). Letters followed by digits stay together.
+fn split_words(s: &str) -> Vec<String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        if c.is_uppercase() && !current.is_empty() {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_is_lower) {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+
+        current.push(c);
+    }
+
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// `HTTPServer` -> `http_server`, `RefundV2` -> `refund_v2`
+pub fn to_snake_case(s: &str) -> String {
+    split_words(s)
+        .iter()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// `INVALID_INPUT` -> `InvalidInput`, `http_server` -> `HttpServer`
+pub fn to_pascal_case(s: &str) -> String {
+    split_words(s)
+        .iter()
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(|c| c.to_lowercase()))
+                    .collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// `HttpServer` -> `HTTP_SERVER`
+pub fn to_screaming_snake_case(s: &str) -> String {
+    split_words(s)
+        .iter()
+        .map(|w| w.to_uppercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Make `name` usable as a Rust identifier: invalid characters become `_`,
+/// a leading digit gets a `_` prefix, and keywords are escaped per `keywords`
+pub fn escape_ident(name: &str, keywords: KeywordEscape) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_numeric()) {
+        ident.insert(0, '_');
+    }
+
+    if KEYWORDS.contains(&ident.as_str()) {
+        if keywords == KeywordEscape::Raw && !NON_RAW_KEYWORDS.contains(&ident.as_str()) {
+            ident.insert_str(0, "r#");
+        } else {
+            ident.push('_');
+        }
+    }
+
+    ident
+}
+
+/// Snake-case module/function identifier for an intent name
+pub fn to_ident(s: &str) -> String {
+    escape_ident(&to_snake_case(s), KeywordEscape::Suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_snake_case() {
+        assert_eq!(to_snake_case("CreateRefund"), "create_refund");
+        assert_eq!(to_snake_case("HTTPServer"), "http_server");
+        assert_eq!(to_snake_case("RefundV2"), "refund_v2");
+        assert_eq!(to_snake_case("Base64Encoder"), "base64_encoder");
+        assert_eq!(to_snake_case("already_snake"), "already_snake");
+        assert_eq!(to_snake_case("kebab-case name"), "kebab_case_name");
+        assert_eq!(to_snake_case("test"), "test");
+    }
+
+    #[test]
+    fn test_to_pascal_case() {
+        assert_eq!(to_pascal_case("INVALID_INPUT"), "InvalidInput");
+        assert_eq!(to_pascal_case("NOT_FOUND"), "NotFound");
+        assert_eq!(to_pascal_case("internal_error"), "InternalError");
+        assert_eq!(to_pascal_case("HTTPServer"), "HttpServer");
+        assert_eq!(to_pascal_case("RefundV2"), "RefundV2");
+    }
+
+    #[test]
+    fn test_to_screaming_snake_case() {
+        assert_eq!(to_screaming_snake_case("HttpServer"), "HTTP_SERVER");
+        assert_eq!(to_screaming_snake_case("RefundV2"), "REFUND_V2");
+    }
+
+    #[test]
+    fn test_leading_digit() {
+        assert_eq!(to_snake_case("2FactorAuth"), "2_factor_auth");
+        assert_eq!(to_ident("2FactorAuth"), "_2_factor_auth");
+    }
+
+    #[test]
+    fn test_keyword_escaping() {
+        assert_eq!(escape_ident("type", KeywordEscape::Raw), "r#type");
+        assert_eq!(escape_ident("type", KeywordEscape::Suffix), "type_");
+        assert_eq!(escape_ident("self", KeywordEscape::Raw), "self_");
+        assert_eq!(escape_ident("amount", KeywordEscape::Raw), "amount");
+        assert_eq!(to_ident("Match"), "match_");
+    }
+}
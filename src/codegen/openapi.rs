@@ -0,0 +1,430 @@
+//! OpenAPI 3.1 document for the Endpoint intents and the types they use
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::{json, Map, Value};
+
+use crate::model::{EndpointSpec, EnumSpec, IntentKind, TypeRef, TypeSpec, VariantData};
+use crate::parser::IntentStore;
+
+/// Default location of the document written by `intent openapi`
+pub const OPENAPI_FILE: &str = "gen/openapi.json";
+
+/// Component schema for the `{"error": ...}` body every generated error returns
+const ERROR_SCHEMA: &str = "ErrorBody";
+
+/// Where `$ref`s point: OpenAPI component schemas, or the `$defs` of a
+/// standalone JSON Schema
+pub(super) const COMPONENTS: &str = "#/components/schemas/";
+pub(super) const DEFS: &str = "#/$defs/";
+
+/// Build an OpenAPI 3.1 document: one operation per endpoint, with request
+/// and response schemas for the Types and Enums the endpoints reach
+pub fn generate_openapi(store: &IntentStore) -> Value {
+    let mut endpoints = store.endpoints();
+    endpoints.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut paths: BTreeMap<String, Map<String, Value>> = BTreeMap::new();
+    let mut roots = Vec::new();
+    let mut patch_inputs = BTreeSet::new();
+    let mut has_errors = false;
+
+    for doc in endpoints {
+        let Ok(spec) = doc.as_endpoint_spec() else {
+            continue;
+        };
+
+        let input = if spec.partial {
+            patch_inputs.insert(spec.input.clone());
+            format!("{}Patch", spec.input)
+        } else {
+            spec.input.clone()
+        };
+        roots.push(spec.input.clone());
+        roots.push(spec.output.clone());
+        has_errors |= !spec.errors.is_empty();
+
+        let operation = json!({
+            "operationId": doc.name,
+            "requestBody": {
+                "required": true,
+                "content": { "application/json": { "schema": schema_ref(COMPONENTS, &input) } }
+            },
+            "responses": responses(store, &spec),
+        });
+        paths
+            .entry(openapi_path(&spec.path))
+            .or_default()
+            .insert(spec.method.to_string().to_lowercase(), operation);
+    }
+
+    let mut schemas = Map::new();
+    for name in reachable_schemas(store, roots) {
+        if let Some(spec) = type_spec(store, &name) {
+            if patch_inputs.contains(&name) {
+                schemas.insert(format!("{}Patch", name), object_schema(&spec, false));
+            }
+            schemas.insert(name, object_schema(&spec, true));
+        } else if let Some(spec) = enum_spec(store, &name) {
+            schemas.insert(name, enum_schema(&spec, COMPONENTS));
+        }
+    }
+    if has_errors {
+        schemas.insert(
+            ERROR_SCHEMA.to_string(),
+            json!({
+                "type": "object",
+                "properties": { "error": { "type": "string" } },
+                "required": ["error"]
+            }),
+        );
+    }
+
+    json!({
+        "openapi": "3.1.0",
+        "info": { "title": "intent-engine API", "version": "1.0.0" },
+        "paths": paths,
+        "components": { "schemas": schemas },
+    })
+}
+
+/// axum's `/refunds/:id` becomes OpenAPI's `/refunds/{id}`
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':').or_else(|| segment.strip_prefix('*')) {
+            Some(param) => format!("{{{}}}", param),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The success response(s) plus one entry per declared error status
+fn responses(store: &IntentStore, spec: &EndpointSpec) -> Map<String, Value> {
+    let mut responses = Map::new();
+    let success = json!({
+        "description": "Success",
+        "content": { "application/json": { "schema": schema_ref(COMPONENTS, &spec.output) } }
+    });
+
+    // An Enum output answers with each variant's declared status (200 otherwise)
+    let statuses: BTreeSet<u16> = enum_spec(store, &spec.output)
+        .map(|e| e.variants.iter().map(|v| v.status.unwrap_or(200)).collect())
+        .unwrap_or_else(|| BTreeSet::from([200]));
+    for status in statuses {
+        responses.insert(status.to_string(), success.clone());
+    }
+
+    let mut errors: BTreeMap<u16, Vec<&str>> = BTreeMap::new();
+    for error in &spec.errors {
+        errors.entry(error.status).or_default().push(&error.code);
+    }
+    for (status, codes) in errors {
+        responses.insert(
+            status.to_string(),
+            json!({
+                "description": codes.join(", "),
+                "content": { "application/json": { "schema": schema_ref(COMPONENTS, ERROR_SCHEMA) } }
+            }),
+        );
+    }
+
+    responses
+}
+
+/// Names of the Types and Enums reachable from `roots`, sorted
+pub(super) fn reachable_schemas(store: &IntentStore, roots: Vec<String>) -> BTreeSet<String> {
+    let mut seen = BTreeSet::new();
+    let mut pending = roots;
+
+    while let Some(name) = pending.pop() {
+        if !seen.insert(name.clone()) {
+            continue;
+        }
+        let referenced: Vec<TypeRef> = if let Some(spec) = type_spec(store, &name) {
+            spec.fields.into_values().map(|f| f.field_type).collect()
+        } else if let Some(spec) = enum_spec(store, &name) {
+            spec.variants
+                .iter()
+                .flat_map(|v| variant_types(&v.data))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        for type_ref in referenced {
+            pending.extend(type_ref.get_named_references().into_iter().map(str::to_string));
+        }
+    }
+
+    // Only names backed by an intent become component schemas
+    seen.retain(|name| type_spec(store, name).is_some() || enum_spec(store, name).is_some());
+    seen
+}
+
+fn type_spec(store: &IntentStore, name: &str) -> Option<TypeSpec> {
+    store
+        .get_by_kind_name(IntentKind::Type, name)
+        .and_then(|doc| doc.as_type_spec().ok())
+}
+
+fn enum_spec(store: &IntentStore, name: &str) -> Option<EnumSpec> {
+    store
+        .get_by_kind_name(IntentKind::Enum, name)
+        .and_then(|doc| doc.as_enum_spec().ok())
+}
+
+fn variant_types(data: &Option<VariantData>) -> Vec<TypeRef> {
+    let types: Vec<&String> = match data {
+        Some(VariantData::Named(fields)) => fields.values().collect(),
+        Some(VariantData::Tuple(items)) => items.iter().collect(),
+        None => Vec::new(),
+    };
+    types.into_iter().filter_map(|t| TypeRef::parse(t).ok()).collect()
+}
+
+fn schema_ref(refs: &str, name: &str) -> Value {
+    json!({ "$ref": format!("{}{}", refs, name) })
+}
+
+/// A Type as a JSON object; `with_required` is false for the all-optional
+/// `{Type}Patch` body of partial PATCH endpoints
+fn object_schema(spec: &TypeSpec, with_required: bool) -> Value {
+    let mut names: Vec<_> = spec.fields.keys().collect();
+    names.sort();
+
+    let properties: Map<String, Value> = names
+        .iter()
+        .map(|name| {
+            let field = &spec.fields[*name];
+            let mut schema = type_schema(&field.field_type);
+            if field.readonly {
+                schema["readOnly"] = json!(true);
+            }
+            ((*name).clone(), schema)
+        })
+        .collect();
+    let required: Vec<_> = names
+        .into_iter()
+        .filter(|name| with_required && spec.fields[*name].required)
+        .collect();
+
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+    schema
+}
+
+/// An Enum as serde serializes it: unit variants as strings, data variants
+/// externally tagged, or internally tagged under `serde_tag`
+pub(super) fn enum_schema(spec: &EnumSpec, refs: &str) -> Value {
+    let mut schema = if spec.variants.iter().all(|v| v.data.is_none()) && spec.serde_tag.is_none() {
+        let names: Vec<_> = spec.variants.iter().map(variant_name).collect();
+        json!({ "type": "string", "enum": names })
+    } else {
+        let variants: Vec<Value> = spec
+            .variants
+            .iter()
+            .map(|variant| variant_schema(spec, variant, refs))
+            .collect();
+        json!({ "oneOf": variants })
+    };
+    if !spec.description.is_empty() {
+        schema["description"] = json!(spec.description);
+    }
+    schema
+}
+
+fn variant_name(variant: &crate::model::EnumVariant) -> &str {
+    variant.serde_rename.as_deref().unwrap_or(&variant.name)
+}
+
+fn variant_schema(spec: &EnumSpec, variant: &crate::model::EnumVariant, refs: &str) -> Value {
+    let name = variant_name(variant);
+
+    if let Some(tag) = &spec.serde_tag {
+        // Internally tagged: the tag sits beside the variant's own fields
+        let mut schema = named_fields_schema(&variant.data, refs);
+        schema["properties"][tag] = json!({ "const": name });
+        let mut required = vec![json!(tag)];
+        required.extend(schema["required"].as_array().cloned().unwrap_or_default());
+        schema["required"] = json!(required);
+        return schema;
+    }
+
+    let data = match &variant.data {
+        None => return json!({ "const": name }),
+        Some(VariantData::Named(_)) => named_fields_schema(&variant.data, refs),
+        Some(VariantData::Tuple(items)) if items.len() == 1 => parsed_schema(&items[0], refs),
+        Some(VariantData::Tuple(items)) => {
+            let items: Vec<Value> = items.iter().map(|t| parsed_schema(t, refs)).collect();
+            json!({ "type": "array", "prefixItems": items, "minItems": items.len(), "maxItems": items.len() })
+        }
+    };
+    json!({
+        "type": "object",
+        "properties": { name: data },
+        "required": [name],
+        "additionalProperties": false
+    })
+}
+
+/// Object schema for a struct-like variant's fields (empty for other variants)
+fn named_fields_schema(data: &Option<VariantData>, refs: &str) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    if let Some(VariantData::Named(fields)) = data {
+        let mut names: Vec<_> = fields.keys().collect();
+        names.sort();
+        for field in names {
+            properties.insert(field.clone(), parsed_schema(&fields[field], refs));
+            required.push(field.clone());
+        }
+    }
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+/// Schema of an enum field type; unparseable types accept anything
+fn parsed_schema(type_str: &str, refs: &str) -> Value {
+    TypeRef::parse(type_str)
+        .map(|t| type_schema_at(&t, refs))
+        .unwrap_or_else(|_| json!({}))
+}
+
+/// JSON Schema for a field type, matching how the generated Rust serializes it
+pub fn type_schema(type_ref: &TypeRef) -> Value {
+    type_schema_at(type_ref, COMPONENTS)
+}
+
+/// `type_schema` with named types referenced under `refs`
+pub(super) fn type_schema_at(type_ref: &TypeRef, refs: &str) -> Value {
+    let type_schema = |type_ref: &TypeRef| type_schema_at(type_ref, refs);
+    let integer = |format: &str| json!({ "type": "integer", "format": format });
+    let bounded = |min: i64, max: i64| json!({ "type": "integer", "minimum": min, "maximum": max });
+
+    match type_ref {
+        TypeRef::String => json!({ "type": "string" }),
+        TypeRef::Int => integer("int64"),
+        TypeRef::Int32 => integer("int32"),
+        TypeRef::Int8 => bounded(i8::MIN.into(), i8::MAX.into()),
+        TypeRef::Int16 => bounded(i16::MIN.into(), i16::MAX.into()),
+        TypeRef::UInt8 => bounded(0, u8::MAX.into()),
+        TypeRef::UInt16 => bounded(0, u16::MAX.into()),
+        TypeRef::UInt32 => bounded(0, u32::MAX.into()),
+        TypeRef::Float => json!({ "type": "number", "format": "double" }),
+        TypeRef::Bool => json!({ "type": "boolean" }),
+        // rust_decimal's serde support writes decimals as strings
+        TypeRef::Money => json!({ "type": "string", "format": "decimal" }),
+        TypeRef::DateTime => json!({ "type": "string", "format": "date-time" }),
+        TypeRef::Uuid => json!({ "type": "string", "format": "uuid" }),
+        TypeRef::Bytes => json!({ "type": "string", "contentEncoding": "base64" }),
+        TypeRef::Array(inner) => json!({ "type": "array", "items": type_schema(inner) }),
+        TypeRef::FixedArray(inner, size) => {
+            json!({ "type": "array", "items": type_schema(inner), "minItems": size, "maxItems": size })
+        }
+        // serde writes tuples as arrays
+        TypeRef::Tuple(elements) => json!({
+            "type": "array",
+            "prefixItems": elements.iter().map(type_schema).collect::<Vec<_>>(),
+            "items": false,
+            "minItems": elements.len(),
+            "maxItems": elements.len(),
+        }),
+        TypeRef::Map(_, value) => json!({ "type": "object", "additionalProperties": type_schema(value) }),
+        TypeRef::Optional(inner) => json!({ "anyOf": [type_schema(inner), { "type": "null" }] }),
+        TypeRef::Named(name) => schema_ref(refs, name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::IntentDocument;
+
+    #[test]
+    fn test_openapi_for_fixtures() {
+        let store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        let doc = generate_openapi(&store);
+
+        assert_eq!(doc["openapi"], "3.1.0");
+        let operation = &doc["paths"]["/refund"]["post"];
+        assert_eq!(operation["operationId"], "CreateRefund");
+        assert_eq!(
+            operation["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/RefundRequest"
+        );
+        assert_eq!(
+            operation["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/RefundResponse"
+        );
+        assert_eq!(operation["responses"]["400"]["description"], "INVALID_INPUT");
+        assert_eq!(operation["responses"]["502"]["description"], "PAYMENT_FAILED");
+
+        let request = &doc["components"]["schemas"]["RefundRequest"];
+        assert_eq!(request["required"], json!(["amount", "order_id"]));
+        assert_eq!(request["properties"]["order_id"], json!({ "type": "string", "format": "uuid" }));
+        assert_eq!(
+            request["properties"]["reason"],
+            json!({ "anyOf": [{ "type": "string" }, { "type": "null" }] })
+        );
+        assert!(doc["components"]["schemas"]["ErrorBody"].is_object());
+    }
+
+    #[test]
+    fn test_enum_output_and_path_params() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Enum,
+                "ReviewOutcome".to_string(),
+                json!({
+                    "variants": [
+                        { "name": "Approved", "data": { "refund_id": "uuid" } },
+                        { "name": "Pending", "status": 202 }
+                    ]
+                }),
+            ))
+            .unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Endpoint,
+                "ReviewRefund".to_string(),
+                json!({
+                    "method": "PUT",
+                    "path": "/refunds/:id/review",
+                    "input": "RefundRequest",
+                    "output": "ReviewOutcome",
+                    "workflow": "RefundWorkflow"
+                }),
+            ))
+            .unwrap();
+
+        let doc = generate_openapi(&store);
+        let responses = &doc["paths"]["/refunds/{id}/review"]["put"]["responses"];
+        assert!(responses["200"].is_object() && responses["202"].is_object());
+
+        let outcome = &doc["components"]["schemas"]["ReviewOutcome"]["oneOf"];
+        assert_eq!(outcome[0]["required"], json!(["Approved"]));
+        assert_eq!(
+            outcome[0]["properties"]["Approved"]["properties"]["refund_id"]["format"],
+            "uuid"
+        );
+        assert_eq!(outcome[1], json!({ "const": "Pending" }));
+    }
+
+    #[test]
+    fn test_type_schema() {
+        let t = TypeRef::parse("map<string, array<uint8>>").unwrap();
+        assert_eq!(
+            type_schema(&t),
+            json!({
+                "type": "object",
+                "additionalProperties": {
+                    "type": "array",
+                    "items": { "type": "integer", "minimum": 0, "maximum": 255 }
+                }
+            })
+        );
+        assert_eq!(type_schema(&TypeRef::Money), json!({ "type": "string", "format": "decimal" }));
+    }
+}
@@ -0,0 +1,196 @@
+//! Typed HTTP client generation for Service intents
+
+use std::collections::BTreeSet;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::model::ServiceSpec;
+use crate::parser::{GenTarget, IntentConfig, IntentStore};
+
+use super::{to_ident, to_screaming_snake_case};
+
+/// Generate `services/mod.rs`: one client struct per Service, with an async
+/// method per operation
+pub fn generate_service_clients(store: &IntentStore, config: &IntentConfig) -> anyhow::Result<ServiceClientsOutput> {
+    let mut services = store.services();
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut type_names = BTreeSet::new();
+    let mut clients = Vec::new();
+    let mut intent_ids = Vec::new();
+    for doc in services {
+        let Ok(spec) = doc.as_service_spec() else {
+            continue;
+        };
+        intent_ids.push(doc.id);
+
+        let client_ident = format_ident!("{}Client", doc.name);
+        let (env_var, base_url) = service_base_url(config, &doc.name, &spec)?;
+        let struct_doc = format!(" Client for the `{}` service", doc.name);
+        let new_doc = format!(
+            " Client for `{}` when it is set, otherwise for `{}`",
+            env_var, base_url
+        );
+
+        let mut operations: Vec<_> = spec.operations.iter().collect();
+        operations.sort_by(|a, b| a.0.cmp(b.0));
+        let methods = operations.into_iter().map(|(name, operation)| {
+            type_names.insert(operation.input.clone());
+            type_names.insert(operation.output.clone());
+
+            let method_ident = format_ident!("{}", to_ident(name));
+            let input_type = format_ident!("{}", operation.input);
+            let output_type = format_ident!("{}", operation.output);
+            let http_method = operation.method.to_string();
+            let path = &operation.path;
+            let url_format = format!("{{}}{}", path.replace('{', "{{").replace('}', "}}"));
+            let method_doc = format!(" `{} {}` ({}.{})", operation.method, path, doc.name, name);
+            quote! {
+                #[doc = #method_doc]
+                pub async fn #method_ident(&self, input: &#input_type) -> Result<#output_type, HttpError> {
+                    send(#http_method, &format!(#url_format, self.base_url), input).await
+                }
+            }
+        });
+
+        clients.push(quote! {
+            #[doc = #struct_doc]
+            #[derive(Debug, Clone)]
+            pub struct #client_ident {
+                pub base_url: String,
+            }
+
+            impl #client_ident {
+                #[doc = #new_doc]
+                pub fn new() -> Self {
+                    Self {
+                        base_url: std::env::var(#env_var).unwrap_or_else(|_| #base_url.to_string()),
+                    }
+                }
+
+                pub fn with_base_url(base_url: impl Into<String>) -> Self {
+                    Self { base_url: base_url.into() }
+                }
+
+                #(#methods)*
+            }
+
+            impl Default for #client_ident {
+                fn default() -> Self {
+                    Self::new()
+                }
+            }
+        });
+    }
+
+    let type_idents = type_names.iter().map(|name| format_ident!("{}", name));
+    let transport = send_tokens(&config.runtime.http_client, config.generation.target);
+    let tokens = quote! {
+        // @generated by intent-engine v1.0
+        // DO NOT EDIT — changes will be overwritten
+
+        use crate::effects::http::HttpError;
+        use crate::types::{#(#type_idents),*};
+
+        #(#clients)*
+
+        #transport
+    };
+
+    let file = syn::parse2(tokens).expect("Failed to parse services/mod.rs");
+    Ok(ServiceClientsOutput {
+        mod_rs: prettyplease::unparse(&file),
+        intent_ids,
+    })
+}
+
+/// The `{SERVICE}_BASE_URL` variable that overrides a service's base URL, and
+/// the URL used when it is unset: `<Service>.base_url` in the default
+/// environment, else the spec's `base_url`
+pub(super) fn service_base_url(
+    config: &IntentConfig,
+    name: &str,
+    spec: &ServiceSpec,
+) -> anyhow::Result<(String, String)> {
+    let env_var = format!("{}_BASE_URL", to_screaming_snake_case(name));
+    let base_url = config
+        .get_env_value(config.default_env(), &format!("{}.base_url", name))?
+        .unwrap_or(spec.base_url.clone());
+    Ok((env_var, base_url))
+}
+
+/// The `send` helper every client method goes through. GET and DELETE send
+/// the input as query parameters, other methods as a JSON body.
+fn send_tokens(http_client: &str, target: GenTarget) -> TokenStream {
+    let signature = quote! {
+        async fn send<I, O>(method: &str, url: &str, input: &I) -> Result<O, HttpError>
+        where
+            I: serde::Serialize,
+            O: serde::de::DeserializeOwned,
+    };
+
+    let native = match http_client {
+        "reqwest" => quote! {
+            #signature
+            {
+                let client = reqwest::Client::new();
+                let request = match method {
+                    "GET" => client.get(url).query(input),
+                    "DELETE" => client.delete(url).query(input),
+                    "PUT" => client.put(url).json(input),
+                    "PATCH" => client.patch(url).json(input),
+                    _ => client.post(url).json(input),
+                };
+                let response = request.send().await?;
+                if !response.status().is_success() {
+                    return Err(HttpError::StatusError(response.status().as_u16()));
+                }
+                Ok(response.json().await?)
+            }
+        },
+        other => {
+            let message = format!("Send requests with {}", other);
+            quote! {
+                #signature
+                {
+                    let _ = (method, url, input);
+                    todo!(#message)
+                }
+            }
+        }
+    };
+
+    match target {
+        GenTarget::Native => native,
+        // The browser's fetch can't carry a GET body, so GET and DELETE send
+        // no input on wasm32
+        GenTarget::Wasm => quote! {
+            #[cfg(not(target_arch = "wasm32"))]
+            #native
+
+            #[cfg(target_arch = "wasm32")]
+            #signature
+            {
+                let request = match method {
+                    "GET" => gloo_net::http::Request::get(url).build()?,
+                    "DELETE" => gloo_net::http::Request::delete(url).build()?,
+                    "PUT" => gloo_net::http::Request::put(url).json(input)?,
+                    "PATCH" => gloo_net::http::Request::patch(url).json(input)?,
+                    _ => gloo_net::http::Request::post(url).json(input)?,
+                };
+                let response = request.send().await?;
+                if !response.ok() {
+                    return Err(HttpError::StatusError(response.status()));
+                }
+                Ok(response.json().await?)
+            }
+        },
+    }
+}
+
+pub struct ServiceClientsOutput {
+    pub mod_rs: String,
+    /// Services the clients were generated from
+    pub intent_ids: Vec<uuid::Uuid>,
+}
@@ -2,13 +2,17 @@
 
 use std::collections::HashMap;
 
+use crate::model::IntentError;
 use crate::parser::IntentStore;
+use super::check_output_path;
+use super::naming::{to_pascal_case, to_screaming_snake_case, to_snake_case};
 
-/// Process all Template intents and generate output files
+/// Process all Template intents and generate output files. Fails on the
+/// first Template whose `output_file` is absolute or contains `..`.
 pub fn generate_from_templates(
     store: &IntentStore,
     context: &TemplateContext,
-) -> Vec<TemplateOutput> {
+) -> Result<Vec<TemplateOutput>, IntentError> {
     let mut outputs = Vec::new();
 
     let templates: Vec<_> = store.templates().into_iter().collect();
@@ -17,6 +21,7 @@ pub fn generate_from_templates(
         if let Ok(spec) = doc.as_template_spec() {
             let content = render_template(&spec.template, context, &spec.partials);
             let output_path = interpolate_path(&spec.output_file, context);
+            check_output_path(&output_path, &format!("Template '{}'", doc.name))?;
             outputs.push(TemplateOutput {
                 path: output_path,
                 content,
@@ -25,7 +30,7 @@ pub fn generate_from_templates(
         }
     }
 
-    outputs
+    Ok(outputs)
 }
 
 /// Output from template rendering
@@ -372,37 +377,3 @@ fn interpolate_path(path: &str, context: &TemplateContext) -> String {
     interpolate_variables(path, context)
 }
 
-fn to_snake_case(s: &str) -> String {
-    let mut result = String::new();
-    for (i, c) in s.chars().enumerate() {
-        if c.is_uppercase() {
-            if i > 0 {
-                result.push('_');
-            }
-            result.push(c.to_ascii_lowercase());
-        } else {
-            result.push(c);
-        }
-    }
-    result
-}
-
-fn to_pascal_case(s: &str) -> String {
-    let mut result = String::new();
-    let mut capitalize_next = true;
-    for c in s.chars() {
-        if c == '_' || c == '-' {
-            capitalize_next = true;
-        } else if capitalize_next {
-            result.push(c.to_ascii_uppercase());
-            capitalize_next = false;
-        } else {
-            result.push(c);
-        }
-    }
-    result
-}
-
-fn to_screaming_snake_case(s: &str) -> String {
-    to_snake_case(s).to_uppercase()
-}
@@ -11,7 +11,10 @@ use serde::Serialize;
 use uuid::Uuid;
 
 use crate::model::IntentKind;
-use crate::parser::IntentStore;
+use crate::parser::{IntentConfig, IntentStore, TypesLayout};
+
+use super::GenWriter;
+use super::naming::to_ident;
 
 /// Trace entry pointing to a generated code location
 #[derive(Debug, Clone, Serialize)]
@@ -56,26 +59,36 @@ impl TraceMap {
 }
 
 /// Generate trace map from store
-pub fn generate_trace_map(store: &IntentStore) -> TraceMap {
+pub fn generate_trace_map(store: &IntentStore, config: &IntentConfig) -> TraceMap {
     let mut trace = TraceMap::new();
 
     // Types
-    let mut line = 10; // After header
-    for doc in store.types() {
-        trace.add(doc.id, "gen/src/types.rs", line, &doc.name);
-        line += 10; // Rough estimate per type
+    match config.generation.types_layout {
+        TypesLayout::Single => {
+            let mut line = 10; // After header
+            for doc in store.types() {
+                trace.add(doc.id, "gen/src/types.rs", line, &doc.name);
+                line += 10; // Rough estimate per type
+            }
+        }
+        TypesLayout::PerType => {
+            for doc in store.types() {
+                let file = format!("gen/src/types/{}.rs", to_ident(&doc.name));
+                trace.add(doc.id, &file, 10, &doc.name);
+            }
+        }
     }
 
     // Endpoints
     for doc in store.endpoints() {
-        let mod_name = to_snake_case(&doc.name);
+        let mod_name = to_ident(&doc.name);
         let file = format!("gen/src/endpoints/{}.rs", mod_name);
         trace.add(doc.id, &file, 10, &mod_name);
     }
 
     // Workflows
     for doc in store.workflows() {
-        let mod_name = to_snake_case(&doc.name);
+        let mod_name = to_ident(&doc.name);
         let file = format!("gen/src/workflows/{}.rs", mod_name);
         trace.add(doc.id, &file, 10, &mod_name);
     }
@@ -84,31 +97,12 @@ pub fn generate_trace_map(store: &IntentStore) -> TraceMap {
 }
 
 /// Write trace map to lock file
-pub fn write_trace_map(trace: &TraceMap) -> anyhow::Result<()> {
+pub fn write_trace_map(trace: &TraceMap, writer: &mut dyn GenWriter) -> anyhow::Result<()> {
     let lock_path = ".intent/locks/trace-map.json";
 
-    // Create directory if needed
-    if let Some(parent) = std::path::Path::new(lock_path).parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-
     let content = serde_json::to_string_pretty(trace)?;
-    std::fs::write(lock_path, content)?;
+    writer.write(lock_path, &content)?;
 
     Ok(())
 }
 
-fn to_snake_case(s: &str) -> String {
-    let mut result = String::new();
-    for (i, c) in s.chars().enumerate() {
-        if c.is_uppercase() {
-            if i > 0 {
-                result.push('_');
-            }
-            result.push(c.to_lowercase().next().unwrap());
-        } else {
-            result.push(c);
-        }
-    }
-    result
-}
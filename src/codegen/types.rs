@@ -1,18 +1,25 @@
 //! Type code generation
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
-use crate::model::{IntentKind, TypeRef};
-use crate::parser::IntentStore;
+use crate::model::{FieldDef, IntentDocument, IntentKind, TypeRef};
+use crate::parser::{BytesEncoding, IntentConfig, IntentStore};
+use crate::validation::is_sensitive_field;
 
-/// Generate types.rs content
-pub fn generate_types(store: &IntentStore) -> String {
-    let mut types: Vec<_> = store.types().into_iter().collect();
-    types.sort_by(|a, b| a.name.cmp(&b.name));
+use super::naming::{escape_ident, to_ident, to_snake_case, KeywordEscape};
+use super::openapi::{enum_schema, reachable_schemas, type_schema_at, DEFS};
 
+/// Serde `with` path for fields holding `bytes`; the helper module lives in
+/// the types module
+const BYTES_WITH: &str = "crate::types::bytes_encoding";
+
+/// Generate types.rs content
+pub fn generate_types(store: &IntentStore, config: &IntentConfig) -> String {
+    let inputs = EndpointInputs::of(store);
+    let conditional_derives = conditional_derives(config);
     let mut tokens = TokenStream::new();
 
     // Header
@@ -25,61 +32,684 @@ pub fn generate_types(store: &IntentStore) -> String {
     });
 
     // Generate each type
-    for doc in types {
-        let Ok(spec) = doc.as_type_spec() else {
-            continue;
+    for doc in sorted_types(store) {
+        tokens.extend(type_tokens(doc, &inputs, &conditional_derives));
+    }
+
+    if uses_bytes(store) {
+        tokens.extend(bytes_encoding_module(config.generation.bytes_encoding));
+    }
+
+    // Format with prettyplease
+    let file = syn::parse2(tokens).expect("Failed to parse generated code");
+    prettyplease::unparse(&file)
+}
+
+/// Generate one file per type plus a `mod.rs` re-exporting them all
+/// (`[generation] types_layout = "per_type"`)
+pub fn generate_types_per_type(store: &IntentStore, config: &IntentConfig) -> TypesOutput {
+    let inputs = EndpointInputs::of(store);
+    let conditional_derives = conditional_derives(config);
+    let mut mod_decls = Vec::new();
+    let mut files = Vec::new();
+
+    for doc in sorted_types(store) {
+        let mod_name = to_ident(&doc.name);
+        let mod_ident = format_ident!("{}", mod_name);
+
+        mod_decls.push(quote! {
+            mod #mod_ident;
+            pub use #mod_ident::*;
+        });
+
+        // Sibling types are reachable through the re-exports in mod.rs
+        let references_types = doc
+            .as_type_spec()
+            .map(|spec| !spec.get_type_references().is_empty())
+            .unwrap_or(false);
+        let sibling_import = if references_types {
+            quote! { use super::*; }
+        } else {
+            quote! {}
+        };
+
+        let body = type_tokens(doc, &inputs, &conditional_derives);
+        let file_tokens = quote! {
+            // @generated by intent-engine v1.0
+            // DO NOT EDIT — changes will be overwritten
+            // source: #mod_name
+
+            use serde::{Deserialize, Serialize};
+            use std::collections::HashMap;
+            #sibling_import
+
+            #body
+        };
+
+        let file = syn::parse2(file_tokens).expect("Failed to parse type file");
+        files.push(TypeFile {
+            intent_id: doc.id,
+            name: format!("{}.rs", mod_name),
+            content: prettyplease::unparse(&file),
+        });
+    }
+
+    let bytes_module = if uses_bytes(store) {
+        bytes_encoding_module(config.generation.bytes_encoding)
+    } else {
+        quote! {}
+    };
+
+    let mod_rs_tokens = quote! {
+        // @generated by intent-engine v1.0
+        // DO NOT EDIT — changes will be overwritten
+
+        #(#mod_decls)*
+
+        #bytes_module
+    };
+
+    let file = syn::parse2(mod_rs_tokens).expect("Failed to parse mod.rs");
+
+    TypesOutput {
+        mod_rs: prettyplease::unparse(&file),
+        files,
+    }
+}
+
+pub struct TypesOutput {
+    pub mod_rs: String,
+    pub files: Vec<TypeFile>,
+}
+
+pub struct TypeFile {
+    pub intent_id: uuid::Uuid,
+    pub name: String,
+    pub content: String,
+}
+
+/// Type intents sorted by name
+/// Draft 2020-12 JSON Schema for the JSON form of a Type intent: an object
+/// of its fields, with `required: true` fields required and no others
+/// allowed. Named field types are `$ref`s to `#/$defs/<Name>`.
+pub fn type_to_json_schema(doc: &IntentDocument) -> serde_json::Value {
+    let mut schema = serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": doc.name,
+        "type": "object",
+    });
+    let Ok(spec) = doc.as_type_spec() else {
+        return schema;
+    };
+
+    let mut names: Vec<_> = spec.fields.keys().collect();
+    names.sort();
+    let mut properties = serde_json::Map::new();
+    for name in &names {
+        let field = &spec.fields[*name];
+        let mut property = type_schema_at(&field.field_type, DEFS);
+        if let Some(default) = &field.default {
+            property["default"] = default.clone();
+        }
+        properties.insert((*name).clone(), property);
+    }
+    let required: Vec<_> = names.into_iter().filter(|name| spec.fields[*name].required).collect();
+
+    schema["properties"] = serde_json::Value::Object(properties);
+    if !required.is_empty() {
+        schema["required"] = serde_json::json!(required);
+    }
+    schema["additionalProperties"] = serde_json::json!(false);
+    schema
+}
+
+/// `type_to_json_schema` with a `$defs` entry for every Type and Enum the
+/// Type reaches, so the schema stands alone
+pub fn type_json_schema_document(store: &IntentStore, doc: &IntentDocument) -> serde_json::Value {
+    let mut schema = type_to_json_schema(doc);
+    let referenced: Vec<String> = doc
+        .as_type_spec()
+        .map(|spec| {
+            spec.fields
+                .values()
+                .flat_map(|field| field.field_type.get_named_references())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut defs = serde_json::Map::new();
+    for name in reachable_schemas(store, referenced) {
+        let def = match store.get_type_or_enum(&name) {
+            Some(def_doc) if def_doc.kind == IntentKind::Type => {
+                let mut def = type_to_json_schema(def_doc);
+                def.as_object_mut().map(|def| def.remove("$schema"));
+                def
+            }
+            Some(def_doc) => match def_doc.as_enum_spec() {
+                Ok(spec) => enum_schema(&spec, DEFS),
+                Err(_) => continue,
+            },
+            None => continue,
+        };
+        defs.insert(name, def);
+    }
+    if !defs.is_empty() {
+        schema["$defs"] = serde_json::Value::Object(defs);
+    }
+    schema
+}
+
+fn sorted_types(store: &IntentStore) -> Vec<&IntentDocument> {
+    let mut types = store.types();
+    types.sort_by(|a, b| a.name.cmp(&b.name));
+    types
+}
+
+/// Names of the Types endpoints take as input
+struct EndpointInputs {
+    /// Inputs of partial (PATCH) endpoints, which also get a patch struct
+    partial: BTreeSet<String>,
+    /// Inputs of other endpoints, which get an input struct when they have
+    /// readonly fields
+    whole: BTreeSet<String>,
+}
+
+impl EndpointInputs {
+    fn of(store: &IntentStore) -> Self {
+        let mut inputs = Self {
+            partial: BTreeSet::new(),
+            whole: BTreeSet::new(),
         };
+        for spec in store.endpoints().iter().filter_map(|d| d.as_endpoint_spec().ok()) {
+            if spec.partial {
+                inputs.partial.insert(spec.input);
+            } else {
+                inputs.whole.insert(spec.input);
+            }
+        }
+        inputs
+    }
+}
+
+/// `#[cfg_attr(feature = "...", derive(...))]` for each feature in
+/// `[generation.conditional_derives]`; paths are checked when the config loads
+fn conditional_derives(config: &IntentConfig) -> TokenStream {
+    config
+        .generation
+        .conditional_derives
+        .iter()
+        .filter(|(_, derives)| !derives.is_empty())
+        .map(|(feature, derives)| {
+            let derives = derives
+                .iter()
+                .filter_map(|derive| syn::parse_str::<syn::Path>(derive).ok());
+            quote! { #[cfg_attr(feature = #feature, derive(#(#derives),*))] }
+        })
+        .collect()
+}
+
+/// Struct (and optional patch and input structs) for a single Type intent
+fn type_tokens(
+    doc: &IntentDocument,
+    inputs: &EndpointInputs,
+    conditional_derives: &TokenStream,
+) -> TokenStream {
+    let mut tokens = TokenStream::new();
+
+    let Ok(spec) = doc.as_type_spec() else {
+        return tokens;
+    };
+
 
-        let type_name = format_ident!("{}", &doc.name);
-        let mut fields = Vec::new();
-        let mut field_names: Vec<_> = spec.fields.keys().collect();
-        field_names.sort();
+    let type_name = format_ident!("{}", &doc.name);
+    let mut fields = Vec::new();
+    let mut patch_fields = Vec::new();
+    let mut patch_merges = Vec::new();
+    let mut debug_fields = Vec::new();
+    let mut patch_debug_fields = Vec::new();
+    let mut input_fields = Vec::new();
+    let mut input_debug_fields = Vec::new();
+    let mut input_conversions = Vec::new();
+    let mut default_fns = Vec::new();
+    let mut has_sensitive = false;
+    let mut has_deprecated = false;
+    let mut field_names: Vec<_> = spec.fields.keys().collect();
+    field_names.sort();
 
-        for field_name in field_names {
-            let field_def = spec.fields.get(field_name).unwrap();
-            let field_ident = format_ident!("{}", field_name);
-            let field_type = type_ref_to_tokens(&field_def.field_type);
+    for field_name in field_names {
+        let field_def = spec.fields.get(field_name).unwrap();
+        let field_ident = format_ident!("{}", escape_ident(field_name, KeywordEscape::Raw));
+        let field_type = field_type_tokens(field_def);
 
-            // Wrap in Option if not required
-            let field_type = if field_def.required {
-                field_type
+        // Sensitive values never reach logs through Debug
+        let (debug_field, patch_debug_field) = if is_sensitive_field(field_name, field_def) {
+            has_sensitive = true;
+            let patch_debug_field = quote! { .field(#field_name, &self.#field_ident.as_ref().map(|_| "***")) };
+            if field_def.required {
+                (quote! { .field(#field_name, &"***") }, patch_debug_field)
             } else {
-                quote! { Option<#field_type> }
-            };
+                (patch_debug_field.clone(), patch_debug_field)
+            }
+        } else {
+            let debug_field = quote! { .field(#field_name, &self.#field_ident) };
+            (debug_field.clone(), debug_field)
+        };
+        debug_fields.push(debug_field.clone());
 
-            // Add serde skip_serializing_if for optional fields
-            let attrs = if !field_def.required {
-                quote! {
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                }
+        // Requests never set a server-assigned field (which can't be
+        // required), so it is left out of the patch and input structs
+        if field_def.readonly {
+            input_conversions.push(quote! { #field_ident: None, });
+        } else {
+            input_debug_fields.push(debug_field);
+            input_conversions.push(quote! { #field_ident: input.#field_ident, });
+            patch_debug_fields.push(patch_debug_field);
+
+            let patch_with = if contains_bytes(&field_def.field_type) {
+                quote! { #[serde(with = #BYTES_WITH)] }
             } else {
                 quote! {}
             };
-
-            fields.push(quote! {
-                #attrs
-                pub #field_ident: #field_type,
+            patch_fields.push(quote! {
+                #[serde(default, skip_serializing_if = "Option::is_none")]
+                #patch_with
+                pub #field_ident: Option<#field_type>,
+            });
+            patch_merges.push(if field_def.required {
+                quote! {
+                    if let Some(value) = self.#field_ident {
+                        target.#field_ident = value;
+                    }
+                }
+            } else {
+                quote! {
+                    if let Some(value) = self.#field_ident {
+                        target.#field_ident = Some(value);
+                    }
+                }
             });
         }
 
+        // Wrap in Option if not required
+        let field_type = if field_def.required {
+            field_type
+        } else {
+            quote! { Option<#field_type> }
+        };
+
+        // Add serde skip_serializing_if for optional fields
+        let attrs = if !field_def.required {
+            quote! {
+                #[serde(skip_serializing_if = "Option::is_none")]
+            }
+        } else {
+            quote! {}
+        };
+        let bytes_attrs = bytes_field_attrs(&field_def.field_type, field_def.required);
+
+        // Absent fields with a declared default are filled in on deserialize
+        let default_attrs = match &field_def.default {
+            Some(default) => {
+                let fn_name = format!("default_{}_{}", to_snake_case(&doc.name), field_name);
+                let fn_ident = format_ident!("{}", fn_name);
+                let value = if field_def.required {
+                    default_value_tokens(default, &field_def.field_type)
+                } else if default.is_null() {
+                    quote! { None }
+                } else {
+                    let value = default_value_tokens(default, &field_def.field_type);
+                    quote! { Some(#value) }
+                };
+                default_fns.push(quote! {
+                    fn #fn_ident() -> #field_type {
+                        #value
+                    }
+                });
+                quote! { #[serde(default = #fn_name)] }
+            }
+            None => quote! {},
+        };
+
+        let deprecated_attrs = match &field_def.deprecated {
+            Some(reason) => {
+                has_deprecated = true;
+                quote! { #[deprecated = #reason] }
+            }
+            None => quote! {},
+        };
+
+        let field = quote! {
+            #deprecated_attrs
+            #attrs
+            #default_attrs
+            #bytes_attrs
+            pub #field_ident: #field_type,
+        };
+        if !field_def.readonly {
+            input_fields.push(field.clone());
+        }
+        fields.push(field);
+    }
+
+    let derive_debug = if has_sensitive {
+        quote! {}
+    } else {
+        quote! { Debug, }
+    };
+    // The type's own impls read every field, deprecated or not
+    let allow_deprecated = if has_deprecated {
+        quote! { #[allow(deprecated)] }
+    } else {
+        quote! {}
+    };
+
+    tokens.extend(quote! {
+        #[derive(#derive_debug Clone, Serialize, Deserialize)]
+        #conditional_derives
+        pub struct #type_name {
+            #(#fields)*
+        }
+    });
+    if has_sensitive {
+        let debug = debug_impl(&type_name, &debug_fields);
+        tokens.extend(quote! { #allow_deprecated #debug });
+    }
+    tokens.extend(default_fns);
+
+    if inputs.partial.contains(&doc.name) {
+        let patch_name = format_ident!("{}Patch", &doc.name);
+        let patch_doc = format!(
+            " Partial update of `{}`: absent fields are left unchanged",
+            doc.name
+        );
         tokens.extend(quote! {
-            #[derive(Debug, Clone, Serialize, Deserialize)]
-            pub struct #type_name {
-                #(#fields)*
+            #[doc = #patch_doc]
+            #[derive(#derive_debug Clone, Default, Serialize, Deserialize)]
+            pub struct #patch_name {
+                #(#patch_fields)*
+            }
+
+            impl #patch_name {
+                /// Merge the fields present in this patch into `target`
+                #allow_deprecated
+                pub fn apply(self, target: &mut #type_name) {
+                    #(#patch_merges)*
+                }
             }
         });
+        if has_sensitive {
+            tokens.extend(debug_impl(&patch_name, &patch_debug_fields));
+        }
     }
 
-    // Format with prettyplease
-    let file = syn::parse2(tokens).expect("Failed to parse generated code");
-    prettyplease::unparse(&file)
+    if inputs.whole.contains(&doc.name) && spec.has_readonly_fields() {
+        let input_name = format_ident!("{}Input", &doc.name);
+        let input_doc = format!(
+            " `{}` as endpoints receive it, without the server-assigned fields",
+            doc.name
+        );
+        tokens.extend(quote! {
+            #[doc = #input_doc]
+            #[derive(#derive_debug Clone, Serialize, Deserialize)]
+            #conditional_derives
+            pub struct #input_name {
+                #(#input_fields)*
+            }
+
+            impl From<#input_name> for #type_name {
+                /// Server-assigned fields start out unset
+                #allow_deprecated
+                fn from(input: #input_name) -> Self {
+                    Self {
+                        #(#input_conversions)*
+                    }
+                }
+            }
+        });
+        if has_sensitive {
+            let debug = debug_impl(&input_name, &input_debug_fields);
+            tokens.extend(quote! { #allow_deprecated #debug });
+        }
+    }
+
+    tokens
+}
+
+/// The field's declared type, or its `rust_type_override` (kept inside
+/// `Option` for `optional<..>`); validation has checked the override fits
+fn field_type_tokens(field_def: &FieldDef) -> TokenStream {
+    let Some(rust_type) = field_def
+        .rust_type_override
+        .as_deref()
+        .filter(|_| field_def.check_rust_type_override().is_ok())
+    else {
+        return type_ref_to_tokens(&field_def.field_type);
+    };
+    let rust_type: TokenStream = rust_type.parse().expect("allowed overrides are valid Rust");
+    match field_def.field_type {
+        TypeRef::Optional(_) => quote! { Option<#rust_type> },
+        _ => rust_type,
+    }
+}
+
+/// Expression building a field's declared `default`. Primitives become
+/// literals; anything else is parsed from its JSON text.
+fn default_value_tokens(value: &serde_json::Value, type_ref: &TypeRef) -> TokenStream {
+    use serde_json::Value;
+
+    match (type_ref, value) {
+        (TypeRef::Optional(_), Value::Null) => quote! { None },
+        (TypeRef::Optional(inner), _) => {
+            let inner = default_value_tokens(value, inner);
+            quote! { Some(#inner) }
+        }
+        (TypeRef::String, Value::String(s)) => quote! { #s.to_string() },
+        (TypeRef::Bool, Value::Bool(b)) => quote! { #b },
+        (
+            TypeRef::Int
+            | TypeRef::Int8
+            | TypeRef::Int16
+            | TypeRef::Int32
+            | TypeRef::UInt8
+            | TypeRef::UInt16
+            | TypeRef::UInt32,
+            Value::Number(n),
+        ) if n.is_i64() => {
+            let n = proc_macro2::Literal::i64_unsuffixed(n.as_i64().unwrap());
+            quote! { #n }
+        }
+        (TypeRef::Float, Value::Number(n)) => {
+            let n = proc_macro2::Literal::f64_unsuffixed(n.as_f64().unwrap_or_default());
+            quote! { #n }
+        }
+        _ => {
+            let json = value.to_string();
+            quote! { serde_json::from_str(#json).expect("declared default is valid JSON for the field") }
+        }
+    }
+}
+
+/// Hand-written `Debug` for structs with sensitive fields, which print `***`
+fn debug_impl(type_name: &proc_macro2::Ident, fields: &[TokenStream]) -> TokenStream {
+    let name = type_name.to_string();
+    quote! {
+        impl std::fmt::Debug for #type_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(#name)
+                    #(#fields)*
+                    .finish()
+            }
+        }
+    }
+}
+
+/// Serde attributes routing a field holding `bytes` through the encoding
+/// helper; other fields get none. Optional ones default to `None` when absent.
+fn bytes_field_attrs(field_type: &TypeRef, required: bool) -> TokenStream {
+    if !contains_bytes(field_type) {
+        quote! {}
+    } else if required && !matches!(field_type, TypeRef::Optional(_)) {
+        quote! { #[serde(with = #BYTES_WITH)] }
+    } else {
+        quote! { #[serde(default, with = #BYTES_WITH)] }
+    }
+}
+
+/// `bytes`, or options, arrays and map values of it, which the encoding
+/// helper handles. Fixed arrays and tuples keep serde's default encoding.
+fn contains_bytes(type_ref: &TypeRef) -> bool {
+    match type_ref {
+        TypeRef::Bytes => true,
+        TypeRef::Optional(inner) | TypeRef::Array(inner) | TypeRef::Map(_, inner) => contains_bytes(inner),
+        _ => false,
+    }
+}
+
+/// Whether any field holds `bytes`, needing the encoding helper and its
+/// crate
+pub(crate) fn uses_bytes(store: &IntentStore) -> bool {
+    store
+        .types()
+        .iter()
+        .filter_map(|doc| doc.as_type_spec().ok())
+        .flat_map(|spec| spec.fields.into_values())
+        .any(|field| contains_bytes(&field.field_type))
+}
+
+/// `bytes_encoding` helper module for `#[serde(with = ...)]`
+fn bytes_encoding_module(encoding: BytesEncoding) -> TokenStream {
+    let (doc, encode, decode) = match encoding {
+        BytesEncoding::Base64 => (
+            " Serde helpers encoding `bytes` fields as base64 strings",
+            quote! { base64::engine::general_purpose::STANDARD.encode(bytes) },
+            quote! { base64::engine::general_purpose::STANDARD.decode(s) },
+        ),
+        BytesEncoding::Hex => (
+            " Serde helpers encoding `bytes` fields as hex strings",
+            quote! { hex::encode(bytes) },
+            quote! { hex::decode(s) },
+        ),
+    };
+    let engine_import = match encoding {
+        BytesEncoding::Base64 => quote! { use base64::Engine as _; },
+        BytesEncoding::Hex => quote! {},
+    };
+
+    quote! {
+        #[doc = #doc]
+        pub mod bytes_encoding {
+            #engine_import
+            use serde::{Deserialize, Deserializer, Serialize, Serializer};
+            use std::collections::HashMap;
+            use std::hash::Hash;
+
+            fn encode(bytes: &[u8]) -> String {
+                #encode
+            }
+
+            fn decode(s: &str) -> Result<Vec<u8>, impl std::fmt::Display> {
+                #decode
+            }
+
+            /// `bytes`, possibly inside options, arrays and maps; each
+            /// `Vec<u8>` is encoded as a string
+            pub trait Encoded: Sized {
+                fn serialize_encoded<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+
+                fn deserialize_encoded<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+            }
+
+            impl Encoded for Vec<u8> {
+                fn serialize_encoded<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_str(&encode(self))
+                }
+
+                fn deserialize_encoded<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let s = String::deserialize(deserializer)?;
+                    decode(&s).map_err(serde::de::Error::custom)
+                }
+            }
+
+            impl<T: Encoded> Encoded for Option<T> {
+                fn serialize_encoded<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    match self {
+                        Some(value) => serializer.serialize_some(&Wrapped(value)),
+                        None => serializer.serialize_none(),
+                    }
+                }
+
+                fn deserialize_encoded<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    Ok(Option::<Unwrapped<T>>::deserialize(deserializer)?.map(|value| value.0))
+                }
+            }
+
+            impl<T: Encoded> Encoded for Vec<T> {
+                fn serialize_encoded<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_seq(self.iter().map(Wrapped))
+                }
+
+                fn deserialize_encoded<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let values = Vec::<Unwrapped<T>>::deserialize(deserializer)?;
+                    Ok(values.into_iter().map(|value| value.0).collect())
+                }
+            }
+
+            impl<K, V> Encoded for HashMap<K, V>
+            where
+                K: Serialize + for<'de> Deserialize<'de> + Eq + Hash,
+                V: Encoded,
+            {
+                fn serialize_encoded<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_map(self.iter().map(|(key, value)| (key, Wrapped(value))))
+                }
+
+                fn deserialize_encoded<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let values = HashMap::<K, Unwrapped<V>>::deserialize(deserializer)?;
+                    Ok(values.into_iter().map(|(key, value)| (key, value.0)).collect())
+                }
+            }
+
+            /// Serializes a nested value through `Encoded`
+            struct Wrapped<'a, T>(&'a T);
+
+            impl<T: Encoded> Serialize for Wrapped<'_, T> {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    self.0.serialize_encoded(serializer)
+                }
+            }
+
+            /// Deserializes a nested value through `Encoded`
+            struct Unwrapped<T>(T);
+
+            impl<'de, T: Encoded> Deserialize<'de> for Unwrapped<T> {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    T::deserialize_encoded(deserializer).map(Unwrapped)
+                }
+            }
+
+            pub fn serialize<T: Encoded, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+                value.serialize_encoded(serializer)
+            }
+
+            pub fn deserialize<'de, T: Encoded, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+                T::deserialize_encoded(deserializer)
+            }
+        }
+    }
 }
 
 /// Convert a TypeRef to a Rust type token stream
-fn type_ref_to_tokens(type_ref: &TypeRef) -> TokenStream {
+pub(crate) fn type_ref_to_tokens(type_ref: &TypeRef) -> TokenStream {
     match type_ref {
         TypeRef::String => quote! { String },
         TypeRef::Int => quote! { i64 },
+        TypeRef::Int8 => quote! { i8 },
+        TypeRef::Int16 => quote! { i16 },
+        TypeRef::Int32 => quote! { i32 },
+        TypeRef::UInt8 => quote! { u8 },
+        TypeRef::UInt16 => quote! { u16 },
+        TypeRef::UInt32 => quote! { u32 },
         TypeRef::Float => quote! { f64 },
         TypeRef::Bool => quote! { bool },
         TypeRef::Money => quote! { rust_decimal::Decimal },
@@ -90,6 +720,15 @@ fn type_ref_to_tokens(type_ref: &TypeRef) -> TokenStream {
             let inner_tokens = type_ref_to_tokens(inner);
             quote! { Vec<#inner_tokens> }
         }
+        TypeRef::FixedArray(inner, size) => {
+            let inner_tokens = type_ref_to_tokens(inner);
+            let size = proc_macro2::Literal::usize_unsuffixed(*size);
+            quote! { [#inner_tokens; #size] }
+        }
+        TypeRef::Tuple(elements) => {
+            let element_tokens = elements.iter().map(type_ref_to_tokens);
+            quote! { (#(#element_tokens),*) }
+        }
         TypeRef::Map(k, v) => {
             let k_tokens = type_ref_to_tokens(k);
             let v_tokens = type_ref_to_tokens(v);
@@ -118,4 +757,77 @@ mod tests {
         let tokens = type_ref_to_tokens(&TypeRef::Array(Box::new(TypeRef::Int)));
         assert_eq!(tokens.to_string(), "Vec < i64 >");
     }
+
+    #[test]
+    fn test_type_json_schema() {
+        let mut store = IntentStore::new();
+        let order = IntentDocument::with_spec(
+            IntentKind::Type,
+            "Order".to_string(),
+            serde_json::json!({
+                "fields": {
+                    "id": { "type": "uuid", "required": true },
+                    "lines": { "type": "array<OrderLine>", "required": true },
+                    "notes": { "type": "optional<string>" },
+                    "tags": { "type": "map<string, int>", "required": false, "default": {} }
+                }
+            }),
+        );
+        store.add(order.clone()).unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Type,
+                "OrderLine".to_string(),
+                serde_json::json!({ "fields": { "sku": { "type": "string", "required": true } } }),
+            ))
+            .unwrap();
+
+        let schema = type_to_json_schema(&order);
+        assert_eq!(schema["$schema"], "https://json-schema.org/draft/2020-12/schema");
+        assert_eq!(schema["required"], serde_json::json!(["id", "lines"]));
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(
+            schema["properties"]["lines"],
+            serde_json::json!({ "type": "array", "items": { "$ref": "#/$defs/OrderLine" } })
+        );
+        assert_eq!(
+            schema["properties"]["notes"],
+            serde_json::json!({ "anyOf": [{ "type": "string" }, { "type": "null" }] })
+        );
+        assert_eq!(
+            schema["properties"]["tags"],
+            serde_json::json!({
+                "type": "object",
+                "additionalProperties": { "type": "integer", "format": "int64" },
+                "default": {}
+            })
+        );
+        assert!(schema.get("$defs").is_none());
+
+        let document = type_json_schema_document(&store, &order);
+        assert_eq!(document["$defs"]["OrderLine"]["required"], serde_json::json!(["sku"]));
+        assert!(document["$defs"]["OrderLine"].get("$schema").is_none());
+    }
+
+    #[test]
+    fn test_deprecated_field_is_marked() {
+        let mut store = IntentStore::new();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Type,
+                "Order".to_string(),
+                serde_json::json!({
+                    "fields": {
+                        "id": { "type": "uuid", "required": true },
+                        "card_number": { "type": "string", "deprecated": "use payment_token" }
+                    }
+                }),
+            ))
+            .unwrap();
+
+        let code = generate_types(&store, &IntentConfig::default());
+        assert!(code.contains("#[deprecated = \"use payment_token\"]\n    #[serde(skip_serializing_if = \"Option::is_none\")]\n    pub card_number: Option<String>,"));
+        // The redacting Debug impl reads the deprecated field
+        assert!(code.contains("#[allow(deprecated)]\nimpl std::fmt::Debug for Order {"));
+    }
 }
@@ -1,10 +1,13 @@
 //! Workflow code generation
 
+use std::collections::HashMap;
+
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
-use crate::model::{EffectKind, OnErrorStrategy, TypeRef, WorkflowStep};
+use crate::model::{EffectKind, IntentKind, OnErrorStrategy, TypeRef, TypeSpec, WorkflowSpec, WorkflowStep};
 use crate::parser::IntentStore;
+use super::naming::{escape_ident, to_ident, KeywordEscape};
 
 /// Generate workflows module
 pub fn generate_workflows(store: &IntentStore) -> WorkflowsOutput {
@@ -20,7 +23,7 @@ pub fn generate_workflows(store: &IntentStore) -> WorkflowsOutput {
     let mut mod_decls = Vec::new();
 
     for doc in &workflows {
-        let mod_name = to_snake_case(&doc.name);
+        let mod_name = to_ident(&doc.name);
         let mod_ident = format_ident!("{}", mod_name);
 
         mod_decls.push(quote! {
@@ -44,10 +47,15 @@ pub fn generate_workflows(store: &IntentStore) -> WorkflowsOutput {
             continue;
         };
 
-        let mod_name = to_snake_case(&doc.name);
+        let mod_name = to_ident(&doc.name);
         let fn_ident = format_ident!("{}", mod_name);
         let input_type = format_ident!("{}", &spec.input);
         let output_type = format_ident!("{}", &spec.output);
+        let context_type = format_ident!("{}Context", doc.name);
+        let context_doc = format!(" Typed state threaded through the {} steps", doc.name);
+        let input_spec = store
+            .get_by_kind_name(IntentKind::Type, &spec.input)
+            .and_then(|d| d.as_type_spec().ok());
 
         // Generate context struct
         let mut context_fields = Vec::new();
@@ -57,8 +65,8 @@ pub fn generate_workflows(store: &IntentStore) -> WorkflowsOutput {
 
         for name in &context_field_names {
             let type_ref = spec.context.get(*name).unwrap();
-            let field_ident = format_ident!("{}", name);
-            let field_type = type_ref_to_tokens(type_ref);
+            let field_ident = field_ident(name);
+            let field_type = type_ref_to_tokens(type_ref, store);
 
             context_fields.push(quote! {
                 pub #field_ident: Option<#field_type>,
@@ -72,22 +80,38 @@ pub fn generate_workflows(store: &IntentStore) -> WorkflowsOutput {
         let mut step_code = Vec::new();
 
         for (i, step) in spec.steps.iter().enumerate() {
+            // Named steps get readable variable names and trace fields
+            let step_name = step
+                .name()
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| format!("step{}", i));
+            let result_ident = format_ident!("_{}_result", to_ident(&step_name));
+
+            step_code.push(quote! {
+                tracing::debug!(step = #step_name, "workflow step");
+            });
+
+            let mut body = Vec::new();
             match step {
                 WorkflowStep::Transform(t) => {
-                    // Generate assignments
-                    for (target, _source) in &t.assign {
-                        let target_ident = format_ident!("{}", target);
-                        // Simplified: in real impl, would parse and evaluate source expression
-                        step_code.push(quote! {
-                            // Step: #i - Transform
-                            // context.#target_ident = Some(evaluate(#source));
+                    // Plain `input.*` / `context.*` reads are assigned into the
+                    // typed context; other expressions aren't translated yet
+                    let mut targets: Vec<_> = t.assign.iter().collect();
+                    targets.sort();
+                    for (target, source) in targets {
+                        let Some(value) = assignment_tokens(&spec, input_spec.as_ref(), target, source) else {
+                            continue;
+                        };
+                        let target_ident = field_ident(target);
+                        body.push(quote! {
+                            context.#target_ident = #value;
                         });
                     }
 
                     // Generate raise_if
                     if let Some(raise) = &t.raise_if {
                         let error_code = &raise.error;
-                        step_code.push(quote! {
+                        body.push(quote! {
                             // raise_if: #error_code
                             // if evaluate(condition) { return Err(error); }
                         });
@@ -100,10 +124,10 @@ pub fn generate_workflows(store: &IntentStore) -> WorkflowsOutput {
                             let operation = e.operation.as_deref().unwrap_or("unknown");
                             quote! {
                                 // HttpCall to #service.#operation
-                                let _result = crate::effects::http::call(
+                                let #result_ident = crate::effects::http::call(
                                     #service,
                                     #operation,
-                                    &input,
+                                    &context.input,
                                 ).await;
                             }
                         }
@@ -111,7 +135,7 @@ pub fn generate_workflows(store: &IntentStore) -> WorkflowsOutput {
                             let table = e.table.as_deref().unwrap_or("unknown");
                             quote! {
                                 // DbRead from #table
-                                let _result = crate::effects::db::read(#table, &context).await;
+                                let #result_ident = crate::effects::db::read(#table, &context).await;
                             }
                         }
                         EffectKind::DbWrite => {
@@ -151,26 +175,78 @@ pub fn generate_workflows(store: &IntentStore) -> WorkflowsOutput {
                         },
                     };
 
-                    step_code.push(wrapped);
+                    body.push(wrapped);
+
+                    // A binding to a context field stores the effect result there,
+                    // decoded into the field's declared type
+                    if let Some(binding) = e
+                        .output_binding
+                        .as_ref()
+                        .filter(|b| spec.context.contains_key(*b))
+                    {
+                        let binding_ident = field_ident(binding);
+                        let store_result = match e.effect {
+                            EffectKind::HttpCall => quote! {
+                                context.#binding_ident = Some(serde_json::from_value(#result_ident?)?);
+                            },
+                            EffectKind::DbRead => quote! {
+                                context.#binding_ident = Some(#result_ident?);
+                            },
+                            _ => quote! {},
+                        };
+                        body.push(store_result);
+                    }
+                }
+            }
+
+            // A conditional step only runs when its `when` holds
+            match step.when() {
+                Some(condition) => {
+                    let condition = condition_tokens(condition, &spec.context);
+                    step_code.push(quote! {
+                        if #condition {
+                            #(#body)*
+                        }
+                    });
                 }
+                None => step_code.extend(body),
             }
         }
 
+        // An Enum output comes from the enums module
+        let (output_types, output_import) = if store.get_by_kind_name(IntentKind::Enum, &spec.output).is_some() {
+            (vec![], quote! { use crate::enums::#output_type; })
+        } else if spec.output == spec.input {
+            (vec![], quote! {})
+        } else {
+            (vec![&output_type], quote! {})
+        };
+
         let file_tokens = quote! {
             // @generated by intent-engine v1.0
             // DO NOT EDIT — changes will be overwritten
 
-            use crate::types::{#input_type, #output_type};
+            use crate::types::{#input_type #(, #output_types)*};
+            #output_import
 
-            #[derive(Debug, Default)]
-            struct Context {
+            #[doc = #context_doc]
+            #[derive(Debug, serde::Serialize)]
+            pub struct #context_type {
+                pub input: #input_type,
                 #(#context_fields)*
             }
 
+            impl #context_type {
+                pub fn new(input: #input_type) -> Self {
+                    Self {
+                        input,
+                        #(#context_defaults)*
+                    }
+                }
+            }
+
             pub async fn #fn_ident(input: #input_type) -> Result<#output_type, anyhow::Error> {
-                let mut context = Context {
-                    #(#context_defaults)*
-                };
+                let mut context = #context_type::new(input);
 
                 #(#step_code)*
 
@@ -181,6 +257,7 @@ pub fn generate_workflows(store: &IntentStore) -> WorkflowsOutput {
 
         let file = syn::parse2(file_tokens).expect("Failed to parse workflow file");
         output.files.push(WorkflowFile {
+            intent_id: doc.id,
             name: format!("{}.rs", mod_name),
             content: prettyplease::unparse(&file),
         });
@@ -195,14 +272,123 @@ pub struct WorkflowsOutput {
 }
 
 pub struct WorkflowFile {
+    pub intent_id: uuid::Uuid,
     pub name: String,
     pub content: String,
 }
 
-fn type_ref_to_tokens(type_ref: &TypeRef) -> TokenStream {
+/// Context and input fields are named as their Type fields are
+fn field_ident(name: &str) -> proc_macro2::Ident {
+    format_ident!("{}", escape_ident(name, KeywordEscape::Raw))
+}
+
+/// Rust for assigning a source that just reads `input.<field>` or
+/// `context.<field>` to the context field `target`. The context field holds
+/// an `Option` of its declared type, so the read is wrapped in `Some` or
+/// flattened until the two agree. `None` for any other source, or one whose
+/// type doesn't fit (which validation reports).
+fn assignment_tokens(
+    spec: &WorkflowSpec,
+    input: Option<&TypeSpec>,
+    target: &str,
+    source: &str,
+) -> Option<TokenStream> {
+    let target_type = spec.context.get(target)?;
+    let source_type = spec.path_source_type(source, input)?;
+    let (source_base, source_depth) = source_type.strip_optional();
+    let (target_base, declared_depth) = target_type.strip_optional();
+    if source_base != target_base {
+        return None;
+    }
+    let target_depth = declared_depth + 1;
+
+    let (root, field) = source.trim().split_once('.')?;
+    let field_ident = field_ident(field);
+    let mut value = match root {
+        "input" => quote! { context.input.#field_ident.clone() },
+        _ => quote! { context.#field_ident.clone() },
+    };
+    for _ in target_depth..source_depth {
+        value = quote! { #value.flatten() };
+    }
+    for _ in source_depth..target_depth {
+        value = quote! { Some(#value) };
+    }
+    Some(value)
+}
+
+/// Rust for a step's `when` condition. `input.*` reads come from the
+/// context's input and `context.<field>` reads unwrap the optional context
+/// field, falling back to its default when unset. `null` is `None` and
+/// single-quoted strings become string literals; the operators are the same
+/// in Rust.
+fn condition_tokens(source: &str, context: &HashMap<String, TypeRef>) -> TokenStream {
+    let chars: Vec<char> = source.chars().collect();
+    let mut rust = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\'' || c == '"' {
+            let end = chars[i + 1..].iter().position(|&d| d == c).map_or(chars.len(), |n| i + 1 + n);
+            let text: String = chars[i + 1..end.min(chars.len())].iter().collect();
+            rust.push_str(&format!("{:?}", text));
+            i = end + 1;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                i += 1;
+            }
+            let path: String = chars[start..i].iter().collect();
+            let mut segments = path.split('.');
+            match (segments.next(), segments.next()) {
+                (Some("input"), Some(field)) => {
+                    rust.push_str(&format!("context.input.{}", escape_ident(field, KeywordEscape::Raw)));
+                    for segment in segments {
+                        rust.push('.');
+                        rust.push_str(&escape_ident(segment, KeywordEscape::Raw));
+                    }
+                }
+                (Some("context"), Some(field)) if context.contains_key(field) => {
+                    let rest: Vec<&str> = segments.collect();
+                    rust.push_str(&format!(
+                        "context.{}.clone().unwrap_or_default()",
+                        escape_ident(field, KeywordEscape::Raw)
+                    ));
+                    for segment in rest {
+                        rust.push('.');
+                        rust.push_str(&escape_ident(segment, KeywordEscape::Raw));
+                    }
+                }
+                (Some("null"), None) => rust.push_str("None"),
+                _ => rust.push_str(&path),
+            }
+        } else {
+            rust.push(c);
+            i += 1;
+        }
+    }
+
+    match syn::parse_str::<syn::Expr>(&rust) {
+        Ok(expr) => quote! { #expr },
+        Err(_) => {
+            let message = format!("cannot translate step condition `{}`", source);
+            quote! { compile_error!(#message) }
+        }
+    }
+}
+
+/// Named types come from the types module, or the enums module for Enums
+fn type_ref_to_tokens(type_ref: &TypeRef, store: &IntentStore) -> TokenStream {
     match type_ref {
         TypeRef::String => quote! { String },
         TypeRef::Int => quote! { i64 },
+        TypeRef::Int8 => quote! { i8 },
+        TypeRef::Int16 => quote! { i16 },
+        TypeRef::Int32 => quote! { i32 },
+        TypeRef::UInt8 => quote! { u8 },
+        TypeRef::UInt16 => quote! { u16 },
+        TypeRef::UInt32 => quote! { u32 },
         TypeRef::Float => quote! { f64 },
         TypeRef::Bool => quote! { bool },
         TypeRef::Money => quote! { rust_decimal::Decimal },
@@ -210,18 +396,31 @@ fn type_ref_to_tokens(type_ref: &TypeRef) -> TokenStream {
         TypeRef::Uuid => quote! { uuid::Uuid },
         TypeRef::Bytes => quote! { Vec<u8> },
         TypeRef::Array(inner) => {
-            let inner_tokens = type_ref_to_tokens(inner);
+            let inner_tokens = type_ref_to_tokens(inner, store);
             quote! { Vec<#inner_tokens> }
         }
+        TypeRef::FixedArray(inner, size) => {
+            let inner_tokens = type_ref_to_tokens(inner, store);
+            let size = proc_macro2::Literal::usize_unsuffixed(*size);
+            quote! { [#inner_tokens; #size] }
+        }
+        TypeRef::Tuple(elements) => {
+            let element_tokens = elements.iter().map(|element| type_ref_to_tokens(element, store));
+            quote! { (#(#element_tokens),*) }
+        }
         TypeRef::Map(k, v) => {
-            let k_tokens = type_ref_to_tokens(k);
-            let v_tokens = type_ref_to_tokens(v);
+            let k_tokens = type_ref_to_tokens(k, store);
+            let v_tokens = type_ref_to_tokens(v, store);
             quote! { std::collections::HashMap<#k_tokens, #v_tokens> }
         }
         TypeRef::Optional(inner) => {
-            let inner_tokens = type_ref_to_tokens(inner);
+            let inner_tokens = type_ref_to_tokens(inner, store);
             quote! { Option<#inner_tokens> }
         }
+        TypeRef::Named(name) if store.get_by_kind_name(IntentKind::Enum, name).is_some() => {
+            let ident = format_ident!("{}", name);
+            quote! { crate::enums::#ident }
+        }
         TypeRef::Named(name) => {
             let ident = format_ident!("{}", name);
             quote! { crate::types::#ident }
@@ -229,17 +428,3 @@ fn type_ref_to_tokens(type_ref: &TypeRef) -> TokenStream {
     }
 }
 
-fn to_snake_case(s: &str) -> String {
-    let mut result = String::new();
-    for (i, c) in s.chars().enumerate() {
-        if c.is_uppercase() {
-            if i > 0 {
-                result.push('_');
-            }
-            result.push(c.to_lowercase().next().unwrap());
-        } else {
-            result.push(c);
-        }
-    }
-    result
-}
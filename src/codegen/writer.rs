@@ -0,0 +1,74 @@
+//! Output targets for generated files
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Destination for generated files and lock files
+pub trait GenWriter {
+    /// Write a file, replacing any previous content
+    fn write(&mut self, path: &str, content: &str) -> anyhow::Result<()>;
+
+    /// Read the current content of a file, if it exists
+    fn read_existing(&self, path: &str) -> anyhow::Result<Option<String>>;
+
+    /// Remove a previously generated file
+    fn remove(&mut self, path: &str) -> anyhow::Result<()>;
+}
+
+/// Writes to the real filesystem, relative to the working directory
+#[derive(Debug, Default)]
+pub struct FsWriter;
+
+impl GenWriter for FsWriter {
+    fn write(&mut self, path: &str, content: &str) -> anyhow::Result<()> {
+        // Create parent directory if needed
+        if let Some(parent) = Path::new(path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn read_existing(&self, path: &str) -> anyhow::Result<Option<String>> {
+        if Path::new(path).exists() {
+            Ok(Some(std::fs::read_to_string(path)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn remove(&mut self, path: &str) -> anyhow::Result<()> {
+        if Path::new(path).exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Keeps generated files in memory, keyed by path
+#[derive(Debug, Default)]
+pub struct MemoryWriter {
+    pub files: BTreeMap<String, String>,
+}
+
+impl MemoryWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl GenWriter for MemoryWriter {
+    fn write(&mut self, path: &str, content: &str) -> anyhow::Result<()> {
+        self.files.insert(path.to_string(), content.to_string());
+        Ok(())
+    }
+
+    fn read_existing(&self, path: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.files.get(path).cloned())
+    }
+
+    fn remove(&mut self, path: &str) -> anyhow::Result<()> {
+        self.files.remove(path);
+        Ok(())
+    }
+}
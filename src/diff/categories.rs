@@ -3,7 +3,8 @@
 use serde::Serialize;
 
 /// Diff category
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[value(rename_all = "verbatim")]
 pub enum DiffCategory {
     ApiSurface,
     DataSchema,
@@ -31,7 +32,7 @@ impl std::fmt::Display for DiffCategory {
 }
 
 /// Diff severity
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, clap::ValueEnum)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum DiffSeverity {
     Info,
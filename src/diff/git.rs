@@ -0,0 +1,191 @@
+//! Git subprocess access for diffing against a ref
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::model::IntentError;
+
+/// Default time allowed for each git command
+pub const DEFAULT_GIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Loading more files than this reports progress, when enabled
+pub const PROGRESS_THRESHOLD: usize = 100;
+
+/// Runs git commands, killing any that outlive the timeout
+#[derive(Debug, Clone)]
+pub struct GitRunner {
+    program: PathBuf,
+    timeout: Duration,
+    progress: bool,
+}
+
+impl Default for GitRunner {
+    fn default() -> Self {
+        Self::new(DEFAULT_GIT_TIMEOUT)
+    }
+}
+
+impl GitRunner {
+    pub fn new(timeout: Duration) -> Self {
+        Self::with_program("git", timeout)
+    }
+
+    /// Use a different executable in place of `git` (for tests)
+    pub fn with_program(program: impl Into<PathBuf>, timeout: Duration) -> Self {
+        Self {
+            program: program.into(),
+            timeout,
+            progress: false,
+        }
+    }
+
+    /// Report progress on stderr while loading more than
+    /// `PROGRESS_THRESHOLD` files
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Whether loading `files` files should report progress
+    pub fn reports_progress(&self, files: usize) -> bool {
+        self.progress && files > PROGRESS_THRESHOLD
+    }
+
+    /// Run `git <args>`, feeding `stdin`, and return its stdout.
+    /// `Ok(None)` means git ran but exited unsuccessfully.
+    pub fn run(&self, args: &[&str], stdin: Option<Vec<u8>>) -> Result<Option<Vec<u8>>, IntentError> {
+        let command = format!("git {}", args.join(" "));
+        let mut child = Command::new(&self.program)
+            .args(args)
+            .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| IntentError::Git(format!("Failed to run `{}`: {}", command, e)))?;
+
+        // Feed stdin and drain stdout on threads so a full pipe can't block us
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            thread::spawn(move || {
+                let _ = pipe.write_all(&input);
+            });
+        }
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let reader = thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = stdout.read_to_end(&mut buf);
+            buf
+        });
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(IntentError::Git(format!(
+                    "`{}` timed out after {}s",
+                    command,
+                    self.timeout.as_secs_f64()
+                )));
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+
+        let output = reader
+            .join()
+            .map_err(|_| IntentError::Git(format!("Failed to read output of `{}`", command)))?;
+        Ok(status.success().then_some(output))
+    }
+
+    /// Paths of all files under `dir` at `git_ref`, or `None` if the ref
+    /// doesn't have the directory
+    pub fn ls_tree(&self, git_ref: &str, dir: &str) -> Result<Option<Vec<String>>, IntentError> {
+        let output = self.run(&["ls-tree", "-r", "--name-only", git_ref, dir], None)?;
+        Ok(output.map(|out| {
+            String::from_utf8_lossy(&out)
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }))
+    }
+
+    /// Contents of `paths` at `git_ref`, read in a single `git cat-file --batch`
+    /// session. Paths git can't find are left out.
+    pub fn read_files(&self, git_ref: &str, paths: &[String]) -> Result<HashMap<String, Vec<u8>>, IntentError> {
+        let mut files = HashMap::new();
+        if paths.is_empty() {
+            return Ok(files);
+        }
+
+        let input: String = paths
+            .iter()
+            .map(|path| format!("{}:{}\n", git_ref, path))
+            .collect();
+        let Some(output) = self.run(&["cat-file", "--batch"], Some(input.into_bytes()))? else {
+            return Err(IntentError::Git("`git cat-file --batch` failed".to_string()));
+        };
+
+        // Each object is `<sha> <type> <size>\n<content>\n`, or `<name> missing\n`
+        let mut rest = output.as_slice();
+        for path in paths {
+            let Some(newline) = rest.iter().position(|b| *b == b'\n') else {
+                break;
+            };
+            let header = String::from_utf8_lossy(&rest[..newline]).to_string();
+            rest = &rest[newline + 1..];
+
+            let parts: Vec<&str> = header.split(' ').collect();
+            let size = match parts.as_slice() {
+                [_, _, size] => size.parse::<usize>().ok(),
+                _ => None,
+            };
+            let Some(size) = size else {
+                continue;
+            };
+            if rest.len() < size {
+                return Err(IntentError::Git(format!(
+                    "Truncated output from `git cat-file --batch` for {}",
+                    path
+                )));
+            }
+            if parts[1] == "blob" {
+                files.insert(path.clone(), rest[..size].to_vec());
+            }
+            rest = rest.get(size + 1..).unwrap_or_default();
+        }
+
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_slow_git_times_out() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("slow-git");
+        std::fs::write(&script, "#!/bin/sh\nexec sleep 5\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let git = GitRunner::with_program(&script, Duration::from_millis(200));
+        let started = Instant::now();
+        let err = git.ls_tree("HEAD", ".intent/model/").unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(matches!(err, IntentError::Git(_)));
+        assert!(err
+            .to_string()
+            .contains("`git ls-tree -r --name-only HEAD .intent/model/` timed out after 0.2s"));
+    }
+}
@@ -0,0 +1,103 @@
+//! Markdown rendering of a semantic diff, for PR comments
+
+use std::fmt::Write;
+
+use super::{DiffSeverity, SemanticChange, SemanticDiffResult};
+
+/// Render `result` as a Markdown summary: a table of every change, then a
+/// collapsible section per severity with the old and new values. Changes
+/// keep the result's order, most severe first.
+pub fn render_markdown(result: &SemanticDiffResult) -> String {
+    let mut out = String::new();
+    if result.changes.is_empty() {
+        out.push_str("### Semantic diff\n\nNo semantic changes detected.\n");
+        return out;
+    }
+
+    let _ = writeln!(out, "### Semantic diff ({} changes)\n", result.changes.len());
+    let _ = writeln!(
+        out,
+        "**HIGH:** {} · **MEDIUM:** {} · **LOW:** {} · **INFO:** {}\n",
+        result.high_count, result.medium_count, result.low_count, result.info_count
+    );
+
+    out.push_str("| Severity | Category | Intent | Description |\n");
+    out.push_str("|----------|----------|--------|-------------|\n");
+    for change in &result.changes {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            change.severity,
+            change.category,
+            intent_cell(change),
+            table_cell(&change.description)
+        );
+    }
+
+    for severity in [DiffSeverity::High, DiffSeverity::Medium, DiffSeverity::Low, DiffSeverity::Info] {
+        let changes: Vec<_> = result.changes.iter().filter(|c| c.severity == severity).collect();
+        if changes.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "\n<details>\n<summary>{} ({})</summary>\n", severity, changes.len());
+        for change in changes {
+            let _ = writeln!(out, "- **{}** {}: {}", change.category, intent_cell(change), change.description);
+            if let Some(old) = &change.old_value {
+                let _ = writeln!(out, "  - Before: `{}`", old);
+            }
+            if let Some(new) = &change.new_value {
+                let _ = writeln!(out, "  - After: `{}`", new);
+            }
+        }
+        out.push_str("\n</details>\n");
+    }
+
+    out
+}
+
+/// `Kind Name` in code style, or a dash for model-wide changes
+fn intent_cell(change: &SemanticChange) -> String {
+    match (&change.intent_kind, &change.intent_name) {
+        (Some(kind), Some(name)) => format!("{} `{}`", kind, name),
+        (None, Some(name)) => format!("`{}`", name),
+        _ => "-".to_string(),
+    }
+}
+
+/// A table cell can't hold a pipe or a line break
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::DiffCategory;
+
+    #[test]
+    fn test_render_markdown_table_and_details() {
+        let authz = SemanticChange::new(DiffCategory::AuthZ, DiffSeverity::High, "AuthZ scope changed")
+            .with_intent("CreateRefund", "Endpoint")
+            .with_values("refund:write", "*");
+        let mut field = SemanticChange::new(DiffCategory::DataSchema, DiffSeverity::Low, "Field 'a|b' added");
+        field.intent_name = Some("RefundRequest".to_string());
+        let result = SemanticDiffResult::new(vec![authz, field]);
+
+        let markdown = render_markdown(&result);
+        assert!(markdown.starts_with("### Semantic diff (2 changes)\n"));
+        assert!(markdown.contains("**HIGH:** 1 · **MEDIUM:** 0 · **LOW:** 1 · **INFO:** 0"));
+        assert!(markdown.contains(
+            "| HIGH | AuthZ | Endpoint `CreateRefund` | AuthZ scope changed |\n\
+             | LOW | Data Schema | `RefundRequest` | Field 'a\\|b' added |\n"
+        ));
+        assert!(markdown.contains(
+            "<summary>HIGH (1)</summary>\n\n\
+             - **AuthZ** Endpoint `CreateRefund`: AuthZ scope changed\n  - Before: `refund:write`\n  - After: `*`\n"
+        ));
+        assert!(!markdown.contains("MEDIUM ("));
+        assert!(markdown.find("HIGH (1)") < markdown.find("LOW (1)"));
+
+        let empty = render_markdown(&SemanticDiffResult::new(Vec::new()));
+        assert!(empty.contains("No semantic changes detected."));
+    }
+}
@@ -2,6 +2,10 @@
 
 mod semantic;
 mod categories;
+mod git;
+mod markdown;
 
 pub use semantic::*;
 pub use categories::*;
+pub use git::*;
+pub use markdown::*;
@@ -2,16 +2,15 @@
 
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use std::process::Command;
 
 use serde::Serialize;
 use uuid::Uuid;
 
-use crate::model::{EffectKind, IntentDocument, IntentKind, WorkflowStep};
+use crate::model::{EffectKind, IntentDocument, IntentKind, KindCategory, WorkflowStep};
 use crate::parser::IntentStore;
 use crate::validation::check_authz_widening;
 
-use super::{DiffCategory, DiffSeverity, SemanticChange};
+use super::{DiffCategory, GitRunner, DiffSeverity, SemanticChange};
 
 /// Result of semantic diff
 #[derive(Debug, Clone, Serialize)]
@@ -52,60 +51,89 @@ impl SemanticDiffResult {
     }
 }
 
-/// Compute semantic diff against a git ref
-pub fn compute_semantic_diff(base_ref: &str) -> anyhow::Result<SemanticDiffResult> {
-    // Load current intents
-    let current_store = IntentStore::load_from_default_path()?;
+/// Which changes a diff reports
+#[derive(Debug, Clone, Default)]
+pub struct DiffFilter {
+    /// Categories to keep (`--category`); every category when empty
+    pub categories: Vec<DiffCategory>,
+    /// Drop changes less severe than this (`--min-severity`)
+    pub min_severity: Option<DiffSeverity>,
+}
+
+impl DiffFilter {
+    /// Whether `change` passes the filter
+    pub fn keeps(&self, change: &SemanticChange) -> bool {
+        (self.categories.is_empty() || self.categories.contains(&change.category))
+            && self.min_severity.is_none_or(|min| change.severity >= min)
+    }
+}
+
+/// Compute semantic diff against a git ref. A `base..head` range compares
+/// the two refs instead of the ref and the working tree. The filter applies
+/// before the severity counts.
+pub fn compute_semantic_diff(
+    base_ref: &str,
+    model_dir: &Path,
+    git: &GitRunner,
+    filter: &DiffFilter,
+) -> anyhow::Result<SemanticDiffResult> {
+    let (base_ref, current_store) = match base_ref.split_once("..") {
+        Some((base, head)) => (base, load_intents_from_git_ref(head, model_dir, git)?),
+        None => (base_ref, IntentStore::load_from_path(model_dir)?),
+    };
 
     // Load base intents from git
-    let base_store = load_intents_from_git_ref(base_ref)?;
+    let base_store = load_intents_from_git_ref(base_ref, model_dir, git)?;
 
     // Compute diff
-    let changes = compute_diff(&base_store, &current_store);
+    let mut changes = compute_diff(&base_store, &current_store);
+    changes.retain(|change| filter.keeps(change));
 
     Ok(SemanticDiffResult::new(changes))
 }
 
 /// Load intents from a git ref
-fn load_intents_from_git_ref(git_ref: &str) -> anyhow::Result<IntentStore> {
-    let temp_dir = tempfile::tempdir()?;
-    let temp_path = temp_dir.path();
-
+fn load_intents_from_git_ref(
+    git_ref: &str,
+    model_dir: &Path,
+    git: &GitRunner,
+) -> anyhow::Result<IntentStore> {
     // Get list of intent files at the ref
-    let output = Command::new("git")
-        .args(["ls-tree", "-r", "--name-only", git_ref, ".intent/model/"])
-        .output()?;
-
-    if !output.status.success() {
-        // No .intent/model at this ref, return empty store
+    let model_dir = format!("{}/", model_dir.to_string_lossy().trim_end_matches('/'));
+    let Some(files) = git.ls_tree(git_ref, &model_dir)? else {
+        // No model directory at this ref, return empty store
         return Ok(IntentStore::new());
-    }
+    };
 
-    let files = String::from_utf8_lossy(&output.stdout);
+    let files: Vec<String> = files
+        .into_iter()
+        .filter(|file| file.ends_with(".intent.json"))
+        .collect();
+    let progress = git.reports_progress(files.len());
+    if progress {
+        eprintln!("Reading {} intent files at {}...", files.len(), git_ref);
+    }
+    let contents = git.read_files(git_ref, &files)?;
     let mut store = IntentStore::new();
 
-    for file in files.lines() {
-        if !file.ends_with(".intent.json") {
-            continue;
+    for (i, file) in files.iter().enumerate() {
+        if progress {
+            eprint!("\rParsed {}/{}", i + 1, files.len());
         }
-
-        // Get file content at ref
-        let content_output = Command::new("git")
-            .args(["show", &format!("{}:{}", git_ref, file)])
-            .output()?;
-
-        if !content_output.status.success() {
+        let Some(content) = contents.get(file) else {
             continue;
-        }
-
-        let content = String::from_utf8_lossy(&content_output.stdout);
+        };
+        let content = String::from_utf8_lossy(content);
 
         // Parse the intent
         if let Ok(mut doc) = serde_json::from_str::<IntentDocument>(&content) {
-            doc.source_file = Some(file.to_string());
+            doc.source_file = Some(file.clone());
             let _ = store.add(doc);
         }
     }
+    if progress {
+        eprintln!();
+    }
 
     Ok(store)
 }
@@ -159,6 +187,8 @@ fn compute_diff(base: &IntentStore, current: &IntentStore) -> Vec<SemanticChange
         let base_doc = base_by_id.get(id).unwrap();
         let current_doc = current_by_id.get(id).unwrap();
 
+        // `Value` objects compare by key, so key order and whitespace in the
+        // source files never count as a change
         if base_doc.spec != current_doc.spec || base_doc.name != current_doc.name {
             changes.extend(diff_intent(base_doc, current_doc));
         }
@@ -204,21 +234,10 @@ fn added_intent_severity(doc: &IntentDocument) -> DiffSeverity {
 }
 
 fn category_for_kind(kind: IntentKind) -> DiffCategory {
-    match kind {
-        IntentKind::Type => DiffCategory::DataSchema,
-        IntentKind::Endpoint => DiffCategory::ApiSurface,
-        IntentKind::Workflow => DiffCategory::Effects,
-        IntentKind::Service => DiffCategory::Effects,
-        IntentKind::ContractTest => DiffCategory::Effects,
-        IntentKind::Migration => DiffCategory::DataSchema,
-        // v2 Meta kinds - internal/system changes
-        IntentKind::Function => DiffCategory::Effects,
-        IntentKind::Pipeline => DiffCategory::Effects,
-        IntentKind::Template => DiffCategory::DataSchema,
-        IntentKind::Enum => DiffCategory::DataSchema,
-        IntentKind::Module => DiffCategory::DataSchema,
-        IntentKind::Command => DiffCategory::ApiSurface,
-        IntentKind::Trait => DiffCategory::DataSchema,
+    match kind.category() {
+        KindCategory::Data | KindCategory::Infra => DiffCategory::DataSchema,
+        KindCategory::Surface => DiffCategory::ApiSurface,
+        KindCategory::Behavior | KindCategory::Integration => DiffCategory::Effects,
     }
 }
 
@@ -319,15 +338,22 @@ fn diff_type(base: &IntentDocument, current: &IntentDocument, changes: &mut Vec<
         );
     }
 
-    // Removed fields
+    // Removed fields; clients were warned about deprecated ones
     for field in base_fields.difference(&current_fields) {
-        changes.push(
-            SemanticChange::new(
-                DiffCategory::DataSchema,
+        let (severity, description) = if base_spec.fields[*field].deprecated.is_some() {
+            (
+                DiffSeverity::Medium,
+                format!("Removed deprecated field '{}' from type '{}'", field, current.name),
+            )
+        } else {
+            (
                 DiffSeverity::High,
                 format!("Removed field '{}' from type '{}'", field, current.name),
             )
-            .with_intent(&current.name, "Type"),
+        };
+        changes.push(
+            SemanticChange::new(DiffCategory::DataSchema, severity, description)
+                .with_intent(&current.name, "Type"),
         );
     }
 
@@ -354,6 +380,22 @@ fn diff_type(base: &IntentDocument, current: &IntentDocument, changes: &mut Vec<
             );
         }
 
+        if base_field.deprecated.is_none() {
+            if let Some(reason) = &current_field.deprecated {
+                changes.push(
+                    SemanticChange::new(
+                        DiffCategory::DataSchema,
+                        DiffSeverity::Info,
+                        format!(
+                            "Deprecated field '{}' in '{}': {}",
+                            field, current.name, reason
+                        ),
+                    )
+                    .with_intent(&current.name, "Type"),
+                );
+            }
+        }
+
         if base_field.required != current_field.required {
             let severity = if current_field.required && !base_field.required {
                 DiffSeverity::High // Making required is breaking
@@ -685,4 +727,162 @@ mod tests {
         assert_eq!(result.low_count, 1);
         assert_eq!(result.info_count, 1);
     }
+
+    fn store_from_json(json: &str) -> IntentStore {
+        let mut store = IntentStore::new();
+        store.add(serde_json::from_str(json).unwrap()).unwrap();
+        store
+    }
+
+    #[test]
+    fn test_reordered_fields_are_not_a_change() {
+        let base = store_from_json(
+            r#"{"schema_version":"1.0","id":"550e8400-e29b-41d4-a716-446655440001",
+                "kind":"Type","name":"RefundRequest",
+                "spec":{"fields":{"amount":{"type":"money","required":true},
+                                  "order_id":{"type":"uuid","required":true}}}}"#,
+        );
+        let current = store_from_json(
+            r#"{
+              "spec": {
+                "fields": {
+                  "order_id": { "required": true,   "type": "uuid" },
+                  "amount":   { "required": true,   "type": "money" }
+                }
+              },
+              "name": "RefundRequest",
+              "kind": "Type",
+              "id": "550e8400-e29b-41d4-a716-446655440001",
+              "schema_version": "1.0"
+            }"#,
+        );
+
+        assert!(compute_diff(&base, &current).is_empty());
+    }
+
+    #[test]
+    fn test_genuine_change_still_detected() {
+        let base = store_from_json(
+            r#"{"schema_version":"1.0","id":"550e8400-e29b-41d4-a716-446655440001",
+                "kind":"Type","name":"RefundRequest",
+                "spec":{"fields":{"amount":{"type":"money","required":true}}}}"#,
+        );
+        let current = store_from_json(
+            r#"{"schema_version":"1.0","id":"550e8400-e29b-41d4-a716-446655440001",
+                "kind":"Type","name":"RefundRequest",
+                "spec":{"fields":{"amount":{"type":"money","required":false}}}}"#,
+        );
+
+        assert!(!compute_diff(&base, &current).is_empty());
+    }
+
+    fn refund_request(fields: &str) -> IntentStore {
+        store_from_json(&format!(
+            r#"{{"schema_version":"1.0","id":"550e8400-e29b-41d4-a716-446655440001",
+                "kind":"Type","name":"RefundRequest","spec":{{"fields":{{{}}}}}}}"#,
+            fields
+        ))
+    }
+
+    #[test]
+    fn test_deprecating_a_field_is_info() {
+        let base = refund_request(r#""amount":{"type":"money","required":true}"#);
+        let current = refund_request(
+            r#""amount":{"type":"money","required":true,"deprecated":"use amount_minor"}"#,
+        );
+
+        let changes = compute_diff(&base, &current);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].severity, DiffSeverity::Info);
+        assert_eq!(
+            changes[0].description,
+            "Deprecated field 'amount' in 'RefundRequest': use amount_minor"
+        );
+
+        // Already deprecated: nothing new to report
+        assert!(compute_diff(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_removing_a_deprecated_field_is_medium() {
+        let current = refund_request(r#""order_id":{"type":"uuid","required":true}"#);
+
+        let deprecated = refund_request(
+            r#""order_id":{"type":"uuid","required":true},
+               "amount":{"type":"money","required":true,"deprecated":"use amount_minor"}"#,
+        );
+        let changes = compute_diff(&deprecated, &current);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].severity, DiffSeverity::Medium);
+        assert_eq!(
+            changes[0].description,
+            "Removed deprecated field 'amount' from type 'RefundRequest'"
+        );
+
+        let live = refund_request(
+            r#""order_id":{"type":"uuid","required":true},
+               "amount":{"type":"money","required":true}"#,
+        );
+        let changes = compute_diff(&live, &current);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].severity, DiffSeverity::High);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_git_ref_files_load_in_one_batch() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A stand-in git that serves a directory of blobs and logs each call
+        let dir = tempfile::tempdir().unwrap();
+        let blobs = dir.path().join("blobs");
+        std::fs::create_dir(&blobs).unwrap();
+        for entry in std::fs::read_dir("fixtures/valid").unwrap() {
+            let path = entry.unwrap().path();
+            std::fs::copy(&path, blobs.join(path.file_name().unwrap())).unwrap();
+        }
+        std::fs::write(blobs.join("broken.intent.json"), "{ not json").unwrap();
+        std::fs::write(blobs.join("notes.md"), "# Notes").unwrap();
+
+        let log = dir.path().join("calls.log");
+        let script = dir.path().join("fake-git");
+        std::fs::write(
+            &script,
+            format!(
+                r#"#!/bin/sh
+echo "$*" >> {log}
+case "$1" in
+  ls-tree) for f in {blobs}/*; do echo ".intent/model/${{f##*/}}"; done ;;
+  cat-file)
+    while read -r object; do
+      f={blobs}/${{object##*/}}
+      echo "0000 blob $(wc -c < "$f" | tr -d ' ')"
+      cat "$f"
+      echo
+    done ;;
+  *) exit 1 ;;
+esac
+"#,
+                log = log.display(),
+                blobs = blobs.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let git = GitRunner::with_program(&script, std::time::Duration::from_secs(10));
+        let store = load_intents_from_git_ref("main", Path::new(".intent/model"), &git).unwrap();
+
+        let fixtures = IntentStore::load_from_path("fixtures/valid").unwrap();
+        assert_eq!(store.len(), fixtures.len());
+        for doc in fixtures.iter() {
+            assert!(store.get(&doc.id).is_some(), "{} not loaded", doc.name);
+        }
+
+        let calls = std::fs::read_to_string(&log).unwrap();
+        let calls: Vec<&str> = calls.lines().collect();
+        assert_eq!(calls.len(), 2, "{:?}", calls);
+        assert!(calls[0].starts_with("ls-tree -r --name-only main "));
+        assert_eq!(calls[1], "cat-file --batch");
+    }
 }
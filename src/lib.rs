@@ -8,6 +8,7 @@ pub mod codegen;
 pub mod diff;
 pub mod model;
 pub mod parser;
+pub mod simulate;
 pub mod validation;
 
 pub use model::*;
@@ -1,5 +1,9 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
 use clap::{Parser, Subcommand};
-use intent_engine::cli;
+use intent_engine::{cli, codegen, diff, parser};
+use intent_engine::parser::resolve_model_path;
 
 #[derive(Parser)]
 #[command(name = "intent")]
@@ -12,6 +16,10 @@ struct Cli {
     /// Output format
     #[arg(long, default_value = "human", global = true)]
     format: OutputFormat,
+
+    /// Intent model directory (overrides INTENT_MODEL_PATH; default .intent/model)
+    #[arg(long, global = true)]
+    model_dir: Option<PathBuf>,
 }
 
 #[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
@@ -19,6 +27,12 @@ enum OutputFormat {
     #[default]
     Human,
     Json,
+    /// JUnit XML (validate only)
+    Junit,
+    /// Graphviz DOT (graph only)
+    Dot,
+    /// Markdown for PR comments (diff only)
+    Markdown,
 }
 
 #[derive(Subcommand)]
@@ -29,42 +43,181 @@ enum Commands {
         kind: String,
         /// Intent name
         name: String,
+        /// Print the file that would be created without writing it
+        #[arg(long)]
+        dry_run: bool,
     },
     /// List all intents
     List {
         /// Filter by kind
         #[arg(long)]
         kind: Option<String>,
+        /// Only intents with this tag
+        #[arg(long)]
+        tag: Option<String>,
     },
+    /// Count intents by kind and by tag
+    Stats,
     /// Show details of an intent
     Show {
         /// Intent name
         name: String,
+        /// Show the transitive dependency tree
+        #[arg(long)]
+        deps_tree: bool,
+        /// Maximum depth of the dependency tree
+        #[arg(long, requires = "deps_tree")]
+        depth: Option<usize>,
+        /// Show how many intents depend on it, and the endpoints exposing a type
+        #[arg(long, conflicts_with = "deps_tree")]
+        impact: bool,
+        /// Print a Type's JSON Schema (draft 2020-12) instead of its spec
+        #[arg(long, conflicts_with_all = ["deps_tree", "impact"])]
+        schema: bool,
+    },
+    /// Delete an intent file
+    Delete {
+        /// Intent name
+        name: String,
+        /// Delete even if other intents depend on it
+        #[arg(long)]
+        force: bool,
+        /// List dependents without deleting
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Rename an intent and update every reference to it
+    Rename {
+        /// Current intent name
+        old_name: String,
+        /// New intent name
+        new_name: String,
+        /// Print the planned edits without writing
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Format intent files (canonicalize JSON)
     Fmt {
         /// Check formatting without writing
         #[arg(long)]
         check: bool,
+        /// Also format the lock files under .intent/locks
+        #[arg(long, conflicts_with = "file")]
+        all: bool,
         /// Specific file to format
         file: Option<String>,
     },
+    /// Upgrade every intent file to the newest schema_version
+    MigrateSchema {
+        /// List the files that would be upgraded without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check naming conventions (configured in [lint])
+    Lint,
     /// Validate intent files
-    Validate,
+    Validate {
+        /// Check raw files against the embedded JSON Schemas first
+        #[arg(long)]
+        json_schema_validate: bool,
+        /// Only report diagnostics not recorded in this baseline file
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<PathBuf>,
+        /// Record the current diagnostics as the new baseline
+        #[arg(long, requires = "baseline")]
+        write_baseline: bool,
+        /// List informational diagnostics (hidden by default)
+        #[arg(long)]
+        show_info: bool,
+        /// Exit non-zero when there are informational diagnostics
+        #[arg(long)]
+        fail_on_info: bool,
+    },
     /// Generate Rust code
     Gen {
         /// Check if generated code matches without writing
         #[arg(long)]
         check: bool,
+        /// Report files that would be created, updated or orphaned without writing
+        #[arg(long, conflicts_with = "check")]
+        dry_run: bool,
+        /// Delete previously generated files that are no longer produced
+        #[arg(long, conflicts_with_all = ["check", "dry_run"])]
+        prune: bool,
+        /// Skip writing all lock files (manifest, trace map, obligations)
+        #[arg(long)]
+        no_locks: bool,
+        /// Skip writing the generation manifest lock file
+        #[arg(long)]
+        no_manifest: bool,
+        /// Skip writing the trace map lock file
+        #[arg(long)]
+        no_trace_map: bool,
+        /// Skip writing the obligations lock file
+        #[arg(long)]
+        no_obligations: bool,
+        /// Only generate files derived from intents of these kinds (comma-separated)
+        #[arg(long, value_delimiter = ',', conflicts_with = "prune")]
+        only: Vec<String>,
+        /// Only generate files derived from the named intents (comma-separated)
+        #[arg(long, value_delimiter = ',', conflicts_with = "prune")]
+        name: Vec<String>,
+        /// Only generate files derived from intents with any of these tags (comma-separated)
+        #[arg(long, value_delimiter = ',', conflicts_with = "prune")]
+        tag: Vec<String>,
+        /// Print the generation manifest (files, hashes, source intents) as JSON
+        #[arg(long, conflicts_with = "dry_run")]
+        print_manifest: bool,
+        /// Platform to generate for, overriding `[generation] target`
+        #[arg(long, value_enum)]
+        target: Option<parser::GenTarget>,
+    },
+    /// Report generated files whose content differs from the recorded manifest
+    VerifyManifest,
+    /// Validate and regenerate whenever an intent file changes, until Ctrl-C
+    Watch,
+    /// Export the dependency graph between intents (`--format dot` for Graphviz)
+    Graph,
+    /// Write an OpenAPI 3.1 document for the Endpoint intents
+    Openapi {
+        /// Output file
+        #[arg(long, default_value = codegen::OPENAPI_FILE)]
+        out: PathBuf,
     },
     /// Show semantic diff against a git ref
     Diff {
-        /// Base git ref to compare against
+        /// Base git ref to compare against, or `base..head` to compare two refs
         #[arg(long)]
         base: String,
+        /// Seconds to wait for each git command before giving up
+        #[arg(long, default_value_t = 30)]
+        timeout: u64,
+        /// Comma-separated categories to report (e.g. ApiSurface,AuthZ); default all
+        #[arg(long, value_enum, value_delimiter = ',', ignore_case = true)]
+        category: Vec<diff::DiffCategory>,
+        /// Only report changes at least this severe
+        #[arg(long, value_enum, ignore_case = true)]
+        min_severity: Option<diff::DiffSeverity>,
     },
     /// Verify all intents (fmt + validate + gen --check + obligations)
-    Verify,
+    Verify {
+        /// Comma-separated phases to run (fmt,validate,gen,obligations); default all
+        #[arg(long, value_enum, value_delimiter = ',')]
+        steps: Vec<cli::VerifyStep>,
+        /// Also diff against this git ref and fail on severe changes
+        #[arg(long)]
+        diff_base: Option<String>,
+        /// Fail when the diff has changes at least this severe (default high)
+        #[arg(long, value_enum, ignore_case = true, requires = "diff_base")]
+        fail_on: Option<diff::DiffSeverity>,
+    },
+    /// Dry-run a workflow's steps against sample input with stubbed effects
+    Simulate {
+        /// Workflow name
+        workflow: String,
+        /// JSON file holding the workflow input
+        input_file: PathBuf,
+    },
     /// Apply a patch file
     Patch {
         #[command(subcommand)]
@@ -87,19 +240,147 @@ enum PatchAction {
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
     let json_output = matches!(cli.format, OutputFormat::Json);
+    if matches!(cli.format, OutputFormat::Junit) && !matches!(cli.command, Commands::Validate { .. }) {
+        anyhow::bail!("--format junit is only supported by `intent validate`");
+    }
+    if matches!(cli.format, OutputFormat::Dot) && !matches!(cli.command, Commands::Graph) {
+        anyhow::bail!("--format dot is only supported by `intent graph`");
+    }
+    if matches!(cli.format, OutputFormat::Markdown) && !matches!(cli.command, Commands::Diff { .. }) {
+        anyhow::bail!("--format markdown is only supported by `intent diff`");
+    }
+    if cli.model_dir.is_some() && matches!(cli.command, Commands::VerifyManifest) {
+        anyhow::bail!("--model-dir is not used by `intent verify-manifest`, which only reads gen/");
+    }
+    let model_dir = resolve_model_path(cli.model_dir.as_deref());
 
     let exit_code = match cli.command {
-        Commands::New { kind, name } => cli::cmd_new(&kind, &name, json_output)?,
-        Commands::List { kind } => cli::cmd_list(kind.as_deref(), json_output)?,
-        Commands::Show { name } => cli::cmd_show(&name, json_output)?,
-        Commands::Fmt { check, file } => cli::cmd_fmt(check, file.as_deref(), json_output)?,
-        Commands::Validate => cli::cmd_validate(json_output)?,
-        Commands::Gen { check } => cli::cmd_gen(check, json_output)?,
-        Commands::Diff { base } => cli::cmd_diff(&base, json_output)?,
-        Commands::Verify => cli::cmd_verify(json_output)?,
+        Commands::New {
+            kind,
+            name,
+            dry_run,
+        } => cli::cmd_new(&kind, &name, dry_run, &model_dir, json_output)?,
+        Commands::List { kind, tag } => {
+            cli::cmd_list(kind.as_deref(), tag.as_deref(), &model_dir, json_output)?
+        }
+        Commands::Stats => cli::cmd_stats(&model_dir, json_output)?,
+        Commands::Show {
+            name,
+            deps_tree,
+            depth,
+            impact,
+            schema,
+        } => cli::cmd_show(&name, deps_tree, depth, impact, schema, &model_dir, json_output)?,
+        Commands::Delete {
+            name,
+            force,
+            dry_run,
+        } => cli::cmd_delete(&name, force, dry_run, &model_dir, json_output)?,
+        Commands::Rename {
+            old_name,
+            new_name,
+            dry_run,
+        } => cli::cmd_rename(&old_name, &new_name, dry_run, &model_dir, json_output)?,
+        Commands::Fmt { check, all, file } => {
+            cli::cmd_fmt(check, all, file.as_deref(), &model_dir, json_output)?
+        }
+        Commands::MigrateSchema { dry_run } => cli::cmd_migrate_schema(dry_run, &model_dir, json_output)?,
+        Commands::Lint => cli::cmd_lint(&model_dir, json_output)?,
+        Commands::Validate {
+            json_schema_validate,
+            baseline,
+            write_baseline,
+            show_info,
+            fail_on_info,
+        } => cli::cmd_validate(
+            json_schema_validate,
+            baseline.as_deref(),
+            write_baseline,
+            show_info,
+            fail_on_info,
+            &model_dir,
+            match cli.format {
+                OutputFormat::Human => cli::ValidateOutput::Human,
+                OutputFormat::Json => cli::ValidateOutput::Json,
+                OutputFormat::Junit => cli::ValidateOutput::Junit,
+                OutputFormat::Dot | OutputFormat::Markdown => unreachable!("rejected above"),
+            },
+        )?,
+        Commands::Gen {
+            check,
+            dry_run,
+            prune,
+            no_locks,
+            no_manifest,
+            no_trace_map,
+            no_obligations,
+            only,
+            name,
+            tag,
+            print_manifest,
+            target,
+        } => {
+            let locks = codegen::LockFiles {
+                manifest: !(no_locks || no_manifest),
+                trace_map: !(no_locks || no_trace_map),
+                obligations: !(no_locks || no_obligations),
+            };
+            let filter = codegen::GenFilter::parse(&only, name, tag)?;
+            let output = if print_manifest {
+                cli::GenOutput::Manifest
+            } else if json_output {
+                cli::GenOutput::Json
+            } else {
+                cli::GenOutput::Human
+            };
+            let options = cli::GenOptions { check, dry_run, prune, locks, target };
+            cli::cmd_gen(options, &filter, &model_dir, output)?
+        }
+        Commands::VerifyManifest => cli::cmd_verify_manifest(json_output)?,
+        Commands::Watch => cli::cmd_watch(json_output, &model_dir)?,
+        Commands::Graph => {
+            let format = match cli.format {
+                OutputFormat::Dot => "dot",
+                _ => "json",
+            };
+            cli::cmd_graph(format, &model_dir)?
+        }
+        Commands::Diff {
+            base,
+            timeout,
+            category,
+            min_severity,
+        } => {
+            let filter = diff::DiffFilter {
+                categories: category,
+                min_severity,
+            };
+            let output = match cli.format {
+                OutputFormat::Json => cli::DiffOutput::Json,
+                OutputFormat::Markdown => cli::DiffOutput::Markdown,
+                _ => cli::DiffOutput::Human,
+            };
+            cli::cmd_diff(&base, Duration::from_secs(timeout), &filter, &model_dir, output)?
+        }
+        Commands::Openapi { out } => cli::cmd_openapi(&out, &model_dir, json_output)?,
+        Commands::Verify {
+            steps,
+            diff_base,
+            fail_on,
+        } => cli::cmd_verify(
+            &steps,
+            diff_base.as_deref(),
+            fail_on.unwrap_or(diff::DiffSeverity::High),
+            &model_dir,
+            json_output,
+        )?,
+        Commands::Simulate {
+            workflow,
+            input_file,
+        } => cli::cmd_simulate(&workflow, &input_file, &model_dir, json_output)?,
         Commands::Patch { action } => match action {
             PatchAction::Apply { file, dry_run } => {
-                cli::cmd_patch_apply(&file, dry_run, json_output)?
+                cli::cmd_patch_apply(&file, dry_run, &model_dir, json_output)?
             }
         },
     };
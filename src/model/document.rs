@@ -1,8 +1,21 @@
 //! Intent document envelope and kinds
 
+use std::ops::RangeInclusive;
+
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// `schema_version`s this engine reads, as (major, minor). Files outside
+/// the range are refused rather than parsed leniently, since a newer
+/// version may use features this engine would misread.
+pub const SUPPORTED_SCHEMA_VERSIONS: RangeInclusive<(u32, u32)> = (1, 0)..=(2, 0);
+
+/// `"2.0"` (or `"2"`) as `(2, 0)`
+pub fn parse_schema_version(version: &str) -> Option<(u32, u32)> {
+    let (major, minor) = version.split_once('.').unwrap_or((version, "0"));
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
 /// The main intent document structure (envelope)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IntentDocument {
@@ -10,6 +23,11 @@ pub struct IntentDocument {
     pub id: Uuid,
     pub kind: IntentKind,
     pub name: String,
+
+    /// Labels for grouping intents by domain, owner or lifecycle
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
     pub spec: serde_json::Value,
 
     /// Source file path (not serialized, set during loading)
@@ -25,6 +43,7 @@ impl IntentDocument {
             id: Uuid::new_v4(),
             kind,
             name,
+            tags: Vec::new(),
             spec: serde_json::json!({}),
             source_file: None,
         }
@@ -37,6 +56,7 @@ impl IntentDocument {
             id: Uuid::new_v4(),
             kind,
             name,
+            tags: Vec::new(),
             spec,
             source_file: None,
         }
@@ -129,6 +149,32 @@ impl IntentKind {
                 | Self::Trait
         )
     }
+
+    /// Broad role of this kind, for tooling that groups intents
+    pub fn category(&self) -> KindCategory {
+        match self {
+            Self::Type | Self::Enum => KindCategory::Data,
+            Self::Workflow | Self::Function | Self::Pipeline => KindCategory::Behavior,
+            Self::Endpoint | Self::Command => KindCategory::Surface,
+            Self::Service | Self::ContractTest => KindCategory::Integration,
+            Self::Migration | Self::Module | Self::Template | Self::Trait => KindCategory::Infra,
+        }
+    }
+}
+
+/// Grouping of intent kinds by role, independent of v1/v2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KindCategory {
+    /// Data shapes: Type, Enum
+    Data,
+    /// Logic: Workflow, Function, Pipeline
+    Behavior,
+    /// Externally invoked entry points: Endpoint, Command
+    Surface,
+    /// External systems and their contracts: Service, ContractTest
+    Integration,
+    /// Schema changes and code organisation: Migration, Module, Template, Trait
+    Infra,
 }
 
 impl std::fmt::Display for IntentKind {
@@ -158,6 +204,8 @@ pub struct IntentSummary {
     pub kind: String,
     pub name: String,
     pub file: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 impl From<&IntentDocument> for IntentSummary {
@@ -167,6 +215,36 @@ impl From<&IntentDocument> for IntentSummary {
             kind: doc.kind.to_string(),
             name: doc.name.clone(),
             file: doc.source_file.clone().unwrap_or_default(),
+            tags: doc.tags.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_categories() {
+        let expected = [
+            (IntentKind::Type, KindCategory::Data),
+            (IntentKind::Enum, KindCategory::Data),
+            (IntentKind::Workflow, KindCategory::Behavior),
+            (IntentKind::Function, KindCategory::Behavior),
+            (IntentKind::Pipeline, KindCategory::Behavior),
+            (IntentKind::Endpoint, KindCategory::Surface),
+            (IntentKind::Command, KindCategory::Surface),
+            (IntentKind::Service, KindCategory::Integration),
+            (IntentKind::ContractTest, KindCategory::Integration),
+            (IntentKind::Migration, KindCategory::Infra),
+            (IntentKind::Module, KindCategory::Infra),
+            (IntentKind::Template, KindCategory::Infra),
+            (IntentKind::Trait, KindCategory::Infra),
+        ];
+
+        assert_eq!(expected.len(), IntentKind::all().len());
+        for (kind, category) in expected {
+            assert_eq!(kind.category(), category, "{}", kind);
         }
     }
 }
@@ -83,6 +83,72 @@ pub mod codes {
     pub const E008_MISSING_POLICY: &str = "E008";
     pub const E009_INVALID_MAPPING: &str = "E009";
     pub const E010_DUPLICATE_NAME: &str = "E010";
+    pub const E011_SCHEMA_VIOLATION: &str = "E011";
+    pub const E012_INVALID_TAG: &str = "E012";
+    pub const E013_INVALID_RUST: &str = "E013";
+
+    /// Where every code is documented; each has a `### CODE: Title` heading
+    pub const DOCS_URL: &str =
+        "https://github.com/mazharm/intent-engine/blob/main/docs/error-codes.md";
+
+    /// A diagnostic code and the title of its section in `docs/error-codes.md`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CodeInfo {
+        pub code: &'static str,
+        pub title: &'static str,
+    }
+
+    /// Every documented error, warning and info code
+    pub const REGISTRY: &[CodeInfo] = &[
+        CodeInfo { code: "E001", title: "Invalid JSON" },
+        CodeInfo { code: "E002", title: "Missing Required Field" },
+        CodeInfo { code: "E003", title: "Invalid Kind" },
+        CodeInfo { code: "E004", title: "Invalid Type" },
+        CodeInfo { code: "E005", title: "Unknown Reference" },
+        CodeInfo { code: "E006", title: "Circular Reference" },
+        CodeInfo { code: "E007", title: "Type Mismatch" },
+        CodeInfo { code: "E008", title: "Missing Policy" },
+        CodeInfo { code: "E009", title: "Invalid Mapping" },
+        CodeInfo { code: "E010", title: "Duplicate Name" },
+        CodeInfo { code: "E011", title: "Schema Violation" },
+        CodeInfo { code: "E012", title: "Invalid Tag" },
+        CodeInfo { code: "E013", title: "Invalid Rust" },
+        CodeInfo { code: "W001", title: "Missing Authorization" },
+        CodeInfo { code: "W002", title: "Broad Authorization Scope" },
+        CodeInfo { code: "W003", title: "Potential PII Field" },
+        CodeInfo { code: "W004", title: "PII Emitted to Topic" },
+        CodeInfo { code: "W015", title: "Unused Endpoint Error" },
+        CodeInfo { code: "W016", title: "Event Emitted Before Write" },
+        CodeInfo { code: "W017", title: "Type Without Fields" },
+        CodeInfo { code: "W018", title: "Division by Constant Zero" },
+        CodeInfo { code: "W019", title: "Mismatched Literal Comparison" },
+        CodeInfo { code: "W020", title: "Constant Condition" },
+        CodeInfo { code: "W021", title: "Unreachable Intent" },
+        CodeInfo { code: "W022", title: "Money Without Currency" },
+        CodeInfo { code: "W023", title: "Optional Idempotency Key" },
+        CodeInfo { code: "W101", title: "Naming Convention" },
+        CodeInfo { code: "I001", title: "Unreferenced Type" },
+        CodeInfo { code: "I002", title: "Structurally Duplicate Type" },
+        CodeInfo { code: "I003", title: "Read After Write" },
+    ];
+
+    /// Look up a code in the registry
+    pub fn lookup(code: &str) -> Option<&'static CodeInfo> {
+        REGISTRY.iter().find(|info| info.code == code)
+    }
+
+    /// Link to the documentation of `code`, using the anchor GitHub gives
+    /// its heading (`### E005: Unknown Reference` -> `#e005-unknown-reference`)
+    pub fn help_url(code: &str) -> Option<String> {
+        let info = lookup(code)?;
+        let heading = format!("{}: {}", info.code, info.title).to_lowercase();
+        let anchor: String = heading
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-')
+            .map(|c| if c == ' ' { '-' } else { c })
+            .collect();
+        Some(format!("{}#{}", DOCS_URL, anchor))
+    }
 }
 
 /// Structured error for JSON output
@@ -92,6 +158,9 @@ pub struct StructuredError {
     pub severity: Severity,
     pub message: String,
     pub location: Option<StructuredLocation>,
+    /// Documentation for the code, when it is in the registry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help_url: Option<String>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -116,3 +185,28 @@ impl From<&Location> for StructuredLocation {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::codes;
+
+    #[test]
+    fn test_registry_matches_docs() {
+        let docs = include_str!("../../docs/error-codes.md");
+        let headings: Vec<&str> = docs
+            .lines()
+            .filter_map(|line| line.strip_prefix("### "))
+            .collect();
+        let registered: Vec<String> = codes::REGISTRY
+            .iter()
+            .map(|info| format!("{}: {}", info.code, info.title))
+            .collect();
+        assert_eq!(registered, headings);
+
+        assert_eq!(
+            codes::help_url("E005").unwrap(),
+            format!("{}#e005-unknown-reference", codes::DOCS_URL)
+        );
+        assert!(codes::help_url("X999").is_none());
+    }
+}
@@ -60,6 +60,32 @@ pub struct WorkflowSpec {
     pub steps: Vec<WorkflowStep>,
 }
 
+impl WorkflowSpec {
+    /// Type of an assignment source that only reads `input.<field>` or
+    /// `context.<field>`, as the generated workflow holds it: context fields,
+    /// and input fields that aren't required, are wrapped in `optional`.
+    /// `None` for any other expression and for input fields with a
+    /// `rust_type_override`. `input` is the spec of the input Type.
+    pub fn path_source_type(&self, source: &str, input: Option<&TypeSpec>) -> Option<TypeRef> {
+        let (root, field) = source.trim().split_once('.')?;
+        match root {
+            "input" => {
+                let def = input?.fields.get(field)?;
+                if def.rust_type_override.is_some() {
+                    return None;
+                }
+                Some(if def.required {
+                    def.field_type.clone()
+                } else {
+                    TypeRef::Optional(Box::new(def.field_type.clone()))
+                })
+            }
+            "context" => Some(TypeRef::Optional(Box::new(self.context.get(field)?.clone()))),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "kind")]
 pub enum WorkflowStep {
@@ -67,6 +93,32 @@ pub enum WorkflowStep {
     Effect(EffectStep),
 }
 
+impl WorkflowStep {
+    /// Step name, if any (always present on Transform steps)
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            WorkflowStep::Transform(t) => Some(&t.name),
+            WorkflowStep::Effect(e) => e.name.as_deref(),
+        }
+    }
+
+    /// Condition under which the step runs; the step is skipped when it is false
+    pub fn when(&self) -> Option<&str> {
+        match self {
+            WorkflowStep::Transform(t) => t.when.as_deref(),
+            WorkflowStep::Effect(e) => e.when.as_deref(),
+        }
+    }
+
+    /// Reference used in diagnostics: `step 'name'`, or `step <index>` if unnamed
+    pub fn label(&self, index: usize) -> String {
+        match self.name() {
+            Some(name) => format!("step '{}'", name),
+            None => format!("step {}", index),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransformStep {
     pub name: String,
@@ -74,6 +126,8 @@ pub struct TransformStep {
     pub assign: HashMap<String, String>,
     #[serde(default)]
     pub raise_if: Option<RaiseCondition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,6 +138,8 @@ pub struct RaiseCondition {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EffectStep {
+    #[serde(default)]
+    pub name: Option<String>,
     pub effect: EffectKind,
     #[serde(default)]
     pub service: Option<String>,
@@ -101,6 +157,8 @@ pub struct EffectStep {
     pub output_binding: Option<String>,
     #[serde(default = "default_on_error")]
     pub on_error: OnErrorStrategy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
 }
 
 fn default_on_error() -> OnErrorStrategy {
@@ -156,6 +214,13 @@ pub struct EndpointSpec {
     pub authz: Option<AuthzConfig>,
     #[serde(default)]
     pub errors: Vec<EndpointError>,
+    /// Enum whose variants are the only valid `errors[].code` values; the
+    /// generated error type wraps the shared enum
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_enum: Option<String>,
+    /// PATCH only: accept `{input}Patch` (all fields optional) and merge it
+    #[serde(default)]
+    pub partial: bool,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -180,10 +245,66 @@ pub enum BackoffStrategy {
     Exponential,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AuthzConfig {
     pub principal: String,
-    pub scope: String,
+    /// A single scope, or a list combined according to `require`
+    pub scope: AuthzScopes,
+    /// Whether a caller needs all of the listed scopes or any one of them
+    #[serde(default, skip_serializing_if = "ScopeRequirement::is_all")]
+    pub require: ScopeRequirement,
+}
+
+impl AuthzConfig {
+    /// The required scopes as a human-readable phrase: `'a'`, or
+    /// `all of ['a', 'b']` / `any of ['a', 'b']`
+    pub fn describe(&self) -> String {
+        match &self.scope {
+            AuthzScopes::One(scope) => format!("'{}'", scope),
+            AuthzScopes::Many(scopes) => {
+                let quoted: Vec<String> = scopes.iter().map(|s| format!("'{}'", s)).collect();
+                let require = match self.require {
+                    ScopeRequirement::All => "all",
+                    ScopeRequirement::Any => "any",
+                };
+                format!("{} of [{}]", require, quoted.join(", "))
+            }
+        }
+    }
+}
+
+/// `"scope": "refund:write"` or `"scope": ["refund:write", "refund:admin"]`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AuthzScopes {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl AuthzScopes {
+    /// The scopes, whichever form they were written in
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            AuthzScopes::One(scope) => std::slice::from_ref(scope),
+            AuthzScopes::Many(scopes) => scopes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScopeRequirement {
+    /// The caller must hold every listed scope
+    #[default]
+    All,
+    /// Any one of the listed scopes is enough
+    Any,
+}
+
+impl ScopeRequirement {
+    fn is_all(&self) -> bool {
+        *self == ScopeRequirement::All
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -209,13 +330,36 @@ pub struct ContractTestSpec {
 pub struct ContractScenario {
     pub name: String,
     pub request: serde_json::Value,
-    pub response: ContractResponse,
+    pub response: ExpectedResponse,
+}
+
+/// The response a scenario expects: a single response, or a list of
+/// acceptable ones of which any may match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ExpectedResponse {
+    One(ContractResponse),
+    AnyOf(Vec<ContractResponse>),
+}
+
+impl ExpectedResponse {
+    /// Every acceptable response, in declaration order
+    pub fn candidates(&self) -> &[ContractResponse] {
+        match self {
+            Self::One(response) => std::slice::from_ref(response),
+            Self::AnyOf(responses) => responses,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContractResponse {
     pub status: u16,
     pub body: serde_json::Value,
+
+    /// Headers the response must carry, compared by exact value
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
 }
 
 // ============================================================================
@@ -461,6 +605,15 @@ pub struct EnumSpec {
     pub generics: Vec<GenericParam>,
 }
 
+impl EnumSpec {
+    /// The variant called `name`, or serialized as `name` via `serde_rename`
+    pub fn find_variant(&self, name: &str) -> Option<&EnumVariant> {
+        self.variants
+            .iter()
+            .find(|v| v.name == name || v.serde_rename.as_deref() == Some(name))
+    }
+}
+
 /// An enum variant
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnumVariant {
@@ -478,6 +631,10 @@ pub struct EnumVariant {
     /// Serde rename for this variant
     #[serde(default)]
     pub serde_rename: Option<String>,
+
+    /// HTTP status an endpoint responds with when it returns this variant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
 }
 
 /// Data associated with an enum variant
@@ -777,7 +934,10 @@ impl IntentDocument {
             }
             IntentKind::Endpoint => {
                 if let Ok(spec) = self.as_endpoint_spec() {
-                    vec![spec.input.clone(), spec.output.clone()]
+                    let mut refs = named_references(&spec.input);
+                    refs.extend(named_references(&spec.output));
+                    refs.extend(spec.error_enum);
+                    refs
                 } else {
                     vec![]
                 }
@@ -786,7 +946,11 @@ impl IntentDocument {
                 if let Ok(spec) = self.as_service_spec() {
                     spec.operations
                         .values()
-                        .flat_map(|op| vec![op.input.clone(), op.output.clone()])
+                        .flat_map(|op| {
+                            let mut refs = named_references(&op.input);
+                            refs.extend(named_references(&op.output));
+                            refs
+                        })
                         .collect()
                 } else {
                     vec![]
@@ -881,6 +1045,20 @@ impl IntentDocument {
     }
 }
 
+/// Named types an endpoint or operation's input/output names, looking
+/// inside `array<..>`, `map<..>` and the other collections. A name that
+/// doesn't parse is kept as written so resolution reports it.
+fn named_references(type_name: &str) -> Vec<String> {
+    match TypeRef::parse(type_name) {
+        Ok(type_ref) => type_ref
+            .get_named_references()
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        Err(_) => vec![type_name.to_string()],
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -961,6 +1139,31 @@ mod tests {
         assert_eq!(spec.method, HttpMethod::Post);
         assert_eq!(spec.path, "/refund");
         assert_eq!(spec.policies.timeout_ms, Some(1500));
+        assert_eq!(
+            spec.authz.unwrap().scope,
+            AuthzScopes::One("refund:write".to_string())
+        );
+    }
+
+    #[test]
+    fn test_authz_scope_list_round_trips() {
+        let json = serde_json::json!({
+            "principal": "user",
+            "scope": ["refund:write", "refund:admin"],
+            "require": "any"
+        });
+
+        let authz: AuthzConfig = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(authz.scope.as_slice(), ["refund:write", "refund:admin"]);
+        assert_eq!(authz.require, ScopeRequirement::Any);
+        assert_eq!(authz.describe(), "any of ['refund:write', 'refund:admin']");
+        assert_eq!(serde_json::to_value(&authz).unwrap(), json);
+
+        // The single-scope form keeps its shape and the default requirement
+        let json = serde_json::json!({ "principal": "user", "scope": "refund:read" });
+        let authz: AuthzConfig = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(authz.require, ScopeRequirement::All);
+        assert_eq!(serde_json::to_value(&authz).unwrap(), json);
     }
 
     #[test]
@@ -984,4 +1187,27 @@ mod tests {
         assert_eq!(spec.table, "refunds");
         assert_eq!(spec.operations.len(), 1);
     }
+
+    #[test]
+    fn test_service_collection_types_reference_named_types() {
+        let doc = IntentDocument::with_spec(
+            IntentKind::Service,
+            "Payments".to_string(),
+            serde_json::json!({
+                "base_url": "http://payments",
+                "protocol": "http",
+                "operations": {
+                    "BatchRefund": {
+                        "method": "POST",
+                        "path": "/v1/refunds",
+                        "input": "array<RefundRequest>",
+                        "output": "map<string, RefundResponse>"
+                    }
+                }
+            }),
+        );
+        let mut refs = doc.get_type_references();
+        refs.sort();
+        assert_eq!(refs, ["RefundRequest", "RefundResponse"]);
+    }
 }
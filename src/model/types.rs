@@ -9,7 +9,14 @@ use std::collections::HashMap;
 pub enum TypeRef {
     // Primitives
     String,
+    /// `int`, a 64-bit signed integer
     Int,
+    Int8,
+    Int16,
+    Int32,
+    UInt8,
+    UInt16,
+    UInt32,
     Float,
     Bool,
     Money,
@@ -19,6 +26,10 @@ pub enum TypeRef {
 
     // Collections
     Array(Box<TypeRef>),
+    /// `array<T; N>`, exactly `N` elements
+    FixedArray(Box<TypeRef>, usize),
+    /// `tuple<A, B, ...>`
+    Tuple(Vec<TypeRef>),
     Map(Box<TypeRef>, Box<TypeRef>),
     Optional(Box<TypeRef>),
 
@@ -33,10 +44,30 @@ impl TypeRef {
 
         // Check for collection types
         if let Some(inner) = s.strip_prefix("array<").and_then(|s| s.strip_suffix('>')) {
+            if let [element, size] = split_top_level(inner, ';')?[..] {
+                let size = size
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| (1..=MAX_FIXED_ARRAY_SIZE).contains(n))
+                    .ok_or_else(|| TypeParseError::InvalidArraySize(s.to_string()))?;
+                return Ok(TypeRef::FixedArray(Box::new(Self::parse(element)?), size));
+            }
             let inner_type = Self::parse(inner)?;
             return Ok(TypeRef::Array(Box::new(inner_type)));
         }
 
+        if let Some(inner) = s.strip_prefix("tuple<").and_then(|s| s.strip_suffix('>')) {
+            let elements = split_top_level(inner, ',')?;
+            if !(2..=MAX_TUPLE_ARITY).contains(&elements.len()) {
+                return Err(TypeParseError::InvalidTupleArity(s.to_string()));
+            }
+            let elements = elements
+                .into_iter()
+                .map(Self::parse)
+                .collect::<Result<_, _>>()?;
+            return Ok(TypeRef::Tuple(elements));
+        }
+
         if let Some(inner) = s.strip_prefix("optional<").and_then(|s| s.strip_suffix('>')) {
             let inner_type = Self::parse(inner)?;
             return Ok(TypeRef::Optional(Box::new(inner_type)));
@@ -60,6 +91,12 @@ impl TypeRef {
         match s.to_lowercase().as_str() {
             "string" => Ok(TypeRef::String),
             "int" => Ok(TypeRef::Int),
+            "int8" => Ok(TypeRef::Int8),
+            "int16" => Ok(TypeRef::Int16),
+            "int32" => Ok(TypeRef::Int32),
+            "uint8" => Ok(TypeRef::UInt8),
+            "uint16" => Ok(TypeRef::UInt16),
+            "uint32" => Ok(TypeRef::UInt32),
             "float" => Ok(TypeRef::Float),
             "bool" => Ok(TypeRef::Bool),
             "money" => Ok(TypeRef::Money),
@@ -82,29 +119,60 @@ impl TypeRef {
 
     /// Check if this type is valid as a map key
     pub fn is_valid_map_key(&self) -> bool {
-        matches!(self, TypeRef::String | TypeRef::Int | TypeRef::Uuid)
+        self.is_integer() || matches!(self, TypeRef::String | TypeRef::Uuid)
     }
 
-    /// Check if this type is a primitive
-    pub fn is_primitive(&self) -> bool {
+    /// Check if this type is an integer of any width
+    pub fn is_integer(&self) -> bool {
         matches!(
             self,
-            TypeRef::String
-                | TypeRef::Int
-                | TypeRef::Float
-                | TypeRef::Bool
-                | TypeRef::Money
-                | TypeRef::DateTime
-                | TypeRef::Uuid
-                | TypeRef::Bytes
+            TypeRef::Int
+                | TypeRef::Int8
+                | TypeRef::Int16
+                | TypeRef::Int32
+                | TypeRef::UInt8
+                | TypeRef::UInt16
+                | TypeRef::UInt32
         )
     }
 
+    /// The type inside any `optional<..>` wrappers, and how many wrap it
+    pub fn strip_optional(&self) -> (&TypeRef, usize) {
+        let mut current = self;
+        let mut depth = 0;
+        while let TypeRef::Optional(inner) = current {
+            current = inner;
+            depth += 1;
+        }
+        (current, depth)
+    }
+
+    /// Check if this type is a primitive
+    pub fn is_primitive(&self) -> bool {
+        self.is_integer()
+            || matches!(
+                self,
+                TypeRef::String
+                    | TypeRef::Float
+                    | TypeRef::Bool
+                    | TypeRef::Money
+                    | TypeRef::DateTime
+                    | TypeRef::Uuid
+                    | TypeRef::Bytes
+            )
+    }
+
     /// Get the Rust type representation
     pub fn to_rust_type(&self) -> String {
         match self {
             TypeRef::String => "String".to_string(),
             TypeRef::Int => "i64".to_string(),
+            TypeRef::Int8 => "i8".to_string(),
+            TypeRef::Int16 => "i16".to_string(),
+            TypeRef::Int32 => "i32".to_string(),
+            TypeRef::UInt8 => "u8".to_string(),
+            TypeRef::UInt16 => "u16".to_string(),
+            TypeRef::UInt32 => "u32".to_string(),
             TypeRef::Float => "f64".to_string(),
             TypeRef::Bool => "bool".to_string(),
             TypeRef::Money => "rust_decimal::Decimal".to_string(),
@@ -112,6 +180,11 @@ impl TypeRef {
             TypeRef::Uuid => "uuid::Uuid".to_string(),
             TypeRef::Bytes => "Vec<u8>".to_string(),
             TypeRef::Array(inner) => format!("Vec<{}>", inner.to_rust_type()),
+            TypeRef::FixedArray(inner, size) => format!("[{}; {}]", inner.to_rust_type(), size),
+            TypeRef::Tuple(elements) => {
+                let elements: Vec<_> = elements.iter().map(TypeRef::to_rust_type).collect();
+                format!("({})", elements.join(", "))
+            }
             TypeRef::Map(k, v) => {
                 format!("std::collections::HashMap<{}, {}>", k.to_rust_type(), v.to_rust_type())
             }
@@ -124,7 +197,11 @@ impl TypeRef {
     pub fn get_named_references(&self) -> Vec<&str> {
         match self {
             TypeRef::Named(name) => vec![name.as_str()],
-            TypeRef::Array(inner) => inner.get_named_references(),
+            TypeRef::Array(inner) | TypeRef::FixedArray(inner, _) => inner.get_named_references(),
+            TypeRef::Tuple(elements) => elements
+                .iter()
+                .flat_map(TypeRef::get_named_references)
+                .collect(),
             TypeRef::Map(k, v) => {
                 let mut refs = k.get_named_references();
                 refs.extend(v.get_named_references());
@@ -141,6 +218,12 @@ impl std::fmt::Display for TypeRef {
         match self {
             TypeRef::String => write!(f, "string"),
             TypeRef::Int => write!(f, "int"),
+            TypeRef::Int8 => write!(f, "int8"),
+            TypeRef::Int16 => write!(f, "int16"),
+            TypeRef::Int32 => write!(f, "int32"),
+            TypeRef::UInt8 => write!(f, "uint8"),
+            TypeRef::UInt16 => write!(f, "uint16"),
+            TypeRef::UInt32 => write!(f, "uint32"),
             TypeRef::Float => write!(f, "float"),
             TypeRef::Bool => write!(f, "bool"),
             TypeRef::Money => write!(f, "money"),
@@ -148,6 +231,11 @@ impl std::fmt::Display for TypeRef {
             TypeRef::Uuid => write!(f, "uuid"),
             TypeRef::Bytes => write!(f, "bytes"),
             TypeRef::Array(inner) => write!(f, "array<{}>", inner),
+            TypeRef::FixedArray(inner, size) => write!(f, "array<{}; {}>", inner, size),
+            TypeRef::Tuple(elements) => {
+                let elements: Vec<_> = elements.iter().map(TypeRef::to_string).collect();
+                write!(f, "tuple<{}>", elements.join(", "))
+            }
             TypeRef::Map(k, v) => write!(f, "map<{}, {}>", k, v),
             TypeRef::Optional(inner) => write!(f, "optional<{}>", inner),
             TypeRef::Named(name) => write!(f, "{}", name),
@@ -178,7 +266,7 @@ pub enum TypeParseError {
     #[error("Invalid type name: {0}")]
     InvalidName(String),
 
-    #[error("Invalid map key type: {0} (must be string, int, or uuid)")]
+    #[error("Invalid map key type: {0} (must be string, an integer type, or uuid)")]
     InvalidMapKey(String),
 
     #[error("Unbalanced brackets in type: {0}")]
@@ -186,6 +274,44 @@ pub enum TypeParseError {
 
     #[error("Missing map value type")]
     MissingMapValue,
+
+    #[error("Invalid fixed array size in {0} (must be 1 to 32)")]
+    InvalidArraySize(String),
+
+    #[error("Invalid tuple type: {0} (must have 2 to 16 element types)")]
+    InvalidTupleArity(String),
+}
+
+/// Largest `array<T; N>`; serde only implements its traits for arrays up to 32
+const MAX_FIXED_ARRAY_SIZE: usize = 32;
+
+/// Most elements in a `tuple<...>`; serde implements its traits for tuples up to 16
+const MAX_TUPLE_ARITY: usize = 16;
+
+/// Split on `separator` outside of nested generics, trimming each part
+fn split_top_level(s: &str, separator: char) -> Result<Vec<&str>, TypeParseError> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+        if depth < 0 {
+            return Err(TypeParseError::UnbalancedBrackets(s.to_string()));
+        }
+    }
+    if depth != 0 {
+        return Err(TypeParseError::UnbalancedBrackets(s.to_string()));
+    }
+    parts.push(s[start..].trim());
+    Ok(parts)
 }
 
 /// Split map types at the comma, handling nested generics
@@ -217,6 +343,69 @@ pub struct FieldDef {
 
     #[serde(default)]
     pub required: bool,
+
+    /// Redact this field from generated `Debug` output. When absent, fields
+    /// whose names look like PII are redacted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensitive: Option<bool>,
+
+    /// Value used when the field is absent from incoming JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+
+    /// Why the field is on its way out; generated as `#[deprecated]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+
+    /// Assigned by the server (e.g. `id`, `created_at`), so left out of the
+    /// `{Type}Input` struct endpoints accept
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub readonly: bool,
+
+    /// Rust type to generate in place of the default mapping, e.g. `u32`
+    /// for an `int` ID; must be one of `rust_type_overrides` for the type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rust_type_override: Option<String>,
+}
+
+impl FieldDef {
+    /// Check `rust_type_override` against the Rust types allowed for the
+    /// declared type (seen through `optional<..>`)
+    pub fn check_rust_type_override(&self) -> Result<(), String> {
+        let Some(rust_type) = &self.rust_type_override else {
+            return Ok(());
+        };
+        let declared = match &self.field_type {
+            TypeRef::Optional(inner) => inner,
+            other => other,
+        };
+        let allowed = rust_type_overrides(declared);
+        if allowed.contains(&rust_type.as_str()) {
+            Ok(())
+        } else if allowed.is_empty() {
+            Err(format!("{} fields can't override their Rust type", declared))
+        } else {
+            Err(format!(
+                "'{}' can't hold {}; use one of {}",
+                rust_type,
+                declared,
+                allowed.join(", ")
+            ))
+        }
+    }
+}
+
+/// Rust types a field of type `type_ref` may be generated as instead of
+/// `to_rust_type`
+pub fn rust_type_overrides(type_ref: &TypeRef) -> &'static [&'static str] {
+    match type_ref {
+        t if t.is_integer() => &[
+            "i8", "i16", "i32", "i64", "i128", "u8", "u16", "u32", "u64", "u128", "isize", "usize",
+        ],
+        TypeRef::Float => &["f32", "f64"],
+        TypeRef::String => &["String", "Box<str>"],
+        _ => &[],
+    }
 }
 
 // ============================================================================
@@ -352,6 +541,93 @@ pub enum Expression {
         expr: Box<Expression>,
         default: Box<Expression>,
     },
+
+    /// String formatting: each `{}` in `template` is replaced by the next
+    /// argument (`{{` and `}}` are literal braces)
+    Format {
+        template: String,
+        #[serde(default)]
+        args: Vec<Expression>,
+    },
+}
+
+impl Expression {
+    /// Direct sub-expressions, in source order
+    pub fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Literal { .. } | Expression::Variable { .. } => vec![],
+            Expression::Field { expr, .. }
+            | Expression::Unary { expr, .. }
+            | Expression::Try { expr } => vec![expr],
+            Expression::Index { expr, index } => vec![expr, index],
+            Expression::Call { args, .. } | Expression::Format { args, .. } => args.iter().collect(),
+            Expression::Method { expr, args, .. } => {
+                std::iter::once(&**expr).chain(args.iter()).collect()
+            }
+            Expression::Binary { left, right, .. } => vec![left, right],
+            Expression::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => vec![cond, then_branch, else_branch],
+            Expression::Match { on, arms } => {
+                let mut children = vec![&**on];
+                for arm in arms {
+                    children.extend(arm.guard.as_deref());
+                    children.push(&arm.body);
+                }
+                children
+            }
+            Expression::Let { bindings, body } => bindings
+                .iter()
+                .map(|b| &b.value)
+                .chain(std::iter::once(&**body))
+                .collect(),
+            Expression::For { iterable, body, .. } => vec![iterable, body],
+            Expression::Return { value } => vec![value],
+            Expression::Raise { message, .. } => message.as_deref().into_iter().collect(),
+            Expression::Block { exprs } => exprs.iter().collect(),
+            Expression::Struct { fields, .. } => {
+                let mut names: Vec<_> = fields.keys().collect();
+                names.sort();
+                names.into_iter().map(|n| &fields[n]).collect()
+            }
+            Expression::Array { elements } | Expression::Tuple { elements } => {
+                elements.iter().collect()
+            }
+            Expression::Closure { body, .. } => vec![body],
+            Expression::UnwrapOr { expr, default } => vec![expr, default],
+        }
+    }
+
+    /// Number of `{}` placeholders in a `Format` template, or `None` if it
+    /// has any other use of braces (`{0}`, `{name}`, `{:?}`, a lone `{`)
+    pub fn format_placeholders(template: &str) -> Option<usize> {
+        let mut count = 0;
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match (c, chars.clone().next()) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    chars.next();
+                }
+                ('{', Some('}')) => {
+                    chars.next();
+                    count += 1;
+                }
+                ('{', _) | ('}', _) => return None,
+                _ => {}
+            }
+        }
+        Some(count)
+    }
+
+    /// Visit this expression and all of its descendants, depth first
+    pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Expression)) {
+        visit(self);
+        for child in self.children() {
+            child.walk(visit);
+        }
+    }
 }
 
 /// Binary operators
@@ -502,6 +778,10 @@ pub struct LetBinding {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypeSpec {
     pub fields: HashMap<String, FieldDef>,
+
+    /// Marks a Type that is meant to have no fields, which silences W017
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unit: bool,
 }
 
 impl TypeSpec {
@@ -517,6 +797,11 @@ impl TypeSpec {
             .flat_map(|f| f.field_type.get_named_references())
             .collect()
     }
+
+    /// Whether any field is server-assigned
+    pub fn has_readonly_fields(&self) -> bool {
+        self.fields.values().any(|f| f.readonly)
+    }
 }
 
 #[cfg(test)]
@@ -535,6 +820,29 @@ mod tests {
         assert_eq!(TypeRef::parse("bytes").unwrap(), TypeRef::Bytes);
     }
 
+    #[test]
+    fn test_sized_integers_round_trip() {
+        for (text, type_ref, rust) in [
+            ("int", TypeRef::Int, "i64"),
+            ("int8", TypeRef::Int8, "i8"),
+            ("int16", TypeRef::Int16, "i16"),
+            ("int32", TypeRef::Int32, "i32"),
+            ("uint8", TypeRef::UInt8, "u8"),
+            ("uint16", TypeRef::UInt16, "u16"),
+            ("uint32", TypeRef::UInt32, "u32"),
+        ] {
+            let parsed = TypeRef::parse(text).unwrap();
+            assert_eq!(parsed, type_ref);
+            assert_eq!(parsed.to_string(), text);
+            assert_eq!(parsed.to_rust_type(), rust);
+            assert!(parsed.is_primitive() && parsed.is_valid_map_key());
+        }
+
+        let t = TypeRef::parse("map<uint16, array<int32>>").unwrap();
+        assert_eq!(TypeRef::parse(&t.to_string()).unwrap(), t);
+        assert_eq!(t.to_rust_type(), "std::collections::HashMap<u16, Vec<i32>>");
+    }
+
     #[test]
     fn test_parse_array() {
         let t = TypeRef::parse("array<string>").unwrap();
@@ -571,6 +879,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_tuple() {
+        let t = TypeRef::parse("tuple<float, float>").unwrap();
+        assert_eq!(t, TypeRef::Tuple(vec![TypeRef::Float, TypeRef::Float]));
+        assert_eq!(t.to_rust_type(), "(f64, f64)");
+        assert_eq!(TypeRef::parse(&t.to_string()).unwrap(), t);
+
+        let t = TypeRef::parse("tuple<map<string, int>, optional<Point>>").unwrap();
+        assert_eq!(t.to_string(), "tuple<map<string, int>, optional<Point>>");
+        assert_eq!(t.get_named_references(), ["Point"]);
+
+        assert!(TypeRef::parse("tuple<float>").is_err());
+        assert!(TypeRef::parse("tuple<int, >").is_err());
+    }
+
+    #[test]
+    fn test_parse_fixed_array() {
+        let t = TypeRef::parse("array<uint8; 16>").unwrap();
+        assert_eq!(t, TypeRef::FixedArray(Box::new(TypeRef::UInt8), 16));
+        assert_eq!(t.to_rust_type(), "[u8; 16]");
+        assert_eq!(TypeRef::parse(&t.to_string()).unwrap(), t);
+
+        let t = TypeRef::parse("array<tuple<Point, int>; 2>").unwrap();
+        assert_eq!(t.to_rust_type(), "[(Point, i64); 2]");
+        assert_eq!(t.get_named_references(), ["Point"]);
+
+        for bad in ["array<uint8; 0>", "array<uint8; 33>", "array<uint8; -1>", "array<uint8; n>"] {
+            assert!(
+                matches!(TypeRef::parse(bad), Err(TypeParseError::InvalidArraySize(_))),
+                "{}",
+                bad
+            );
+        }
+    }
+
     #[test]
     fn test_invalid_map_key() {
         let result = TypeRef::parse("map<float, string>");
@@ -590,4 +933,23 @@ mod tests {
             "Option<i64>"
         );
     }
+
+    #[test]
+    fn test_rust_type_override_must_fit_declared_type() {
+        let field = |field_type: &str, rust_type: &str| FieldDef {
+            rust_type_override: Some(rust_type.to_string()),
+            ..serde_json::from_value(serde_json::json!({ "type": field_type })).unwrap()
+        };
+
+        assert!(field("int", "u32").check_rust_type_override().is_ok());
+        assert!(field("optional<float>", "f32").check_rust_type_override().is_ok());
+        assert_eq!(
+            field("int", "String").check_rust_type_override().unwrap_err(),
+            "'String' can't hold int; use one of i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, isize, usize"
+        );
+        assert_eq!(
+            field("uuid", "String").check_rust_type_override().unwrap_err(),
+            "uuid fields can't override their Rust type"
+        );
+    }
 }
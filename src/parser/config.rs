@@ -1,7 +1,7 @@
 //! Configuration file parsing (intent.toml)
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
 /// Path to the configuration file
@@ -16,6 +16,12 @@ pub struct IntentConfig {
     #[serde(default)]
     pub generation: GenerationConfig,
 
+    #[serde(default)]
+    pub validation: ValidationConfig,
+
+    #[serde(default)]
+    pub lint: LintConfig,
+
     #[serde(default)]
     pub runtime: RuntimeConfig,
 
@@ -23,33 +29,202 @@ pub struct IntentConfig {
     pub environments: EnvironmentsConfig,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectConfig {
     #[serde(default)]
     pub name: String,
 
     #[serde(default)]
     pub version: String,
+
+    /// Directory names skipped when discovering intent files
+    #[serde(default = "default_ignore_dirs")]
+    pub ignore_dirs: Vec<String>,
+
+    /// How many directory levels below the model path to search
+    /// (0 = only the model path itself); unlimited when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            version: String::new(),
+            ignore_dirs: default_ignore_dirs(),
+            max_depth: None,
+        }
+    }
+}
+
+fn default_ignore_dirs() -> Vec<String> {
+    vec![".git".to_string(), "target".to_string(), "node_modules".to_string()]
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenerationConfig {
     #[serde(default = "default_rust_edition")]
     pub rust_edition: String,
+
+    /// Emit a runnable `src/main.rs` serving the generated router
+    #[serde(default)]
+    pub binary: bool,
+
+    /// Default bind address for the generated binary (overridable via `BIND_ADDRESS`)
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+
+    /// Emit types into a single `types.rs` or one file per type
+    #[serde(default)]
+    pub types_layout: TypesLayout,
+
+    /// JSON string encoding for `bytes` fields
+    #[serde(default)]
+    pub bytes_encoding: BytesEncoding,
+
+    /// Response bodies handlers can produce, chosen by the `Accept` header
+    #[serde(default = "default_response_formats")]
+    pub response_formats: Vec<ResponseFormat>,
+
+    /// Derives added to every generated Type struct only when a cargo feature
+    /// is enabled, e.g. `sqlx = ["sqlx::FromRow"]`; the features are declared
+    /// in the generated Cargo.toml
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub conditional_derives: BTreeMap<String, Vec<String>>,
+
+    /// Run every generated `.rs` file through a prettyplease pass before writing
+    #[serde(default = "default_format")]
+    pub format: bool,
+
+    /// Derive `Clone` and `PartialEq` on generated endpoint error enums; their
+    /// `Internal` variant then holds the error's message instead of the error
+    #[serde(default)]
+    pub comparable_errors: bool,
+
+    /// Platform the generated crate is built for
+    #[serde(default)]
+    pub target: GenTarget,
+
+    /// Add `gen` to the `[workspace] members` of the root `Cargo.toml`
+    #[serde(default)]
+    pub workspace: bool,
+}
+
+impl GenerationConfig {
+    /// Whether to emit `src/main.rs`; a wasm crate has no tokio `main`
+    pub fn emits_binary(&self) -> bool {
+        self.binary && self.target == GenTarget::Native
+    }
+
+    /// Whether handlers negotiate the response format instead of always
+    /// answering with JSON
+    pub fn negotiates_responses(&self) -> bool {
+        self.response_formats
+            .iter()
+            .any(|format| *format != ResponseFormat::Json)
+    }
+}
+
+/// Layout of the generated types module
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TypesLayout {
+    /// `gen/src/types.rs`
+    #[default]
+    Single,
+    /// `gen/src/types/{snake_name}.rs` plus a re-exporting `mod.rs`
+    PerType,
+}
+
+/// Platform the generated crate is built for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum GenTarget {
+    /// Servers on tokio, with reqwest and sqlx
+    #[default]
+    Native,
+    /// Also builds for `wasm32`: HTTP effects use `gloo-net`, native-only
+    /// dependencies are behind target cfg, and no tokio `main` is emitted
+    Wasm,
+}
+
+/// How generated types serialize `bytes` (`Vec<u8>`) fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BytesEncoding {
+    /// Standard base64 with padding
+    #[default]
+    Base64,
+    /// Lowercase hex
+    Hex,
+}
+
+/// Serialization format for endpoint responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// `application/json`, always the fallback
+    Json,
+    /// `application/msgpack` via `rmp-serde`
+    Msgpack,
+    /// `application/cbor` via `ciborium`
+    Cbor,
 }
 
 fn default_rust_edition() -> String {
     "2021".to_string()
 }
 
+fn default_bind_address() -> String {
+    "0.0.0.0:3000".to_string()
+}
+
+fn default_response_formats() -> Vec<ResponseFormat> {
+    vec![ResponseFormat::Json]
+}
+
+fn default_format() -> bool {
+    true
+}
+
 impl Default for GenerationConfig {
     fn default() -> Self {
         Self {
             rust_edition: default_rust_edition(),
+            binary: false,
+            bind_address: default_bind_address(),
+            types_layout: TypesLayout::default(),
+            bytes_encoding: BytesEncoding::default(),
+            response_formats: default_response_formats(),
+            conditional_derives: BTreeMap::new(),
+            format: default_format(),
+            comparable_errors: false,
+            target: GenTarget::default(),
+            workspace: false,
         }
     }
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationConfig {
+    /// Check raw intent files against the embedded JSON Schemas before other phases
+    #[serde(default)]
+    pub schema: bool,
+
+    /// Bundled optional rules to run after the built-in phases
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<String>,
+}
+
+/// Settings for `intent lint`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LintConfig {
+    /// Naming rules to skip, e.g. `["field-snake-case"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RuntimeConfig {
     #[serde(default = "default_http_client")]
@@ -90,7 +265,145 @@ pub struct EnvironmentsConfig {
     pub default: String,
 
     #[serde(flatten)]
-    pub environments: HashMap<String, HashMap<String, String>>,
+    pub environments: HashMap<String, BTreeMap<String, EnvValue>>,
+}
+
+/// A value under `[environments.<name>]`. Strings may reference process
+/// environment variables as `${VAR}` or `${VAR:-default}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EnvValue {
+    Value(String),
+    /// Flattened to a comma-separated string
+    List(Vec<String>),
+    /// Flattened to dotted keys, so `[environments.dev.Payments]
+    /// base_url = ...` is the same as `"Payments.base_url" = ...`
+    Table(BTreeMap<String, EnvValue>),
+}
+
+impl EnvironmentsConfig {
+    /// Flatten environment `env` to dotted keys and interpolate `${VAR}`
+    /// references against the process environment
+    pub fn resolve(&self, env: &str) -> anyhow::Result<HashMap<String, String>> {
+        self.resolve_with(env, &|name| std::env::var(name).ok())
+    }
+
+    /// The value of dotted `key` in environment `env`, with its `${VAR}`
+    /// references interpolated against the process environment; `None` if
+    /// the environment or key isn't set. Other keys are not resolved.
+    pub fn resolve_key(&self, env: &str, key: &str) -> anyhow::Result<Option<String>> {
+        self.resolve_key_with(env, key, &|name| std::env::var(name).ok())
+    }
+
+    fn resolve_key_with(
+        &self,
+        env: &str,
+        key: &str,
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<Option<String>> {
+        let Some(table) = self.environments.get(env) else {
+            return Ok(None);
+        };
+        let mut flat = Vec::new();
+        flatten_env(table, "", &mut flat);
+        flat.into_iter()
+            .find(|(flat_key, _)| flat_key == key)
+            .map(|(_, value)| {
+                interpolate(&value, lookup)
+                    .map_err(|e| anyhow::anyhow!("In [environments.{}] \"{}\": {}", env, key, e))
+            })
+            .transpose()
+    }
+
+    /// Check every environment's `${VAR}` references are well formed, and
+    /// that those of `selected`, the one commands resolve, are set or have a
+    /// default. Unset variables in other environments are not an error.
+    fn check_with(&self, selected: &str, lookup: &dyn Fn(&str) -> Option<String>) -> anyhow::Result<()> {
+        let mut environments: Vec<_> = self.environments.keys().collect();
+        environments.sort();
+        for env in environments {
+            if env == selected {
+                self.resolve_with(env, lookup)?;
+            } else {
+                self.resolve_with(env, &|_| Some(String::new()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_with(
+        &self,
+        env: &str,
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        let table = self
+            .environments
+            .get(env)
+            .ok_or_else(|| anyhow::anyhow!("Unknown environment '{}' in [environments]", env))?;
+
+        let mut flat = Vec::new();
+        flatten_env(table, "", &mut flat);
+
+        let mut resolved = HashMap::new();
+        for (key, value) in flat {
+            let value = interpolate(&value, lookup)
+                .map_err(|e| anyhow::anyhow!("In [environments.{}] \"{}\": {}", env, key, e))?;
+            resolved.insert(key, value);
+        }
+        Ok(resolved)
+    }
+}
+
+fn flatten_env(table: &BTreeMap<String, EnvValue>, prefix: &str, out: &mut Vec<(String, String)>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            EnvValue::Value(v) => out.push((key, v.clone())),
+            EnvValue::List(items) => out.push((key, items.join(","))),
+            EnvValue::Table(inner) => flatten_env(inner, &key, out),
+        }
+    }
+}
+
+/// Replace each `${VAR}` / `${VAR:-default}` in `value`; a variable that is
+/// unset and has no default is an error
+fn interpolate(value: &str, lookup: &dyn Fn(&str) -> Option<String>) -> anyhow::Result<String> {
+    let mut output = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| anyhow::anyhow!("Unterminated '${{' in '{}'", value))?;
+        let reference = &after[..end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            anyhow::bail!("Invalid environment variable reference '${{{}}}'", reference);
+        }
+
+        match (lookup(name), default) {
+            (Some(found), _) => output.push_str(&found),
+            (None, Some(default)) => output.push_str(default),
+            (None, None) => anyhow::bail!(
+                "Environment variable '{}' is not set and has no default (use ${{{}:-default}})",
+                name,
+                name
+            ),
+        }
+        rest = &after[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
 }
 
 impl IntentConfig {
@@ -109,16 +422,49 @@ impl IntentConfig {
 
         let content = std::fs::read_to_string(path)?;
         let config: IntentConfig = toml::from_str(&content)?;
+        config.validate()?;
 
         Ok(config)
     }
 
-    /// Get an environment variable value for a given environment
-    pub fn get_env_value(&self, env: &str, key: &str) -> Option<&String> {
+    /// Check settings serde can't: cargo feature names, derive paths,
+    /// `[runtime]` client backends and `${VAR}` references in environment
+    /// values (resolved only for the default environment)
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (feature, derives) in &self.generation.conditional_derives {
+            if !is_valid_feature_name(feature) {
+                anyhow::bail!(
+                    "Invalid feature name '{}' in [generation.conditional_derives]",
+                    feature
+                );
+            }
+            for derive in derives {
+                if syn::parse_str::<syn::Path>(derive).is_err() {
+                    anyhow::bail!(
+                        "Invalid derive '{}' for feature '{}' in [generation.conditional_derives]",
+                        derive,
+                        feature
+                    );
+                }
+            }
+        }
+
+        crate::codegen::check_runtime_backends(&self.runtime)?;
+
         self.environments
-            .environments
-            .get(env)
-            .and_then(|e| e.get(key))
+            .check_with(self.default_env(), &|name| std::env::var(name).ok())
+    }
+
+    /// All settings of environment `env` as flat dotted keys, with `${VAR}`
+    /// references resolved against the process environment
+    pub fn resolve_env(&self, env: &str) -> anyhow::Result<HashMap<String, String>> {
+        self.environments.resolve(env)
+    }
+
+    /// Get an environment variable value for a given environment; an error
+    /// if its `${VAR}` references can't be resolved
+    pub fn get_env_value(&self, env: &str, key: &str) -> anyhow::Result<Option<String>> {
+        self.environments.resolve_key(env, key)
     }
 
     /// Get the default environment name
@@ -131,6 +477,16 @@ impl IntentConfig {
     }
 }
 
+/// Cargo feature names: ASCII letters, digits, `_`, `-` and `+`, not starting
+/// with `-` or `+`; `default` is reserved for the generated crate
+fn is_valid_feature_name(name: &str) -> bool {
+    name != "default"
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,6 +497,61 @@ mod tests {
         assert_eq!(config.generation.rust_edition, "2021");
         assert_eq!(config.runtime.http_client, "reqwest");
         assert_eq!(config.runtime.db_client, "sqlx");
+        assert!(!config.generation.binary);
+        assert_eq!(config.generation.bind_address, "0.0.0.0:3000");
+        assert_eq!(config.generation.bytes_encoding, BytesEncoding::Base64);
+    }
+
+    #[test]
+    fn test_bytes_encoding() {
+        let config: IntentConfig = toml::from_str("[generation]\nbytes_encoding = \"hex\"\n").unwrap();
+        assert_eq!(config.generation.bytes_encoding, BytesEncoding::Hex);
+
+        let err = toml::from_str::<IntentConfig>("[generation]\nbytes_encoding = \"base32\"\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown variant `base32`"));
+    }
+
+    #[test]
+    fn test_response_formats() {
+        let config = IntentConfig::default();
+        assert!(!config.generation.negotiates_responses());
+
+        let config: IntentConfig =
+            toml::from_str("[generation]\nresponse_formats = [\"json\", \"msgpack\"]\n").unwrap();
+        assert_eq!(
+            config.generation.response_formats,
+            [ResponseFormat::Json, ResponseFormat::Msgpack]
+        );
+        assert!(config.generation.negotiates_responses());
+    }
+
+    #[test]
+    fn test_conditional_derives() {
+        let config: IntentConfig = toml::from_str(
+            "[generation.conditional_derives]\nsqlx = [\"sqlx::FromRow\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.generation.conditional_derives["sqlx"], ["sqlx::FromRow"]);
+        assert!(config.validate().is_ok());
+
+        for bad in ["\"-sqlx\" = [\"A\"]", "default = [\"A\"]", "sqlx = [\"not a path\"]"] {
+            let config: IntentConfig =
+                toml::from_str(&format!("[generation.conditional_derives]\n{}\n", bad)).unwrap();
+            assert!(config.validate().is_err(), "{} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn test_runtime_backends_must_be_supported() {
+        let config: IntentConfig = toml::from_str("[runtime]\ndb_client = \"diesel\"\n").unwrap();
+        assert!(config.validate().is_ok());
+
+        let config: IntentConfig = toml::from_str("[runtime]\ndb_client = \"mongodb\"\n").unwrap();
+        assert_eq!(
+            config.validate().unwrap_err().to_string(),
+            "Unsupported db_client 'mongodb' in [runtime]; supported: sqlx, diesel"
+        );
     }
 
     #[test]
@@ -168,5 +579,91 @@ default = "dev"
         let config: IntentConfig = toml::from_str(toml).unwrap();
         assert_eq!(config.project.name, "test-service");
         assert_eq!(config.environments.default, "dev");
+        assert_eq!(
+            config.get_env_value("dev", "Payments.base_url").unwrap().as_deref(),
+            Some("http://localhost:8080")
+        );
+    }
+
+    #[test]
+    fn test_nested_environment_values() {
+        let config: IntentConfig = toml::from_str(
+            r#"
+[environments.dev]
+"db.connection_string" = "postgres://localhost/dev"
+brokers = ["kafka-1:9092", "kafka-2:9092"]
+
+[environments.dev.Payments]
+base_url = "http://localhost:8080"
+retry = { attempts = "3" }
+"#,
+        )
+        .unwrap();
+
+        let resolved = config.environments.resolve_with("dev", &|_| None).unwrap();
+        assert_eq!(resolved["db.connection_string"], "postgres://localhost/dev");
+        assert_eq!(resolved["brokers"], "kafka-1:9092,kafka-2:9092");
+        assert_eq!(resolved["Payments.base_url"], "http://localhost:8080");
+        assert_eq!(resolved["Payments.retry.attempts"], "3");
+        assert!(config.resolve_env("prod").is_err());
+    }
+
+    #[test]
+    fn test_environment_interpolation() {
+        let config: IntentConfig = toml::from_str(
+            r#"
+[environments.prod]
+"Payments.base_url" = "https://${PAYMENTS_HOST}/v1"
+"db.pool_size" = "${DB_POOL_SIZE:-10}"
+"#,
+        )
+        .unwrap();
+        let lookup = |name: &str| (name == "PAYMENTS_HOST").then(|| "payments.internal".to_string());
+
+        let resolved = config.environments.resolve_with("prod", &lookup).unwrap();
+        assert_eq!(resolved["Payments.base_url"], "https://payments.internal/v1");
+        assert_eq!(resolved["db.pool_size"], "10");
+
+        let err = config.environments.resolve_with("prod", &|_| None).unwrap_err();
+        assert!(err.to_string().contains("'PAYMENTS_HOST' is not set and has no default"));
+
+        // A single key resolves without the others' variables
+        let pool_size = config.environments.resolve_key_with("prod", "db.pool_size", &|_| None);
+        assert_eq!(pool_size.unwrap().as_deref(), Some("10"));
+        let err = config
+            .environments
+            .resolve_key_with("prod", "Payments.base_url", &|_| None)
+            .unwrap_err();
+        assert!(err.to_string().contains("\"Payments.base_url\": Environment variable 'PAYMENTS_HOST'"));
+        assert!(config.environments.resolve_key_with("prod", "missing", &|_| None).unwrap().is_none());
+        assert!(config.environments.resolve_key_with("dev", "db.pool_size", &|_| None).unwrap().is_none());
+
+        // Only the default environment's variables must be set
+        let config: IntentConfig = toml::from_str(
+            r#"
+[environments]
+default = "dev"
+
+[environments.dev]
+"Payments.base_url" = "${PAYMENTS_URL:-http://localhost:8080}"
+
+[environments.prod]
+"Payments.base_url" = "https://${PAYMENTS_HOST}/v1"
+"#,
+        )
+        .unwrap();
+        assert!(config.environments.check_with("dev", &|_| None).is_ok());
+        let err = config.environments.check_with("prod", &|_| None).unwrap_err();
+        assert!(err.to_string().contains("'PAYMENTS_HOST' is not set"));
+
+        let mut malformed = config.clone();
+        malformed.environments.environments.get_mut("prod").unwrap().insert(
+            "db.pool_size".to_string(),
+            EnvValue::Value("${UNCLOSED".to_string()),
+        );
+        assert!(malformed.environments.check_with("dev", &|_| None).is_err());
+
+        assert!(interpolate("${UNCLOSED", &lookup).is_err());
+        assert!(interpolate("${not valid}", &lookup).is_err());
     }
 }
@@ -1,18 +1,28 @@
 //! Intent file discovery and loading
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::Deserialize;
 use uuid::Uuid;
 use walkdir::WalkDir;
 
-use crate::model::{IntentDocument, IntentKind, IntentSummary};
+use crate::codegen::{to_snake_case, GenManifest};
+use crate::model::{
+    parse_schema_version, IntentDocument, IntentKind, IntentSummary, SUPPORTED_SCHEMA_VERSIONS,
+};
 use crate::parser::canonical::{pretty_canonical, FormatResult};
+use crate::parser::migrate::upgrade_document;
+use crate::parser::{IntentConfig, ProjectConfig};
 
 /// The default path for intent model files
 pub const DEFAULT_MODEL_PATH: &str = ".intent/model";
 
+/// Environment variable overriding the model path
+pub const MODEL_PATH_ENV: &str = "INTENT_MODEL_PATH";
+
 /// The intent file extension
 pub const INTENT_EXTENSION: &str = ".intent.json";
 
@@ -35,34 +45,28 @@ impl IntentStore {
         Self::default()
     }
 
-    /// Load all intent files from the default path
+    /// Load all intent files from the default path (honours `INTENT_MODEL_PATH`)
     pub fn load_from_default_path() -> Result<Self> {
-        Self::load_from_path(DEFAULT_MODEL_PATH)
+        Self::load_from_path(resolve_model_path(None))
     }
 
-    /// Load all intent files from a specific path
+    /// Load all intent files from a specific path with the default discovery
+    /// settings; `intent.toml` is not read
     pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self> {
-        let mut store = Self::new();
-        let path = path.as_ref();
+        Self::load_from_path_with_config(path, &IntentConfig::default())
+    }
 
-        if !path.exists() {
-            return Ok(store);
-        }
+    /// Load all intent files from a specific path, honouring the discovery
+    /// settings of `config`
+    pub fn load_from_path_with_config(path: impl AsRef<Path>, config: &IntentConfig) -> Result<Self> {
+        // Parse in parallel, then add in walk order so the first bad file
+        // or duplicate reported is the same as for a serial load
+        let files = walk_intent_files(path.as_ref(), &config.project);
+        let docs: Vec<Result<IntentDocument>> = files.par_iter().map(load_intent_file).collect();
 
-        for entry in WalkDir::new(path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let file_path = entry.path();
-            if file_path.is_file()
-                && file_path
-                    .to_string_lossy()
-                    .ends_with(INTENT_EXTENSION)
-            {
-                let doc = load_intent_file(file_path)?;
-                store.add(doc)?;
-            }
+        let mut store = Self::new();
+        for doc in docs {
+            store.add(doc?)?;
         }
 
         Ok(store)
@@ -96,6 +100,55 @@ impl IntentStore {
         Ok(())
     }
 
+    /// Remove a document from the store, returning it if present
+    pub fn remove(&mut self, id: &Uuid) -> Option<IntentDocument> {
+        let doc = self.by_id.remove(id)?;
+
+        self.by_kind_name.remove(&(doc.kind, doc.name.clone()));
+        if let Some(ids) = self.by_name.get_mut(&doc.name) {
+            ids.retain(|other| other != id);
+            if ids.is_empty() {
+                self.by_name.remove(&doc.name);
+            }
+        }
+
+        Some(doc)
+    }
+
+    /// Re-parse a single intent file and bring the store in line with it,
+    /// returning the affected document's ID.
+    ///
+    /// The document previously loaded from `path` is replaced; if the file's
+    /// ID changed this is a remove plus an add, and if the file no longer
+    /// exists its document is removed. On error the store is left unchanged.
+    pub fn reload_file(&mut self, path: impl AsRef<Path>) -> Result<Uuid> {
+        let path = path.as_ref();
+        let previous = self
+            .by_id
+            .values()
+            .find(|doc| doc.source_file.as_deref().map(Path::new) == Some(path))
+            .map(|doc| doc.id);
+
+        if !path.exists() {
+            let id = previous
+                .with_context(|| format!("No intent loaded from {}", path.display()))?;
+            self.remove(&id);
+            return Ok(id);
+        }
+
+        let doc = load_intent_file(path)?;
+        let id = doc.id;
+        let old = previous.and_then(|previous| self.remove(&previous));
+        if let Err(err) = self.add(doc) {
+            if let Some(old) = old {
+                self.add(old)?;
+            }
+            return Err(err);
+        }
+
+        Ok(id)
+    }
+
     /// Get a document by ID
     pub fn get(&self, id: &Uuid) -> Option<&IntentDocument> {
         self.by_id.get(id)
@@ -108,6 +161,12 @@ impl IntentStore {
             .and_then(|id| self.by_id.get(id))
     }
 
+    /// Look up a name usable as a field or context type: a Type, else an Enum
+    pub fn get_type_or_enum(&self, name: &str) -> Option<&IntentDocument> {
+        self.get_by_kind_name(IntentKind::Type, name)
+            .or_else(|| self.get_by_kind_name(IntentKind::Enum, name))
+    }
+
     /// Find a document by name (searching all kinds)
     pub fn find_by_name(&self, name: &str) -> Option<&IntentDocument> {
         self.by_name
@@ -116,6 +175,19 @@ impl IntentStore {
             .and_then(|id| self.by_id.get(id))
     }
 
+    /// All documents called `name`, of any kind, ordered by kind
+    pub fn find_all_by_name(&self, name: &str) -> Vec<&IntentDocument> {
+        let mut docs: Vec<_> = self
+            .by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.by_id.get(id))
+            .collect();
+        docs.sort_by_key(|doc| doc.kind.to_string());
+        docs
+    }
+
     /// Get all documents of a specific kind
     pub fn get_by_kind(&self, kind: IntentKind) -> Vec<&IntentDocument> {
         self.by_id
@@ -124,14 +196,15 @@ impl IntentStore {
             .collect()
     }
 
-    /// List all intents, optionally filtered by kind
-    pub fn list(&self, kind_filter: Option<&str>) -> Vec<IntentSummary> {
+    /// List all intents, optionally filtered by kind and tag
+    pub fn list(&self, kind_filter: Option<&str>, tag_filter: Option<&str>) -> Vec<IntentSummary> {
         let kind_filter = kind_filter.and_then(IntentKind::from_str);
 
         let mut summaries: Vec<IntentSummary> = self
             .by_id
             .values()
             .filter(|d| kind_filter.map_or(true, |k| d.kind == k))
+            .filter(|d| tag_filter.is_none_or(|t| d.tags.iter().any(|tag| tag == t)))
             .map(IntentSummary::from)
             .collect();
 
@@ -237,7 +310,7 @@ impl IntentStore {
 
         // Type references
         for type_name in doc.get_type_references() {
-            if let Some(type_doc) = self.get_by_kind_name(IntentKind::Type, &type_name) {
+            if let Some(type_doc) = self.get_type_or_enum(&type_name) {
                 deps.push(type_doc);
             }
         }
@@ -274,7 +347,7 @@ impl IntentStore {
 
                 // Check if other references this doc
                 match doc.kind {
-                    IntentKind::Type => other
+                    IntentKind::Type | IntentKind::Enum => other
                         .get_type_references()
                         .contains(&doc.name),
                     IntentKind::Workflow => other
@@ -288,38 +361,334 @@ impl IntentStore {
             })
             .collect()
     }
+
+    /// IDs of every intent that references `id` directly or through other
+    /// intents, not including `id` itself
+    pub fn transitive_dependents(&self, id: &Uuid) -> BTreeSet<Uuid> {
+        let mut dependents = BTreeSet::new();
+        let mut pending = vec![*id];
+        while let Some(current) = pending.pop() {
+            for dependent in self.get_dependents(&current) {
+                if dependent.id != *id && dependents.insert(dependent.id) {
+                    pending.push(dependent.id);
+                }
+            }
+        }
+        dependents
+    }
+
+    /// How many intents depend on `id`, directly and transitively, and for a
+    /// Type or Enum the Endpoints that end up exposing it
+    pub fn impact(&self, id: &Uuid) -> Option<ImpactReport> {
+        let doc = self.get(id)?;
+        let summarize = |docs: Vec<&IntentDocument>| {
+            let mut summaries: Vec<IntentSummary> = docs.into_iter().map(IntentSummary::from).collect();
+            summaries.sort_by(|a, b| (&a.kind, &a.name).cmp(&(&b.kind, &b.name)));
+            summaries
+        };
+
+        let direct = summarize(self.get_dependents(id));
+        let transitive = summarize(
+            self.transitive_dependents(id)
+                .iter()
+                .filter_map(|dependent| self.get(dependent))
+                .collect(),
+        );
+        let endpoints = matches!(doc.kind, IntentKind::Type | IntentKind::Enum).then(|| {
+            transitive
+                .iter()
+                .filter(|summary| summary.kind == IntentKind::Endpoint.to_string())
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+
+        Some(ImpactReport {
+            kind: doc.kind,
+            name: doc.name.clone(),
+            direct_count: direct.len(),
+            transitive_count: transitive.len(),
+            endpoint_count: endpoints.as_ref().map(Vec::len),
+            direct,
+            transitive,
+            endpoints,
+        })
+    }
+
+    /// Everything that must be regenerated when intent `id` changes: the intent
+    /// and its transitive dependents, plus every file the manifest records
+    /// as generated from any of them
+    pub fn regeneration_closure(&self, id: &Uuid, manifest: &GenManifest) -> RegenerationClosure {
+        let mut affected_intents = self.transitive_dependents(id);
+        if self.get(id).is_some() {
+            affected_intents.insert(*id);
+        }
+
+        let affected_files = manifest
+            .files
+            .iter()
+            .filter(|(_, entry)| {
+                entry
+                    .source_intents
+                    .iter()
+                    .filter_map(|source| source.parse::<Uuid>().ok())
+                    .any(|source| affected_intents.contains(&source))
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        RegenerationClosure {
+            affected_intents,
+            affected_files,
+        }
+    }
+
+    /// Plan renaming intent `id` to `new_name`: its own file is renamed (when
+    /// the file name follows the intent name) and every dependent's spec has
+    /// its references rewritten. Nothing is written until `RenamePlan::apply`.
+    pub fn plan_rename(&self, id: &Uuid, new_name: &str) -> Result<RenamePlan> {
+        let doc = self
+            .get(id)
+            .with_context(|| format!("No intent with ID {}", id))?;
+        if self.get_by_kind_name(doc.kind, new_name).is_some() {
+            anyhow::bail!("A {} named '{}' already exists", doc.kind, new_name);
+        }
+        let file = doc
+            .source_file
+            .clone()
+            .with_context(|| format!("Intent '{}' has no source file", doc.name))?;
+        let new_file = renamed_file(Path::new(&file), &doc.name, new_name);
+        if new_file != Path::new(&file) && new_file.exists() {
+            anyhow::bail!("File already exists: {}", new_file.display());
+        }
+
+        let mut value = read_json(&file)?;
+        value["name"] = serde_json::Value::String(new_name.to_string());
+        // Self-references, e.g. a recursive Type
+        rename_references(&mut value["spec"], doc.kind, &doc.name, new_name);
+
+        let mut plan = RenamePlan {
+            kind: doc.kind,
+            from: doc.name.clone(),
+            to: new_name.to_string(),
+            file,
+            new_file: new_file.to_string_lossy().to_string(),
+            updated: Vec::new(),
+            writes: vec![(new_file, pretty_canonical(&value))],
+        };
+
+        let mut dependents = self.get_dependents(id);
+        dependents.sort_by(|a, b| a.source_file.cmp(&b.source_file));
+        for dependent in dependents {
+            let Some(path) = dependent.source_file.as_deref() else {
+                continue;
+            };
+            let mut value = read_json(path)?;
+            if rename_references(&mut value["spec"], doc.kind, &doc.name, new_name) {
+                plan.updated.push(path.to_string());
+                plan.writes.push((PathBuf::from(path), pretty_canonical(&value)));
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Build the transitive dependency tree of an intent. A dependency that
+    /// already appears on the path from the root is marked as a cycle and not
+    /// expanded; nodes at `max_depth` are marked truncated.
+    pub fn dependency_tree(&self, id: &Uuid, max_depth: Option<usize>) -> Option<DependencyNode> {
+        let doc = self.get(id)?;
+        let mut path = vec![doc.id];
+        Some(self.dependency_node(doc, 0, max_depth, &mut path))
+    }
+
+    fn dependency_node(
+        &self,
+        doc: &IntentDocument,
+        depth: usize,
+        max_depth: Option<usize>,
+        path: &mut Vec<Uuid>,
+    ) -> DependencyNode {
+        let mut node = DependencyNode {
+            id: doc.id,
+            name: doc.name.clone(),
+            kind: doc.kind,
+            cycle: false,
+            truncated: false,
+            dependencies: Vec::new(),
+        };
+
+        // The same type can be referenced more than once (e.g. by several operations)
+        let mut deps = self.get_dependencies(&doc.id);
+        let mut seen = std::collections::HashSet::new();
+        deps.retain(|d| seen.insert(d.id));
+
+        if deps.is_empty() {
+            return node;
+        }
+        if max_depth.is_some_and(|max| depth >= max) {
+            node.truncated = true;
+            return node;
+        }
+
+        for dep in deps {
+            if path.contains(&dep.id) {
+                node.dependencies.push(DependencyNode {
+                    id: dep.id,
+                    name: dep.name.clone(),
+                    kind: dep.kind,
+                    cycle: true,
+                    truncated: false,
+                    dependencies: Vec::new(),
+                });
+                continue;
+            }
+
+            path.push(dep.id);
+            node.dependencies
+                .push(self.dependency_node(dep, depth + 1, max_depth, path));
+            path.pop();
+        }
+
+        node
+    }
+}
+
+/// Intents and generated files affected by a change to one intent
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct RegenerationClosure {
+    /// The changed intent and everything that transitively references it
+    pub affected_intents: BTreeSet<Uuid>,
+    /// Generated files (manifest paths) produced from any affected intent
+    pub affected_files: BTreeSet<String>,
 }
 
-/// Load a single intent file
+/// The intents that would be affected by changing or removing one intent
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ImpactReport {
+    pub kind: IntentKind,
+    pub name: String,
+    pub direct_count: usize,
+    pub transitive_count: usize,
+    /// Endpoints exposing a Type or Enum; absent for other kinds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint_count: Option<usize>,
+    /// Intents referencing this one
+    pub direct: Vec<IntentSummary>,
+    /// Intents referencing this one directly or through other intents
+    pub transitive: Vec<IntentSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoints: Option<Vec<IntentSummary>>,
+}
+
+/// A node in an intent's transitive dependency tree
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DependencyNode {
+    pub id: Uuid,
+    pub name: String,
+    pub kind: IntentKind,
+    /// This dependency already appears higher up the tree
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cycle: bool,
+    /// Dependencies exist below this node but were cut off by the depth bound
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    pub dependencies: Vec<DependencyNode>,
+}
+
+/// Resolve the model directory: explicit `--model-dir`, then
+/// `INTENT_MODEL_PATH`, then `.intent/model`
+pub fn resolve_model_path(model_dir: Option<&Path>) -> PathBuf {
+    if let Some(dir) = model_dir {
+        return dir.to_path_buf();
+    }
+
+    match std::env::var(MODEL_PATH_ENV) {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(DEFAULT_MODEL_PATH),
+    }
+}
+
+/// Load a single intent file. A file with an older `schema_version` is
+/// upgraded in memory; `migrate-schema` writes the upgrade back.
 pub fn load_intent_file(path: impl AsRef<Path>) -> Result<IntentDocument> {
     let path = path.as_ref();
     let content = std::fs::read_to_string(path)
         .with_context(|| format!("Failed to read file: {}", path.display()))?;
 
-    let mut doc: IntentDocument = serde_json::from_str(&content)
+    let mut value: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse intent file: {}", path.display()))?;
+    let mut doc = IntentDocument::deserialize(&value)
         .with_context(|| format!("Failed to parse intent file: {}", path.display()))?;
+    check_schema_version(&doc, path)?;
+
+    let version = parse_schema_version(&doc.schema_version).expect("checked above");
+    if version < *SUPPORTED_SCHEMA_VERSIONS.end() {
+        upgrade_document(&mut value, version);
+        doc = serde_json::from_value(value)
+            .with_context(|| format!("Upgraded {} is not a valid intent", path.display()))?;
+    }
 
     doc.source_file = Some(path.to_string_lossy().to_string());
 
     Ok(doc)
 }
 
-/// Create a new intent file
-pub fn create_new_intent(kind: &str, name: &str) -> Result<PathBuf> {
+/// Refuse a document whose `schema_version` this engine doesn't support
+pub(super) fn check_schema_version(doc: &IntentDocument, path: &Path) -> Result<()> {
+    let format = |(major, minor): (u32, u32)| format!("{}.{}", major, minor);
+    let (oldest, newest) = (
+        *SUPPORTED_SCHEMA_VERSIONS.start(),
+        *SUPPORTED_SCHEMA_VERSIONS.end(),
+    );
+    let Some(version) = parse_schema_version(&doc.schema_version) else {
+        anyhow::bail!(
+            "{} has an invalid schema_version '{}' (expected MAJOR.MINOR, e.g. \"{}\")",
+            path.display(),
+            doc.schema_version,
+            format(newest)
+        );
+    };
+    if version > newest {
+        anyhow::bail!(
+            "{} uses schema_version {}, but intent-engine {} only supports up to {}; \
+             this file requires a newer intent-engine",
+            path.display(),
+            doc.schema_version,
+            env!("CARGO_PKG_VERSION"),
+            format(newest)
+        );
+    }
+    if version < oldest {
+        anyhow::bail!(
+            "{} uses schema_version {}, older than the oldest version intent-engine supports ({})",
+            path.display(),
+            doc.schema_version,
+            format(oldest)
+        );
+    }
+    Ok(())
+}
+
+/// A scaffolded intent file, not yet written
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NewIntent {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// Work out the path and canonical content of a new intent file without
+/// touching the disk. Fails if the file already exists.
+pub fn plan_new_intent(kind: &str, name: &str, model_dir: &Path) -> Result<NewIntent> {
     let kind = IntentKind::from_str(kind)
         .ok_or_else(|| anyhow::anyhow!("Invalid intent kind: {}", kind))?;
 
-    // Create the directory if it doesn't exist
-    let model_dir = Path::new(DEFAULT_MODEL_PATH);
-    std::fs::create_dir_all(model_dir)?;
-
     // Create the file path
     let file_name = format!("{}{}", name.to_lowercase(), INTENT_EXTENSION);
-    let file_path = model_dir.join(&file_name);
+    let path = model_dir.join(&file_name);
 
     // Check if file already exists
-    if file_path.exists() {
-        anyhow::bail!("File already exists: {}", file_path.display());
+    if path.exists() {
+        anyhow::bail!("File already exists: {}", path.display());
     }
 
     // Create the document
@@ -329,57 +698,106 @@ pub fn create_new_intent(kind: &str, name: &str) -> Result<PathBuf> {
     let json_value = serde_json::to_value(&doc)?;
     let content = pretty_canonical(&json_value);
 
+    Ok(NewIntent { path, content })
+}
+
+/// Create a new intent file
+pub fn create_new_intent(kind: &str, name: &str, model_dir: &Path) -> Result<PathBuf> {
+    let new_intent = plan_new_intent(kind, name, model_dir)?;
+
+    // Create the directory if it doesn't exist
+    if let Some(dir) = new_intent.path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
     // Write the file
-    std::fs::write(&file_path, content)?;
+    std::fs::write(&new_intent.path, &new_intent.content)?;
 
-    Ok(file_path)
+    Ok(new_intent.path)
 }
 
 /// Format intent files (canonicalize JSON)
 pub fn format_intent_files(
     specific_file: Option<&str>,
     check_only: bool,
+    model_dir: &Path,
+    config: &IntentConfig,
 ) -> Result<Vec<FormatResult>> {
     let mut results = Vec::new();
 
     let files: Vec<PathBuf> = if let Some(file) = specific_file {
         vec![PathBuf::from(file)]
     } else {
-        discover_intent_files(DEFAULT_MODEL_PATH)?
+        discover_intent_files(model_dir, config)
     };
 
     for file_path in files {
-        let content = std::fs::read_to_string(&file_path)?;
-        let value: serde_json::Value = serde_json::from_str(&content)?;
-        let canonical = pretty_canonical(&value);
+        results.push(format_json_file(&file_path, check_only)?);
+    }
 
-        let changed = content != canonical;
+    Ok(results)
+}
 
-        if changed && !check_only {
-            std::fs::write(&file_path, &canonical)?;
-        }
+/// Format other JSON files (lock files) the same way as intent files.
+/// Paths that don't exist are skipped.
+pub fn format_json_files(paths: &[&str], check_only: bool) -> Result<Vec<FormatResult>> {
+    paths
+        .iter()
+        .map(Path::new)
+        .filter(|path| path.is_file())
+        .map(|path| format_json_file(path, check_only))
+        .collect()
+}
 
-        results.push(FormatResult {
-            path: file_path.to_string_lossy().to_string(),
-            changed,
-        });
+fn format_json_file(file_path: &Path, check_only: bool) -> Result<FormatResult> {
+    let content = std::fs::read_to_string(file_path)?;
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", file_path.display()))?;
+    let canonical = pretty_canonical(&value);
+
+    let changed = content != canonical;
+
+    if changed && !check_only {
+        std::fs::write(file_path, &canonical)?;
     }
 
-    Ok(results)
+    Ok(FormatResult {
+        path: file_path.to_string_lossy().to_string(),
+        changed,
+    })
 }
 
-/// Discover all intent files in a directory
-pub fn discover_intent_files(path: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
-    let path = path.as_ref();
+/// Discover all intent files in a directory, honouring the discovery
+/// settings of `config`
+pub fn discover_intent_files(path: impl AsRef<Path>, config: &IntentConfig) -> Vec<PathBuf> {
+    walk_intent_files(path.as_ref(), &config.project)
+}
+
+/// Intent files under `path`, skipping `ignore_dirs` and anything deeper
+/// than `max_depth`
+fn walk_intent_files(path: &Path, project: &ProjectConfig) -> Vec<PathBuf> {
     let mut files = Vec::new();
 
     if !path.exists() {
-        return Ok(files);
+        return files;
     }
 
-    for entry in WalkDir::new(path)
-        .follow_links(true)
+    let mut walker = WalkDir::new(path).follow_links(true);
+    if let Some(depth) = project.max_depth {
+        // Files directly in the model path are at walk depth 1
+        walker = walker.max_depth(depth + 1);
+    }
+
+    for entry in walker
         .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !project
+                    .ignore_dirs
+                    .iter()
+                    .any(|dir| entry.file_name() == dir.as_str())
+        })
         .filter_map(|e| e.ok())
     {
         let file_path = entry.path();
@@ -395,7 +813,7 @@ pub fn discover_intent_files(path: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
     // Sort for deterministic ordering
     files.sort();
 
-    Ok(files)
+    files
 }
 
 /// Result of applying a patch
@@ -411,8 +829,159 @@ pub struct PatchOperation {
     pub target: String,
 }
 
+/// The edits `IntentStore::plan_rename` would make
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RenamePlan {
+    pub kind: IntentKind,
+    pub from: String,
+    pub to: String,
+    /// The renamed intent's file, before and after
+    pub file: String,
+    pub new_file: String,
+    /// Dependent intent files whose references are rewritten
+    pub updated: Vec<String>,
+    #[serde(skip)]
+    writes: Vec<(PathBuf, String)>,
+}
+
+impl RenamePlan {
+    /// Write every planned edit, then remove the old file if it moved
+    pub fn apply(&self) -> Result<()> {
+        for (path, content) in &self.writes {
+            std::fs::write(path, content)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        if self.new_file != self.file {
+            std::fs::remove_file(&self.file)
+                .with_context(|| format!("Failed to remove {}", self.file))?;
+        }
+        Ok(())
+    }
+}
+
+fn read_json(path: &str) -> Result<serde_json::Value> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read file: {}", path))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse intent file: {}", path))
+}
+
+/// `path` with the intent name in its file name replaced, keeping the
+/// file's casing style (`refund-response`, `refund_response`, `refundresponse`)
+fn renamed_file(path: &Path, old_name: &str, new_name: &str) -> PathBuf {
+    let Some(file_name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+        return path.to_path_buf();
+    };
+    let styles: [fn(&str) -> String; 3] = [
+        |name| to_snake_case(name).replace('_', "-"),
+        to_snake_case,
+        |name| name.to_lowercase(),
+    ];
+    let stem = file_name.strip_suffix(INTENT_EXTENSION).unwrap_or(&file_name);
+    let is_boundary = |c: Option<char>| c.is_none_or(|c| c == '-' || c == '_');
+    for style in styles {
+        let old = style(old_name);
+        // Only whole words of the file name, so `Refund` leaves `refund-response` alone
+        let found = stem.match_indices(&old).find(|(start, _)| {
+            is_boundary(stem[..*start].chars().next_back())
+                && is_boundary(stem[start + old.len()..].chars().next())
+        });
+        if let Some((start, _)) = found {
+            let renamed = format!("{}{}{}", &stem[..start], style(new_name), &file_name[start + old.len()..]);
+            return path.with_file_name(renamed);
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Point references to the `kind` intent `old_name` in `spec` at `new_name`.
+/// Workflows are referenced from `workflow` fields and services from
+/// `service` fields; types and enums from the type expressions under
+/// `TYPE_KEYS` (`RefundRequest`, `array<RefundRequest>`, ...). Returns
+/// whether anything changed.
+fn rename_references(spec: &mut serde_json::Value, kind: IntentKind, old_name: &str, new_name: &str) -> bool {
+    match spec {
+        serde_json::Value::Object(map) => {
+            let mut changed = false;
+            for (key, value) in map.iter_mut() {
+                changed |= match (kind, key.as_str(), value) {
+                    (IntentKind::Workflow, "workflow", serde_json::Value::String(text))
+                    | (IntentKind::Service, "service", serde_json::Value::String(text)) => {
+                        let matched = text == old_name;
+                        if matched {
+                            *text = new_name.to_string();
+                        }
+                        matched
+                    }
+                    (IntentKind::Type | IntentKind::Enum, key, value) if TYPE_KEYS.contains(&key) => {
+                        rename_in_type_exprs(value, old_name, new_name)
+                    }
+                    (_, key, _) if DATA_KEYS.contains(&key) => false,
+                    (_, _, value) => rename_references(value, kind, old_name, new_name),
+                };
+            }
+            changed
+        }
+        serde_json::Value::Array(items) => items
+            .iter_mut()
+            .fold(false, |changed, item| rename_references(item, kind, old_name, new_name) | changed),
+        _ => false,
+    }
+}
+
+/// Fields holding a type expression, or an array or map of them: field,
+/// parameter and return `type`s, `input`/`output`, Workflow `context`,
+/// Enum variant `data`, Trait `implementors` and Endpoint `error_enum`
+const TYPE_KEYS: &[&str] = &["type", "input", "output", "context", "data", "implementors", "error_enum"];
+
+/// Expressions, literal values and request bodies, which hold no references
+const DATA_KEYS: &[&str] = &["assign", "input_mapping", "raise_if", "body", "default", "query", "scenarios"];
+
+/// Rename `old_name` in the type expression `value`, or in each string of an
+/// array or map of them
+fn rename_in_type_exprs(value: &mut serde_json::Value, old_name: &str, new_name: &str) -> bool {
+    match value {
+        serde_json::Value::String(text) => match rename_in_type_expr(text, old_name, new_name) {
+            Some(renamed) => {
+                *text = renamed;
+                true
+            }
+            None => false,
+        },
+        serde_json::Value::Array(items) => items
+            .iter_mut()
+            .fold(false, |changed, item| rename_in_type_exprs(item, old_name, new_name) | changed),
+        serde_json::Value::Object(map) => map
+            .values_mut()
+            .fold(false, |changed, item| rename_in_type_exprs(item, old_name, new_name) | changed),
+        _ => false,
+    }
+}
+
+fn rename_in_type_expr(text: &str, old_name: &str, new_name: &str) -> Option<String> {
+    let type_ref = crate::model::TypeRef::parse(text).ok()?;
+    if !type_ref.get_named_references().contains(&old_name) {
+        return None;
+    }
+
+    // Swap whole identifiers only, so `Refund` doesn't touch `RefundRequest`
+    let mut renamed = String::with_capacity(text.len());
+    let mut word = String::new();
+    for c in text.chars().chain(std::iter::once('\0')) {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        renamed.push_str(if word == old_name { new_name } else { &word });
+        word.clear();
+        if c != '\0' {
+            renamed.push(c);
+        }
+    }
+    Some(renamed)
+}
+
 /// Apply a patch file
-pub fn apply_patch(file: &str, dry_run: bool) -> Result<PatchResult> {
+pub fn apply_patch(file: &str, dry_run: bool, model_dir: &Path) -> Result<PatchResult> {
     let content = std::fs::read_to_string(file)?;
     let patch: serde_json::Value = serde_json::from_str(&content)?;
 
@@ -443,14 +1012,14 @@ pub fn apply_patch(file: &str, dry_run: bool) -> Result<PatchResult> {
                 match action {
                     "create" => {
                         if let Some(content) = op.get("content") {
-                            let path = Path::new(DEFAULT_MODEL_PATH).join(target);
+                            let path = model_dir.join(target);
                             let canonical = pretty_canonical(content);
                             std::fs::write(path, canonical)?;
                         }
                     }
                     "update" => {
                         if let Some(content) = op.get("content") {
-                            let path = Path::new(DEFAULT_MODEL_PATH).join(target);
+                            let path = model_dir.join(target);
                             if !path.exists() {
                                 result.conflicts.push(format!("File not found: {}", target));
                                 continue;
@@ -460,7 +1029,7 @@ pub fn apply_patch(file: &str, dry_run: bool) -> Result<PatchResult> {
                         }
                     }
                     "delete" => {
-                        let path = Path::new(DEFAULT_MODEL_PATH).join(target);
+                        let path = model_dir.join(target);
                         if path.exists() {
                             std::fs::remove_file(path)?;
                         }
@@ -520,4 +1089,296 @@ mod tests {
 
         assert_eq!(store.len(), 2);
     }
+
+    #[test]
+    fn test_remove_updates_indices() {
+        let mut store = IntentStore::new();
+
+        let doc1 = IntentDocument::new(IntentKind::Type, "Test".to_string());
+        let doc2 = IntentDocument::new(IntentKind::Service, "Test".to_string());
+        let id1 = doc1.id;
+        let id2 = doc2.id;
+
+        store.add(doc1).unwrap();
+        store.add(doc2).unwrap();
+
+        assert_eq!(store.find_all_by_name("Test").len(), 2);
+
+        let removed = store.remove(&id1).unwrap();
+        assert_eq!(removed.id, id1);
+        assert_eq!(store.len(), 1);
+        assert!(store.get(&id1).is_none());
+        assert!(store.get_by_kind_name(IntentKind::Type, "Test").is_none());
+        assert_eq!(store.find_by_name("Test").unwrap().id, id2);
+        assert_eq!(store.find_all_by_name("Test").len(), 1);
+
+        // Re-adding the same kind and name is allowed once removed
+        let doc3 = IntentDocument::new(IntentKind::Type, "Test".to_string());
+        store.add(doc3).unwrap();
+
+        assert!(store.remove(&id1).is_none());
+    }
+
+    fn write_type(dir: &TempDir, doc: &IntentDocument) -> PathBuf {
+        let path = dir.path().join("test.intent.json");
+        std::fs::write(&path, serde_json::to_string_pretty(doc).unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_reload_file_updates_spec() {
+        let dir = TempDir::new().unwrap();
+        let mut doc = IntentDocument::new(IntentKind::Type, "Test".to_string());
+        let path = write_type(&dir, &doc);
+        let mut store = IntentStore::load_from_path(dir.path()).unwrap();
+
+        doc.spec = serde_json::json!({ "fields": { "id": { "type": "uuid" } } });
+        write_type(&dir, &doc);
+        assert_eq!(store.reload_file(&path).unwrap(), doc.id);
+
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(&doc.id).unwrap().spec, doc.spec);
+    }
+
+    #[test]
+    fn test_reload_file_renames_and_changes_id() {
+        let dir = TempDir::new().unwrap();
+        let doc = IntentDocument::new(IntentKind::Type, "Old".to_string());
+        let old_id = doc.id;
+        let path = write_type(&dir, &doc);
+        let mut store = IntentStore::load_from_path(dir.path()).unwrap();
+
+        let mut renamed = doc.clone();
+        renamed.name = "New".to_string();
+        write_type(&dir, &renamed);
+        store.reload_file(&path).unwrap();
+
+        assert!(store.get_by_kind_name(IntentKind::Type, "Old").is_none());
+        assert!(store.find_by_name("Old").is_none());
+        assert_eq!(store.get_by_kind_name(IntentKind::Type, "New").unwrap().id, old_id);
+
+        // A new ID in the same file replaces the old document
+        let replacement = IntentDocument::new(IntentKind::Type, "New".to_string());
+        write_type(&dir, &replacement);
+        assert_eq!(store.reload_file(&path).unwrap(), replacement.id);
+        assert!(store.get(&old_id).is_none());
+        assert_eq!(store.len(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(store.reload_file(&path).unwrap(), replacement.id);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_dependency_tree_marks_cycles() {
+        let mut store = IntentStore::new();
+
+        let mut node = IntentDocument::new(IntentKind::Type, "Node".to_string());
+        node.spec = serde_json::json!({
+            "fields": { "next": { "type": "Node", "required": false } }
+        });
+        let id = node.id;
+        store.add(node).unwrap();
+
+        let tree = store.dependency_tree(&id, None).unwrap();
+        assert_eq!(tree.dependencies.len(), 1);
+        assert!(tree.dependencies[0].cycle);
+        assert!(tree.dependencies[0].dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_discovery_skips_ignored_dirs_and_respects_max_depth() {
+        let dir = TempDir::new().unwrap();
+        let write = |rel: &str, name: &str| {
+            let path = dir.path().join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let doc = IntentDocument::new(IntentKind::Type, name.to_string());
+            std::fs::write(&path, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+        };
+        write("top.intent.json", "Top");
+        write("billing/nested.intent.json", "Nested");
+        write("billing/deep/deeper.intent.json", "Deeper");
+        write("target/copy.intent.json", "TargetCopy");
+        write("billing/node_modules/vendored.intent.json", "Vendored");
+
+        let mut config = IntentConfig::default();
+        let names = |config: &IntentConfig| {
+            let store = IntentStore::load_from_path_with_config(dir.path(), config).unwrap();
+            let mut names: Vec<_> = store.iter().map(|doc| doc.name.clone()).collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names(&config), ["Deeper", "Nested", "Top"]);
+
+        config.project.max_depth = Some(1);
+        assert_eq!(names(&config), ["Nested", "Top"]);
+
+        config.project.max_depth = Some(0);
+        assert_eq!(names(&config), ["Top"]);
+
+        config.project.max_depth = None;
+        config.project.ignore_dirs = vec!["billing".to_string()];
+        assert_eq!(names(&config), ["TargetCopy", "Top"]);
+    }
+
+    #[test]
+    fn test_load_reports_first_error_in_file_order() {
+        let dir = TempDir::new().unwrap();
+        let write_doc = |file: &str, doc: &IntentDocument| {
+            std::fs::write(dir.path().join(file), serde_json::to_string_pretty(doc).unwrap()).unwrap();
+        };
+        let first = IntentDocument::new(IntentKind::Type, "Shared".to_string());
+        write_doc("a.intent.json", &first);
+        write_doc("c.intent.json", &IntentDocument::new(IntentKind::Type, "Shared".to_string()));
+        for i in 0..32 {
+            let doc = IntentDocument::new(IntentKind::Type, format!("Filler{}", i));
+            write_doc(&format!("d{:02}.intent.json", i), &doc);
+        }
+
+        let load = || IntentStore::load_from_path_with_config(dir.path(), &IntentConfig::default());
+        let error = load().unwrap_err().to_string();
+        assert_eq!(error, "Duplicate intent name 'Shared' for kind Type");
+
+        // A broken file sorting before the duplicate is reported instead
+        std::fs::write(dir.path().join("b.intent.json"), "{ not json").unwrap();
+        let error = format!("{:#}", load().unwrap_err());
+        assert!(error.starts_with("Failed to parse intent file: "), "{}", error);
+        assert!(error.contains("b.intent.json"), "{}", error);
+
+        std::fs::remove_file(dir.path().join("b.intent.json")).unwrap();
+        write_doc("c.intent.json", &first);
+        let error = load().unwrap_err().to_string();
+        assert_eq!(error, format!("Duplicate intent ID: {}", first.id));
+    }
+
+    #[test]
+    fn test_default_path_honours_model_path_env() {
+        let dir = TempDir::new().unwrap();
+        let doc = IntentDocument::new(IntentKind::Type, "FromEnv".to_string());
+        std::fs::write(
+            dir.path().join("from-env.intent.json"),
+            serde_json::to_string_pretty(&doc).unwrap(),
+        )
+        .unwrap();
+
+        // No other test in this crate reads the variable
+        std::env::set_var(MODEL_PATH_ENV, dir.path());
+        let store = IntentStore::load_from_default_path();
+        let planned = plan_new_intent("Type", "Planned", &resolve_model_path(None));
+        std::env::remove_var(MODEL_PATH_ENV);
+
+        let store = store.unwrap();
+        assert_eq!(store.len(), 1);
+        assert!(store.get_by_kind_name(IntentKind::Type, "FromEnv").is_some());
+        assert_eq!(planned.unwrap().path, dir.path().join("planned.intent.json"));
+    }
+
+    #[test]
+    fn test_rename_references() {
+        let mut spec = serde_json::json!({
+            "input": "Refund",
+            "output": "RefundRequest",
+            "context": { "history": "array<Refund>", "by_id": "map<string, optional<Refund>>" },
+            "steps": [
+                { "kind": "Effect", "service": "Refund", "input_mapping": { "refund": "Refund" } }
+            ]
+        });
+        assert!(rename_references(&mut spec, IntentKind::Type, "Refund", "Repayment"));
+        assert_eq!(spec["input"], "Repayment");
+        assert_eq!(spec["output"], "RefundRequest");
+        assert_eq!(spec["context"]["history"], "array<Repayment>");
+        assert_eq!(spec["context"]["by_id"], "map<string, optional<Repayment>>");
+        // Service names and step expressions are not type positions
+        assert_eq!(spec["steps"][0]["service"], "Refund");
+        assert_eq!(spec["steps"][0]["input_mapping"]["refund"], "Refund");
+
+        assert!(rename_references(&mut spec, IntentKind::Service, "Refund", "Payouts"));
+        assert_eq!(spec["steps"][0]["service"], "Payouts");
+        assert!(!rename_references(&mut spec, IntentKind::Workflow, "Refund", "Payouts"));
+    }
+
+    #[test]
+    fn test_rename_leaves_same_named_values() {
+        let mut spec = serde_json::json!({
+            "fields": {
+                "status": { "type": "Pending", "default": "Pending", "serde_rename": "Pending" },
+                "label": { "type": "string", "rust_type_override": "String" }
+            },
+            "serde_tag": "Pending",
+            "variants": [
+                { "name": "Pending", "serde_rename": "Pending", "data": ["Pending", "array<Pending>"] },
+                { "name": "Held", "data": { "reason": "optional<Pending>" } }
+            ]
+        });
+        assert!(rename_references(&mut spec, IntentKind::Type, "Pending", "Queued"));
+        assert_eq!(spec["fields"]["status"]["type"], "Queued");
+        assert_eq!(spec["variants"][0]["data"], serde_json::json!(["Queued", "array<Queued>"]));
+        assert_eq!(spec["variants"][1]["data"]["reason"], "optional<Queued>");
+        // Values, serde names and other strings that happen to match stay
+        assert_eq!(spec["fields"]["status"]["default"], "Pending");
+        assert_eq!(spec["fields"]["status"]["serde_rename"], "Pending");
+        assert_eq!(spec["serde_tag"], "Pending");
+        assert_eq!(spec["variants"][0]["name"], "Pending");
+        assert_eq!(spec["variants"][0]["serde_rename"], "Pending");
+    }
+
+    #[test]
+    fn test_renamed_file_keeps_style() {
+        let renamed = |file: &str, old: &str, new: &str| {
+            renamed_file(Path::new(file), old, new).to_string_lossy().to_string()
+        };
+        assert_eq!(
+            renamed("m/refund-response.intent.json", "RefundResponse", "RefundResult"),
+            "m/refund-result.intent.json"
+        );
+        assert_eq!(
+            renamed("m/payments-service.intent.json", "Payments", "Payouts"),
+            "m/payouts-service.intent.json"
+        );
+        assert_eq!(renamed("m/user.intent.json", "User", "Customer"), "m/customer.intent.json");
+        // Only whole words of the file name are replaced
+        assert_eq!(
+            renamed("m/refund-response.intent.json", "Refund", "Payout"),
+            "m/payout-response.intent.json"
+        );
+        assert_eq!(
+            renamed("m/refunds-table.intent.json", "Refund", "Payout"),
+            "m/refunds-table.intent.json"
+        );
+    }
+
+    #[test]
+    fn test_schema_version_must_be_supported() {
+        let dir = TempDir::new().unwrap();
+        let mut doc = IntentDocument::new(IntentKind::Type, "Test".to_string());
+        doc.schema_version = "2.0".to_string();
+        let path = write_type(&dir, &doc);
+        assert_eq!(load_intent_file(&path).unwrap().id, doc.id);
+
+        // Older files load upgraded, without rewriting the file
+        doc.schema_version = "1.0".to_string();
+        write_type(&dir, &doc);
+        let before = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(load_intent_file(&path).unwrap().schema_version, "2.0");
+        let store = IntentStore::load_from_path(dir.path()).unwrap();
+        assert_eq!(store.get(&doc.id).unwrap().schema_version, "2.0");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+
+        doc.schema_version = "3.1".to_string();
+        write_type(&dir, &doc);
+        let error = load_intent_file(&path).unwrap_err().to_string();
+        assert!(error.ends_with(&format!(
+            "uses schema_version 3.1, but intent-engine {} only supports up to 2.0; \
+             this file requires a newer intent-engine",
+            env!("CARGO_PKG_VERSION")
+        )));
+
+        doc.schema_version = "latest".to_string();
+        write_type(&dir, &doc);
+        assert!(load_intent_file(&path)
+            .unwrap_err()
+            .to_string()
+            .contains("invalid schema_version 'latest'"));
+    }
 }
@@ -0,0 +1,109 @@
+//! Upgrade of intent documents to the newest `schema_version`: in memory on
+//! load, and on disk in batch with `migrate-schema`
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::model::{parse_schema_version, IntentDocument, SUPPORTED_SCHEMA_VERSIONS};
+use crate::parser::canonical::pretty_canonical;
+use crate::parser::loader::check_schema_version;
+use crate::parser::{discover_intent_files, IntentConfig};
+
+/// A registered upgrade: documents older than `to` are rewritten by `apply`
+/// and then carry version `to`
+pub struct SchemaMigration {
+    pub to: (u32, u32),
+    pub apply: fn(&mut Value),
+}
+
+/// Upgrades in version order, ending at the newest supported version. v2
+/// only added kinds, so a v1 document needs nothing beyond the version bump.
+pub const SCHEMA_MIGRATIONS: &[SchemaMigration] = &[SchemaMigration {
+    to: (2, 0),
+    apply: |_| {},
+}];
+
+/// An intent file `migrate-schema` upgraded (or would upgrade)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MigratedFile {
+    pub path: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// What `migrate_intent_files` did
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SchemaMigrationReport {
+    /// Version every file is at afterwards
+    pub version: String,
+    pub migrated: Vec<MigratedFile>,
+    /// Files already at `version`, left untouched
+    pub unchanged: usize,
+}
+
+/// Upgrade every intent file under `model_dir` to the newest schema
+/// version, rewriting each upgraded file in canonical form. With `dry_run`
+/// nothing is written. A file this engine can't load fails the whole run
+/// before anything is written.
+pub fn migrate_intent_files(
+    model_dir: &Path,
+    config: &IntentConfig,
+    dry_run: bool,
+) -> Result<SchemaMigrationReport> {
+    let newest = *SUPPORTED_SCHEMA_VERSIONS.end();
+    let mut report = SchemaMigrationReport {
+        version: format_version(newest),
+        migrated: Vec::new(),
+        unchanged: 0,
+    };
+
+    let mut upgrades = Vec::new();
+    for path in discover_intent_files(model_dir, config) {
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        let mut value: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse intent file: {}", path.display()))?;
+        let doc: IntentDocument = serde_json::from_value(value.clone())
+            .with_context(|| format!("Failed to parse intent file: {}", path.display()))?;
+        check_schema_version(&doc, &path)?;
+
+        let from = parse_schema_version(&doc.schema_version).expect("checked above");
+        if from >= newest {
+            report.unchanged += 1;
+            continue;
+        }
+        upgrade_document(&mut value, from);
+        serde_json::from_value::<IntentDocument>(value.clone())
+            .with_context(|| format!("Upgraded {} is not a valid intent", path.display()))?;
+
+        report.migrated.push(MigratedFile {
+            path: path.to_string_lossy().to_string(),
+            from: doc.schema_version,
+            to: report.version.clone(),
+        });
+        upgrades.push((path, value));
+    }
+
+    if !dry_run {
+        for (path, value) in upgrades {
+            std::fs::write(&path, pretty_canonical(&value))
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+    Ok(report)
+}
+
+/// Apply every migration newer than `from` to the document `value`, which
+/// then carries the newest supported version
+pub(crate) fn upgrade_document(value: &mut Value, from: (u32, u32)) {
+    for migration in SCHEMA_MIGRATIONS.iter().filter(|m| m.to > from) {
+        (migration.apply)(value);
+        value["schema_version"] = Value::String(format_version(migration.to));
+    }
+}
+
+fn format_version((major, minor): (u32, u32)) -> String {
+    format!("{}.{}", major, minor)
+}
@@ -3,7 +3,9 @@
 mod loader;
 mod canonical;
 mod config;
+mod migrate;
 
 pub use loader::*;
 pub use canonical::*;
 pub use config::*;
+pub use migrate::*;
@@ -0,0 +1,437 @@
+//! Evaluator for the expression strings used in workflow steps
+//! (`input.amount <= 0`, `context.validated_amount`, `uuid_generate()`)
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Value};
+
+/// Evaluate `source` with `vars` as the root scope (`input`, `context`, ...).
+/// Free function calls such as `uuid_generate()` are delegated to `call`.
+pub fn evaluate(
+    source: &str,
+    vars: &Map<String, Value>,
+    call: &mut dyn FnMut(&str, &[Value]) -> Result<Value>,
+) -> Result<Value> {
+    let mut parser = Parser::new(source, vars, call, false)?;
+    let value = parser.or()?;
+    parser.finish(source)?;
+    Ok(value)
+}
+
+/// Value of `source` if it reads no variables and calls no functions, so it
+/// is the same on every run
+pub fn constant_value(source: &str) -> Option<Value> {
+    let mut call = |function: &str, _: &[Value]| -> Result<Value> { bail!("'{}' is not constant", function) };
+    evaluate(source, &Map::new(), &mut call).ok()
+}
+
+/// Parse `source` without evaluating it and return the variable paths it
+/// reads, such as `input.amount` or `context.total`, in source order.
+/// Method calls end a path: `input.name.trim()` reads `input.name`.
+pub fn variable_paths(source: &str) -> Result<Vec<String>> {
+    let vars = Map::new();
+    let mut call = |_: &str, _: &[Value]| Ok(Value::Null);
+    let mut parser = Parser::new(source, &vars, &mut call, true)?;
+    parser.or()?;
+    parser.finish(source)?;
+    Ok(parser.paths)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64, bool),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+const OPERATORS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")", ",", ".",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = text
+                .parse::<f64>()
+                .map_err(|_| anyhow!("Invalid number '{}'", text))?;
+            tokens.push(Token::Number(number, !text.contains('.')));
+        } else if c == '\'' || c == '"' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&d| d == c)
+                .ok_or_else(|| anyhow!("Unterminated string in '{}'", source))?;
+            tokens.push(Token::Str(chars[i + 1..i + 1 + end].iter().collect()));
+            i += end + 2;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| anyhow!("Unexpected '{}' in '{}'", c, source))?;
+            tokens.push(Token::Op(op));
+            i += op.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    vars: &'a Map<String, Value>,
+    call: &'a mut dyn FnMut(&str, &[Value]) -> Result<Value>,
+    /// Only check the syntax: every value is `null` and operators, methods
+    /// and calls are not applied
+    check_only: bool,
+    /// Variable paths read so far
+    paths: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(
+        source: &str,
+        vars: &'a Map<String, Value>,
+        call: &'a mut dyn FnMut(&str, &[Value]) -> Result<Value>,
+        check_only: bool,
+    ) -> Result<Self> {
+        Ok(Self {
+            tokens: tokenize(source)?,
+            pos: 0,
+            vars,
+            call,
+            check_only,
+            paths: Vec::new(),
+        })
+    }
+
+    fn finish(&self, source: &str) -> Result<()> {
+        if let Some(token) = self.tokens.get(self.pos) {
+            bail!("Unexpected {:?} in '{}'", token, source);
+        }
+        Ok(())
+    }
+
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if self.peek_op() == Some(op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<()> {
+        if !self.eat(op) {
+            bail!("Expected '{}'", op);
+        }
+        Ok(())
+    }
+
+    /// Left-associative binary operators at one precedence level
+    fn binary(
+        &mut self,
+        ops: &[&str],
+        next: fn(&mut Self) -> Result<Value>,
+    ) -> Result<Value> {
+        let mut left = next(self)?;
+        while let Some(op) = self.peek_op().filter(|op| ops.contains(op)) {
+            self.pos += 1;
+            let right = next(self)?;
+            if !self.check_only {
+                left = apply_binary(op, left, right)?;
+            }
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Value> {
+        self.binary(&["||"], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Value> {
+        self.binary(&["&&"], Self::equality)
+    }
+
+    fn equality(&mut self) -> Result<Value> {
+        self.binary(&["==", "!="], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Value> {
+        self.binary(&["<", "<=", ">", ">="], Self::additive)
+    }
+
+    fn additive(&mut self) -> Result<Value> {
+        self.binary(&["+", "-"], Self::multiplicative)
+    }
+
+    fn multiplicative(&mut self) -> Result<Value> {
+        self.binary(&["*", "/", "%"], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Value> {
+        if self.eat("!") {
+            let value = self.unary()?;
+            if self.check_only {
+                return Ok(value);
+            }
+            return Ok(Value::Bool(!as_bool(&value)?));
+        }
+        if self.eat("-") {
+            let value = self.unary()?;
+            if self.check_only {
+                return Ok(value);
+            }
+            return apply_binary("-", Value::from(0), value);
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Value> {
+        let start = self.pos;
+        let mut value = self.primary()?;
+
+        // A bare variable starts a path that field accesses extend
+        let mut path = match &self.tokens[start] {
+            Token::Ident(name) if self.pos == start + 1 && !matches!(name.as_str(), "true" | "false" | "null") => {
+                Some(name.clone())
+            }
+            _ => None,
+        };
+
+        while self.eat(".") {
+            let Some(Token::Ident(name)) = self.tokens.get(self.pos).cloned() else {
+                bail!("Expected a field name after '.'");
+            };
+            self.pos += 1;
+            if self.eat("(") {
+                self.paths.extend(path.take());
+                let args = self.args()?;
+                if !self.check_only {
+                    value = apply_method(&name, &value, &args)?;
+                }
+            } else {
+                if let Some(path) = &mut path {
+                    path.push('.');
+                    path.push_str(&name);
+                }
+                value = value.get(&name).cloned().unwrap_or(Value::Null);
+            }
+        }
+
+        self.paths.extend(path);
+        Ok(value)
+    }
+
+    fn args(&mut self) -> Result<Vec<Value>> {
+        let mut args = Vec::new();
+        if self.eat(")") {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.or()?);
+            if self.eat(")") {
+                return Ok(args);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn primary(&mut self) -> Result<Value> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unexpected end of expression"))?;
+        self.pos += 1;
+
+        match token {
+            Token::Number(n, true) if n.abs() < i64::MAX as f64 => Ok(Value::from(n as i64)),
+            Token::Number(n, _) => Ok(Value::from(n)),
+            Token::Str(s) => Ok(Value::String(s)),
+            Token::Ident(name) => match name.as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                "null" => Ok(Value::Null),
+                _ if self.eat("(") => {
+                    let args = self.args()?;
+                    if self.check_only {
+                        return Ok(Value::Null);
+                    }
+                    (self.call)(&name, &args)
+                }
+                _ if self.check_only => Ok(Value::Null),
+                _ => self
+                    .vars
+                    .get(&name)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Unknown variable '{}'", name)),
+            },
+            Token::Op("(") => {
+                let value = self.or()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Token::Op(op) => bail!("Unexpected '{}'", op),
+        }
+    }
+}
+
+fn as_bool(value: &Value) -> Result<bool> {
+    value
+        .as_bool()
+        .ok_or_else(|| anyhow!("Expected a boolean, found {}", value))
+}
+
+/// Numbers compare by value; money often arrives as a decimal string
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn apply_binary(op: &str, left: Value, right: Value) -> Result<Value> {
+    match op {
+        "&&" => Ok(Value::Bool(as_bool(&left)? && as_bool(&right)?)),
+        "||" => Ok(Value::Bool(as_bool(&left)? || as_bool(&right)?)),
+        "==" | "!=" => {
+            let equal = match (&left, &right) {
+                (Value::Number(_), _) | (_, Value::Number(_)) => {
+                    as_number(&left).is_some() && as_number(&left) == as_number(&right)
+                }
+                _ => left == right,
+            };
+            Ok(Value::Bool(equal == (op == "==")))
+        }
+        "<" | "<=" | ">" | ">=" => {
+            let ordering = match (&left, &right) {
+                (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+                _ => match (as_number(&left), as_number(&right)) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b),
+                    _ => None,
+                },
+            }
+            .ok_or_else(|| anyhow!("Cannot compare {} with {}", left, right))?;
+            Ok(Value::Bool(match op {
+                "<" => ordering.is_lt(),
+                "<=" => ordering.is_le(),
+                ">" => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            }))
+        }
+        "+" if left.is_string() && right.is_string() => Ok(Value::String(format!(
+            "{}{}",
+            left.as_str().unwrap_or_default(),
+            right.as_str().unwrap_or_default()
+        ))),
+        _ => {
+            let (Some(a), Some(b)) = (as_number(&left), as_number(&right)) else {
+                bail!("Cannot apply '{}' to {} and {}", op, left, right);
+            };
+            if let (Some(a), Some(b)) = (left.as_i64(), right.as_i64()) {
+                let value = match op {
+                    "+" => a.checked_add(b),
+                    "-" => a.checked_sub(b),
+                    "*" => a.checked_mul(b),
+                    "/" => a.checked_div(b),
+                    _ => a.checked_rem(b),
+                };
+                return value
+                    .map(Value::from)
+                    .ok_or_else(|| anyhow!("Integer overflow or division by zero in '{}'", op));
+            }
+            let value = match op {
+                "+" => a + b,
+                "-" => a - b,
+                "*" => a * b,
+                "/" => a / b,
+                _ => a % b,
+            };
+            Ok(Value::from(value))
+        }
+    }
+}
+
+fn apply_method(name: &str, value: &Value, args: &[Value]) -> Result<Value> {
+    match (name, value, args) {
+        ("len", Value::String(s), []) => Ok(Value::from(s.chars().count())),
+        ("len", Value::Array(items), []) => Ok(Value::from(items.len())),
+        ("is_empty", Value::String(s), []) => Ok(Value::Bool(s.is_empty())),
+        ("is_empty", Value::Array(items), []) => Ok(Value::Bool(items.is_empty())),
+        ("is_none", _, []) => Ok(Value::Bool(value.is_null())),
+        ("is_some", _, []) => Ok(Value::Bool(!value.is_null())),
+        ("trim", Value::String(s), []) => Ok(Value::String(s.trim().to_string())),
+        ("to_lowercase", Value::String(s), []) => Ok(Value::String(s.to_lowercase())),
+        ("to_uppercase", Value::String(s), []) => Ok(Value::String(s.to_uppercase())),
+        ("contains", Value::String(s), [Value::String(needle)]) => Ok(Value::Bool(s.contains(needle.as_str()))),
+        ("contains", Value::Array(items), [needle]) => Ok(Value::Bool(items.contains(needle))),
+        _ => bail!("Unsupported method '{}' on {}", name, value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eval(source: &str) -> Value {
+        let vars = json!({
+            "input": { "amount": 25, "name": " Ada ", "items": [1, 2] },
+            "context": { "total": "10.50" }
+        });
+        evaluate(source, vars.as_object().unwrap(), &mut |name, _| {
+            Ok(Value::String(format!("{}()", name)))
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(eval("input.amount <= 0"), json!(false));
+        assert_eq!(eval("input.amount * 2 + 1"), json!(51));
+        assert_eq!(eval("context.total > 10 && !(input.items.len() == 0)"), json!(true));
+        assert_eq!(eval("input.name.trim()"), json!("Ada"));
+        assert_eq!(eval("'pending'"), json!("pending"));
+        assert_eq!(eval("uuid_generate()"), json!("uuid_generate()"));
+        assert_eq!(eval("input.missing"), Value::Null);
+    }
+
+    #[test]
+    fn test_variable_paths() {
+        assert_eq!(
+            variable_paths("input.amount > 0 && !context.total.is_empty()").unwrap(),
+            ["input.amount", "context.total"]
+        );
+        assert_eq!(variable_paths("uuid_generate()").unwrap(), Vec::<String>::new());
+        assert_eq!(variable_paths("len(input.items) == 0").unwrap(), ["input.items"]);
+        assert!(variable_paths("input.amount >").is_err());
+        assert!(variable_paths("input.").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+//! Workflow simulation: interpret a workflow's steps against sample input
+//! with stubbed effects, without generating code
+
+mod expr;
+
+pub use expr::*;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::model::{EffectKind, EffectStep, IntentKind, TypeRef, WorkflowStep};
+use crate::parser::IntentStore;
+
+/// What a simulated workflow did
+#[derive(Debug, Clone, Serialize)]
+pub struct Simulation {
+    pub workflow: String,
+    pub steps: Vec<SimulatedStep>,
+    /// Context after the last step that ran
+    pub context: Map<String, Value>,
+    /// Output assembled from the context, absent if a step raised
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    /// Error code raised by a `raise_if`, which stops the workflow
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raised: Option<String>,
+}
+
+/// One executed step and the context it left behind
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulatedStep {
+    pub step: String,
+    /// What a stubbed effect would have done, or the transform's assignments
+    pub actions: Vec<String>,
+    pub context: Map<String, Value>,
+}
+
+/// Run workflow `name` against `input`. Expressions are evaluated; effects
+/// are logged and return a sample value of their declared output type.
+pub fn simulate_workflow(store: &IntentStore, name: &str, input: Value) -> Result<Simulation> {
+    let doc = store
+        .get_by_kind_name(IntentKind::Workflow, name)
+        .with_context(|| format!("Workflow not found: {}", name))?;
+    let spec = doc.as_workflow_spec()?;
+
+    let mut simulation = Simulation {
+        workflow: doc.name.clone(),
+        steps: Vec::new(),
+        context: Map::new(),
+        output: None,
+        raised: None,
+    };
+    let mut uuids = 0u32;
+    let mut call = |function: &str, _args: &[Value]| -> Result<Value> {
+        match function {
+            "uuid_generate" | "uuid_v4" => {
+                uuids += 1;
+                Ok(Value::String(format!("00000000-0000-4000-8000-{:012}", uuids)))
+            }
+            "now" => Ok(Value::String("2024-01-01T00:00:00Z".to_string())),
+            _ => anyhow::bail!("Cannot simulate call to '{}'", function),
+        }
+    };
+
+    for (i, step) in spec.steps.iter().enumerate() {
+        let label = step.label(i);
+        let scope = |context: &Map<String, Value>| {
+            let mut vars = Map::new();
+            vars.insert("input".to_string(), input.clone());
+            vars.insert("context".to_string(), Value::Object(context.clone()));
+            vars
+        };
+        let mut actions = Vec::new();
+
+        if let Some(condition) = step.when() {
+            let vars = scope(&simulation.context);
+            let run = evaluate(condition, &vars, &mut call)
+                .with_context(|| format!("In {}: when {}", label, condition))?;
+            if run != Value::Bool(true) {
+                simulation.steps.push(SimulatedStep {
+                    step: label,
+                    actions: vec![format!("skipped (when {})", condition)],
+                    context: simulation.context.clone(),
+                });
+                continue;
+            }
+        }
+
+        match step {
+            WorkflowStep::Transform(t) => {
+                let vars = scope(&simulation.context);
+                let mut targets: Vec<_> = t.assign.iter().collect();
+                targets.sort();
+                for (target, source) in targets {
+                    let value = evaluate(source, &vars, &mut call)
+                        .with_context(|| format!("In {}: {} = {}", label, target, source))?;
+                    actions.push(format!("{} = {}", target, value));
+                    simulation.context.insert(target.clone(), value);
+                }
+
+                if let Some(raise) = &t.raise_if {
+                    let vars = scope(&simulation.context);
+                    let condition = evaluate(&raise.condition, &vars, &mut call)
+                        .with_context(|| format!("In {}: raise_if {}", label, raise.condition))?;
+                    if condition == Value::Bool(true) {
+                        actions.push(format!("raise {} ({})", raise.error, raise.condition));
+                        simulation.raised = Some(raise.error.clone());
+                    }
+                }
+            }
+            WorkflowStep::Effect(e) => {
+                let vars = scope(&simulation.context);
+                let mut payload = Map::new();
+                let mut fields: Vec<_> = e.input_mapping.iter().collect();
+                fields.sort();
+                for (field, source) in fields {
+                    let value = evaluate(source, &vars, &mut call)
+                        .with_context(|| format!("In {}: {} <- {}", label, field, source))?;
+                    payload.insert(field.clone(), value);
+                }
+
+                actions.push(describe_effect(e, &Value::Object(payload)));
+                if let Some(binding) = &e.output_binding {
+                    let value = stub_result(store, e);
+                    actions.push(format!("{} = {} (stubbed)", binding, value));
+                    simulation.context.insert(binding.clone(), value);
+                }
+            }
+        }
+
+        simulation.steps.push(SimulatedStep {
+            step: label,
+            actions,
+            context: simulation.context.clone(),
+        });
+        if simulation.raised.is_some() {
+            return Ok(simulation);
+        }
+    }
+
+    simulation.output = Some(assemble_output(store, &spec.output, &simulation.context));
+    Ok(simulation)
+}
+
+fn describe_effect(e: &EffectStep, payload: &Value) -> String {
+    let target = match e.effect {
+        EffectKind::HttpCall => format!(
+            "{}.{}",
+            e.service.as_deref().unwrap_or("?"),
+            e.operation.as_deref().unwrap_or("?")
+        ),
+        EffectKind::DbRead | EffectKind::DbWrite | EffectKind::DbDelete => {
+            format!("table '{}'", e.table.as_deref().unwrap_or("?"))
+        }
+        EffectKind::EmitEvent => format!("topic '{}'", e.topic.as_deref().unwrap_or("?")),
+    };
+    format!("{} {} with {}", e.effect, target, payload)
+}
+
+/// Canned result of an effect: a sample of the called operation's output
+/// type for HTTP calls, an empty object otherwise
+fn stub_result(store: &IntentStore, e: &EffectStep) -> Value {
+    let output_type = (e.effect == EffectKind::HttpCall)
+        .then(|| {
+            let service = store.get_by_kind_name(IntentKind::Service, e.service.as_deref()?)?;
+            let spec = service.as_service_spec().ok()?;
+            Some(spec.operations.get(e.operation.as_deref()?)?.output.clone())
+        })
+        .flatten();
+
+    match output_type {
+        Some(name) => sample_value(store, &TypeRef::Named(name), 0),
+        None => Value::Object(Map::new()),
+    }
+}
+
+/// The workflow's output type built from same-named context values
+fn assemble_output(store: &IntentStore, output_type: &str, context: &Map<String, Value>) -> Value {
+    let Some(spec) = store
+        .get_type_or_enum(output_type)
+        .and_then(|doc| doc.as_type_spec().ok())
+    else {
+        return Value::Object(context.clone());
+    };
+
+    let mut names: Vec<_> = spec.fields.keys().collect();
+    names.sort();
+    let output = names
+        .into_iter()
+        .map(|name| (name.clone(), context.get(name).cloned().unwrap_or(Value::Null)))
+        .collect();
+    Value::Object(output)
+}
+
+/// A placeholder value of `type_ref`; named types expand a few levels deep
+fn sample_value(store: &IntentStore, type_ref: &TypeRef, depth: usize) -> Value {
+    match type_ref {
+        TypeRef::String => Value::String("sample".to_string()),
+        TypeRef::Int
+        | TypeRef::Int8
+        | TypeRef::Int16
+        | TypeRef::Int32
+        | TypeRef::UInt8
+        | TypeRef::UInt16
+        | TypeRef::UInt32 => Value::from(0),
+        TypeRef::Float => Value::from(0.0),
+        TypeRef::Bool => Value::Bool(false),
+        TypeRef::Money => Value::String("0.00".to_string()),
+        TypeRef::DateTime => Value::String("2024-01-01T00:00:00Z".to_string()),
+        TypeRef::Uuid => Value::String("00000000-0000-4000-8000-000000000000".to_string()),
+        TypeRef::Bytes => Value::String(String::new()),
+        TypeRef::Array(_) => Value::Array(Vec::new()),
+        TypeRef::FixedArray(inner, size) => Value::Array(vec![sample_value(store, inner, depth); *size]),
+        TypeRef::Tuple(elements) => Value::Array(
+            elements
+                .iter()
+                .map(|element| sample_value(store, element, depth))
+                .collect(),
+        ),
+        TypeRef::Map(_, _) => Value::Object(Map::new()),
+        TypeRef::Optional(_) => Value::Null,
+        TypeRef::Named(name) => {
+            let Some(spec) = store
+                .get_by_kind_name(IntentKind::Type, name)
+                .and_then(|doc| doc.as_type_spec().ok())
+                .filter(|_| depth < 4)
+            else {
+                return Value::Null;
+            };
+            let mut fields: Vec<_> = spec.fields.iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, field)| (name.clone(), sample_value(store, &field.field_type, depth + 1)))
+                    .collect(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::IntentDocument;
+    use serde_json::json;
+
+    #[test]
+    fn test_simulate_transform_workflow() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Workflow,
+                "PriceWorkflow".to_string(),
+                json!({
+                    "input": "RefundRequest",
+                    "output": "RefundResponse",
+                    "steps": [
+                        {
+                            "kind": "Transform",
+                            "name": "compute",
+                            "assign": {
+                                "total": "input.amount * 2",
+                                "status": "'priced'"
+                            }
+                        },
+                        {
+                            "kind": "Transform",
+                            "name": "check",
+                            "assign": { "refund_id": "uuid_generate()" },
+                            "raise_if": { "condition": "context.total > 100", "error": "TOO_LARGE" }
+                        }
+                    ]
+                }),
+            ))
+            .unwrap();
+
+        let simulation =
+            simulate_workflow(&store, "PriceWorkflow", json!({ "amount": 20 })).unwrap();
+        assert_eq!(simulation.raised, None);
+        assert_eq!(simulation.steps.len(), 2);
+        assert_eq!(simulation.steps[0].context["total"], json!(40));
+        assert_eq!(simulation.context["status"], json!("priced"));
+        assert_eq!(
+            simulation.output.unwrap(),
+            json!({ "refund_id": "00000000-0000-4000-8000-000000000001", "status": "priced" })
+        );
+
+        let simulation =
+            simulate_workflow(&store, "PriceWorkflow", json!({ "amount": 60 })).unwrap();
+        assert_eq!(simulation.raised.as_deref(), Some("TOO_LARGE"));
+        assert!(simulation.output.is_none());
+    }
+}
@@ -0,0 +1,118 @@
+//! Diagnostic baselines: accept the diagnostics a model already has and only
+//! report ones introduced afterwards
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::StructuredError;
+
+use super::ValidationResult;
+
+/// Signatures of accepted diagnostics, stored as JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    pub signatures: BTreeSet<String>,
+}
+
+impl Baseline {
+    /// Accept every diagnostic in `result`
+    pub fn from_result(result: &ValidationResult) -> Self {
+        let signatures = result
+            .errors
+            .iter()
+            .chain(&result.warnings)
+            .chain(&result.infos)
+            .map(diagnostic_signature)
+            .collect();
+        Self { signatures }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            anyhow::bail!(
+                "Baseline file not found: {} (create it with --write-baseline)",
+                path.display()
+            );
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    /// Drop baselined diagnostics from `result`, returning how many were dropped
+    pub fn filter(&self, result: &mut ValidationResult) -> usize {
+        let count = |result: &ValidationResult| {
+            result.errors.len() + result.warnings.len() + result.infos.len()
+        };
+        let before = count(result);
+        for diagnostics in [&mut result.errors, &mut result.warnings, &mut result.infos] {
+            diagnostics.retain(|d| !self.signatures.contains(&diagnostic_signature(d)));
+        }
+        before - count(result)
+    }
+}
+
+/// `code|file|path|message`, with the message lowercased, whitespace collapsed
+/// and numbers replaced by `#` so counts in messages don't break the match
+pub fn diagnostic_signature(diagnostic: &StructuredError) -> String {
+    let (file, path) = diagnostic
+        .location
+        .as_ref()
+        .map(|loc| (loc.file.replace('\\', "/"), loc.path.as_str()))
+        .unwrap_or_default();
+
+    let mut message = String::new();
+    for word in diagnostic.message.split_whitespace() {
+        if !message.is_empty() {
+            message.push(' ');
+        }
+        let mut in_number = false;
+        for c in word.chars() {
+            if c.is_ascii_digit() {
+                if !in_number {
+                    message.push('#');
+                }
+                in_number = true;
+            } else {
+                message.extend(c.to_lowercase());
+                in_number = false;
+            }
+        }
+    }
+
+    format!("{}|{}|{}|{}", diagnostic.code, file, path, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::StructuredLocation;
+
+    #[test]
+    fn test_signature_normalizes_message() {
+        let mut result = ValidationResult::new();
+        let location = || {
+            Some(StructuredLocation {
+                file: "a.intent.json".to_string(),
+                path: "$.spec.steps[0]".to_string(),
+            })
+        };
+        result.add_warning("W001", "Found  12 Problems", location());
+        result.add_warning("W001", "found 3 problems", location());
+
+        let signatures = Baseline::from_result(&result).signatures;
+        assert_eq!(
+            signatures.into_iter().collect::<Vec<_>>(),
+            ["W001|a.intent.json|$.spec.steps[0]|found # problems"]
+        );
+    }
+}
@@ -1,11 +1,11 @@
 //! Effect analysis phase
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet};
 
 use serde::Serialize;
 use uuid::Uuid;
 
-use crate::model::{EffectKind, IntentDocument, IntentKind, WorkflowStep};
+use crate::model::{EffectKind, IntentDocument, IntentKind, StructuredLocation, WorkflowStep};
 use crate::parser::IntentStore;
 
 use super::ValidationResult;
@@ -14,13 +14,13 @@ use super::ValidationResult;
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct EffectAnalysis {
     /// Effects by workflow ID
-    pub workflow_effects: HashMap<Uuid, Vec<EffectInfo>>,
+    pub workflow_effects: BTreeMap<Uuid, Vec<EffectInfo>>,
 
-    /// Tables written to (for migration obligation detection)
-    pub tables_written: HashSet<String>,
+    /// Tables written to (for migration obligation detection), sorted
+    pub tables_written: BTreeSet<String>,
 
-    /// Services called (for contract test obligation detection)
-    pub services_called: HashSet<(String, String)>, // (service_name, operation)
+    /// Services called (for contract test obligation detection), sorted
+    pub services_called: BTreeSet<(String, String)>, // (service_name, operation)
 }
 
 /// Information about a single effect
@@ -52,6 +52,9 @@ pub fn analyze_effects(store: &IntentStore) -> (EffectAnalysis, ValidationResult
         let mut effects = Vec::new();
 
         for (i, step) in spec.steps.iter().enumerate() {
+            if is_always_skipped(step) {
+                continue;
+            }
             if let WorkflowStep::Effect(e) = step {
                 let info = EffectInfo {
                     kind: e.effect,
@@ -89,6 +92,87 @@ pub fn analyze_effects(store: &IntentStore) -> (EffectAnalysis, ValidationResult
     (analysis, result)
 }
 
+/// Whether a step's `when` condition is constantly false, so the step never
+/// runs and its effect needs no obligations
+pub fn is_always_skipped(step: &WorkflowStep) -> bool {
+    step.when()
+        .and_then(crate::simulate::constant_value)
+        .is_some_and(|value| value == serde_json::Value::Bool(false))
+}
+
+/// Flag effect ordering hazards within each workflow: an event emitted
+/// before a later write (W016), and a read of a table the workflow already
+/// wrote (I003)
+pub fn check_effect_ordering(store: &IntentStore, analysis: &EffectAnalysis) -> ValidationResult {
+    let mut result = ValidationResult::new();
+
+    for (id, effects) in &analysis.workflow_effects {
+        let Some(doc) = store.get(id) else {
+            continue;
+        };
+        let Ok(spec) = doc.as_workflow_spec() else {
+            continue;
+        };
+        let label = |effect: &EffectInfo| spec.steps[effect.step_index].label(effect.step_index);
+        let location = |effect: &EffectInfo| {
+            Some(StructuredLocation {
+                file: doc.source_file.clone().unwrap_or_default(),
+                path: format!("$.spec.steps[{}]", effect.step_index),
+            })
+        };
+
+        for (i, effect) in effects.iter().enumerate() {
+            match effect.kind {
+                EffectKind::EmitEvent => {
+                    // Consumers may see the event before the change is committed
+                    let later_write = effects[i + 1..]
+                        .iter()
+                        .find(|e| matches!(e.kind, EffectKind::DbWrite | EffectKind::DbDelete));
+                    if let Some(write) = later_write {
+                        result.add_warning(
+                            "W016",
+                            format!(
+                                "Workflow '{}' emits event '{}' in {} before writing table '{}' in {}",
+                                doc.name,
+                                effect.topic.as_deref().unwrap_or("?"),
+                                label(effect),
+                                write.table.as_deref().unwrap_or("?"),
+                                label(write)
+                            ),
+                            location(effect),
+                        );
+                    }
+                }
+                EffectKind::DbRead => {
+                    let Some(table) = &effect.table else {
+                        continue;
+                    };
+                    let earlier_write = effects[..i].iter().find(|e| {
+                        matches!(e.kind, EffectKind::DbWrite | EffectKind::DbDelete)
+                            && e.table.as_ref() == Some(table)
+                    });
+                    if let Some(write) = earlier_write {
+                        result.add_info(
+                            "I003",
+                            format!(
+                                "Workflow '{}' reads table '{}' in {} after writing it in {}",
+                                doc.name,
+                                table,
+                                label(effect),
+                                label(write)
+                            ),
+                            location(effect),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    result
+}
+
 /// Get effect severity for semantic diff
 pub fn effect_severity(kind: EffectKind) -> &'static str {
     match kind {
@@ -126,4 +210,23 @@ mod tests {
         assert_eq!(effect_severity(EffectKind::DbRead), "LOW");
         assert_eq!(effect_severity(EffectKind::EmitEvent), "MEDIUM");
     }
+
+    #[test]
+    fn test_always_skipped_steps_have_no_effects() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        let mut doc = store.get_by_kind_name(IntentKind::Workflow, "RefundWorkflow").unwrap().clone();
+        let workflow_id = doc.id;
+        let count = |store: &IntentStore| analyze_effects(store).0.workflow_effects[&workflow_id].len();
+        let before = count(&store);
+
+        doc.spec["steps"][1]["when"] = serde_json::json!("input.reason != null");
+        store.remove(&workflow_id);
+        store.add(doc.clone()).unwrap();
+        assert_eq!(count(&store), before);
+
+        doc.spec["steps"][1]["when"] = serde_json::json!("false");
+        store.remove(&workflow_id);
+        store.add(doc).unwrap();
+        assert_eq!(count(&store), before - 1);
+    }
 }
@@ -0,0 +1,208 @@
+//! Static checks on Function body expressions whose operands fold to constants
+
+use serde_json::Value;
+
+use crate::model::{BinaryOp, Expression, StructuredLocation, UnaryOp};
+use crate::parser::IntentStore;
+
+use super::ValidationResult;
+
+/// Warn about division by a literal zero (W018), comparisons between literals
+/// of different types (W019) and `if` conditions that are constant (W020).
+/// Only sub-expressions built entirely from literals are considered.
+pub fn check_expressions(store: &IntentStore) -> ValidationResult {
+    let mut result = ValidationResult::new();
+
+    let mut functions = store.functions();
+    functions.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for doc in functions {
+        let Ok(spec) = doc.as_function_spec() else {
+            continue;
+        };
+        let location = || {
+            Some(StructuredLocation {
+                file: doc.source_file.clone().unwrap_or_default(),
+                path: "$.spec.body".to_string(),
+            })
+        };
+
+        spec.body.walk(&mut |expr| match expr {
+            Expression::Binary { op, left, right } => {
+                if matches!(op, BinaryOp::Div | BinaryOp::Mod)
+                    && fold(right).as_ref().and_then(Value::as_f64) == Some(0.0)
+                {
+                    result.add_warning(
+                        "W018",
+                        format!("Function '{}' divides by a constant zero", doc.name),
+                        location(),
+                    );
+                }
+                if is_comparison(*op) {
+                    if let (Some(l), Some(r)) = (fold(left), fold(right)) {
+                        let (l_kind, r_kind) = (literal_kind(&l), literal_kind(&r));
+                        if l_kind != r_kind && !l.is_null() && !r.is_null() {
+                            result.add_warning(
+                                "W019",
+                                format!(
+                                    "Function '{}' compares {} literal {} with {} literal {}",
+                                    doc.name, l_kind, l, r_kind, r
+                                ),
+                                location(),
+                            );
+                        }
+                    }
+                }
+            }
+            Expression::If { cond, .. } => match fold(cond) {
+                Some(Value::Bool(value)) => result.add_warning(
+                    "W020",
+                    format!(
+                        "Function '{}' has an `if` whose condition is always {}, so its {} branch is unreachable",
+                        doc.name,
+                        value,
+                        if value { "else" } else { "then" }
+                    ),
+                    location(),
+                ),
+                Some(other) => result.add_warning(
+                    "W020",
+                    format!(
+                        "Function '{}' has an `if` whose condition is a {} literal, not a boolean",
+                        doc.name,
+                        literal_kind(&other)
+                    ),
+                    location(),
+                ),
+                None => {}
+            },
+            _ => {}
+        });
+    }
+
+    result
+}
+
+/// The constant value of `expr`, if it is built only from literals and
+/// operators with unambiguous results
+fn fold(expr: &Expression) -> Option<Value> {
+    match expr {
+        Expression::Literal { value } => Some(value.clone()),
+        Expression::Unary { op, expr } => match (op, fold(expr)?) {
+            (UnaryOp::Not, Value::Bool(b)) => Some(Value::Bool(!b)),
+            (UnaryOp::Neg, Value::Number(n)) => match n.as_i64() {
+                Some(i) => Some(Value::from(i.checked_neg()?)),
+                None => Some(Value::from(-n.as_f64()?)),
+            },
+            _ => None,
+        },
+        Expression::Binary { op, left, right } => {
+            let (l, r) = (fold(left)?, fold(right)?);
+            match (op, &l, &r) {
+                (BinaryOp::And, Value::Bool(a), Value::Bool(b)) => Some(Value::Bool(*a && *b)),
+                (BinaryOp::Or, Value::Bool(a), Value::Bool(b)) => Some(Value::Bool(*a || *b)),
+                (BinaryOp::Eq, _, _) if literal_kind(&l) == literal_kind(&r) => Some(Value::Bool(l == r)),
+                (BinaryOp::Ne, _, _) if literal_kind(&l) == literal_kind(&r) => Some(Value::Bool(l != r)),
+                (BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul, Value::Number(a), Value::Number(b)) => {
+                    let (a, b) = (a.as_i64()?, b.as_i64()?);
+                    let value = match op {
+                        BinaryOp::Add => a.checked_add(b),
+                        BinaryOp::Sub => a.checked_sub(b),
+                        _ => a.checked_mul(b),
+                    };
+                    value.map(Value::from)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_comparison(op: BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+    )
+}
+
+fn literal_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{IntentDocument, IntentKind};
+
+    fn store_with_body(body: serde_json::Value) -> IntentStore {
+        let mut doc = IntentDocument::new(IntentKind::Function, "Check".to_string());
+        doc.spec = serde_json::json!({ "returns": { "type": "int" }, "body": body });
+        let mut store = IntentStore::new();
+        store.add(doc).unwrap();
+        store
+    }
+
+    fn lit(value: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({ "kind": "Literal", "value": value })
+    }
+
+    fn codes(result: &ValidationResult) -> Vec<&str> {
+        result.warnings.iter().map(|w| w.code.as_str()).collect()
+    }
+
+    #[test]
+    fn test_literal_division_by_zero() {
+        let store = store_with_body(serde_json::json!({
+            "kind": "Binary", "op": "/",
+            "left": { "kind": "Variable", "name": "amount" },
+            "right": { "kind": "Binary", "op": "-", "left": lit(2.into()), "right": lit(2.into()) }
+        }));
+        let result = check_expressions(&store);
+        assert_eq!(codes(&result), ["W018"]);
+
+        // A variable divisor is never flagged
+        let store = store_with_body(serde_json::json!({
+            "kind": "Binary", "op": "/",
+            "left": lit(1.into()),
+            "right": { "kind": "Variable", "name": "count" }
+        }));
+        assert!(check_expressions(&store).warnings.is_empty());
+    }
+
+    #[test]
+    fn test_mismatched_literal_comparison() {
+        let store = store_with_body(serde_json::json!({
+            "kind": "If",
+            "cond": { "kind": "Binary", "op": "==", "left": lit("1".into()), "right": lit(1.into()) },
+            "then_branch": lit(1.into()),
+            "else_branch": lit(0.into())
+        }));
+        let result = check_expressions(&store);
+        assert_eq!(codes(&result), ["W019"]);
+        assert_eq!(
+            result.warnings[0].message,
+            "Function 'Check' compares string literal \"1\" with number literal 1"
+        );
+    }
+
+    #[test]
+    fn test_constant_if_condition() {
+        let store = store_with_body(serde_json::json!({
+            "kind": "If",
+            "cond": { "kind": "Unary", "op": "!", "expr": lit(false.into()) },
+            "then_branch": lit(1.into()),
+            "else_branch": lit(0.into())
+        }));
+        let result = check_expressions(&store);
+        assert_eq!(codes(&result), ["W020"]);
+        assert!(result.warnings[0].message.contains("always true, so its else branch"));
+    }
+}
@@ -0,0 +1,158 @@
+//! Naming-convention lints, run by `intent lint` rather than `validate`
+
+use crate::model::{IntentDocument, IntentKind, StructuredLocation};
+use crate::parser::{IntentConfig, IntentStore};
+
+use super::ValidationResult;
+
+/// Names of the naming rules, as listed in `[lint] disabled`
+pub const NAMING_RULES: &[&str] = &[
+    "type-pascal-case",
+    "field-snake-case",
+    "endpoint-pascal-case",
+    "service-pascal-case",
+];
+
+/// Check every naming rule, reporting each violation as W101
+pub fn lint_naming(store: &IntentStore) -> ValidationResult {
+    lint_naming_rules(store, NAMING_RULES)
+}
+
+/// Check the naming rules `[lint] disabled` leaves on
+pub fn lint_with_config(store: &IntentStore, config: &IntentConfig) -> anyhow::Result<ValidationResult> {
+    for name in &config.lint.disabled {
+        if !NAMING_RULES.contains(&name.as_str()) {
+            anyhow::bail!(
+                "Unknown lint rule '{}' in [lint] disabled; available: {}",
+                name,
+                NAMING_RULES.join(", ")
+            );
+        }
+    }
+
+    let enabled: Vec<&str> = NAMING_RULES
+        .iter()
+        .copied()
+        .filter(|rule| !config.lint.disabled.iter().any(|disabled| disabled == rule))
+        .collect();
+    Ok(lint_naming_rules(store, &enabled))
+}
+
+fn lint_naming_rules(store: &IntentStore, enabled: &[&str]) -> ValidationResult {
+    let mut result = ValidationResult::new();
+    let mut docs: Vec<&IntentDocument> = store.iter().collect();
+    docs.sort_by(|a, b| (a.kind.to_string(), &a.name).cmp(&(b.kind.to_string(), &b.name)));
+
+    for doc in docs {
+        let rule = match doc.kind {
+            IntentKind::Type => "type-pascal-case",
+            IntentKind::Endpoint => "endpoint-pascal-case",
+            IntentKind::Service => "service-pascal-case",
+            _ => continue,
+        };
+        if enabled.contains(&rule) && !is_pascal_case(&doc.name) {
+            result.add_warning(
+                "W101",
+                format!("{} '{}' should be PascalCase ({})", doc.kind, doc.name, rule),
+                location(doc, "$.name"),
+            );
+        }
+
+        if doc.kind != IntentKind::Type || !enabled.contains(&"field-snake-case") {
+            continue;
+        }
+        let Ok(spec) = doc.as_type_spec() else {
+            continue;
+        };
+        let mut fields: Vec<_> = spec.fields.keys().filter(|name| !is_snake_case(name)).collect();
+        fields.sort();
+        for field in fields {
+            result.add_warning(
+                "W101",
+                format!(
+                    "Field '{}' of Type '{}' should be snake_case (field-snake-case)",
+                    field, doc.name
+                ),
+                location(doc, &format!("$.spec.fields.{}", field)),
+            );
+        }
+    }
+
+    result
+}
+
+/// `RefundRequest`, `Http2Client`: an uppercase letter, then letters and digits
+fn is_pascal_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// `order_id`, `line2`: a lowercase letter, then lowercase letters, digits
+/// and single underscores
+fn is_snake_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !name.contains("__")
+        && !name.ends_with('_')
+}
+
+fn location(doc: &IntentDocument, path: &str) -> Option<StructuredLocation> {
+    Some(StructuredLocation {
+        file: doc.source_file.clone().unwrap_or_default(),
+        path: path.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fixture_names_follow_conventions() {
+        let store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        assert!(lint_naming(&store).warnings.is_empty());
+    }
+
+    #[test]
+    fn test_naming_violations_and_disabled_rules() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Type,
+                "refund_note".to_string(),
+                json!({ "fields": { "noteText": { "type": "string" }, "order_id": { "type": "uuid" } } }),
+            ))
+            .unwrap();
+
+        let messages: Vec<_> = lint_naming(&store)
+            .warnings
+            .into_iter()
+            .map(|w| (w.code, w.message))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    "W101".to_string(),
+                    "Type 'refund_note' should be PascalCase (type-pascal-case)".to_string()
+                ),
+                (
+                    "W101".to_string(),
+                    "Field 'noteText' of Type 'refund_note' should be snake_case (field-snake-case)"
+                        .to_string()
+                ),
+            ]
+        );
+
+        let mut config = IntentConfig::default();
+        config.lint.disabled = vec!["type-pascal-case".to_string()];
+        let result = lint_with_config(&store, &config).unwrap();
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].message.starts_with("Field 'noteText'"));
+
+        config.lint.disabled = vec!["bogus".to_string()];
+        assert!(lint_with_config(&store, &config).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+//! Informational model lints: nothing here blocks validation
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::model::{IntentDocument, IntentKind, StructuredLocation};
+use crate::parser::IntentStore;
+
+use super::ValidationResult;
+
+/// Report Types and Enums nothing references (I001) and Types with exactly
+/// the same fields as another Type (I002)
+pub fn check_model_lints(store: &IntentStore) -> ValidationResult {
+    let mut result = ValidationResult::new();
+
+    let mut data_intents: Vec<_> = store
+        .iter()
+        .filter(|doc| matches!(doc.kind, IntentKind::Type | IntentKind::Enum))
+        .collect();
+    data_intents.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for doc in &data_intents {
+        if store.get_dependents(&doc.id).is_empty() {
+            result.add_info(
+                "I001",
+                format!("{} '{}' is not referenced by any intent", doc.kind, doc.name),
+                location(doc, "$"),
+            );
+        }
+    }
+
+    // Canonical field list -> first Type (by name) declaring it
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    for doc in data_intents.iter().filter(|doc| doc.kind == IntentKind::Type) {
+        let Ok(spec) = doc.as_type_spec() else {
+            continue;
+        };
+        if spec.fields.is_empty() {
+            continue;
+        }
+        let fields: BTreeMap<_, _> = spec.fields.iter().collect();
+        let Ok(key) = serde_json::to_string(&fields) else {
+            continue;
+        };
+
+        match seen.get(&key) {
+            Some(first) => result.add_info(
+                "I002",
+                format!("Type '{}' has exactly the same fields as '{}'", doc.name, first),
+                location(doc, "$.spec.fields"),
+            ),
+            None => {
+                seen.insert(key, &doc.name);
+            }
+        }
+    }
+
+    result
+}
+
+fn location(doc: &IntentDocument, path: &str) -> Option<StructuredLocation> {
+    Some(StructuredLocation {
+        file: doc.source_file.clone().unwrap_or_default(),
+        path: path.to_string(),
+    })
+}
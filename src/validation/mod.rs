@@ -7,6 +7,14 @@ mod policies;
 mod security;
 mod obligations;
 mod result;
+mod schema;
+mod raises;
+mod baseline;
+mod lints;
+mod lint;
+mod expressions;
+mod reachability;
+mod rules;
 
 pub use resolve::*;
 pub use typecheck::*;
@@ -15,13 +23,58 @@ pub use policies::*;
 pub use security::*;
 pub use obligations::*;
 pub use result::*;
+pub use schema::*;
+pub use raises::*;
+pub use baseline::*;
+pub use lints::*;
+pub use lint::*;
+pub use expressions::*;
+pub use reachability::*;
+pub use rules::*;
 
-use crate::parser::IntentStore;
+use crate::parser::{IntentConfig, IntentStore};
+
+/// The minimal correctness check: only reference resolution (phase 1), so
+/// unknown or circular references are reported without the type, effect,
+/// policy and security analysis of `validate_all`. Much cheaper on large
+/// models, for gates that only need every reference to resolve.
+pub fn validate_reference_integrity(store: &IntentStore) -> ValidationResult {
+    resolve_references(store).1
+}
 
 /// Run all validation phases on the intent store
 pub fn validate_all(store: &IntentStore) -> anyhow::Result<ValidationResult> {
+    let config = IntentConfig::load()?;
+    validate_with_config(store, &config)
+}
+
+/// Run all validation phases using an explicit configuration
+pub fn validate_with_config(
+    store: &IntentStore,
+    config: &IntentConfig,
+) -> anyhow::Result<ValidationResult> {
+    validate_with_rules(store, config, &[])
+}
+
+/// Run all validation phases, then the custom `rules` alongside the bundled
+/// rules `config` enables
+pub fn validate_with_rules(
+    store: &IntentStore,
+    config: &IntentConfig,
+    rules: &[&dyn ValidationRule],
+) -> anyhow::Result<ValidationResult> {
     let mut result = ValidationResult::new();
 
+    // Phase 0: JSON Schema preflight on the raw files
+    if config.validation.schema {
+        result.merge(validate_schemas(store));
+
+        // Schema violations make the typed phases unreliable
+        if !result.errors.is_empty() {
+            return Ok(result);
+        }
+    }
+
     // Phase 1: Reference resolution
     let (_, resolve_result) = resolve_references(store);
     result.merge(resolve_result);
@@ -36,7 +89,8 @@ pub fn validate_all(store: &IntentStore) -> anyhow::Result<ValidationResult> {
     result.merge(typecheck_result);
 
     // Phase 3: Effect analysis (doesn't produce errors, just analysis)
-    let (_, _effect_result) = analyze_effects(store);
+    let (effect_analysis, _effect_result) = analyze_effects(store);
+    result.merge(check_effect_ordering(store, &effect_analysis));
 
     // Phase 4: Policy analysis
     let policy_result = analyze_policies(store);
@@ -46,5 +100,65 @@ pub fn validate_all(store: &IntentStore) -> anyhow::Result<ValidationResult> {
     let security_result = check_security(store);
     result.merge(security_result);
 
+    // Phase 6: Declared errors that are never raised
+    result.merge(check_unused_errors(store));
+
+    // Phase 7: Constant expressions in Function bodies
+    result.merge(check_expressions(store));
+
+    // Phase 8: Intents no entry point reaches
+    result.merge(analyze_reachability(store));
+
+    // Phase 9: Informational lints
+    result.merge(check_model_lints(store));
+
+    // Phase 10: Caller-supplied and configured custom rules
+    run_custom_rules(store, config, rules, &mut result)?;
+
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{codes, IntentDocument, IntentKind};
+    use serde_json::json;
+
+    fn endpoint(name: &str, workflow: &str) -> IntentDocument {
+        IntentDocument::with_spec(
+            IntentKind::Endpoint,
+            name.to_string(),
+            json!({
+                "method": "POST",
+                "path": format!("/{}", name.to_lowercase()),
+                "input": "RefundRequest",
+                "output": "RefundResponse",
+                "workflow": workflow
+            }),
+        )
+    }
+
+    #[test]
+    fn test_reference_integrity_skips_later_phases() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        // RefundWorkflow writes to the database; without an idempotency key
+        // the policy phase warns
+        store.add(endpoint("RetryRefund", "RefundWorkflow")).unwrap();
+
+        let full = validate_with_config(&store, &IntentConfig::default()).unwrap();
+        assert!(full
+            .warnings
+            .iter()
+            .any(|w| w.message == "Endpoint 'RetryRefund' has database writes but no idempotency_key"));
+
+        let integrity = validate_reference_integrity(&store);
+        assert!(integrity.errors.is_empty());
+        assert!(integrity.warnings.is_empty());
+
+        store.add(endpoint("LostRefund", "MissingWorkflow")).unwrap();
+        let integrity = validate_reference_integrity(&store);
+        assert_eq!(integrity.errors.len(), 1);
+        assert_eq!(integrity.errors[0].code, codes::E005_UNKNOWN_REFERENCE);
+        assert!(integrity.errors[0].message.contains("MissingWorkflow"));
+    }
+}
@@ -3,11 +3,14 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::model::IntentKind;
+use crate::codegen::GenWriter;
 use crate::parser::IntentStore;
 
 use super::effects::analyze_effects;
 
+/// Namespace for deterministic (UUIDv5) obligation IDs
+const OBLIGATION_NAMESPACE: Uuid = Uuid::from_u128(0x6f62_6c69_6761_4e53_8000_696e_7465_6e74);
+
 /// Obligation severity levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
@@ -50,24 +53,36 @@ pub struct Obligation {
     pub table: Option<String>,
 }
 
+/// Stable obligation ID derived from what the obligation is about, so an
+/// unchanged model always yields the same lock file
+pub fn obligation_id(obligation_type: ObligationType, key: &str) -> Uuid {
+    Uuid::new_v5(
+        &OBLIGATION_NAMESPACE,
+        format!("{:?}:{}", obligation_type, key).as_bytes(),
+    )
+}
+
 /// Check obligations for all intents
 pub fn check_obligations(store: &IntentStore) -> anyhow::Result<Vec<Obligation>> {
     let mut obligations = Vec::new();
 
-    // Analyze effects to find required obligations
+    // Analyze effects to find required obligations. The effect sets are
+    // sorted, so obligations come out in a stable order.
     let (effect_analysis, _) = analyze_effects(store);
 
+    // Resolve against intents in name order so the same one always wins
+    let mut contract_tests = store.contract_tests();
+    contract_tests.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut migrations = store.migrations();
+    migrations.sort_by(|a, b| a.name.cmp(&b.name));
+
     // Create ContractTest obligations for each service call
     for (service, operation) in &effect_analysis.services_called {
         let mut status = ObligationStatus::Open;
         let mut resolving_intent_id = None;
 
         // Check if a ContractTest exists for this service/operation
-        for doc in store.iter() {
-            if doc.kind != IntentKind::ContractTest {
-                continue;
-            }
-
+        for doc in &contract_tests {
             if let Ok(spec) = doc.as_contract_test_spec() {
                 if &spec.service == service && &spec.operation == operation {
                     status = ObligationStatus::Resolved;
@@ -78,7 +93,10 @@ pub fn check_obligations(store: &IntentStore) -> anyhow::Result<Vec<Obligation>>
         }
 
         obligations.push(Obligation {
-            id: Uuid::new_v4(),
+            id: obligation_id(
+                ObligationType::ContractTest,
+                &format!("{}.{}", service, operation),
+            ),
             obligation_type: ObligationType::ContractTest,
             intent_id: resolving_intent_id,
             status,
@@ -95,11 +113,7 @@ pub fn check_obligations(store: &IntentStore) -> anyhow::Result<Vec<Obligation>>
         let mut resolving_intent_id = None;
 
         // Check if a Migration exists for this table
-        for doc in store.iter() {
-            if doc.kind != IntentKind::Migration {
-                continue;
-            }
-
+        for doc in &migrations {
             if let Ok(spec) = doc.as_migration_spec() {
                 if &spec.table == table {
                     status = ObligationStatus::Resolved;
@@ -110,7 +124,7 @@ pub fn check_obligations(store: &IntentStore) -> anyhow::Result<Vec<Obligation>>
         }
 
         obligations.push(Obligation {
-            id: Uuid::new_v4(),
+            id: obligation_id(ObligationType::Migration, table),
             obligation_type: ObligationType::Migration,
             intent_id: resolving_intent_id,
             status,
@@ -125,19 +139,17 @@ pub fn check_obligations(store: &IntentStore) -> anyhow::Result<Vec<Obligation>>
 }
 
 /// Write obligations to the lock file
-pub fn write_obligations_lock(obligations: &[Obligation]) -> anyhow::Result<()> {
+pub fn write_obligations_lock(
+    obligations: &[Obligation],
+    writer: &mut dyn GenWriter,
+) -> anyhow::Result<()> {
     let lock_path = ".intent/locks/obligations.json";
 
-    // Create directory if needed
-    if let Some(parent) = std::path::Path::new(lock_path).parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-
     let content = serde_json::json!({
         "obligations": obligations
     });
 
-    std::fs::write(lock_path, serde_json::to_string_pretty(&content)?)?;
+    writer.write(lock_path, &serde_json::to_string_pretty(&content)?)?;
 
     Ok(())
 }
@@ -160,3 +172,56 @@ pub fn load_obligations_lock() -> anyhow::Result<Vec<Obligation>> {
 
     Ok(obligations)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(obligations: &[Obligation]) -> Vec<String> {
+        obligations
+            .iter()
+            .map(|o| format!("{:?} {:?} {}", o.obligation_type, o.status, o.description))
+            .collect()
+    }
+
+    #[test]
+    fn test_obligations_are_ordered_deterministically() {
+        let store = IntentStore::load_from_path("fixtures/valid").unwrap();
+
+        let first = check_obligations(&store).unwrap();
+        let second = check_obligations(&IntentStore::load_from_path("fixtures/valid").unwrap())
+            .unwrap();
+
+        assert!(!first.is_empty());
+        assert_eq!(signature(&first), signature(&second));
+
+        // Contract tests come first, each group sorted by its key
+        let keys: Vec<_> = first
+            .iter()
+            .map(|o| (o.obligation_type as u8, o.service_operation.clone(), o.table.clone()))
+            .collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+    }
+
+    #[test]
+    fn test_obligation_ids_are_stable() {
+        let first = check_obligations(&IntentStore::load_from_path("fixtures/valid").unwrap())
+            .unwrap();
+        let second = check_obligations(&IntentStore::load_from_path("fixtures/valid").unwrap())
+            .unwrap();
+
+        let ids = |obligations: &[Obligation]| obligations.iter().map(|o| o.id).collect::<Vec<_>>();
+        assert_eq!(ids(&first), ids(&second));
+
+        assert_eq!(
+            obligation_id(ObligationType::Migration, "refunds"),
+            obligation_id(ObligationType::Migration, "refunds")
+        );
+        assert_ne!(
+            obligation_id(ObligationType::Migration, "refunds"),
+            obligation_id(ObligationType::ContractTest, "refunds")
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Error raise reachability and unused endpoint error detection
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::model::{Expression, FunctionSpec, IntentDocument, IntentKind, StructuredLocation, WorkflowStep};
+use crate::parser::IntentStore;
+
+use super::ValidationResult;
+
+/// Error codes a workflow can raise: its own `raise_if` conditions plus
+/// `Raise` expressions in every Function reachable from its step expressions
+pub fn reachable_raises(store: &IntentStore, workflow: &IntentDocument) -> BTreeSet<String> {
+    let mut raises = BTreeSet::new();
+    let Ok(spec) = workflow.as_workflow_spec() else {
+        return raises;
+    };
+
+    let functions: HashMap<String, FunctionSpec> = store
+        .functions()
+        .into_iter()
+        .filter_map(|doc| Some((call_key(&doc.name), doc.as_function_spec().ok()?)))
+        .collect();
+
+    let mut pending = Vec::new();
+    for step in &spec.steps {
+        let WorkflowStep::Transform(t) = step else {
+            continue;
+        };
+        if let Some(raise) = &t.raise_if {
+            raises.insert(raise.error.clone());
+            pending.extend(called_names(&raise.condition));
+        }
+        for source in t.assign.values() {
+            pending.extend(called_names(source));
+        }
+    }
+
+    // Follow calls through function bodies, visiting each function once
+    let mut visited = BTreeSet::new();
+    while let Some(name) = pending.pop() {
+        let key = call_key(&name);
+        let Some(function) = functions.get(&key) else {
+            continue;
+        };
+        if !visited.insert(key) {
+            continue;
+        }
+
+        function.body.walk(&mut |expr| match expr {
+            Expression::Raise { error, .. } => {
+                raises.insert(error.clone());
+            }
+            Expression::Call { function, .. } => pending.push(function.clone()),
+            _ => {}
+        });
+    }
+
+    raises
+}
+
+/// Warn about endpoint error codes that nothing reachable from the endpoint's
+/// workflow ever raises (W015)
+pub fn check_unused_errors(store: &IntentStore) -> ValidationResult {
+    let mut result = ValidationResult::new();
+
+    let mut endpoints = store.endpoints();
+    endpoints.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for doc in endpoints {
+        let Ok(spec) = doc.as_endpoint_spec() else {
+            continue;
+        };
+        if spec.errors.is_empty() {
+            continue;
+        }
+        let Some(workflow) = store.get_by_kind_name(IntentKind::Workflow, &spec.workflow) else {
+            continue;
+        };
+
+        let raises = reachable_raises(store, workflow);
+        for (i, error) in spec.errors.iter().enumerate() {
+            if raises.contains(&error.code) {
+                continue;
+            }
+            result.add_warning(
+                "W015",
+                format!(
+                    "Endpoint '{}' declares error '{}' but workflow '{}' never raises it",
+                    doc.name, error.code, spec.workflow
+                ),
+                Some(StructuredLocation {
+                    file: doc.source_file.clone().unwrap_or_default(),
+                    path: format!("$.spec.errors[{}].code", i),
+                }),
+            );
+        }
+    }
+
+    result
+}
+
+/// Function calls (`name(`) in a workflow expression string, skipping method calls
+fn called_names(expr: &str) -> Vec<String> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut names = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if !(chars[i].is_alphabetic() || chars[i] == '_') {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+            i += 1;
+        }
+        let is_method = start > 0 && chars[start - 1] == '.';
+        let next = chars[i..].iter().find(|c| !c.is_whitespace());
+        if !is_method && next == Some(&'(') {
+            names.push(chars[start..i].iter().collect());
+        }
+    }
+
+    names
+}
+
+/// Match `uuid_generate` in an expression against a Function named `UuidGenerate`
+pub(super) fn call_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_called_names() {
+        assert_eq!(called_names("uuid_generate()"), ["uuid_generate"]);
+        assert_eq!(
+            called_names("check_amount(input.amount) && input.items.len() > 0"),
+            ["check_amount"]
+        );
+        assert!(called_names("input.amount").is_empty());
+    }
+}
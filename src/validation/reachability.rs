@@ -0,0 +1,212 @@
+//! Reachability of intents from the model's entry points
+
+use std::collections::HashSet;
+
+use uuid::Uuid;
+
+use crate::model::{Expression, IntentDocument, IntentKind, StructuredLocation};
+use crate::parser::IntentStore;
+
+use super::ValidationResult;
+
+/// Kinds whose intents are entry points into the model
+const ROOT_KINDS: &[IntentKind] = &[
+    IntentKind::Endpoint,
+    IntentKind::Command,
+    IntentKind::Migration,
+    IntentKind::ContractTest,
+];
+
+/// Kinds reported when no entry point reaches them
+const CHECKED_KINDS: &[IntentKind] = &[
+    IntentKind::Type,
+    IntentKind::Workflow,
+    IntentKind::Function,
+    IntentKind::Enum,
+];
+
+/// Warn (W021) about each Type, Workflow, Function and Enum that no Endpoint,
+/// Command, Migration or ContractTest reaches through its dependencies.
+/// Types and Enums nothing references are left to I001. A model without any
+/// entry point is not checked.
+pub fn analyze_reachability(store: &IntentStore) -> ValidationResult {
+    let mut result = ValidationResult::new();
+
+    let mut pending: Vec<&IntentDocument> = store
+        .iter()
+        .filter(|doc| ROOT_KINDS.contains(&doc.kind))
+        .collect();
+    if pending.is_empty() {
+        return result;
+    }
+
+    let mut reached: HashSet<Uuid> = HashSet::new();
+    while let Some(doc) = pending.pop() {
+        if !reached.insert(doc.id) {
+            continue;
+        }
+        pending.extend(store.get_dependencies(&doc.id));
+        pending.extend(
+            called_names(doc)
+                .iter()
+                .filter_map(|name| callable(store, name)),
+        );
+    }
+
+    let mut unreachable: Vec<_> = store
+        .iter()
+        .filter(|doc| CHECKED_KINDS.contains(&doc.kind) && !reached.contains(&doc.id))
+        .collect();
+    unreachable.sort_by(|a, b| (a.kind.to_string(), &a.name).cmp(&(b.kind.to_string(), &b.name)));
+
+    for doc in unreachable {
+        let referenced = !store.get_dependents(&doc.id).is_empty();
+        if !referenced && matches!(doc.kind, IntentKind::Type | IntentKind::Enum) {
+            continue;
+        }
+        let called = doc.kind == IntentKind::Function
+            && store
+                .iter()
+                .any(|other| other.id != doc.id && called_names(other).contains(&doc.name));
+        let message = if !referenced && !called {
+            format!("{} '{}' is not referenced by any intent", doc.kind, doc.name)
+        } else {
+            format!(
+                "{} '{}' is only referenced by intents no Endpoint, Command, Migration or ContractTest reaches",
+                doc.kind, doc.name
+            )
+        };
+        result.add_warning(
+            "W021",
+            message,
+            Some(StructuredLocation {
+                file: doc.source_file.clone().unwrap_or_default(),
+                path: "$".to_string(),
+            }),
+        );
+    }
+
+    result
+}
+
+/// Functions and Pipelines an intent invokes by name; these aren't part of
+/// the store's dependency graph
+fn called_names(doc: &IntentDocument) -> Vec<String> {
+    match doc.kind {
+        IntentKind::Command => doc
+            .as_command_spec()
+            .map(|spec| vec![spec.handler])
+            .unwrap_or_default(),
+        IntentKind::Pipeline => doc
+            .as_pipeline_spec()
+            .map(|spec| spec.stages.into_iter().map(|s| s.function).collect())
+            .unwrap_or_default(),
+        IntentKind::Function => {
+            let Ok(spec) = doc.as_function_spec() else {
+                return Vec::new();
+            };
+            let mut names = Vec::new();
+            spec.body.walk(&mut |expr| {
+                if let Expression::Call { function, .. } = expr {
+                    names.push(function.clone());
+                }
+            });
+            names
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn callable<'a>(store: &'a IntentStore, name: &str) -> Option<&'a IntentDocument> {
+    store
+        .get_by_kind_name(IntentKind::Function, name)
+        .or_else(|| store.get_by_kind_name(IntentKind::Pipeline, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn messages(result: &ValidationResult) -> Vec<&str> {
+        result.warnings.iter().map(|w| w.message.as_str()).collect()
+    }
+
+    #[test]
+    fn test_fixtures_are_all_reachable() {
+        let store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        assert!(analyze_reachability(&store).warnings.is_empty());
+    }
+
+    #[test]
+    fn test_unreachable_intents() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Type,
+                "Orphan".to_string(),
+                json!({ "fields": { "leaf": { "type": "Leaf" } } }),
+            ))
+            .unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Enum,
+                "Leaf".to_string(),
+                json!({ "variants": [{ "name": "A" }] }),
+            ))
+            .unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Function,
+                "helper".to_string(),
+                json!({ "returns": { "type": "int" }, "body": { "kind": "Literal", "value": 1 } }),
+            ))
+            .unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Command,
+                "Run".to_string(),
+                json!({ "command": "run", "handler": "helper" }),
+            ))
+            .unwrap();
+
+        let result = analyze_reachability(&store);
+        assert!(result.warnings.iter().all(|w| w.code == "W021"));
+        assert_eq!(
+            messages(&result),
+            ["Enum 'Leaf' is only referenced by intents no Endpoint, Command, Migration or ContractTest reaches"]
+        );
+    }
+
+    #[test]
+    fn test_unreferenced_intents_get_one_diagnostic() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        for (kind, name, spec) in [
+            (IntentKind::Type, "Orphan", json!({ "fields": { "id": { "type": "uuid" } } })),
+            (IntentKind::Enum, "Status", json!({ "variants": [{ "name": "A" }] })),
+            (
+                IntentKind::Function,
+                "helper",
+                json!({ "returns": { "type": "string" }, "body": { "kind": "Literal", "value": "x" } }),
+            ),
+        ] {
+            store
+                .add(IntentDocument::with_spec(kind, name.to_string(), spec))
+                .unwrap();
+        }
+
+        let result = crate::validation::validate_with_config(&store, &crate::parser::IntentConfig::default()).unwrap();
+        for name in ["Orphan", "Status", "helper"] {
+            let quoted = format!("'{}' is not referenced by any intent", name);
+            let diagnostics: Vec<_> = result
+                .warnings
+                .iter()
+                .chain(&result.infos)
+                .filter(|d| d.message.contains(&quoted))
+                .map(|d| d.code.as_str())
+                .collect();
+            let expected = if name == "helper" { "W021" } else { "I001" };
+            assert_eq!(diagnostics, [expected], "{}", name);
+        }
+    }
+}
@@ -4,7 +4,7 @@ use std::collections::{HashMap, HashSet};
 
 use uuid::Uuid;
 
-use crate::model::{codes, IntentKind, StructuredLocation};
+use crate::model::{codes, IntentDocument, IntentKind, StructuredLocation, TypeRef, WorkflowStep};
 use crate::parser::IntentStore;
 
 use super::ValidationResult;
@@ -20,6 +20,27 @@ pub struct ResolvedGraph {
     pub dependents: HashMap<Uuid, Vec<Uuid>>,
 }
 
+impl ResolvedGraph {
+    /// Dependency cycles, each listing its intents in reference order; the
+    /// last one refers back to the first
+    pub fn cycles(&self) -> Vec<Vec<Uuid>> {
+        let mut cycles = Vec::new();
+        let mut visited = HashSet::new();
+        let mut rec_stack = HashSet::new();
+        let mut path = Vec::new();
+
+        // Sorted so the same cycle is always reported from the same intent
+        let mut ids: Vec<&Uuid> = self.dependencies.keys().collect();
+        ids.sort();
+        for id in ids {
+            if !visited.contains(id) {
+                detect_cycle_dfs(*id, self, &mut visited, &mut rec_stack, &mut path, &mut cycles);
+            }
+        }
+        cycles
+    }
+}
+
 /// Resolve all references in the intent store
 pub fn resolve_references(store: &IntentStore) -> (ResolvedGraph, ValidationResult) {
     let mut result = ValidationResult::new();
@@ -34,19 +55,35 @@ pub fn resolve_references(store: &IntentStore) -> (ResolvedGraph, ValidationResu
             if is_native_or_engine_type(&type_name) {
                 continue;
             }
-            match store.get_by_kind_name(IntentKind::Type, &type_name) {
+            match store.get_type_or_enum(&type_name) {
                 Some(type_doc) => {
                     deps.push(type_doc.id);
                 }
                 None => {
-                    result.add_error(
-                        codes::E005_UNKNOWN_REFERENCE,
-                        format!("Unknown type reference: {}", type_name),
-                        Some(StructuredLocation {
-                            file: doc.source_file.clone().unwrap_or_default(),
-                            path: "$.spec".to_string(),
-                        }),
-                    );
+                    let path = find_context_field(doc, &type_name)
+                        .map(|field| format!("$.spec.context.{}", field))
+                        .unwrap_or_else(|| "$.spec".to_string());
+                    let location = Some(StructuredLocation {
+                        file: doc.source_file.clone().unwrap_or_default(),
+                        path,
+                    });
+
+                    // Naming an Endpoint, Workflow, etc. where a type belongs
+                    match store.find_by_name(&type_name) {
+                        Some(other) => result.add_error(
+                            codes::E007_TYPE_MISMATCH,
+                            format!(
+                                "'{}' is an intent of kind {:?}, not a Type or Enum",
+                                type_name, other.kind
+                            ),
+                            location,
+                        ),
+                        None => result.add_error(
+                            codes::E005_UNKNOWN_REFERENCE,
+                            format!("Unknown type reference: {}", type_name),
+                            location,
+                        ),
+                    }
                 }
             }
         }
@@ -77,12 +114,19 @@ pub fn resolve_references(store: &IntentStore) -> (ResolvedGraph, ValidationResu
                     deps.push(service_doc.id);
                 }
                 None => {
+                    // Point workflow references at the step that makes the call
+                    let (context, path) = match find_service_step(doc, &service_name) {
+                        Some((i, label)) => {
+                            (format!(" (in {})", label), format!("$.spec.steps[{}].service", i))
+                        }
+                        None => (String::new(), "$.spec".to_string()),
+                    };
                     result.add_error(
                         codes::E005_UNKNOWN_REFERENCE,
-                        format!("Unknown service reference: {}", service_name),
+                        format!("Unknown service reference: {}{}", service_name, context),
                         Some(StructuredLocation {
                             file: doc.source_file.clone().unwrap_or_default(),
-                            path: "$.spec".to_string(),
+                            path,
                         }),
                     );
                 }
@@ -96,45 +140,111 @@ pub fn resolve_references(store: &IntentStore) -> (ResolvedGraph, ValidationResu
         graph.dependencies.insert(doc.id, deps);
     }
 
-    // Check for circular references
-    let circular = detect_circular_references(&graph);
+    // Check for circular references, located at the first intent's
+    // reference to the next one in the cycle
+    let circular = detect_circular_references(&graph, store);
     for cycle in circular {
         let cycle_names: Vec<String> = cycle
             .iter()
-            .filter_map(|id| store.get(id).map(|d| d.name.clone()))
+            .filter_map(|edge| store.get(&edge.from).map(|d| d.name.clone()))
             .collect();
+        let location = cycle.first().and_then(|edge| {
+            Some(StructuredLocation {
+                file: store.get(&edge.from)?.source_file.clone().unwrap_or_default(),
+                path: edge.path.clone(),
+            })
+        });
 
         result.add_error(
             codes::E006_CIRCULAR_REFERENCE,
             format!("Circular reference detected: {}", cycle_names.join(" -> ")),
-            None,
+            location,
         );
     }
 
     (graph, result)
 }
 
-/// Detect circular references in the dependency graph
-fn detect_circular_references(graph: &ResolvedGraph) -> Vec<Vec<Uuid>> {
-    let mut cycles = Vec::new();
-    let mut visited = HashSet::new();
-    let mut rec_stack = HashSet::new();
-    let mut path = Vec::new();
-
-    for id in graph.dependencies.keys() {
-        if !visited.contains(id) {
-            detect_cycle_dfs(
-                *id,
-                graph,
-                &mut visited,
-                &mut rec_stack,
-                &mut path,
-                &mut cycles,
-            );
+/// Find the workflow step (index and label) that calls `service_name`
+fn find_service_step(doc: &IntentDocument, service_name: &str) -> Option<(usize, String)> {
+    let spec = doc.as_workflow_spec().ok()?;
+    spec.steps.iter().enumerate().find_map(|(i, step)| match step {
+        WorkflowStep::Effect(e) if e.service.as_deref() == Some(service_name) => {
+            Some((i, step.label(i)))
         }
-    }
+        _ => None,
+    })
+}
+
+/// Find the workflow context field whose type references `type_name`
+fn find_context_field(doc: &IntentDocument, type_name: &str) -> Option<String> {
+    let spec = doc.as_workflow_spec().ok()?;
+    let mut fields: Vec<_> = spec.context.iter().collect();
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+    fields
+        .into_iter()
+        .find(|(_, ty)| ty.get_named_references().contains(&type_name))
+        .map(|(field, _)| field.clone())
+}
 
-    cycles
+/// One reference in a dependency cycle
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReferenceEdge {
+    from: Uuid,
+    to: Uuid,
+    /// JSON path of the reference in `from`, e.g. `$.spec.fields.parent.type`
+    path: String,
+}
+
+/// Detect circular references in the dependency graph. Each cycle lists its
+/// references in order; the last one leads back to the first intent.
+fn detect_circular_references(graph: &ResolvedGraph, store: &IntentStore) -> Vec<Vec<ReferenceEdge>> {
+    graph
+        .cycles()
+        .into_iter()
+        .map(|cycle| {
+            let next = cycle.iter().cycle().skip(1);
+            cycle
+                .iter()
+                .zip(next)
+                .map(|(&from, &to)| ReferenceEdge {
+                    from,
+                    to,
+                    path: reference_path(store, from, to),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// JSON path of the first string in `from`'s spec that refers to `to` by
+/// name, or `$.spec` if there is none
+fn reference_path(store: &IntentStore, from: Uuid, to: Uuid) -> String {
+    let (Some(from), Some(to)) = (store.get(&from), store.get(&to)) else {
+        return "$.spec".to_string();
+    };
+    find_reference(&from.spec, &to.name, "$.spec".to_string()).unwrap_or_else(|| "$.spec".to_string())
+}
+
+fn find_reference(value: &serde_json::Value, name: &str, path: String) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => {
+            let refers = s == name
+                || TypeRef::parse(s).is_ok_and(|t| t.get_named_references().contains(&name));
+            refers.then_some(path)
+        }
+        serde_json::Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(i, item)| find_reference(item, name, format!("{}[{}]", path, i))),
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            keys.into_iter()
+                .find_map(|key| find_reference(&map[key], name, format!("{}.{}", path, key)))
+        }
+        _ => None,
+    }
 }
 
 fn detect_cycle_dfs(
@@ -178,4 +288,31 @@ mod tests {
         assert!(result.is_valid());
         assert!(graph.dependencies.is_empty());
     }
+
+    #[test]
+    fn test_cycle_is_located_at_first_reference() {
+        let mut store = IntentStore::new();
+        for (name, spec) in [
+            ("Node", serde_json::json!({ "fields": { "id": { "type": "string" }, "parent": { "type": "optional<Edge>" } } })),
+            ("Edge", serde_json::json!({ "fields": { "targets": { "type": "array<Node>" } } })),
+        ] {
+            let mut doc = IntentDocument::with_spec(IntentKind::Type, name.to_string(), spec);
+            doc.source_file = Some(format!("{}.intent.json", name.to_lowercase()));
+            store.add(doc).unwrap();
+        }
+
+        let (_, result) = resolve_references(&store);
+        assert_eq!(result.errors.len(), 1);
+        let error = &result.errors[0];
+        assert_eq!(error.code, codes::E006_CIRCULAR_REFERENCE);
+        let location = error.location.as_ref().unwrap();
+        if error.message.ends_with("Node -> Edge") {
+            assert_eq!(location.file, "node.intent.json");
+            assert_eq!(location.path, "$.spec.fields.parent.type");
+        } else {
+            assert!(error.message.ends_with("Edge -> Node"), "{}", error.message);
+            assert_eq!(location.file, "edge.intent.json");
+            assert_eq!(location.path, "$.spec.fields.targets.type");
+        }
+    }
 }
@@ -2,13 +2,33 @@
 
 use serde::Serialize;
 
-use crate::model::{Severity, StructuredError, StructuredLocation};
+use crate::model::{codes, Severity, StructuredError, StructuredLocation};
 
 /// Result of validation
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct ValidationResult {
     pub errors: Vec<StructuredError>,
     pub warnings: Vec<StructuredError>,
+    /// Informational findings; never affect the exit code unless
+    /// `validate --fail-on-info` is given
+    pub infos: Vec<StructuredError>,
+}
+
+/// Diagnostic counts per severity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SeveritySummary {
+    pub errors: usize,
+    pub warnings: usize,
+    pub infos: usize,
+}
+
+/// Count the diagnostics in `result` per severity
+pub fn severity_summary(result: &ValidationResult) -> SeveritySummary {
+    SeveritySummary {
+        errors: result.errors.len(),
+        warnings: result.warnings.len(),
+        infos: result.infos.len(),
+    }
 }
 
 impl ValidationResult {
@@ -26,8 +46,10 @@ impl ValidationResult {
         message: impl Into<String>,
         location: Option<StructuredLocation>,
     ) {
+        let code = code.into();
         self.errors.push(StructuredError {
-            code: code.into(),
+            help_url: codes::help_url(&code),
+            code,
             severity: Severity::Error,
             message: message.into(),
             location,
@@ -40,16 +62,35 @@ impl ValidationResult {
         message: impl Into<String>,
         location: Option<StructuredLocation>,
     ) {
+        let code = code.into();
         self.warnings.push(StructuredError {
-            code: code.into(),
+            help_url: codes::help_url(&code),
+            code,
             severity: Severity::Warning,
             message: message.into(),
             location,
         });
     }
 
+    pub fn add_info(
+        &mut self,
+        code: impl Into<String>,
+        message: impl Into<String>,
+        location: Option<StructuredLocation>,
+    ) {
+        let code = code.into();
+        self.infos.push(StructuredError {
+            help_url: codes::help_url(&code),
+            code,
+            severity: Severity::Info,
+            message: message.into(),
+            location,
+        });
+    }
+
     pub fn merge(&mut self, other: ValidationResult) {
         self.errors.extend(other.errors);
         self.warnings.extend(other.warnings);
+        self.infos.extend(other.infos);
     }
 }
@@ -0,0 +1,164 @@
+//! Custom validation rules that run alongside the built-in phases
+
+use crate::model::{IntentDocument, StructuredLocation, TypeRef};
+use crate::parser::{IntentConfig, IntentStore};
+
+use super::ValidationResult;
+
+/// An organization-specific check over the whole model. Rules report
+/// through `result` with their own codes, like the built-in phases.
+pub trait ValidationRule: Send + Sync {
+    /// Name used to enable the rule in `[validation] rules`
+    fn name(&self) -> &str;
+
+    fn check(&self, store: &IntentStore, result: &mut ValidationResult);
+}
+
+/// Rules shipped with the engine, off unless named in `[validation] rules`
+fn bundled_rules() -> Vec<Box<dyn ValidationRule>> {
+    vec![Box::new(MoneyCurrencyRule)]
+}
+
+/// Run the caller's `rules`, then the bundled rules the config enables
+pub fn run_custom_rules(
+    store: &IntentStore,
+    config: &IntentConfig,
+    rules: &[&dyn ValidationRule],
+    result: &mut ValidationResult,
+) -> anyhow::Result<()> {
+    let bundled = bundled_rules();
+    let mut enabled = Vec::new();
+    for name in &config.validation.rules {
+        let Some(rule) = bundled.iter().find(|rule| rule.name() == name) else {
+            let known: Vec<_> = bundled.iter().map(|rule| rule.name()).collect();
+            anyhow::bail!(
+                "Unknown validation rule '{}' in [validation] rules; available: {}",
+                name,
+                known.join(", ")
+            );
+        };
+        enabled.push(rule.as_ref());
+    }
+
+    for rule in rules.iter().chain(&enabled) {
+        rule.check(store, result);
+    }
+    Ok(())
+}
+
+/// `money-currency`: warn (W022) about each Type with a money field but no
+/// `currency` field to say what the amount is in
+pub struct MoneyCurrencyRule;
+
+impl ValidationRule for MoneyCurrencyRule {
+    fn name(&self) -> &str {
+        "money-currency"
+    }
+
+    fn check(&self, store: &IntentStore, result: &mut ValidationResult) {
+        let mut types: Vec<&IntentDocument> = store.types();
+        types.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for doc in types {
+            let Ok(spec) = doc.as_type_spec() else {
+                continue;
+            };
+            if spec.fields.contains_key("currency") {
+                continue;
+            }
+            let mut money: Vec<_> = spec
+                .fields
+                .iter()
+                .filter(|(_, field)| is_money(&field.field_type))
+                .map(|(name, _)| name)
+                .collect();
+            money.sort();
+            for name in money {
+                result.add_warning(
+                    "W022",
+                    format!(
+                        "Money field '{}' of Type '{}' has no 'currency' field beside it",
+                        name, doc.name
+                    ),
+                    Some(StructuredLocation {
+                        file: doc.source_file.clone().unwrap_or_default(),
+                        path: format!("$.spec.fields.{}", name),
+                    }),
+                );
+            }
+        }
+    }
+}
+
+fn is_money(type_ref: &TypeRef) -> bool {
+    match type_ref {
+        TypeRef::Money => true,
+        TypeRef::Optional(inner) => is_money(inner),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::IntentKind;
+    use crate::validation::{validate_with_config, validate_with_rules};
+    use serde_json::json;
+
+    struct NoDraftTypes;
+
+    impl ValidationRule for NoDraftTypes {
+        fn name(&self) -> &str {
+            "no-draft-types"
+        }
+
+        fn check(&self, store: &IntentStore, result: &mut ValidationResult) {
+            for doc in store.types() {
+                if doc.name.starts_with("DraftRule") {
+                    result.add_warning("X001", format!("Type '{}' is a draft", doc.name), None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_caller_rule_runs_in_validation() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Type,
+                "DraftRuleOrder".to_string(),
+                json!({ "fields": { "id": { "type": "uuid" } } }),
+            ))
+            .unwrap();
+
+        let result = validate_with_rules(&store, &IntentConfig::default(), &[&NoDraftTypes]).unwrap();
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.code == "X001" && w.message == "Type 'DraftRuleOrder' is a draft"));
+
+        // Rules only run for the call they're passed to
+        let result = validate_with_config(&store, &IntentConfig::default()).unwrap();
+        assert!(result.warnings.iter().all(|w| w.code != "X001"));
+    }
+
+    #[test]
+    fn test_money_currency_rule_is_opt_in() {
+        let store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        let mut config = IntentConfig::default();
+        let result = validate_with_config(&store, &config).unwrap();
+        assert!(result.warnings.iter().all(|w| w.code != "W022"));
+
+        config.validation.rules = vec!["money-currency".to_string()];
+        let result = validate_with_config(&store, &config).unwrap();
+        let warning = result.warnings.iter().find(|w| w.code == "W022").unwrap();
+        assert_eq!(
+            warning.message,
+            "Money field 'amount' of Type 'RefundRequest' has no 'currency' field beside it"
+        );
+
+        config.validation.rules = vec!["bogus".to_string()];
+        assert!(validate_with_config(&store, &config).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! JSON Schema preflight for raw intent files
+//!
+//! Serde silently drops unknown fields and its errors carry no path, so this
+//! phase validates the raw JSON of each intent file against an embedded
+//! schema for its kind before the typed phases run.
+
+use jsonschema::error::ValidationErrorKind;
+use jsonschema::paths::{JSONPointer, PathChunk};
+use jsonschema::JSONSchema;
+use serde_json::Value;
+
+use crate::model::{codes, IntentKind, StructuredLocation};
+use crate::parser::IntentStore;
+
+use super::ValidationResult;
+
+/// Embedded JSON Schema for the `spec` of a given kind
+pub fn spec_schema(kind: IntentKind) -> &'static str {
+    match kind {
+        IntentKind::Type => include_str!("schemas/type.json"),
+        IntentKind::Endpoint => include_str!("schemas/endpoint.json"),
+        IntentKind::Workflow => include_str!("schemas/workflow.json"),
+        IntentKind::Service => include_str!("schemas/service.json"),
+        IntentKind::ContractTest => include_str!("schemas/contract_test.json"),
+        IntentKind::Migration => include_str!("schemas/migration.json"),
+        IntentKind::Function => include_str!("schemas/function.json"),
+        IntentKind::Pipeline => include_str!("schemas/pipeline.json"),
+        IntentKind::Template => include_str!("schemas/template.json"),
+        IntentKind::Enum => include_str!("schemas/enum.json"),
+        IntentKind::Module => include_str!("schemas/module.json"),
+        IntentKind::Command => include_str!("schemas/command.json"),
+        IntentKind::Trait => include_str!("schemas/trait.json"),
+    }
+}
+
+/// Full document schema (envelope + spec) for a given kind
+pub fn document_schema(kind: IntentKind) -> Value {
+    let spec: Value =
+        serde_json::from_str(spec_schema(kind)).expect("embedded spec schema is valid JSON");
+
+    serde_json::json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": kind.to_string(),
+        "type": "object",
+        "required": ["schema_version", "id", "kind", "name", "spec"],
+        "additionalProperties": false,
+        "properties": {
+            "schema_version": { "type": "string" },
+            "id": { "type": "string", "format": "uuid" },
+            "kind": { "const": kind.to_string() },
+            "name": { "type": "string" },
+            "tags": {
+                "type": "array",
+                "items": { "type": "string", "pattern": "^[a-z0-9]+(-[a-z0-9]+)*$" }
+            },
+            "spec": spec
+        }
+    })
+}
+
+/// Validate every loaded intent file against the schema for its kind
+pub fn validate_schemas(store: &IntentStore) -> ValidationResult {
+    let mut result = ValidationResult::new();
+
+    let mut docs: Vec<_> = store.iter().collect();
+    docs.sort_by(|a, b| a.source_file.cmp(&b.source_file));
+
+    for doc in docs {
+        let Some(file) = doc.source_file.as_deref() else {
+            continue;
+        };
+
+        // Files that fail to read or parse were already rejected by the loader
+        let Ok(content) = std::fs::read_to_string(file) else {
+            continue;
+        };
+        let Ok(raw) = serde_json::from_str::<Value>(&content) else {
+            continue;
+        };
+
+        result.merge(validate_schema_value(&raw, doc.kind, file));
+    }
+
+    result
+}
+
+/// Validate a raw intent document against the schema for `kind`
+pub fn validate_schema_value(raw: &Value, kind: IntentKind, file: &str) -> ValidationResult {
+    let mut result = ValidationResult::new();
+
+    let schema = document_schema(kind);
+    let compiled = JSONSchema::compile(&schema).expect("embedded schema compiles");
+
+    if let Err(errors) = compiled.validate(raw) {
+        for error in errors {
+            let path = json_path(&error.instance_path);
+
+            match &error.kind {
+                ValidationErrorKind::AdditionalProperties { unexpected } => {
+                    for property in unexpected {
+                        let property_path = format!("{}.{}", path, property);
+                        result.add_error(
+                            codes::E011_SCHEMA_VIOLATION,
+                            format!(
+                                "Additional property '{}' not allowed at {}",
+                                property, property_path
+                            ),
+                            location(file, &property_path),
+                        );
+                    }
+                }
+                ValidationErrorKind::Required { property } => {
+                    let property = property.as_str().unwrap_or_default();
+                    result.add_error(
+                        codes::E002_MISSING_FIELD,
+                        format!("Missing required property '{}' at {}", property, path),
+                        location(file, &format!("{}.{}", path, property)),
+                    );
+                }
+                _ => {
+                    result.add_error(
+                        codes::E011_SCHEMA_VIOLATION,
+                        format!("Schema violation at {}: {}", path, error),
+                        location(file, &path),
+                    );
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// Render a JSON pointer in the `$.spec.steps[0]` form used by other phases
+fn json_path(pointer: &JSONPointer) -> String {
+    let mut path = "$".to_string();
+    for chunk in pointer {
+        match chunk {
+            PathChunk::Property(name) => {
+                path.push('.');
+                path.push_str(name);
+            }
+            PathChunk::Index(index) => path.push_str(&format!("[{}]", index)),
+            PathChunk::Keyword(keyword) => {
+                path.push('.');
+                path.push_str(keyword);
+            }
+        }
+    }
+    path
+}
+
+fn location(file: &str, path: &str) -> Option<StructuredLocation> {
+    Some(StructuredLocation {
+        file: file.to_string(),
+        path: path.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint_doc() -> Value {
+        serde_json::json!({
+            "schema_version": "1.0",
+            "id": "550e8400-e29b-41d4-a716-446655440005",
+            "kind": "Endpoint",
+            "name": "CreateRefund",
+            "spec": {
+                "method": "POST",
+                "path": "/refund",
+                "input": "RefundRequest",
+                "output": "RefundResponse",
+                "workflow": "RefundWorkflow"
+            }
+        })
+    }
+
+    #[test]
+    fn test_valid_document_passes() {
+        let result = validate_schema_value(&endpoint_doc(), IntentKind::Endpoint, "e.json");
+        assert!(result.is_valid(), "{:?}", result.errors);
+    }
+
+    #[test]
+    fn test_tags_checked_as_slugs() {
+        let mut doc = endpoint_doc();
+        doc["tags"] = serde_json::json!(["payments", "lifecycle-ga"]);
+        let result = validate_schema_value(&doc, IntentKind::Endpoint, "e.json");
+        assert!(result.is_valid(), "{:?}", result.errors);
+
+        doc["tags"] = serde_json::json!(["payments", "Lifecycle GA"]);
+        let result = validate_schema_value(&doc, IntentKind::Endpoint, "e.json");
+
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].code, codes::E011_SCHEMA_VIOLATION);
+        assert_eq!(result.errors[0].location.as_ref().unwrap().path, "$.tags[1]");
+    }
+
+    #[test]
+    fn test_additional_property_reported_with_path() {
+        let mut doc = endpoint_doc();
+        doc["spec"]["policies"] = serde_json::json!({ "timeout_ms": 100, "timeuot": 5 });
+
+        let result = validate_schema_value(&doc, IntentKind::Endpoint, "e.json");
+
+        assert_eq!(result.errors.len(), 1);
+        let error = &result.errors[0];
+        assert_eq!(error.code, codes::E011_SCHEMA_VIOLATION);
+        assert!(error.message.contains("'timeuot'"));
+        assert_eq!(
+            error.location.as_ref().unwrap().path,
+            "$.spec.policies.timeuot"
+        );
+    }
+
+    #[test]
+    fn test_missing_required_reported_with_path() {
+        let mut doc = endpoint_doc();
+        doc["spec"].as_object_mut().unwrap().remove("workflow");
+
+        let result = validate_schema_value(&doc, IntentKind::Endpoint, "e.json");
+
+        assert_eq!(result.errors.len(), 1);
+        let error = &result.errors[0];
+        assert_eq!(error.code, codes::E002_MISSING_FIELD);
+        assert!(error.message.contains("'workflow'"));
+        assert_eq!(error.location.as_ref().unwrap().path, "$.spec.workflow");
+    }
+
+    #[test]
+    fn test_nested_tagged_step_checked() {
+        let doc = serde_json::json!({
+            "schema_version": "1.0",
+            "id": "550e8400-e29b-41d4-a716-446655440006",
+            "kind": "Workflow",
+            "name": "RefundWorkflow",
+            "spec": {
+                "input": "RefundRequest",
+                "output": "RefundResponse",
+                "steps": [
+                    { "kind": "Transform", "name": "validate", "asign": {} }
+                ]
+            }
+        });
+
+        let result = validate_schema_value(&doc, IntentKind::Workflow, "w.json");
+
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(
+            result.errors[0].location.as_ref().unwrap().path,
+            "$.spec.steps[0].asign"
+        );
+    }
+}
@@ -1,6 +1,11 @@
 //! Security checks phase
 
-use crate::model::{codes, IntentDocument, IntentKind, StructuredLocation};
+use std::collections::BTreeSet;
+
+use crate::model::{
+    codes, AuthzConfig, AuthzScopes, EffectKind, FieldDef, IntentDocument, IntentKind,
+    ScopeRequirement, StructuredLocation, WorkflowStep,
+};
 use crate::parser::IntentStore;
 
 use super::ValidationResult;
@@ -37,6 +42,10 @@ pub fn check_security(store: &IntentStore) -> ValidationResult {
         if doc.kind == IntentKind::Type {
             check_type_pii(doc, &mut result);
         }
+
+        if doc.kind == IntentKind::Workflow {
+            check_emitted_pii(doc, store, &mut result);
+        }
     }
 
     result
@@ -61,48 +70,142 @@ fn check_endpoint_security(doc: &IntentDocument, result: &mut ValidationResult)
 
     // Check authz scope is not overly broad
     if let Some(ref authz) = spec.authz {
-        if authz.scope == "*" || authz.scope == "admin" {
+        for (i, scope) in authz.scope.as_slice().iter().enumerate() {
+            if !is_broad_scope(scope) {
+                continue;
+            }
+            let path = match authz.scope {
+                AuthzScopes::One(_) => "$.spec.authz.scope".to_string(),
+                AuthzScopes::Many(_) => format!("$.spec.authz.scope[{}]", i),
+            };
             result.add_warning(
                 "W002",
                 format!(
                     "Endpoint '{}' has broad authorization scope: {}",
-                    doc.name, authz.scope
+                    doc.name, scope
                 ),
                 Some(StructuredLocation {
                     file: doc.source_file.clone().unwrap_or_default(),
-                    path: "$.spec.authz.scope".to_string(),
+                    path,
                 }),
             );
         }
     }
 }
 
+fn is_broad_scope(scope: &str) -> bool {
+    scope == "*" || scope == "admin"
+}
+
 fn check_type_pii(doc: &IntentDocument, result: &mut ValidationResult) {
     let Ok(spec) = doc.as_type_spec() else {
         return;
     };
 
-    for field_name in spec.fields.keys() {
-        let lower_name = field_name.to_lowercase();
-        for pattern in PII_PATTERNS {
-            if lower_name.contains(pattern) {
+    for (field_name, field) in &spec.fields {
+        // An explicit `sensitive` marker means the field has been reviewed
+        if field.sensitive.is_some() {
+            continue;
+        }
+        if let Some(pattern) = pii_pattern(field_name) {
+            result.add_warning(
+                "W003",
+                format!(
+                    "Field '{}' in type '{}' may contain PII (matches pattern '{}')",
+                    field_name, doc.name, pattern
+                ),
+                Some(StructuredLocation {
+                    file: doc.source_file.clone().unwrap_or_default(),
+                    path: format!("$.spec.fields.{}", field_name),
+                }),
+            );
+        }
+    }
+}
+
+/// Warn (W004) about each EmitEvent step whose `input_mapping` reads a
+/// sensitive field of the workflow's input Type. Only direct `input.<field>`
+/// reads are followed, not values copied into the context first.
+fn check_emitted_pii(doc: &IntentDocument, store: &IntentStore, result: &mut ValidationResult) {
+    let Ok(spec) = doc.as_workflow_spec() else {
+        return;
+    };
+    let Some(input) = store
+        .get_by_kind_name(IntentKind::Type, &spec.input)
+        .and_then(|input| input.as_type_spec().ok())
+    else {
+        return;
+    };
+
+    for (i, step) in spec.steps.iter().enumerate() {
+        let WorkflowStep::Effect(effect) = step else {
+            continue;
+        };
+        if effect.effect != EffectKind::EmitEvent {
+            continue;
+        }
+        let topic = effect.topic.as_deref().unwrap_or("?");
+
+        let mut mapping: Vec<_> = effect.input_mapping.iter().collect();
+        mapping.sort();
+        for (target, source) in mapping {
+            let Ok(paths) = crate::simulate::variable_paths(source) else {
+                continue;
+            };
+            // Mapping the whole `input` emits every one of its fields
+            let mut fields = BTreeSet::new();
+            for path in &paths {
+                if path == "input" {
+                    fields.extend(input.fields.keys().map(String::as_str));
+                } else if let Some(rest) = path.strip_prefix("input.") {
+                    fields.insert(rest.split('.').next().unwrap_or(rest));
+                }
+            }
+
+            for field_name in fields {
+                let Some(field) = input.fields.get(field_name) else {
+                    continue;
+                };
+                if !is_sensitive_field(field_name, field) {
+                    continue;
+                }
                 result.add_warning(
-                    "W003",
+                    "W004",
                     format!(
-                        "Field '{}' in type '{}' may contain PII (matches pattern '{}')",
-                        field_name, doc.name, pattern
+                        "PII field '{}' of type '{}' is emitted to topic '{}' by workflow '{}' ({})",
+                        field_name,
+                        spec.input,
+                        topic,
+                        doc.name,
+                        step.label(i)
                     ),
                     Some(StructuredLocation {
                         file: doc.source_file.clone().unwrap_or_default(),
-                        path: format!("$.spec.fields.{}", field_name),
+                        path: format!("$.spec.steps[{}].input_mapping.{}", i, target),
                     }),
                 );
-                break;
             }
         }
     }
 }
 
+/// The PII pattern a field name matches, if any
+pub fn pii_pattern(field_name: &str) -> Option<&'static str> {
+    let lower_name = field_name.to_lowercase();
+    PII_PATTERNS
+        .iter()
+        .copied()
+        .find(|pattern| lower_name.contains(pattern))
+}
+
+/// Whether generated `Debug` output must redact a field: the explicit
+/// `sensitive` marker, or a PII-looking name when the marker is absent
+pub fn is_sensitive_field(field_name: &str, field: &FieldDef) -> bool {
+    field
+        .sensitive
+        .unwrap_or_else(|| pii_pattern(field_name).is_some())
+}
+
 /// Check for authz scope widening between two versions
 pub fn check_authz_widening(
     old_doc: &IntentDocument,
@@ -111,24 +214,160 @@ pub fn check_authz_widening(
     let old_spec = old_doc.as_endpoint_spec().ok()?;
     let new_spec = new_doc.as_endpoint_spec().ok()?;
 
-    let old_scope = old_spec.authz.as_ref().map(|a| &a.scope);
-    let new_scope = new_spec.authz.as_ref().map(|a| &a.scope);
-
-    match (old_scope, new_scope) {
-        (Some(old), Some(new)) if old != new => {
-            // Check if new scope is broader
-            if new == "*" || new == "admin" || new.contains("write") && !old.contains("write") {
-                return Some(format!(
-                    "AuthZ scope widened from '{}' to '{}'",
-                    old, new
-                ));
-            }
-        }
-        (Some(old), None) => {
-            return Some(format!("AuthZ removed (was scope '{}')", old));
+    match (&old_spec.authz, &new_spec.authz) {
+        (Some(old), Some(new)) if old != new && is_wider(old, new) => Some(format!(
+            "AuthZ scope widened from {} to {}",
+            old.describe(),
+            new.describe()
+        )),
+        (Some(old), None) => Some(format!("AuthZ removed (was scope {})", old.describe())),
+        _ => None,
+    }
+}
+
+/// Whether `new` admits a caller `old` would have rejected, or newly
+/// requires a broad or write scope
+fn is_wider(old: &AuthzConfig, new: &AuthzConfig) -> bool {
+    let old_scopes: BTreeSet<&str> = old.scope.as_slice().iter().map(String::as_str).collect();
+    let new_scopes: BTreeSet<&str> = new.scope.as_slice().iter().map(String::as_str).collect();
+
+    let added_broad = new_scopes
+        .difference(&old_scopes)
+        .any(|scope| is_broad_scope(scope));
+    let added_write = new_scopes.iter().any(|s| s.contains("write"))
+        && !old_scopes.iter().any(|s| s.contains("write"));
+    if added_broad || added_write {
+        return true;
+    }
+
+    // With a single scope `all` and `any` mean the same
+    let requirement = |scopes: &BTreeSet<&str>, require| {
+        if scopes.len() == 1 {
+            ScopeRequirement::All
+        } else {
+            require
         }
-        _ => {}
+    };
+    let at_least_as_strict = match (
+        requirement(&old_scopes, old.require),
+        requirement(&new_scopes, new.require),
+    ) {
+        // Every scope the old rule required is still required
+        (ScopeRequirement::All, ScopeRequirement::All) => old_scopes.is_subset(&new_scopes),
+        // No new alternative scope is accepted
+        (ScopeRequirement::Any, ScopeRequirement::Any) => new_scopes.is_subset(&old_scopes),
+        // Holding all new scopes includes one of the old ones
+        (ScopeRequirement::Any, ScopeRequirement::All) => !new_scopes.is_disjoint(&old_scopes),
+        // Any one of several scopes never implies holding all the old ones
+        (ScopeRequirement::All, ScopeRequirement::Any) => false,
+    };
+    !at_least_as_strict
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn endpoint(authz: serde_json::Value) -> IntentDocument {
+        IntentDocument::with_spec(
+            IntentKind::Endpoint,
+            "CreateRefund".to_string(),
+            json!({
+                "method": "POST",
+                "path": "/refund",
+                "input": "RefundRequest",
+                "output": "RefundResponse",
+                "workflow": "RefundWorkflow",
+                "authz": authz
+            }),
+        )
+    }
+
+    fn widening(old: serde_json::Value, new: serde_json::Value) -> Option<String> {
+        check_authz_widening(&endpoint(old), &endpoint(new))
     }
 
-    None
+    #[test]
+    fn test_authz_widening_across_scope_sets() {
+        let all = json!({ "principal": "user", "scope": ["refund:read", "refund:audit"] });
+        let any = json!({ "principal": "user", "scope": ["refund:read", "refund:audit"], "require": "any" });
+
+        // Switching from all to any of the same scopes admits more callers
+        assert_eq!(
+            widening(all.clone(), any.clone()).as_deref(),
+            Some("AuthZ scope widened from all of ['refund:read', 'refund:audit'] to any of ['refund:read', 'refund:audit']")
+        );
+        assert_eq!(widening(any.clone(), all.clone()), None);
+
+        // Dropping a required scope widens; requiring another narrows
+        let fewer = json!({ "principal": "user", "scope": "refund:read" });
+        assert!(widening(all.clone(), fewer.clone()).is_some());
+        assert_eq!(widening(fewer.clone(), all.clone()), None);
+
+        // Accepting another alternative widens; accepting fewer narrows
+        let more = json!({ "principal": "user", "scope": ["refund:read", "refund:audit", "refund:ops"], "require": "any" });
+        assert!(widening(any.clone(), more.clone()).is_some());
+        assert_eq!(widening(more, any.clone()), None);
+
+        // A broad scope is widening even when added to an `all` list
+        let admin = json!({ "principal": "user", "scope": ["refund:read", "refund:audit", "admin"] });
+        assert!(widening(all.clone(), admin).is_some());
+
+        assert_eq!(
+            check_authz_widening(&endpoint(any), &endpoint(serde_json::Value::Null)).as_deref(),
+            Some("AuthZ removed (was scope any of ['refund:read', 'refund:audit'])")
+        );
+    }
+
+    #[test]
+    fn test_pii_emitted_to_topic() {
+        let mut store = IntentStore::load_from_path("fixtures/valid").unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Type,
+                "SignupRequest".to_string(),
+                json!({ "fields": {
+                    "email": { "type": "string", "sensitive": true },
+                    "plan": { "type": "string" },
+                    "contact_phone": { "type": "string" }
+                } }),
+            ))
+            .unwrap();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Workflow,
+                "SignupWorkflow".to_string(),
+                json!({
+                    "input": "SignupRequest",
+                    "output": "RefundResponse",
+                    "steps": [{
+                        "kind": "Effect",
+                        "name": "announce",
+                        "effect": "EmitEvent",
+                        "topic": "signups",
+                        "input_mapping": {
+                            "contact": "input.email",
+                            "plan": "input.plan"
+                        }
+                    }]
+                }),
+            ))
+            .unwrap();
+
+        let warnings: Vec<_> = check_security(&store)
+            .warnings
+            .into_iter()
+            .filter(|w| w.code == "W004")
+            .collect();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].message,
+            "PII field 'email' of type 'SignupRequest' is emitted to topic 'signups' by workflow 'SignupWorkflow' (step 'announce')"
+        );
+        assert_eq!(
+            warnings[0].location.as_ref().unwrap().path,
+            "$.spec.steps[0].input_mapping.contact"
+        );
+    }
 }
@@ -1,10 +1,14 @@
 //! Type checking phase
 
+use std::collections::{HashMap, HashSet};
+
 use crate::model::{
-    codes, EffectKind, IntentDocument, IntentKind, StructuredLocation, TypeRef, WorkflowStep,
+    codes, EffectKind, ExpectedResponse, Expression, HttpMethod, IntentDocument, IntentError, IntentKind, ModuleSpec,
+    Pattern, StructuredLocation, TypeRef, VariantData, WorkflowStep,
 };
 use crate::parser::IntentStore;
 
+use super::raises::call_key;
 use super::ValidationResult;
 
 /// Native Rust types and engine types that don't need to be defined as Type intents
@@ -31,7 +35,7 @@ const NATIVE_TYPES: &[&str] = &[
     "WorkflowSpec", "WorkflowStep", "TransformStep", "EffectStep", "RaiseCondition",
     "EffectKind", "OnErrorStrategy",
     "EndpointSpec", "EndpointPolicies", "RetryPolicy", "BackoffStrategy", "AuthzConfig", "EndpointError",
-    "ContractTestSpec", "ContractScenario", "ContractResponse",
+    "ContractTestSpec", "ContractScenario", "ExpectedResponse", "ContractResponse",
     "MigrationSpec", "MigrationOperation", "ColumnDef",
     "ValidationResult", "StructuredError", "StructuredLocation", "Severity",
     "GenerationResult", "VerificationResult",
@@ -65,6 +69,7 @@ pub fn typecheck(store: &IntentStore) -> ValidationResult {
     let mut result = ValidationResult::new();
 
     for doc in store.iter() {
+        check_tags(doc, &mut result);
         match doc.kind {
             // v1 Domain Kinds
             IntentKind::Type => typecheck_type(doc, store, &mut result),
@@ -78,15 +83,58 @@ pub fn typecheck(store: &IntentStore) -> ValidationResult {
             IntentKind::Pipeline => typecheck_pipeline(doc, store, &mut result),
             IntentKind::Template => typecheck_template(doc, &mut result),
             IntentKind::Enum => typecheck_enum(doc, &mut result),
-            IntentKind::Module => typecheck_module(doc, &mut result),
+            IntentKind::Module => typecheck_module(doc, store, &mut result),
             IntentKind::Command => typecheck_command(doc, store, &mut result),
             IntentKind::Trait => typecheck_trait(doc, store, &mut result),
         }
     }
 
+    check_module_file_collisions(store, &mut result);
+
     result
 }
 
+/// Tags must be distinct slugs: lowercase words of ASCII letters and digits
+/// joined by single hyphens
+fn check_tags(doc: &IntentDocument, result: &mut ValidationResult) {
+    let mut seen = HashSet::new();
+    for (i, tag) in doc.tags.iter().enumerate() {
+        let is_slug = tag
+            .split('-')
+            .all(|word| !word.is_empty() && word.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+        let problem = if !is_slug {
+            "is not a slug (lowercase letters and digits joined by single hyphens)"
+        } else if !seen.insert(tag) {
+            "is listed twice"
+        } else {
+            continue;
+        };
+        result.add_error(
+            codes::E012_INVALID_TAG,
+            format!("Tag '{}' of {} '{}' {}", tag, doc.kind, doc.name, problem),
+            location(doc, &format!("$.tags[{}]", i)),
+        );
+    }
+}
+
+/// Endpoints or Workflows whose names snake-case to the same generated file;
+/// reported on every intent after the first by name
+fn check_module_file_collisions(store: &IntentStore, result: &mut ValidationResult) {
+    for (file, docs) in crate::codegen::module_file_collisions(store) {
+        let first = docs[0];
+        for doc in &docs[1..] {
+            result.add_error(
+                codes::E010_DUPLICATE_NAME,
+                format!(
+                    "{} '{}' generates '{}', which {} '{}' also generates",
+                    doc.kind, doc.name, file, first.kind, first.name
+                ),
+                location(doc, "$.name"),
+            );
+        }
+    }
+}
+
 fn typecheck_type(doc: &IntentDocument, store: &IntentStore, result: &mut ValidationResult) {
     let Ok(spec) = doc.as_type_spec() else {
         result.add_error(
@@ -97,6 +145,17 @@ fn typecheck_type(doc: &IntentDocument, store: &IntentStore, result: &mut Valida
         return;
     };
 
+    if spec.fields.is_empty() && !spec.unit {
+        result.add_warning(
+            "W017",
+            format!(
+                "Type '{}' has no fields; add them, or set \"unit\": true if it is a marker type",
+                doc.name
+            ),
+            location(doc, "$.spec.fields"),
+        );
+    }
+
     for (field_name, field_def) in &spec.fields {
         // Check that type references exist
         for type_name in field_def.field_type.get_named_references() {
@@ -115,6 +174,93 @@ fn typecheck_type(doc: &IntentDocument, store: &IntentStore, result: &mut Valida
                 );
             }
         }
+
+        // Endpoints never receive a server-assigned field
+        if field_def.readonly && field_def.required {
+            result.add_error(
+                codes::E009_INVALID_MAPPING,
+                format!(
+                    "Readonly field '{}' of Type '{}' must not be required; requests never carry it",
+                    field_name, doc.name
+                ),
+                location(doc, &format!("$.spec.fields.{}.readonly", field_name)),
+            );
+        }
+
+        if let Err(reason) = field_def.check_rust_type_override() {
+            result.add_error(
+                codes::E007_TYPE_MISMATCH,
+                format!(
+                    "rust_type_override of field '{}' in Type '{}' is invalid: {}",
+                    field_name, doc.name, reason
+                ),
+                location(doc, &format!("$.spec.fields.{}.rust_type_override", field_name)),
+            );
+        }
+
+        if let Some(default) = &field_def.default {
+            // A null default on an optional field is just `None`
+            let nullable = !field_def.required || matches!(field_def.field_type, TypeRef::Optional(_));
+            let fits = (default.is_null() && nullable) || default_fits(default, &field_def.field_type, store);
+            if !fits {
+                result.add_error(
+                    codes::E007_TYPE_MISMATCH,
+                    format!(
+                        "Default {} of field '{}' is not a valid '{}'",
+                        default, field_name, field_def.field_type
+                    ),
+                    location(doc, &format!("$.spec.fields.{}.default", field_name)),
+                );
+            }
+        }
+    }
+}
+
+/// Whether JSON `value` deserializes as `type_ref`. Named types only have
+/// their JSON shape checked; unknown names are reported as E005 elsewhere.
+fn default_fits(value: &serde_json::Value, type_ref: &TypeRef, store: &IntentStore) -> bool {
+    use serde_json::Value;
+
+    let int_in = |min: i64, max: i64| value.as_i64().is_some_and(|n| (min..=max).contains(&n));
+    match type_ref {
+        TypeRef::String | TypeRef::DateTime | TypeRef::Bytes => value.is_string(),
+        TypeRef::Int => value.is_i64(),
+        TypeRef::Int8 => int_in(i8::MIN.into(), i8::MAX.into()),
+        TypeRef::Int16 => int_in(i16::MIN.into(), i16::MAX.into()),
+        TypeRef::Int32 => int_in(i32::MIN.into(), i32::MAX.into()),
+        TypeRef::UInt8 => int_in(0, u8::MAX.into()),
+        TypeRef::UInt16 => int_in(0, u16::MAX.into()),
+        TypeRef::UInt32 => int_in(0, u32::MAX.into()),
+        TypeRef::Float => value.is_number(),
+        TypeRef::Bool => value.is_boolean(),
+        TypeRef::Money => match value {
+            Value::Number(_) => true,
+            Value::String(s) => s.parse::<f64>().is_ok(),
+            _ => false,
+        },
+        TypeRef::Uuid => value.as_str().is_some_and(|s| uuid::Uuid::parse_str(s).is_ok()),
+        TypeRef::Array(inner) => value
+            .as_array()
+            .is_some_and(|items| items.iter().all(|item| default_fits(item, inner, store))),
+        TypeRef::FixedArray(inner, size) => value.as_array().is_some_and(|items| {
+            items.len() == *size && items.iter().all(|item| default_fits(item, inner, store))
+        }),
+        TypeRef::Tuple(elements) => value.as_array().is_some_and(|items| {
+            items.len() == elements.len()
+                && items
+                    .iter()
+                    .zip(elements)
+                    .all(|(item, element)| default_fits(item, element, store))
+        }),
+        TypeRef::Map(_, v) => value
+            .as_object()
+            .is_some_and(|entries| entries.values().all(|entry| default_fits(entry, v, store))),
+        TypeRef::Optional(inner) => value.is_null() || default_fits(value, inner, store),
+        TypeRef::Named(name) => match store.get_type_or_enum(name).map(|doc| doc.kind) {
+            Some(IntentKind::Type) => value.is_object(),
+            Some(_) => value.is_string() || value.is_object(),
+            None => true,
+        },
     }
 }
 
@@ -140,46 +286,126 @@ fn typecheck_workflow(doc: &IntentDocument, store: &IntentStore, result: &mut Va
         );
     }
 
-    // Check output type exists
-    if store
-        .get_by_kind_name(IntentKind::Type, &spec.output)
-        .is_none()
-    {
-        result.add_error(
+    // Check output type exists; an Enum output must serialize unambiguously
+    match store.get_type_or_enum(&spec.output) {
+        Some(output) if output.kind == IntentKind::Enum => {
+            check_enum_output(doc, output, result);
+        }
+        Some(_) => {}
+        None => result.add_error(
             codes::E005_UNKNOWN_REFERENCE,
             format!("Unknown output type: {}", spec.output),
             location(doc, "$.spec.output"),
-        );
+        ),
     }
 
-    // Check context types
+    // Check context types: each named reference must be a Type or Enum
     for (name, type_ref) in &spec.context {
         for type_name in type_ref.get_named_references() {
-            if store
-                .get_by_kind_name(IntentKind::Type, type_name)
-                .is_none()
-            {
-                result.add_error(
+            if store.get_type_or_enum(type_name).is_some() {
+                continue;
+            }
+            match store.find_by_name(type_name) {
+                Some(other) => result.add_error(
+                    codes::E007_TYPE_MISMATCH,
+                    format!(
+                        "Context field '{}' has type '{}', an intent of kind {:?}, not a Type or Enum",
+                        name, type_name, other.kind
+                    ),
+                    location(doc, &format!("$.spec.context.{}", name)),
+                ),
+                None => result.add_error(
                     codes::E005_UNKNOWN_REFERENCE,
                     format!("Unknown type '{}' in context field '{}'", type_name, name),
                     location(doc, &format!("$.spec.context.{}", name)),
+                ),
+            }
+        }
+    }
+
+    // Step names must be unique within a workflow
+    let mut seen_steps = std::collections::HashSet::new();
+    for (i, step) in spec.steps.iter().enumerate() {
+        if let Some(name) = step.name() {
+            if !seen_steps.insert(name) {
+                result.add_error(
+                    codes::E010_DUPLICATE_NAME,
+                    format!("Duplicate step name '{}' in workflow '{}'", name, doc.name),
+                    location(doc, &format!("$.spec.steps[{}].name", i)),
                 );
             }
         }
     }
 
-    // Check steps
+    // Check steps; `bound` holds what earlier steps put in the context
+    let input_spec = store
+        .get_by_kind_name(IntentKind::Type, &spec.input)
+        .and_then(|d| d.as_type_spec().ok());
+    let mut bound: HashSet<&str> = HashSet::new();
     for (i, step) in spec.steps.iter().enumerate() {
+        let step_label = step.label(i);
+
+        if let Some(condition) = step.when() {
+            let at = location(doc, &format!("$.spec.steps[{}].when", i));
+            if let Some(problem) = unresolved_source(store, &spec, &bound, condition) {
+                result.add_error(
+                    codes::E009_INVALID_MAPPING,
+                    format!("Condition of {} {}", step_label, problem),
+                    at,
+                );
+            } else if super::is_always_skipped(step) {
+                result.add_warning(
+                    "W020",
+                    format!("Condition '{}' of {} is always false, so the step never runs", condition, step_label),
+                    at,
+                );
+            }
+        }
+
         match step {
             WorkflowStep::Transform(t) => {
+                let mut targets: Vec<_> = t.assign.iter().collect();
+                targets.sort();
+                for (target, source) in targets {
+                    let at = location(doc, &format!("$.spec.steps[{}].assign.{}", i, target));
+                    if let Some(problem) = unresolved_source(store, &spec, &bound, source) {
+                        result.add_error(
+                            codes::E009_INVALID_MAPPING,
+                            format!("Source of '{}' in {} {}", target, step_label, problem),
+                            at,
+                        );
+                        continue;
+                    }
+
+                    // A plain read is assigned as is, so its type must be the
+                    // context field's, give or take `optional`
+                    let (Some(target_type), Some(source_type)) = (
+                        spec.context.get(target),
+                        spec.path_source_type(source, input_spec.as_ref()),
+                    ) else {
+                        continue;
+                    };
+                    let (source_base, target_base) = (source_type.strip_optional().0, target_type.strip_optional().0);
+                    if source_base != target_base {
+                        result.add_error(
+                            codes::E007_TYPE_MISMATCH,
+                            format!(
+                                "Source '{}' of '{}' in {} has type '{}', but context field '{}' is '{}'",
+                                source, target, step_label, source_base, target, target_base
+                            ),
+                            at,
+                        );
+                    }
+                }
+
                 // Validate assignments reference valid context fields
                 for (target, _source) in &t.assign {
                     if !spec.context.contains_key(target) {
                         result.add_warning(
                             codes::E009_INVALID_MAPPING,
                             format!(
-                                "Assignment target '{}' is not declared in context",
-                                target
+                                "Assignment target '{}' is not declared in context (in {})",
+                                target, step_label
                             ),
                             location(doc, &format!("$.spec.steps[{}].assign.{}", i, target)),
                         );
@@ -196,14 +422,14 @@ fn typecheck_workflow(doc: &IntentDocument, store: &IntentStore, result: &mut Va
                         {
                             result.add_error(
                                 codes::E005_UNKNOWN_REFERENCE,
-                                format!("Unknown service: {}", service_name),
+                                format!("Unknown service: {} (in {})", service_name, step_label),
                                 location(doc, &format!("$.spec.steps[{}].service", i)),
                             );
                         }
                     } else {
                         result.add_error(
                             codes::E002_MISSING_FIELD,
-                            "HttpCall effect requires 'service' field",
+                            format!("HttpCall effect requires 'service' field (in {})", step_label),
                             location(doc, &format!("$.spec.steps[{}]", i)),
                         );
                     }
@@ -215,8 +441,8 @@ fn typecheck_workflow(doc: &IntentDocument, store: &IntentStore, result: &mut Va
                         result.add_warning(
                             codes::E009_INVALID_MAPPING,
                             format!(
-                                "Output binding '{}' is not declared in context",
-                                binding
+                                "Output binding '{}' is not declared in context (in {})",
+                                binding, step_label
                             ),
                             location(doc, &format!("$.spec.steps[{}].output_binding", i)),
                         );
@@ -224,7 +450,134 @@ fn typecheck_workflow(doc: &IntentDocument, store: &IntentStore, result: &mut Va
                 }
             }
         }
+
+        match step {
+            WorkflowStep::Transform(t) => bound.extend(t.assign.keys().map(String::as_str)),
+            WorkflowStep::Effect(e) => bound.extend(e.output_binding.as_deref()),
+        }
     }
+
+    check_output_coverage(doc, store, &spec, result);
+}
+
+/// Warn (E009) when required fields of the workflow's output Type are never
+/// populated. A field is populated by a step that assigns or binds a context
+/// value of the same name, and all of them by one that assigns or binds a
+/// whole value of the output type. Fields with a default don't need a step.
+fn check_output_coverage(
+    doc: &IntentDocument,
+    store: &IntentStore,
+    spec: &crate::model::WorkflowSpec,
+    result: &mut ValidationResult,
+) {
+    let Some(output) = store
+        .get_by_kind_name(IntentKind::Type, &spec.output)
+        .and_then(|output| output.as_type_spec().ok())
+    else {
+        return;
+    };
+
+    let is_output = |name: &str| {
+        matches!(spec.context.get(name), Some(TypeRef::Named(type_name)) if *type_name == spec.output)
+    };
+    let mut populated: HashSet<&str> = HashSet::new();
+    let mut whole = false;
+    for step in spec.steps.iter().filter(|step| !super::is_always_skipped(step)) {
+        match step {
+            WorkflowStep::Transform(t) => {
+                for target in t.assign.keys() {
+                    populated.insert(target);
+                    whole |= is_output(target);
+                }
+            }
+            WorkflowStep::Effect(e) => {
+                let Some(binding) = &e.output_binding else {
+                    continue;
+                };
+                populated.insert(binding);
+                whole |= is_output(binding) || operation_output(store, e).as_ref() == Some(&spec.output);
+            }
+        }
+    }
+    if whole {
+        return;
+    }
+
+    let mut missing: Vec<&str> = output
+        .fields
+        .iter()
+        .filter(|(name, field)| field.required && field.default.is_none() && !populated.contains(name.as_str()))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+    missing.sort();
+
+    let fields = missing.iter().map(|name| format!("'{}'", name)).collect::<Vec<_>>().join(", ");
+    let message = if spec.steps.is_empty() {
+        format!(
+            "Workflow '{}' has no steps, so required fields {} of output type '{}' are never assigned",
+            doc.name, fields, spec.output
+        )
+    } else {
+        format!(
+            "Required fields {} of output type '{}' are never assigned or bound by a step of workflow '{}'",
+            fields, spec.output, doc.name
+        )
+    };
+    result.add_warning(codes::E009_INVALID_MAPPING, message, location(doc, "$.spec.output"));
+}
+
+/// Output type of the service operation an HttpCall step invokes
+fn operation_output(store: &IntentStore, e: &crate::model::EffectStep) -> Option<String> {
+    if e.effect != EffectKind::HttpCall {
+        return None;
+    }
+    let service = store.get_by_kind_name(IntentKind::Service, e.service.as_deref()?)?;
+    let spec = service.as_service_spec().ok()?;
+    Some(spec.operations.get(e.operation.as_deref()?)?.output.clone())
+}
+
+/// Why a Transform source or step condition doesn't resolve, if it doesn't. Every
+/// path it reads must start at `input`, at `context` (a declared field or one
+/// an earlier step bound) or at an earlier binding, and fields below a typed
+/// root must exist.
+fn unresolved_source(
+    store: &IntentStore,
+    spec: &crate::model::WorkflowSpec,
+    bound: &HashSet<&str>,
+    source: &str,
+) -> Option<String> {
+    let paths = match crate::simulate::variable_paths(source) {
+        Ok(paths) => paths,
+        Err(e) => return Some(format!("does not parse: {}", e)),
+    };
+
+    for path in paths {
+        let segments: Vec<&str> = path.split('.').collect();
+        let (root_type, fields) = match segments[..] {
+            ["input", ref fields @ ..] => (Some(spec.input.clone()), fields),
+            ["context"] => (None, &[][..]),
+            ["context", field, ref fields @ ..] => match spec.context.get(field) {
+                Some(type_ref) => (Some(type_ref.to_string()), fields),
+                None if bound.contains(field) => (None, fields),
+                None => {
+                    return Some(format!(
+                        "reads '{}', which is neither declared in context nor bound by an earlier step",
+                        path
+                    ))
+                }
+            },
+            [binding, ..] if bound.contains(binding) => (None, &[][..]),
+            [root, ..] => return Some(format!("reads unknown variable '{}'", root)),
+            [] => continue,
+        };
+        if let Some(missing) = root_type.and_then(|type_name| missing_field(store, &type_name, fields)) {
+            return Some(format!("reads '{}', which does not resolve: {}", path, missing));
+        }
+    }
+    None
 }
 
 fn typecheck_endpoint(doc: &IntentDocument, store: &IntentStore, result: &mut ValidationResult) {
@@ -249,16 +602,17 @@ fn typecheck_endpoint(doc: &IntentDocument, store: &IntentStore, result: &mut Va
         );
     }
 
-    // Check output type exists
-    if store
-        .get_by_kind_name(IntentKind::Type, &spec.output)
-        .is_none()
-    {
-        result.add_error(
+    // Check output type exists; an Enum output must serialize unambiguously
+    match store.get_type_or_enum(&spec.output) {
+        Some(output) if output.kind == IntentKind::Enum => {
+            check_enum_output(doc, output, result);
+        }
+        Some(_) => {}
+        None => result.add_error(
             codes::E005_UNKNOWN_REFERENCE,
             format!("Unknown output type: {}", spec.output),
             location(doc, "$.spec.output"),
-        );
+        ),
     }
 
     // Check workflow exists
@@ -273,19 +627,90 @@ fn typecheck_endpoint(doc: &IntentDocument, store: &IntentStore, result: &mut Va
         );
     }
 
-    // Check idempotency_key references valid input field
+    // Error codes backed by a shared Enum must be among its variants
+    if let Some(enum_name) = &spec.error_enum {
+        match store.get_type_or_enum(enum_name) {
+            Some(enum_doc) if enum_doc.kind == IntentKind::Enum => {
+                if let Ok(enum_spec) = enum_doc.as_enum_spec() {
+                    for (i, error) in spec.errors.iter().enumerate() {
+                        if enum_spec.find_variant(&error.code).is_none() {
+                            result.add_error(
+                                codes::E005_UNKNOWN_REFERENCE,
+                                format!(
+                                    "Error code '{}' is not a variant of enum '{}'",
+                                    error.code, enum_name
+                                ),
+                                location(doc, &format!("$.spec.errors[{}].code", i)),
+                            );
+                        }
+                    }
+                    if enum_spec.variants.iter().any(|v| v.data.is_some()) {
+                        result.add_error(
+                            codes::E007_TYPE_MISMATCH,
+                            format!("Error enum '{}' must only have unit variants", enum_name),
+                            location(doc, "$.spec.error_enum"),
+                        );
+                    }
+                }
+            }
+            Some(other) => result.add_error(
+                codes::E007_TYPE_MISMATCH,
+                format!("Error enum '{}' is a {}, not an Enum", enum_name, other.kind),
+                location(doc, "$.spec.error_enum"),
+            ),
+            // Reported during reference resolution
+            None => {}
+        }
+    }
+
+    // Partial (patch) input only makes sense for PATCH
+    if spec.partial && spec.method != HttpMethod::Patch {
+        result.add_error(
+            codes::E009_INVALID_MAPPING,
+            format!(
+                "Partial input is only allowed on PATCH endpoints, not {}",
+                spec.method
+            ),
+            location(doc, "$.spec.partial"),
+        );
+    }
+
+    // Check idempotency_key references a present, stable input field
     if let Some(key) = &spec.idempotency_key {
         if let Some(input_type) = store.get_by_kind_name(IntentKind::Type, &spec.input) {
             if let Ok(input_spec) = input_type.as_type_spec() {
-                if !input_spec.fields.contains_key(key) {
-                    result.add_error(
+                match input_spec.fields.get(key) {
+                    None => result.add_error(
                         codes::E009_INVALID_MAPPING,
                         format!(
                             "Idempotency key '{}' not found in input type '{}'",
                             key, spec.input
                         ),
                         location(doc, "$.spec.idempotency_key"),
-                    );
+                    ),
+                    Some(field) => {
+                        // Same types as map keys: compared and hashed exactly
+                        if !field.field_type.is_valid_map_key() {
+                            result.add_error(
+                                codes::E007_TYPE_MISMATCH,
+                                format!(
+                                    "Idempotency key '{}' of input type '{}' is {}; it must be a string, uuid or integer",
+                                    key, spec.input, field.field_type
+                                ),
+                                location(doc, "$.spec.idempotency_key"),
+                            );
+                        }
+                        if !field.required {
+                            result.add_warning(
+                                "W023",
+                                format!(
+                                    "Idempotency key '{}' of input type '{}' is not required, so requests without it are not deduplicated",
+                                    key, spec.input
+                                ),
+                                location(doc, "$.spec.idempotency_key"),
+                            );
+                        }
+                    }
                 }
             }
         }
@@ -339,6 +764,43 @@ fn typecheck_contract_test(
         return;
     };
 
+    for (i, scenario) in spec.scenarios.iter().enumerate() {
+        let responses = scenario.response.candidates();
+        if responses.is_empty() {
+            result.add_error(
+                codes::E002_MISSING_FIELD,
+                format!("Scenario '{}' lists no expected responses", scenario.name),
+                location(doc, &format!("$.spec.scenarios[{}].response", i)),
+            );
+        }
+
+        // Header names are case-insensitive, so `Content-Type` and
+        // `content-type` would assert the same header twice
+        for (j, response) in responses.iter().enumerate() {
+            let mut names: Vec<_> = response.headers.keys().collect();
+            names.sort();
+            let mut seen = HashSet::new();
+            for name in names {
+                if !seen.insert(name.to_ascii_lowercase()) {
+                    let path = match &scenario.response {
+                        ExpectedResponse::One(_) => format!("$.spec.scenarios[{}].response.headers", i),
+                        ExpectedResponse::AnyOf(_) => {
+                            format!("$.spec.scenarios[{}].response[{}].headers", i, j)
+                        }
+                    };
+                    result.add_error(
+                        codes::E010_DUPLICATE_NAME,
+                        format!(
+                            "Scenario '{}' asserts header '{}' more than once",
+                            scenario.name, name
+                        ),
+                        location(doc, &path),
+                    );
+                }
+            }
+        }
+    }
+
     // Check service exists
     let service = store.get_by_kind_name(IntentKind::Service, &spec.service);
     if service.is_none() {
@@ -443,18 +905,267 @@ fn typecheck_function(doc: &IntentDocument, store: &IntentStore, result: &mut Va
         );
     }
 
-    // Validate the body expression exists and has proper structure
-    typecheck_expression(&spec.body, doc, "$.spec.body", result);
+    // Check the names the body uses resolve
+    let mut check = ExpressionCheck {
+        doc,
+        store,
+        error_codes: declared_error_codes(store),
+        result,
+    };
+    let mut scope: Vec<&str> = spec.parameters.iter().map(|p| p.name.as_str()).collect();
+    check.expression(&spec.body, "$.spec.body", &mut scope);
 }
 
-fn typecheck_expression(
-    _expr: &crate::model::Expression,
-    _doc: &IntentDocument,
-    _path: &str,
-    _result: &mut ValidationResult,
-) {
-    // Expression validation is complex and will be expanded as needed
-    // For now, we rely on serde deserialization to validate structure
+/// Variables a Function body may read without binding them
+const CONTEXT_VARIABLES: &[&str] = &["self", "input", "context"];
+
+/// Free functions available to generated code without a Function intent
+const BUILTIN_CALLS: &[&str] = &["Ok", "Err", "Some", "format", "now", "uuid_generate", "uuid_v4"];
+
+/// Error codes a `Raise` may name: every Endpoint's declared errors, the
+/// variants of their shared error enums, and Workflow `raise_if` codes
+fn declared_error_codes(store: &IntentStore) -> HashSet<String> {
+    let mut codes = HashSet::new();
+    for doc in store.endpoints() {
+        let Ok(spec) = doc.as_endpoint_spec() else {
+            continue;
+        };
+        codes.extend(spec.errors.into_iter().map(|e| e.code));
+        let Some(enum_spec) = spec
+            .error_enum
+            .and_then(|name| store.get_by_kind_name(IntentKind::Enum, &name))
+            .and_then(|doc| doc.as_enum_spec().ok())
+        else {
+            continue;
+        };
+        for variant in enum_spec.variants {
+            codes.extend(variant.serde_rename);
+            codes.insert(variant.name);
+        }
+    }
+    for doc in store.iter().filter(|doc| doc.kind == IntentKind::Workflow) {
+        let Ok(spec) = doc.as_workflow_spec() else {
+            continue;
+        };
+        for step in spec.steps {
+            if let WorkflowStep::Transform(t) = step {
+                codes.extend(t.raise_if.map(|raise| raise.error));
+            }
+        }
+    }
+    codes
+}
+
+/// Walks a Function body tracking which names are bound at each point
+struct ExpressionCheck<'a> {
+    doc: &'a IntentDocument,
+    store: &'a IntentStore,
+    error_codes: HashSet<String>,
+    result: &'a mut ValidationResult,
+}
+
+impl<'a> ExpressionCheck<'a> {
+    fn expression(&mut self, expr: &'a Expression, path: &str, scope: &mut Vec<&'a str>) {
+        match expr {
+            Expression::Literal { .. } => {}
+            Expression::Variable { name } => {
+                let root = name.split('.').next().unwrap_or_default();
+                if !name.contains("::") && !scope.contains(&root) && !CONTEXT_VARIABLES.contains(&root) {
+                    self.result.add_error(
+                        codes::E005_UNKNOWN_REFERENCE,
+                        format!("Unknown variable '{}' in Function '{}'", root, self.doc.name),
+                        location(self.doc, &format!("{}.name", path)),
+                    );
+                }
+            }
+            Expression::Field { expr, .. } | Expression::Unary { expr, .. } | Expression::Try { expr } => {
+                self.expression(expr, &format!("{}.expr", path), scope);
+            }
+            Expression::Index { expr, index } => {
+                self.expression(expr, &format!("{}.expr", path), scope);
+                self.expression(index, &format!("{}.index", path), scope);
+            }
+            Expression::Call { function, args } => {
+                if !self.is_callable(function, scope) {
+                    self.result.add_warning(
+                        codes::E005_UNKNOWN_REFERENCE,
+                        format!("Unknown function '{}' called in Function '{}'", function, self.doc.name),
+                        location(self.doc, &format!("{}.function", path)),
+                    );
+                }
+                self.list(args, &format!("{}.args", path), scope);
+            }
+            Expression::Method { expr, args, .. } => {
+                self.expression(expr, &format!("{}.expr", path), scope);
+                self.list(args, &format!("{}.args", path), scope);
+            }
+            Expression::Binary { left, right, .. } => {
+                self.expression(left, &format!("{}.left", path), scope);
+                self.expression(right, &format!("{}.right", path), scope);
+            }
+            Expression::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                self.expression(cond, &format!("{}.cond", path), scope);
+                self.expression(then_branch, &format!("{}.then_branch", path), scope);
+                self.expression(else_branch, &format!("{}.else_branch", path), scope);
+            }
+            Expression::Match { on, arms } => {
+                self.expression(on, &format!("{}.on", path), scope);
+                for (i, arm) in arms.iter().enumerate() {
+                    let outer = scope.len();
+                    pattern_bindings(&arm.pattern, scope);
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard, &format!("{}.arms[{}].guard", path, i), scope);
+                    }
+                    self.expression(&arm.body, &format!("{}.arms[{}].body", path, i), scope);
+                    scope.truncate(outer);
+                }
+            }
+            Expression::Let { bindings, body } => {
+                let outer = scope.len();
+                for (i, binding) in bindings.iter().enumerate() {
+                    self.expression(&binding.value, &format!("{}.bindings[{}].value", path, i), scope);
+                    scope.push(&binding.name);
+                }
+                self.expression(body, &format!("{}.body", path), scope);
+                scope.truncate(outer);
+            }
+            Expression::For { var, iterable, body } => {
+                self.expression(iterable, &format!("{}.iterable", path), scope);
+                scope.push(var);
+                self.expression(body, &format!("{}.body", path), scope);
+                scope.pop();
+            }
+            Expression::Return { value } => self.expression(value, &format!("{}.value", path), scope),
+            Expression::Raise { error, message } => {
+                if !error.contains("::") && !self.error_codes.contains(error) {
+                    self.result.add_error(
+                        codes::E005_UNKNOWN_REFERENCE,
+                        format!(
+                            "Function '{}' raises '{}', which no Endpoint or Workflow declares",
+                            self.doc.name, error
+                        ),
+                        location(self.doc, &format!("{}.error", path)),
+                    );
+                }
+                if let Some(message) = message {
+                    self.expression(message, &format!("{}.message", path), scope);
+                }
+            }
+            Expression::Block { exprs } => self.list(exprs, &format!("{}.exprs", path), scope),
+            Expression::Struct { name, fields } => {
+                if !is_native_or_engine_type(name)
+                    && self.store.get_by_kind_name(IntentKind::Type, name).is_none()
+                {
+                    self.result.add_error(
+                        codes::E005_UNKNOWN_REFERENCE,
+                        format!("Unknown type '{}' constructed in Function '{}'", name, self.doc.name),
+                        location(self.doc, &format!("{}.name", path)),
+                    );
+                }
+                let mut names: Vec<_> = fields.keys().collect();
+                names.sort();
+                for field in names {
+                    self.expression(&fields[field], &format!("{}.fields.{}", path, field), scope);
+                }
+            }
+            Expression::Array { elements } | Expression::Tuple { elements } => {
+                self.list(elements, &format!("{}.elements", path), scope);
+            }
+            Expression::Closure { params, body } => {
+                let outer = scope.len();
+                scope.extend(params.iter().map(String::as_str));
+                self.expression(body, &format!("{}.body", path), scope);
+                scope.truncate(outer);
+            }
+            Expression::UnwrapOr { expr, default } => {
+                self.expression(expr, &format!("{}.expr", path), scope);
+                self.expression(default, &format!("{}.default", path), scope);
+            }
+            Expression::Format { template, args } => {
+                match Expression::format_placeholders(template) {
+                    None => self.result.add_error(
+                        codes::E009_INVALID_MAPPING,
+                        format!(
+                            "Format template '{}' in Function '{}' may only use '{{}}' placeholders",
+                            template, self.doc.name
+                        ),
+                        location(self.doc, &format!("{}.template", path)),
+                    ),
+                    Some(placeholders) if placeholders != args.len() => self.result.add_error(
+                        codes::E009_INVALID_MAPPING,
+                        format!(
+                            "Format template '{}' in Function '{}' has {} placeholders but {} args",
+                            template,
+                            self.doc.name,
+                            placeholders,
+                            args.len()
+                        ),
+                        location(self.doc, &format!("{}.args", path)),
+                    ),
+                    Some(_) => {}
+                }
+                self.list(args, &format!("{}.args", path), scope);
+            }
+        }
+    }
+
+    fn list(&mut self, exprs: &'a [Expression], path: &str, scope: &mut Vec<&'a str>) {
+        for (i, expr) in exprs.iter().enumerate() {
+            self.expression(expr, &format!("{}[{}]", path, i), scope);
+        }
+    }
+
+    /// A Function or Pipeline intent (`uuid_generate` matches `UuidGenerate`),
+    /// a builtin, a bound closure, or a Rust path such as `String::new`
+    fn is_callable(&self, function: &str, scope: &[&str]) -> bool {
+        if function.contains("::") || BUILTIN_CALLS.contains(&function) || scope.contains(&function) {
+            return true;
+        }
+        let key = call_key(function);
+        self.store
+            .iter()
+            .filter(|doc| matches!(doc.kind, IntentKind::Function | IntentKind::Pipeline))
+            .any(|doc| doc.name == function || call_key(&doc.name) == key)
+    }
+}
+
+/// Push the names `pattern` binds onto `scope`
+fn pattern_bindings<'a>(pattern: &'a Pattern, scope: &mut Vec<&'a str>) {
+    match pattern {
+        Pattern::Variable { name } => scope.push(name),
+        Pattern::Variant { bindings, .. } => scope.extend(bindings.iter().map(String::as_str)),
+        Pattern::Some { binding } | Pattern::Ok { binding } | Pattern::Err { binding } => scope.push(binding),
+        Pattern::Struct { fields, .. } => {
+            for field in fields.values() {
+                pattern_bindings(field, scope);
+            }
+        }
+        Pattern::Tuple { elements } => {
+            for element in elements {
+                pattern_bindings(element, scope);
+            }
+        }
+        Pattern::Array { elements, rest } => {
+            for element in elements {
+                pattern_bindings(element, scope);
+            }
+            scope.extend(rest.as_deref());
+        }
+        Pattern::Or { patterns } => {
+            for pattern in patterns {
+                pattern_bindings(pattern, scope);
+            }
+        }
+        Pattern::Wildcard
+        | Pattern::Literal { .. }
+        | Pattern::StartsWith { .. }
+        | Pattern::EndsWith { .. }
+        | Pattern::None => {}
+    }
 }
 
 fn typecheck_pipeline(doc: &IntentDocument, store: &IntentStore, result: &mut ValidationResult) {
@@ -522,6 +1233,113 @@ fn typecheck_pipeline(doc: &IntentDocument, store: &IntentStore, result: &mut Va
             }
         }
     }
+
+    check_stage_expressions(doc, &spec, store, result);
+}
+
+/// Parse each stage's `skip_if`, `input_transform` and `output_transform` and
+/// check the paths they read. `input` is the stage's input: the pipeline input
+/// for the first stage, the previous stage's result after that. An
+/// `output_transform` can also read `output`, this stage's result.
+fn check_stage_expressions(
+    doc: &IntentDocument,
+    spec: &crate::model::PipelineSpec,
+    store: &IntentStore,
+    result: &mut ValidationResult,
+) {
+    let stage_result = |function: &str| -> Option<String> {
+        if let Some(func) = store.get_by_kind_name(IntentKind::Function, function) {
+            return func.as_function_spec().ok().map(|f| f.returns.return_type);
+        }
+        let pipeline = store.get_by_kind_name(IntentKind::Pipeline, function)?;
+        pipeline.as_pipeline_spec().ok().map(|p| p.output)
+    };
+
+    let mut input_type = Some(spec.input.clone());
+    for (i, stage) in spec.stages.iter().enumerate() {
+        let output_type = stage_result(&stage.function);
+        let expressions = [
+            ("skip_if", &stage.skip_if),
+            ("input_transform", &stage.input_transform),
+            ("output_transform", &stage.output_transform),
+        ];
+
+        for (key, source) in expressions {
+            let Some(source) = source else {
+                continue;
+            };
+            let at = location(doc, &format!("$.spec.stages[{}].{}", i, key));
+            let paths = match crate::simulate::variable_paths(source) {
+                Ok(paths) => paths,
+                Err(e) => {
+                    result.add_error(
+                        codes::E009_INVALID_MAPPING,
+                        format!("Cannot parse {} of stage '{}': {}", key, stage.name, e),
+                        at,
+                    );
+                    continue;
+                }
+            };
+
+            for path in paths {
+                let mut segments = path.split('.');
+                let root = segments.next().unwrap_or_default();
+                let root_type = match root {
+                    "input" => &input_type,
+                    "output" if key == "output_transform" => &output_type,
+                    _ => {
+                        result.add_error(
+                            codes::E009_INVALID_MAPPING,
+                            format!(
+                                "Unknown variable '{}' in {} of stage '{}'",
+                                root, key, stage.name
+                            ),
+                            at.clone(),
+                        );
+                        continue;
+                    }
+                };
+                let fields: Vec<&str> = segments.collect();
+                if let Some(missing) = root_type
+                    .as_deref()
+                    .and_then(|type_name| missing_field(store, type_name, &fields))
+                {
+                    result.add_error(
+                        codes::E009_INVALID_MAPPING,
+                        format!(
+                            "'{}' in {} of stage '{}' does not resolve: {}",
+                            path, key, stage.name, missing
+                        ),
+                        at.clone(),
+                    );
+                }
+            }
+        }
+
+        input_type = output_type;
+    }
+}
+
+/// Walk `fields` from Type `type_name` and describe the first field that
+/// doesn't exist. Stops without complaint at types that aren't Type intents.
+fn missing_field(store: &IntentStore, type_name: &str, fields: &[&str]) -> Option<String> {
+    let mut current = TypeRef::parse(type_name).ok()?;
+    for field in fields {
+        while let TypeRef::Optional(inner) = current {
+            current = *inner;
+        }
+        let TypeRef::Named(name) = &current else {
+            return None;
+        };
+        let spec = store
+            .get_by_kind_name(IntentKind::Type, name)
+            .and_then(|d| d.as_type_spec().ok())?;
+        let Some(def) = spec.fields.get(*field) else {
+            return Some(format!("type '{}' has no field '{}'", name, field));
+        };
+        current = def.field_type.clone();
+    }
+    None
 }
 
 fn typecheck_template(doc: &IntentDocument, result: &mut ValidationResult) {
@@ -548,6 +1366,14 @@ fn typecheck_template(doc: &IntentDocument, result: &mut ValidationResult) {
             "Template must have an output_file",
             location(doc, "$.spec.output_file"),
         );
+    } else if let Err(IntentError::Codegen { message, .. }) =
+        crate::codegen::check_output_path(&spec.output_file, &format!("Template '{}'", doc.name))
+    {
+        result.add_error(
+            codes::E009_INVALID_MAPPING,
+            message,
+            location(doc, "$.spec.output_file"),
+        );
     }
 
     if spec.template.is_empty() {
@@ -594,9 +1420,18 @@ fn typecheck_enum(doc: &IntentDocument, result: &mut ValidationResult) {
             );
         }
     }
+
+    // Spec strings emitted into enums.rs as written
+    for (path, message) in crate::codegen::check_enum_rust(&spec) {
+        result.add_error(
+            codes::E013_INVALID_RUST,
+            format!("{} in Enum '{}'", message, doc.name),
+            location(doc, &path),
+        );
+    }
 }
 
-fn typecheck_module(doc: &IntentDocument, result: &mut ValidationResult) {
+fn typecheck_module(doc: &IntentDocument, store: &IntentStore, result: &mut ValidationResult) {
     let Ok(spec) = doc.as_module_spec() else {
         result.add_error(
             codes::E001_INVALID_JSON,
@@ -631,6 +1466,90 @@ fn typecheck_module(doc: &IntentDocument, result: &mut ValidationResult) {
             );
         }
     }
+
+    // Output paths must stay inside the source tree
+    if spec.path.starts_with(['/', '\\']) {
+        result.add_error(
+            codes::E009_INVALID_MAPPING,
+            format!("Module path '{}' must be relative", spec.path),
+            location(doc, "$.spec.path"),
+        );
+    }
+    if has_parent_traversal(&spec.path) {
+        result.add_error(
+            codes::E009_INVALID_MAPPING,
+            format!("Module path '{}' must not contain '..'", spec.path),
+            location(doc, "$.spec.path"),
+        );
+    }
+    for (i, child) in spec.children.iter().enumerate() {
+        if has_parent_traversal(&child.file) {
+            result.add_error(
+                codes::E009_INVALID_MAPPING,
+                format!("Child module '{}' file '{}' must not contain '..'", child.name, child.file),
+                location(doc, &format!("$.spec.children[{}].file", i)),
+            );
+        }
+    }
+
+    // Files already claimed by modules that sort before this one; a collision
+    // is reported once, on the later module
+    let mut claimed: HashMap<String, String> = HashMap::new();
+    let mut modules = store.modules();
+    modules.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+    for other in modules.into_iter().take_while(|other| other.id != doc.id) {
+        if let Ok(other_spec) = other.as_module_spec() {
+            for (file, _) in module_outputs(&other_spec) {
+                claimed.entry(file).or_insert_with(|| other.name.clone());
+            }
+        }
+    }
+
+    for (file, path) in module_outputs(&spec) {
+        match claimed.get(&file) {
+            Some(owner) if *owner == doc.name => result.add_error(
+                codes::E010_DUPLICATE_NAME,
+                format!("Module '{}' writes '{}' more than once", doc.name, file),
+                location(doc, &path),
+            ),
+            Some(owner) => result.add_error(
+                codes::E010_DUPLICATE_NAME,
+                format!("Module '{}' writes '{}', which module '{}' also writes", doc.name, file, owner),
+                location(doc, &path),
+            ),
+            None => {
+                claimed.insert(file, doc.name.clone());
+            }
+        }
+    }
+}
+
+/// Files a Module generates (`<path>/mod.rs` and each child file), with the
+/// spec path that declares each one
+fn module_outputs(spec: &ModuleSpec) -> Vec<(String, String)> {
+    let mut outputs = vec![(
+        normalize_module_path(&format!("{}/mod.rs", spec.path)),
+        "$.spec.path".to_string(),
+    )];
+    for (i, child) in spec.children.iter().enumerate() {
+        outputs.push((
+            normalize_module_path(&format!("{}/{}", spec.path, child.file)),
+            format!("$.spec.children[{}].file", i),
+        ));
+    }
+    outputs
+}
+
+/// Drop `.` segments and empty segments so `src/./a//b.rs` matches `src/a/b.rs`
+fn normalize_module_path(path: &str) -> String {
+    path.split(['/', '\\'])
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn has_parent_traversal(path: &str) -> bool {
+    path.split(['/', '\\']).any(|segment| segment == "..")
 }
 
 fn typecheck_command(doc: &IntentDocument, store: &IntentStore, result: &mut ValidationResult) {
@@ -735,6 +1654,57 @@ fn typecheck_trait(doc: &IntentDocument, _store: &IntentStore, result: &mut Vali
     }
 }
 
+/// An Enum used as an endpoint or workflow output is sent over the wire, so
+/// its JSON form must be well-defined: no generics, distinct serialized
+/// variant names, only unit or struct-like variants under a `serde_tag` that
+/// doesn't clash with their fields, and real HTTP statuses
+fn check_enum_output(doc: &IntentDocument, output: &IntentDocument, result: &mut ValidationResult) {
+    let Ok(spec) = output.as_enum_spec() else {
+        return;
+    };
+    let mut problem = |message: String| {
+        result.add_error(
+            codes::E007_TYPE_MISMATCH,
+            format!("Output enum '{}' {}", output.name, message),
+            location(doc, "$.spec.output"),
+        );
+    };
+
+    if !spec.generics.is_empty() {
+        problem("is generic, so it has no concrete JSON form".to_string());
+    }
+
+    let mut serialized = HashSet::new();
+    for variant in &spec.variants {
+        let name = variant.serde_rename.as_deref().unwrap_or(&variant.name);
+        if !serialized.insert(name) {
+            problem(format!("has more than one variant serialized as '{}'", name));
+        }
+
+        if let Some(status) = variant.status.filter(|s| !(100..=599).contains(s)) {
+            problem(format!(
+                "gives variant '{}' status {}, which is not an HTTP status",
+                variant.name, status
+            ));
+        }
+
+        let Some(tag) = &spec.serde_tag else {
+            continue;
+        };
+        match &variant.data {
+            Some(VariantData::Tuple(_)) => problem(format!(
+                "is tagged with '{}' but variant '{}' is tuple-like; internally tagged variants must be unit or struct-like",
+                tag, variant.name
+            )),
+            Some(VariantData::Named(fields)) if fields.contains_key(tag) => problem(format!(
+                "is tagged with '{}' but variant '{}' also has a field named '{}'",
+                tag, variant.name, tag
+            )),
+            _ => {}
+        }
+    }
+}
+
 fn location(doc: &IntentDocument, path: &str) -> Option<StructuredLocation> {
     Some(StructuredLocation {
         file: doc.source_file.clone().unwrap_or_default(),
@@ -57,6 +57,30 @@ fn test_new_type() {
     assert!(temp.path().join(".intent/model/testtype.intent.json").exists());
 }
 
+#[test]
+fn test_new_dry_run_writes_nothing() {
+    let temp = TempDir::new().unwrap();
+    fs::create_dir_all(temp.path().join(".intent/model")).unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["new", "Type", "TestType", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Dry run - would create: .intent/model/testtype.intent.json"))
+        .stdout(predicate::str::contains("\"name\": \"TestType\""));
+    assert!(!temp.path().join(".intent/model/testtype.intent.json").exists());
+
+    // A collision is still reported so callers know a real run would fail
+    fs::write(temp.path().join(".intent/model/testtype.intent.json"), "{}").unwrap();
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["--format", "json", "new", "Type", "TestType", "--dry-run"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("File already exists"));
+}
+
 #[test]
 fn test_fmt_check() {
     let temp = TempDir::new().unwrap();
@@ -68,6 +92,87 @@ fn test_fmt_check() {
         .success();
 }
 
+#[test]
+fn test_fmt_all_normalizes_lock_files() {
+    let temp = fixture_project();
+    intent_cmd().current_dir(temp.path()).args(["fmt"]).assert().success();
+    let locks = temp.path().join(".intent/locks");
+    fs::create_dir_all(&locks).unwrap();
+    fs::write(
+        locks.join("gen-manifest.json"),
+        r#"{"version":"1.0","files":{"gen/src/lib.rs":{"hash":"abc","intents":[]}}}"#,
+    )
+    .unwrap();
+
+    // Plain fmt leaves lock files alone
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["fmt", "--check"])
+        .assert()
+        .success();
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["fmt", "--all", "--check"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(".intent/locks/gen-manifest.json"));
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["fmt", "--all"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(locks.join("gen-manifest.json")).unwrap(),
+        "{\n  \"files\": {\n    \"gen/src/lib.rs\": {\n      \"hash\": \"abc\",\n      \"intents\": []\n    }\n  },\n  \"version\": \"1.0\"\n}"
+    );
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["fmt", "--all", "--check"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_lint_reports_naming_conventions() {
+    let temp = fixture_project();
+    intent_cmd()
+        .current_dir(temp.path())
+        .arg("lint")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No naming issues"));
+
+    let file = temp.path().join(".intent/model/refund-request.intent.json");
+    let mut doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    doc["spec"]["fields"]["orderNote"] = serde_json::json!({ "type": "string" });
+    fs::write(&file, serde_json::to_string(&doc).unwrap()).unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .arg("lint")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[W101] Field 'orderNote' of Type 'RefundRequest' should be snake_case (field-snake-case)",
+        ));
+    // Naming is not a validation concern
+    intent_cmd()
+        .current_dir(temp.path())
+        .arg("validate")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("W101").not());
+
+    fs::write(temp.path().join("intent.toml"), "[lint]\ndisabled = [\"field-snake-case\"]\n").unwrap();
+    intent_cmd()
+        .current_dir(temp.path())
+        .arg("lint")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No naming issues"));
+}
+
 #[test]
 fn test_validate_empty() {
     let temp = TempDir::new().unwrap();
@@ -101,3 +206,2359 @@ fn test_json_output() {
         .success()
         .stdout(predicate::str::starts_with("["));
 }
+
+/// Copy the valid fixtures into a temp project's model directory
+fn fixture_project() -> TempDir {
+    let temp = TempDir::new().unwrap();
+    let model_dir = temp.path().join(".intent/model");
+    fs::create_dir_all(&model_dir).unwrap();
+
+    for entry in fs::read_dir("fixtures/valid").unwrap() {
+        let path = entry.unwrap().path();
+        fs::copy(&path, model_dir.join(path.file_name().unwrap())).unwrap();
+    }
+
+    temp
+}
+
+#[test]
+fn test_delete_orphan() {
+    let temp = fixture_project();
+    let file = temp.path().join(".intent/model/refunds-migration.intent.json");
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["delete", "CreateRefundsTable"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Deleted"));
+
+    assert!(!file.exists());
+}
+
+#[test]
+fn test_delete_referenced_type_blocked() {
+    let temp = fixture_project();
+    let file = temp.path().join(".intent/model/refund-request.intent.json");
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["delete", "RefundRequest"])
+        .assert()
+        .code(4) // PATCH_CONFLICT
+        .stdout(predicate::str::contains("CreateRefund"));
+
+    assert!(file.exists());
+
+    // --dry-run lists dependents without deleting, even with --force
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["delete", "RefundRequest", "--force", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("RefundWorkflow"));
+
+    assert!(file.exists());
+}
+
+#[test]
+fn test_validate_json_schema_preflight() {
+    let temp = fixture_project();
+    let model_dir = temp.path().join(".intent/model");
+
+    // Extra property on a Type spec, missing `table` on a Migration spec
+    let type_file = model_dir.join("refund-response.intent.json");
+    let mut doc: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&type_file).unwrap()).unwrap();
+    doc["spec"]["colour"] = serde_json::json!("red");
+    fs::write(&type_file, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+
+    let migration_file = model_dir.join("refunds-migration.intent.json");
+    let mut doc: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&migration_file).unwrap()).unwrap();
+    doc["spec"].as_object_mut().unwrap().remove("table");
+    fs::write(&migration_file, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--json-schema-validate"])
+        .assert()
+        .code(2) // VALIDATION_ERROR
+        .stdout(predicate::str::contains("[E011] Additional property 'colour'"))
+        .stdout(predicate::str::contains("$.spec.colour"))
+        .stdout(predicate::str::contains("[E002] Missing required property 'table'"))
+        .stdout(predicate::str::contains("$.spec.table"));
+
+    // The same check is enabled by `[validation] schema = true`
+    fs::write(temp.path().join("intent.toml"), "[validation]\nschema = true\n").unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("$.spec.colour"));
+}
+
+fn edit_contract_test(temp: &TempDir, edit: impl FnOnce(&mut serde_json::Value)) {
+    let file = temp.path().join(".intent/model/payments-contract-test.intent.json");
+    let mut doc: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    edit(&mut doc);
+    fs::write(&file, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+}
+
+#[test]
+fn test_contract_header_and_any_of_responses() {
+    let temp = fixture_project();
+    edit_contract_test(&temp, |doc| {
+        let scenarios = &mut doc["spec"]["scenarios"];
+        scenarios[0]["response"]["headers"] =
+            serde_json::json!({ "content-type": "application/json" });
+        scenarios[1]["response"] = serde_json::json!([
+            { "status": 400, "body": { "error": "insufficient_funds" } },
+            { "status": 402, "body": { "error": "insufficient_funds" }, "headers": { "retry-after": "60" } }
+        ]);
+    });
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--json-schema-validate"])
+        .assert()
+        .success();
+
+    // Header names collide case-insensitively; an empty any-of list matches nothing
+    edit_contract_test(&temp, |doc| {
+        let scenarios = &mut doc["spec"]["scenarios"];
+        scenarios[0]["response"]["headers"] = serde_json::json!({
+            "Content-Type": "application/json",
+            "content-type": "text/plain"
+        });
+        scenarios[1]["response"] = serde_json::json!([]);
+    });
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["--format", "json", "validate"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let errors: Vec<(String, String)> = json["result"]["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| {
+            (
+                e["code"].as_str().unwrap().to_string(),
+                e["location"]["path"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert!(errors.contains(&("E010".to_string(), "$.spec.scenarios[0].response.headers".to_string())));
+    assert!(errors.contains(&("E002".to_string(), "$.spec.scenarios[1].response".to_string())));
+}
+
+#[test]
+fn test_partial_input_requires_patch() {
+    let temp = fixture_project();
+    let endpoint = r#"{
+  "schema_version": "1.0",
+  "id": "550e8400-e29b-41d4-a716-446655440099",
+  "kind": "Endpoint",
+  "name": "UpdateRefund",
+  "spec": {
+    "method": "PUT",
+    "path": "/refund",
+    "input": "RefundRequest",
+    "output": "RefundResponse",
+    "workflow": "RefundWorkflow",
+    "partial": true
+  }
+}"#;
+    fs::write(
+        temp.path().join(".intent/model/update-refund.intent.json"),
+        endpoint,
+    )
+    .unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "Partial input is only allowed on PATCH endpoints, not PUT",
+        ));
+}
+
+#[test]
+fn test_idempotency_key_must_be_stable_and_required() {
+    let temp = fixture_project();
+    let file = temp.path().join(".intent/model/refund-request.intent.json");
+    let mut doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    doc["spec"]["fields"]["order_id"] = serde_json::json!({ "type": "float", "required": true });
+    fs::write(&file, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E007] Idempotency key 'order_id' of input type 'RefundRequest' is float; it must be a string, uuid or integer",
+        ));
+
+    doc["spec"]["fields"]["order_id"] = serde_json::json!({ "type": "uuid", "required": false });
+    fs::write(&file, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[W023] Idempotency key 'order_id' of input type 'RefundRequest' is not required",
+        ));
+}
+
+#[test]
+fn test_readonly_field_cannot_be_required() {
+    let temp = fixture_project();
+    let file = temp.path().join(".intent/model/refund-response.intent.json");
+    let mut doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    doc["spec"]["fields"]["refund_id"]["readonly"] = serde_json::json!(true);
+    fs::write(&file, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E009] Readonly field 'refund_id' of Type 'RefundResponse' must not be required; requests never carry it",
+        ));
+}
+
+#[test]
+fn test_rust_type_override_must_fit_field_type() {
+    let temp = fixture_project();
+    let file = temp.path().join(".intent/model/refund-request.intent.json");
+    let mut doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    doc["spec"]["fields"]["reason"]["rust_type_override"] = serde_json::json!("u32");
+    fs::write(&file, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E007] rust_type_override of field 'reason' in Type 'RefundRequest' is invalid: 'u32' can't hold string; use one of String, Box<str>",
+        ));
+}
+
+#[test]
+fn test_migrate_schema_upgrades_old_files() {
+    let temp = fixture_project();
+    let model_dir = temp.path().join(".intent/model");
+    let read_all = || {
+        let mut files: Vec<_> = fs::read_dir(&model_dir)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                (path.clone(), fs::read_to_string(path).unwrap())
+            })
+            .collect();
+        files.sort();
+        files
+    };
+    // The fixtures are all 1.0; one file already at 2.0 is left alone
+    let current = model_dir.join("payments-service.intent.json");
+    let mut doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(&current).unwrap()).unwrap();
+    doc["schema_version"] = serde_json::json!("2.0");
+    fs::write(&current, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+    let before = read_all();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["migrate-schema", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Would migrate 6 files to schema_version 2.0 (1 already current):"))
+        .stdout(predicate::str::contains("refund-request.intent.json (1.0 -> 2.0)"));
+    assert_eq!(read_all(), before);
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["migrate-schema"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Migrated 6 files to schema_version 2.0"));
+    for (path, content) in read_all() {
+        let doc: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(doc["schema_version"], "2.0", "{}", path.display());
+    }
+    intent_cmd().current_dir(temp.path()).args(["fmt", "--check"]).assert().success();
+    intent_cmd().current_dir(temp.path()).args(["validate"]).assert().success();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["migrate-schema"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("All 7 intent files are at schema_version 2.0."));
+}
+
+#[test]
+fn test_model_dir_flag() {
+    let temp = TempDir::new().unwrap();
+    let fixtures = std::env::current_dir().unwrap().join("fixtures/valid");
+
+    // The flag takes precedence over INTENT_MODEL_PATH
+    intent_cmd()
+        .current_dir(temp.path())
+        .env("INTENT_MODEL_PATH", temp.path().join("missing"))
+        .args(["validate", "--model-dir"])
+        .arg(&fixtures)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("7 intents validated"));
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["list", "--model-dir"])
+        .arg(&fixtures)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("RefundWorkflow"));
+
+    // Without the flag the env var is used
+    intent_cmd()
+        .current_dir(temp.path())
+        .env("INTENT_MODEL_PATH", &fixtures)
+        .args(["show", "Payments"])
+        .assert()
+        .success();
+
+    // Commands that write or scan the model directory honour it too
+    let model = temp.path().join("model");
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["new", "Type", "Refund", "--model-dir"])
+        .arg(&model)
+        .assert()
+        .success();
+    assert!(model.join("refund.intent.json").exists());
+    fs::write(model.join("refund.intent.json"), "{\"a\": 1,\n\"b\": 2}").unwrap();
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["fmt", "--check", "--model-dir"])
+        .arg(&model)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("refund.intent.json"));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["verify", "--model-dir"])
+        .arg(&model)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("1 files need formatting"));
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["verify-manifest", "--model-dir"])
+        .arg(&fixtures)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--model-dir is not used by `intent verify-manifest`"));
+}
+
+#[test]
+fn test_discovery_settings_apply_to_every_command() {
+    let temp = fixture_project();
+    let drafts = temp.path().join(".intent/model/drafts");
+    fs::create_dir_all(&drafts).unwrap();
+    fs::write(drafts.join("half-done.intent.json"), "{ not json").unwrap();
+    fs::write(
+        temp.path().join("intent.toml"),
+        "[project]\nname = \"refunds\"\nignore_dirs = [\"drafts\"]\n",
+    )
+    .unwrap();
+
+    for args in [&["list"][..], &["validate"], &["fmt"], &["migrate-schema", "--dry-run"]] {
+        intent_cmd()
+            .current_dir(temp.path())
+            .args(args)
+            .assert()
+            .success();
+    }
+}
+
+/// Rewrite the RefundWorkflow fixture in a temp project
+fn edit_refund_workflow(temp: &TempDir, edit: impl FnOnce(&mut serde_json::Value)) {
+    let file = temp.path().join(".intent/model/refund-workflow.intent.json");
+    let mut doc: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    edit(&mut doc);
+    fs::write(&file, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+}
+
+/// Name the RefundWorkflow fixture's two Effect steps
+fn name_refund_steps(doc: &mut serde_json::Value) {
+    doc["spec"]["steps"][1]["name"] = serde_json::json!("refund_payment");
+    doc["spec"]["steps"][2]["name"] = serde_json::json!("record_refund");
+}
+
+fn tag_intent(temp: &TempDir, file: &str, tags: serde_json::Value) {
+    let file = temp.path().join(".intent/model").join(file);
+    let mut doc: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    doc["tags"] = tags;
+    fs::write(&file, serde_json::to_string(&doc).unwrap()).unwrap();
+}
+
+#[test]
+fn test_list_and_gen_filter_by_tag() {
+    let temp = fixture_project();
+    tag_intent(&temp, "refund-request.intent.json", serde_json::json!(["payments", "team-billing"]));
+    tag_intent(&temp, "refund-workflow.intent.json", serde_json::json!(["payments"]));
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["--format", "json", "list", "--tag", "payments"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let intents: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<&str> = intents
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|intent| intent["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["RefundRequest", "RefundWorkflow"]);
+    assert_eq!(intents[0]["tags"], serde_json::json!(["payments", "team-billing"]));
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["--format", "json", "stats"])
+        .output()
+        .unwrap();
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["tags"], serde_json::json!({ "payments": 2, "team-billing": 1 }));
+    assert_eq!(stats["untagged"], 5);
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--tag", "payments"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("gen/src/workflows/refund_workflow.rs"))
+        .stdout(predicate::str::contains("gen/src/endpoints").not());
+}
+
+#[test]
+fn test_tags_round_trip_through_fmt() {
+    let temp = fixture_project();
+    tag_intent(&temp, "refund-request.intent.json", serde_json::json!(["payments", "lifecycle-ga"]));
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["fmt"])
+        .assert()
+        .success();
+    let formatted = fs::read_to_string(temp.path().join(".intent/model/refund-request.intent.json")).unwrap();
+    let doc: serde_json::Value = serde_json::from_str(&formatted).unwrap();
+    assert_eq!(doc["tags"], serde_json::json!(["payments", "lifecycle-ga"]));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["fmt", "--check"])
+        .assert()
+        .success();
+
+    tag_intent(&temp, "refund-request.intent.json", serde_json::json!(["Payments"]));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E012] Tag 'Payments' of Type 'RefundRequest' is not a slug",
+        ));
+}
+
+#[test]
+fn test_watch_regenerates_on_change() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::time::Duration;
+
+    let temp = fixture_project();
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_intent-engine"))
+        .current_dir(temp.path())
+        .args(["--format", "json", "watch"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let (lines, cycles) = std::sync::mpsc::channel();
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let _ = lines.send(line.unwrap());
+        }
+    });
+    let next_cycle = || -> serde_json::Value {
+        let line = cycles.recv_timeout(Duration::from_secs(30)).unwrap();
+        serde_json::from_str(&line).unwrap()
+    };
+
+    let first = next_cycle();
+    assert_eq!(first["outcome"], "generated");
+    assert!(temp.path().join("gen/src/lib.rs").exists());
+
+    // An invalid edit is reported and leaves the generated files alone
+    let workflow = temp.path().join("gen/src/workflows/refund_workflow.rs");
+    let generated = fs::read_to_string(&workflow).unwrap();
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["assign"]["validated_amount"] = serde_json::json!("input.bogus");
+    });
+    let invalid = next_cycle();
+    assert_eq!(invalid["outcome"], "invalid");
+    assert_eq!(invalid["validation_errors"][0]["code"], "E009");
+    assert_eq!(fs::read_to_string(&workflow).unwrap(), generated);
+
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["assign"]["validated_amount"] = serde_json::json!("input.amount * 2");
+    });
+    let fixed = next_cycle();
+    assert_eq!(fixed["outcome"], "generated");
+    assert_ne!(fs::read_to_string(&workflow).unwrap(), generated);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_transform_sources_must_resolve() {
+    let temp = fixture_project();
+    let validate = || {
+        intent_cmd()
+            .current_dir(temp.path())
+            .args(["validate"])
+            .assert()
+    };
+
+    // The fixture reads `input.amount`; a bare literal reads nothing
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["assign"]["validated_amount"] = serde_json::json!("12.50");
+    });
+    validate().success();
+
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["assign"]["validated_amount"] = serde_json::json!("input.bogus");
+    });
+    validate()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E009] Source of 'validated_amount' in step 'validate_input' reads 'input.bogus', which does not resolve: type 'RefundRequest' has no field 'bogus'",
+        ))
+        .stdout(predicate::str::contains("$.spec.steps[0].assign.validated_amount"));
+
+    // A plain read is assigned as is, so the types must agree
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["assign"]["validated_amount"] = serde_json::json!("input.order_id");
+    });
+    validate().code(2).stdout(predicate::str::contains(
+        "[E007] Source 'input.order_id' of 'validated_amount' in step 'validate_input' has type 'uuid', but context field 'validated_amount' is 'money'",
+    ));
+
+    // Context fields bound by a later step aren't available yet
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["assign"]["validated_amount"] =
+            serde_json::json!("context.refund_result.amount");
+    });
+    validate().code(2).stdout(predicate::str::contains(
+        "reads 'context.refund_result.amount', which is neither declared in context nor bound by an earlier step",
+    ));
+
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["assign"]["validated_amount"] = serde_json::json!("amount * 2");
+    });
+    validate()
+        .code(2)
+        .stdout(predicate::str::contains("reads unknown variable 'amount'"));
+}
+
+#[test]
+fn test_workflow_must_populate_required_output_fields() {
+    let temp = fixture_project();
+    let validate = || {
+        intent_cmd()
+            .current_dir(temp.path())
+            .args(["validate"])
+            .assert()
+    };
+
+    // `refund_result` is bound to a whole RefundResponse
+    validate().success().stdout(predicate::str::contains("output type").not());
+
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][1].as_object_mut().unwrap().remove("output_binding");
+    });
+    validate().success().stdout(predicate::str::contains(
+        "[E009] Required fields 'status' of output type 'RefundResponse' are never assigned or bound by a step of workflow 'RefundWorkflow'",
+    ));
+
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"] = serde_json::json!([]);
+    });
+    validate().success().stdout(predicate::str::contains(
+        "Workflow 'RefundWorkflow' has no steps, so required fields 'refund_id', 'status' of output type 'RefundResponse' are never assigned",
+    ));
+}
+
+#[test]
+fn test_step_conditions_are_validated() {
+    let temp = fixture_project();
+    let validate = || {
+        intent_cmd()
+            .current_dir(temp.path())
+            .args(["validate"])
+            .assert()
+    };
+
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][2]["when"] = serde_json::json!("input.reason != null");
+    });
+    validate().success();
+
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["when"] = serde_json::json!("input.bogus > 0");
+    });
+    validate()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E009] Condition of step 'validate_input' reads 'input.bogus', which does not resolve",
+        ))
+        .stdout(predicate::str::contains("$.spec.steps[0].when"));
+
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["when"] = serde_json::json!("false");
+    });
+    validate().success().stdout(predicate::str::contains(
+        "[W020] Condition 'false' of step 'validate_input' is always false, so the step never runs",
+    ));
+}
+
+#[test]
+fn test_duplicate_step_names() {
+    let temp = fixture_project();
+    edit_refund_workflow(&temp, |doc| {
+        name_refund_steps(doc);
+        doc["spec"]["steps"][2]["name"] = serde_json::json!("refund_payment");
+    });
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E010] Duplicate step name 'refund_payment' in workflow 'RefundWorkflow'",
+        ))
+        .stdout(predicate::str::contains("$.spec.steps[2].name"));
+}
+
+#[test]
+fn test_colliding_workflow_module_names() {
+    let temp = fixture_project();
+    let workflow = temp.path().join(".intent/model/refund-workflow.intent.json");
+    let mut doc: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&workflow).unwrap()).unwrap();
+    doc["id"] = serde_json::json!("550e8400-e29b-41d4-a716-446655440012");
+    doc["name"] = serde_json::json!("Refund_Workflow");
+    fs::write(
+        temp.path().join(".intent/model/refund-workflow-copy.intent.json"),
+        serde_json::to_string_pretty(&doc).unwrap(),
+    )
+    .unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E010] Workflow 'Refund_Workflow' generates 'workflows/refund_workflow.rs', which Workflow 'RefundWorkflow' also generates",
+        ));
+}
+
+#[test]
+fn test_step_diagnostics_use_step_names() {
+    let temp = fixture_project();
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][1]["name"] = serde_json::json!("refund_payment");
+        doc["spec"]["steps"][1]["service"] = serde_json::json!("Billing");
+        doc["spec"]["steps"][2]["output_binding"] = serde_json::json!("row");
+    });
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "Unknown service reference: Billing (in step 'refund_payment')",
+        ))
+        .stdout(predicate::str::contains("$.spec.steps[1].service"));
+
+    // Unnamed steps fall back to their index
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][1]["service"] = serde_json::json!("Payments");
+    });
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Output binding 'row' is not declared in context (in step 2)",
+        ));
+}
+
+#[test]
+fn test_gen_types_per_type_layout() {
+    let temp = fixture_project();
+    fs::write(
+        temp.path().join("intent.toml"),
+        "[generation]\ntypes_layout = \"per_type\"\n",
+    )
+    .unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen"])
+        .assert()
+        .success();
+
+    let types_dir = temp.path().join("gen/src/types");
+    let mut files: Vec<_> = fs::read_dir(&types_dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["mod.rs", "refund_request.rs", "refund_response.rs"]);
+    assert!(!temp.path().join("gen/src/types.rs").exists());
+
+    let mod_rs = fs::read_to_string(types_dir.join("mod.rs")).unwrap();
+    assert!(mod_rs.contains("pub use refund_request::*;"));
+
+    // Manifest and trace map track the per-type files
+    let manifest = fs::read_to_string(temp.path().join(".intent/locks/gen-manifest.json")).unwrap();
+    assert!(manifest.contains("gen/src/types/refund_request.rs"));
+    let trace = fs::read_to_string(temp.path().join(".intent/locks/trace-map.json")).unwrap();
+    assert!(trace.contains("gen/src/types/refund_response.rs"));
+
+    // Regenerating with the same layout is a no-op
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--check"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_show_type_schema() {
+    let output = intent_cmd()
+        .args(["--model-dir", "fixtures/valid", "show", "RefundRequest", "--schema"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(schema["title"], "RefundRequest");
+    assert_eq!(schema["required"], serde_json::json!(["amount", "order_id"]));
+    assert_eq!(schema["properties"]["order_id"]["format"], "uuid");
+
+    intent_cmd()
+        .args(["--model-dir", "fixtures/valid", "show", "CreateRefund", "--schema"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--schema needs a Type intent; 'CreateRefund' is of kind Endpoint"));
+}
+
+#[test]
+fn test_show_deps_tree() {
+    let output = intent_cmd()
+        .args(["--model-dir", "fixtures/valid", "show", "CreateRefund", "--deps-tree"])
+        .args(["--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let tree: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tree["kind"], "Endpoint");
+
+    let names = |node: &serde_json::Value| -> Vec<String> {
+        node["dependencies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let deps = names(&tree);
+    assert!(deps.contains(&"RefundWorkflow".to_string()));
+    assert!(deps.contains(&"RefundRequest".to_string()));
+    assert!(deps.contains(&"RefundResponse".to_string()));
+
+    // The workflow is expanded down to its service
+    let workflow = tree["dependencies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|d| d["name"] == "RefundWorkflow")
+        .unwrap();
+    assert!(names(workflow).contains(&"Payments".to_string()));
+
+    // --depth 1 stops below the endpoint's direct dependencies
+    intent_cmd()
+        .args(["--model-dir", "fixtures/valid", "show", "CreateRefund", "--deps-tree"])
+        .args(["--depth", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("RefundWorkflow (Workflow) [...]"))
+        .stdout(predicate::str::contains("Payments").not());
+}
+
+#[test]
+fn test_show_impact() {
+    let output = intent_cmd()
+        .args(["--model-dir", "fixtures/valid", "--format", "json"])
+        .args(["show", "RefundRequest", "--impact"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let names = |list: &str| -> Vec<String> {
+        report[list]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+    // The contract test only reaches the type through the service
+    assert!(!names("direct").contains(&"PaymentsRefundContract".to_string()));
+    assert!(names("transitive").contains(&"PaymentsRefundContract".to_string()));
+    assert!(names("transitive").contains(&"CreateRefund".to_string()));
+    assert_eq!(report["transitive_count"], 4);
+    assert_eq!(names("endpoints"), ["CreateRefund"]);
+    assert_eq!(report["endpoint_count"], 1);
+
+    // Only types report the endpoints exposing them
+    intent_cmd()
+        .args(["--model-dir", "fixtures/valid", "show", "RefundWorkflow", "--impact"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Transitive dependents: 1"))
+        .stdout(predicate::str::contains("Exposed by endpoints").not());
+}
+
+#[test]
+fn test_verify_steps_runs_only_selected_phases() {
+    // Nothing has been generated yet, so only a run including gen fails
+    let temp = fixture_project();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["verify", "--steps", "validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Verification passed."))
+        .stdout(predicate::str::contains("steps run: validate"))
+        .stdout(predicate::str::contains("files generated").not());
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["verify", "--steps", "validate,gen"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("generated code does not match"));
+}
+
+#[test]
+fn test_verify_diff_base_reports_breaking_changes() {
+    let temp = fixture_project();
+    intent_cmd().current_dir(temp.path()).arg("gen").assert().success();
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .current_dir(temp.path())
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "base"]);
+
+    // Dropping a field is breaking, but the model still verifies once regenerated
+    let path = temp.path().join(".intent/model/refund-request.intent.json");
+    let mut doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    doc["spec"]["fields"].as_object_mut().unwrap().remove("reason");
+    fs::write(&path, serde_json::to_string_pretty(&doc).unwrap() + "\n").unwrap();
+    intent_cmd().current_dir(temp.path()).arg("gen").assert().success();
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["verify", "--steps", "validate,gen,obligations", "--diff-base", "HEAD", "--format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(6));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["schema"], "intent-verify/1");
+    let result = &report["result"];
+    assert_eq!(result["success"], false);
+    assert_eq!(result["steps"], serde_json::json!(["validate", "gen", "obligations"]));
+    assert_eq!(result["diagnostics"]["errors"], 0);
+    assert_eq!(result["diff"]["base"], "HEAD");
+    assert_eq!(result["diff"]["result"]["high_count"], 1);
+    assert_eq!(
+        result["diff"]["result"]["changes"][0]["description"],
+        "Removed field 'reason' from type 'RefundRequest'"
+    );
+
+    // Without --diff-base the same tree passes
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["verify", "--steps", "validate,gen,obligations"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_openapi_writes_default_file() {
+    let temp = fixture_project();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .arg("openapi")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Wrote gen/openapi.json (1 paths)"));
+
+    let document: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp.path().join("gen/openapi.json")).unwrap()).unwrap();
+    assert_eq!(document["openapi"], "3.1.0");
+    assert_eq!(document["paths"]["/refund"]["post"]["operationId"], "CreateRefund");
+}
+
+#[test]
+fn test_show_ambiguous_name() {
+    let temp = fixture_project();
+    fs::write(
+        temp.path().join(".intent/model/payments-type.intent.json"),
+        serde_json::to_string_pretty(&serde_json::json!({
+            "schema_version": "2.0",
+            "id": "550e8400-e29b-41d4-a716-446655440011",
+            "kind": "Type",
+            "name": "Payments",
+            "spec": { "fields": { "total": { "type": "money" } } }
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["show", "Payments"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Ambiguous intent name 'Payments' matches 2 intents"))
+        .stderr(predicate::str::contains("  Service:Payments"))
+        .stderr(predicate::str::contains("  Type:Payments"));
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["--format", "json", "show", "Payments"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["candidates"], serde_json::json!(["Service:Payments", "Type:Payments"]));
+
+    // A kind prefix picks one
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["--format", "json", "show", "type:Payments"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["kind"], "Type");
+    assert_eq!(json["spec"]["fields"]["total"]["type"], "money");
+}
+
+#[test]
+fn test_rename_and_delete_take_kind_qualified_names() {
+    let temp = fixture_project();
+    fs::write(
+        temp.path().join(".intent/model/payments-type.intent.json"),
+        serde_json::to_string_pretty(&serde_json::json!({
+            "schema_version": "2.0",
+            "id": "550e8400-e29b-41d4-a716-446655440011",
+            "kind": "Type",
+            "name": "Payments",
+            "spec": { "fields": { "total": { "type": "money" } } }
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+
+    for args in [&["rename", "Payments", "PaymentTotals"][..], &["delete", "Payments", "--dry-run"]] {
+        intent_cmd()
+            .current_dir(temp.path())
+            .args(args)
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("Ambiguous intent name 'Payments' matches 2 intents"));
+    }
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["delete", "Type:Payments", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("would delete: .intent/model/payments-type.intent.json"));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["rename", "Type:Payments", "PaymentTotals"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Renamed Type 'Payments' to 'PaymentTotals'"));
+}
+
+#[test]
+fn test_show_kind_qualified_name() {
+    intent_cmd()
+        .args(["--model-dir", "fixtures/valid", "show", "Service:Payments"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Payments"));
+
+    intent_cmd()
+        .args(["--model-dir", "fixtures/valid", "show", "Type:Payments"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Intent not found: Type:Payments"));
+}
+
+#[test]
+fn test_rename_type_updates_references() {
+    let temp = fixture_project();
+    let model_dir = temp.path().join(".intent/model");
+
+    // A dry run reports the plan and leaves the files alone
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["rename", "RefundResponse", "RefundResult", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Dry run - would rename Type 'RefundResponse' to 'RefundResult'"))
+        .stdout(predicate::str::contains("refund-response.intent.json -> .intent/model/refund-result.intent.json"))
+        .stdout(predicate::str::contains("updated .intent/model/create-refund-endpoint.intent.json"));
+    assert!(model_dir.join("refund-response.intent.json").exists());
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["rename", "RefundResponse", "RefundResult"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("updated .intent/model/payments-service.intent.json"))
+        .stdout(predicate::str::contains("updated .intent/model/refund-workflow.intent.json"));
+
+    assert!(!model_dir.join("refund-response.intent.json").exists());
+    let renamed: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(model_dir.join("refund-result.intent.json")).unwrap())
+            .unwrap();
+    assert_eq!(renamed["name"], "RefundResult");
+    let endpoint: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(model_dir.join("create-refund-endpoint.intent.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(endpoint["spec"]["output"], "RefundResult");
+
+    // Nothing is left pointing at the old name
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_rename_refuses_existing_name() {
+    let temp = fixture_project();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["rename", "RefundResponse", "RefundRequest"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("A Type named 'RefundRequest' already exists"));
+
+    assert!(temp
+        .path()
+        .join(".intent/model/refund-response.intent.json")
+        .exists());
+}
+
+#[test]
+fn test_enum_output_must_serialize_unambiguously() {
+    let temp = fixture_project();
+    let write_outcome = |spec: serde_json::Value| {
+        fs::write(
+            temp.path().join(".intent/model/refund-outcome.intent.json"),
+            serde_json::to_string_pretty(&serde_json::json!({
+                "schema_version": "2.0",
+                "id": "550e8400-e29b-41d4-a716-446655440012",
+                "kind": "Enum",
+                "name": "RefundOutcome",
+                "spec": spec
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+    };
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["output"] = serde_json::json!("RefundOutcome");
+    });
+
+    write_outcome(serde_json::json!({
+        "serde_tag": "outcome",
+        "variants": [
+            { "name": "Succeeded", "data": { "refund_id": "uuid" }, "status": 201 },
+            { "name": "Pending" }
+        ]
+    }));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success();
+
+    // Internally tagged tuple variants have no JSON form
+    write_outcome(serde_json::json!({
+        "serde_tag": "outcome",
+        "variants": [
+            { "name": "Succeeded", "data": ["uuid", "money"] },
+            { "name": "Pending" }
+        ]
+    }));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E007] Output enum 'RefundOutcome' is tagged with 'outcome' but variant 'Succeeded' is tuple-like",
+        ));
+}
+
+#[test]
+fn test_enum_rust_is_validated_before_gen() {
+    let temp = fixture_project();
+    let write_enum = |spec: serde_json::Value| {
+        fs::write(
+            temp.path().join(".intent/model/lookup.intent.json"),
+            serde_json::to_string_pretty(&serde_json::json!({
+                "schema_version": "2.0",
+                "id": "550e8400-e29b-41d4-a716-446655440011",
+                "kind": "Enum",
+                "name": "Lookup",
+                "spec": spec
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+    };
+
+    write_enum(serde_json::json!({
+        "derives": ["Debug", "Clone("],
+        "generics": [{ "name": "T", "bounds": ["Clone +"] }],
+        "variants": [
+            { "name": "Names", "data": ["Vec<String"] },
+            { "name": "Found", "data": { "value": "T" } }
+        ]
+    }));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("[E013] 'Clone(' is not a valid Rust derive in Enum 'Lookup'"))
+        .stdout(predicate::str::contains("[E013] 'Clone +' is not a valid Rust bound in Enum 'Lookup'"))
+        .stdout(predicate::str::contains("[E013] 'Vec<String' is not a valid Rust type in Enum 'Lookup'"))
+        .stdout(predicate::str::contains("$.spec.variants[0].data[0]"));
+    // gen refuses the model instead of panicking
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen"])
+        .assert()
+        .code(2);
+
+    write_enum(serde_json::json!({
+        "generics": [{ "name": "T", "bounds": ["Clone"] }],
+        "variants": [
+            { "name": "Names", "data": ["Vec<String>"] },
+            { "name": "Found", "data": { "value": "T" } }
+        ]
+    }));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen"])
+        .assert()
+        .success();
+    let enums = fs::read_to_string(temp.path().join("gen/src/enums.rs")).unwrap();
+    assert!(enums.contains("Names(Vec<String>)"));
+}
+
+#[test]
+fn test_context_field_types() {
+    let temp = fixture_project();
+    fs::write(
+        temp.path().join(".intent/model/refund-status.intent.json"),
+        serde_json::to_string_pretty(&serde_json::json!({
+            "schema_version": "2.0",
+            "id": "550e8400-e29b-41d4-a716-446655440010",
+            "kind": "Enum",
+            "name": "RefundStatus",
+            "spec": {
+                "variants": [{ "name": "Pending" }, { "name": "Completed" }]
+            }
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+
+    // Enum-typed context fields resolve
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["context"]["status"] = serde_json::json!("RefundStatus");
+    });
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success();
+
+    // Unknown names are reported against the context field
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["context"]["status"] = serde_json::json!("RefundState");
+    });
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("[E005] Unknown type reference: RefundState"))
+        .stdout(predicate::str::contains("$.spec.context.status"));
+
+    // Naming a workflow where a type belongs is a mismatch, not a missing reference
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["context"]["status"] = serde_json::json!("CreateRefund");
+    });
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E007] 'CreateRefund' is an intent of kind Endpoint, not a Type or Enum",
+        ));
+}
+
+#[test]
+fn test_field_default_must_match_type() {
+    let temp = fixture_project();
+    let file = temp.path().join(".intent/model/refund-request.intent.json");
+    let edit_reason = |default: serde_json::Value| {
+        let mut doc: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        doc["spec"]["fields"]["reason"]["type"] = serde_json::json!("int");
+        doc["spec"]["fields"]["reason"]["default"] = default;
+        fs::write(&file, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+    };
+
+    edit_reason(serde_json::json!(3));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success();
+
+    edit_reason(serde_json::json!("three"));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E007] Default \"three\" of field 'reason' is not a valid 'int'",
+        ))
+        .stdout(predicate::str::contains("$.spec.fields.reason.default"));
+}
+
+#[test]
+fn test_graph_export() {
+    intent_cmd()
+        .args(["--model-dir", "fixtures/valid", "graph", "--format", "dot"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("digraph intents {"))
+        .stdout(predicate::str::contains(
+            "\"Workflow:RefundWorkflow\" -> \"Service:Payments\";",
+        ));
+
+    let output = intent_cmd()
+        .args(["--model-dir", "fixtures/valid", "graph", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let graph: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        graph["nodes"]["Endpoint:CreateRefund"],
+        serde_json::json!(["Type:RefundRequest", "Type:RefundResponse", "Workflow:RefundWorkflow"])
+    );
+    assert_eq!(graph["cycles"], serde_json::json!([]));
+
+    intent_cmd()
+        .args(["--model-dir", "fixtures/valid", "--format", "dot", "validate"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--format dot is only supported by `intent graph`"));
+}
+
+#[test]
+fn test_verify_manifest_reports_edited_files() {
+    let temp = fixture_project();
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--print-manifest"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let manifest: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let entry = &manifest["files"]["gen/src/types.rs"];
+    assert_eq!(entry["hash"].as_str().unwrap().len(), 64);
+    assert!(!entry["source_intents"].as_array().unwrap().is_empty());
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["verify-manifest"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Manifest matches."));
+
+    let types = temp.path().join("gen/src/types.rs");
+    let content = fs::read_to_string(&types).unwrap();
+    fs::write(&types, format!("{}\n// hand edit\n", content)).unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["verify-manifest"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("gen/src/types.rs (modified)"));
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["verify-manifest", "--format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["matches"], false);
+    assert_eq!(report["mismatches"].as_array().unwrap().len(), 1);
+    assert_eq!(report["mismatches"][0]["path"], "gen/src/types.rs");
+    assert_eq!(report["mismatches"][0]["expected"], entry["hash"]);
+}
+
+#[test]
+fn test_gen_dry_run_reports_orphans() {
+    let temp = fixture_project();
+    fs::write(
+        temp.path().join("intent.toml"),
+        "[generation]\ntypes_layout = \"per_type\"\n",
+    )
+    .unwrap();
+    let note = temp.path().join(".intent/model/audit-note.intent.json");
+    fs::write(
+        &note,
+        serde_json::to_string_pretty(&serde_json::json!({
+            "schema_version": "1.0",
+            "id": "550e8400-e29b-41d4-a716-446655440011",
+            "kind": "Type",
+            "name": "AuditNote",
+            "spec": { "fields": { "text": { "type": "string", "required": true } } }
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen"])
+        .assert()
+        .success();
+
+    // Nothing to do straight after a generation
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0 to create, 0 to update, 0 orphaned"));
+
+    fs::remove_file(&note).unwrap();
+    let generated = temp.path().join("gen/src/types/audit_note.rs");
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--dry-run", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["summary"]["delete"], 1);
+    assert_eq!(report["orphaned"][0], "gen/src/types/audit_note.rs");
+    // types/mod.rs no longer declares the module
+    assert_eq!(report["summary"]["update"], 1);
+
+    // A dry run never touches the tree
+    assert!(generated.exists());
+}
+
+#[test]
+fn test_gen_json_reports_file_and_tree_hashes() {
+    let temp = fixture_project();
+    let gen_json = || {
+        let output = intent_cmd()
+            .current_dir(temp.path())
+            .args(["--format", "json", "gen"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let first = gen_json();
+    let files = first["files"].as_array().unwrap();
+    assert!(!files.is_empty());
+    for file in files {
+        assert_eq!(file["hash"].as_str().unwrap().len(), 64, "{}", file["path"]);
+        assert_eq!(file["status"], "created");
+    }
+    let hash = first["hash"].as_str().unwrap();
+    assert_eq!(hash.len(), 64);
+
+    // Regenerating the same model reproduces the aggregate
+    let second = gen_json();
+    assert_eq!(second["hash"], hash);
+    assert!(second["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|file| file["status"] == "unchanged"));
+
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["name"] = serde_json::json!("check_order");
+    });
+    let third = gen_json();
+    assert_ne!(third["hash"], hash);
+    assert!(third["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|file| file["status"] == "updated"));
+}
+
+#[test]
+fn test_gen_prune_removes_orphaned_handler() {
+    let temp = fixture_project();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen"])
+        .assert()
+        .success();
+
+    let handler = temp.path().join("gen/src/endpoints/create_refund.rs");
+    assert!(handler.exists());
+
+    fs::remove_file(temp.path().join(".intent/model/create-refund-endpoint.intent.json")).unwrap();
+
+    // Without --prune the handler is left in place and still tracked
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("use --prune to remove"));
+    assert!(handler.exists());
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--prune"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Pruned 1 orphaned files"));
+    assert!(!handler.exists());
+
+    let manifest = fs::read_to_string(temp.path().join(".intent/locks/gen-manifest.json")).unwrap();
+    assert!(!manifest.contains("create_refund.rs"));
+}
+
+#[test]
+fn test_gen_no_locks() {
+    let temp = fixture_project();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--no-locks"])
+        .assert()
+        .success();
+
+    assert!(temp.path().join("gen/src/lib.rs").exists());
+    assert!(!temp.path().join(".intent/locks/gen-manifest.json").exists());
+    assert!(!temp.path().join(".intent/locks/trace-map.json").exists());
+    assert!(!temp.path().join(".intent/locks/obligations.json").exists());
+
+    // Checking still works, but warns that the locks are missing
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--check"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("lock file .intent/locks/gen-manifest.json is missing"));
+}
+
+#[test]
+fn test_gen_adds_member_to_existing_workspace() {
+    let temp = fixture_project();
+    fs::write(temp.path().join("intent.toml"), "[generation]\nworkspace = true\n").unwrap();
+    let root_manifest = r#"# Host project
+[workspace]
+members = ["crates/app"] # keep this comment
+resolver = "2"
+
+[workspace.dependencies]
+serde = "1.0"
+"#;
+    fs::write(temp.path().join("Cargo.toml"), root_manifest).unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added gen to the workspace members in Cargo.toml"));
+
+    let updated = fs::read_to_string(temp.path().join("Cargo.toml")).unwrap();
+    assert_eq!(
+        updated,
+        root_manifest.replace(r#"["crates/app"]"#, r#"["crates/app", "gen"]"#)
+    );
+
+    // A second run finds the member and leaves the manifest alone
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Added gen").not());
+    assert_eq!(fs::read_to_string(temp.path().join("Cargo.toml")).unwrap(), updated);
+}
+
+#[test]
+fn test_gen_check_reports_drift_kinds() {
+    let temp = fixture_project();
+    intent_cmd().current_dir(temp.path()).args(["gen"]).assert().success();
+
+    // One hand-edited file and one deleted file
+    let types_path = temp.path().join("gen/src/types.rs");
+    let mut types = fs::read_to_string(&types_path).unwrap();
+    types.push_str("\n// manual edit\n");
+    fs::write(&types_path, types).unwrap();
+    fs::remove_file(temp.path().join("gen/src/errors.rs")).unwrap();
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--check", "--format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        result["drift"],
+        serde_json::json!({ "modified": 1, "missing": 1, "orphaned": 0, "new": 0 })
+    );
+    let reason = |path: &str| {
+        let files = result["files"].as_array().unwrap();
+        files.iter().find(|f| f["path"] == path).unwrap()["reason"].clone()
+    };
+    assert_eq!(reason("gen/src/types.rs"), "modified");
+    assert_eq!(reason("gen/src/errors.rs"), "missing");
+    assert_eq!(reason("gen/src/lib.rs"), serde_json::Value::Null);
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--check"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains(
+            "Generated code does not match: 1 modified, 1 missing, 0 new, 0 orphaned",
+        ))
+        .stdout(predicate::str::contains("gen/src/types.rs (modified)"))
+        .stdout(predicate::str::contains("gen/src/errors.rs (missing)"));
+
+    // Never generated before: new rather than missing
+    let fresh = fixture_project();
+    intent_cmd()
+        .current_dir(fresh.path())
+        .args(["gen", "--check"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("gen/src/lib.rs (new)"));
+}
+
+#[test]
+fn test_gen_check_skips_regeneration_when_sources_unchanged() {
+    let temp = fixture_project();
+
+    intent_cmd().current_dir(temp.path()).args(["gen"]).assert().success();
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"Generated \d+ files: 0 written, \d+ unchanged").unwrap());
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--check"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Sources unchanged since the manifest was written"));
+
+    // Any change to an intent falls back to the full check, even one that
+    // leaves the generated code as it was
+    tag_intent(&temp, "refund-workflow.intent.json", serde_json::json!(["billing"]));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["gen", "--check"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Generated code matches."))
+        .stdout(predicate::str::contains("Sources unchanged").not());
+}
+
+#[test]
+fn test_unused_endpoint_errors() {
+    let temp = fixture_project();
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["raise_if"] =
+            serde_json::json!({ "condition": "input.amount <= 0", "error": "INVALID_INPUT" });
+    });
+
+    // INVALID_INPUT is raised by the workflow, PAYMENT_FAILED is not
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[W015] Endpoint 'CreateRefund' declares error 'PAYMENT_FAILED' but workflow 'RefundWorkflow' never raises it",
+        ))
+        .stdout(predicate::str::contains("'INVALID_INPUT' but").not());
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("$.spec.errors[1].code"));
+
+    // A Raise inside a function the workflow calls counts as raising it
+    fs::write(
+        temp.path().join(".intent/model/check-refund.intent.json"),
+        serde_json::to_string_pretty(&serde_json::json!({
+            "schema_version": "2.0",
+            "id": "550e8400-e29b-41d4-a716-446655440012",
+            "kind": "Function",
+            "name": "CheckRefund",
+            "spec": {
+                "parameters": [{ "name": "amount", "type": "f64" }],
+                "returns": { "type": "f64" },
+                "body": {
+                    "kind": "If",
+                    "cond": { "kind": "Variable", "name": "amount" },
+                    "then_branch": { "kind": "Variable", "name": "amount" },
+                    "else_branch": { "kind": "Raise", "error": "PAYMENT_FAILED" }
+                }
+            }
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+    edit_refund_workflow(&temp, |doc| {
+        doc["spec"]["steps"][0]["assign"]["validated_amount"] =
+            serde_json::json!("check_refund(input.amount)");
+    });
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("W015").not());
+}
+
+#[test]
+fn test_function_body_names_resolve() {
+    let temp = fixture_project();
+    let write_function = |body: serde_json::Value| {
+        fs::write(
+            temp.path().join(".intent/model/refund-fee.intent.json"),
+            serde_json::to_string_pretty(&serde_json::json!({
+                "schema_version": "2.0",
+                "id": "550e8400-e29b-41d4-a716-446655440013",
+                "kind": "Function",
+                "name": "RefundFee",
+                "spec": {
+                    "parameters": [{ "name": "amounts", "type": "Vec<f64>" }],
+                    "returns": { "type": "f64" },
+                    "body": body
+                }
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+    };
+    let var = |name: &str| serde_json::json!({ "kind": "Variable", "name": name });
+
+    // Parameters, Let, For, Match and Closure bindings are all in scope
+    write_function(serde_json::json!({
+        "kind": "Let",
+        "bindings": [
+            { "name": "total", "value": { "kind": "Literal", "value": 0 } },
+            { "name": "first", "value": { "kind": "Index", "expr": var("amounts"), "index": var("total") } }
+        ],
+        "body": {
+            "kind": "Block",
+            "exprs": [
+                { "kind": "For", "var": "amount", "iterable": var("amounts"), "body": var("amount") },
+                {
+                    "kind": "Method", "expr": var("amounts"), "name": "map",
+                    "args": [{ "kind": "Closure", "params": ["a"], "body": var("a") }]
+                },
+                {
+                    "kind": "Match", "on": var("first"),
+                    "arms": [
+                        { "pattern": { "kind": "Some", "binding": "fee" }, "body": var("fee") },
+                        { "pattern": { "kind": "Wildcard" }, "body": { "kind": "Raise", "error": "INVALID_INPUT" } }
+                    ]
+                },
+                { "kind": "Call", "function": "format", "args": [var("context.currency")] }
+            ]
+        }
+    }));
+    intent_cmd()
+        .current_dir(temp.path())
+        .arg("validate")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("in Function 'RefundFee'").not());
+
+    // A binding doesn't leak out of the expression that introduces it
+    write_function(serde_json::json!({
+        "kind": "Block",
+        "exprs": [
+            { "kind": "For", "var": "amount", "iterable": var("amounts"), "body": var("amount") },
+            var("amount.value"),
+            { "kind": "Call", "function": "round_fee", "args": [] },
+            { "kind": "Struct", "name": "RefundFee", "fields": {} },
+            { "kind": "Raise", "error": "FEE_TOO_HIGH" }
+        ]
+    }));
+    intent_cmd()
+        .current_dir(temp.path())
+        .arg("validate")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("[E005] Unknown variable 'amount' in Function 'RefundFee'"))
+        .stdout(predicate::str::contains("[E005] Unknown type 'RefundFee' constructed in Function 'RefundFee'"))
+        .stdout(predicate::str::contains(
+            "[E005] Function 'RefundFee' raises 'FEE_TOO_HIGH', which no Endpoint or Workflow declares",
+        ))
+        .stdout(predicate::str::contains("$.spec.body.exprs[1].name"));
+
+    // Unknown calls may be helpers the generated crate provides, so they only warn
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--format", "json"])
+        .output()
+        .unwrap();
+    let validate: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let warnings = validate["result"]["warnings"].as_array().unwrap();
+    assert!(warnings
+        .iter()
+        .any(|w| w["message"] == "Unknown function 'round_fee' called in Function 'RefundFee'"));
+
+    // Format placeholders must line up with the args
+    let format = |template: &str, args: Vec<serde_json::Value>| {
+        serde_json::json!({ "kind": "Format", "template": template, "args": args })
+    };
+    write_function(format("fee of {} on {}", vec![var("amounts")]));
+    intent_cmd()
+        .current_dir(temp.path())
+        .arg("validate")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E009] Format template 'fee of {} on {}' in Function 'RefundFee' has 2 placeholders but 1 args",
+        ));
+    write_function(format("fee of {amount}", vec![var("amounts")]));
+    intent_cmd()
+        .current_dir(temp.path())
+        .arg("validate")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E009] Format template 'fee of {amount}' in Function 'RefundFee' may only use '{}' placeholders",
+        ));
+    write_function(format("{{fees}}: {}", vec![var("amounts")]));
+    intent_cmd()
+        .current_dir(temp.path())
+        .arg("validate")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("in Function 'RefundFee'").not());
+}
+
+#[test]
+fn test_pipeline_stage_expressions_resolve() {
+    let temp = fixture_project();
+    let write_pipeline = |skip_if: &str| {
+        fs::write(
+            temp.path().join(".intent/model/refund-pipeline.intent.json"),
+            serde_json::to_string_pretty(&serde_json::json!({
+                "schema_version": "2.0",
+                "id": "550e8400-e29b-41d4-a716-446655440013",
+                "kind": "Pipeline",
+                "name": "RefundPipeline",
+                "spec": {
+                    "input": "RefundRequest",
+                    "output": "RefundResponse",
+                    "stages": [{ "name": "process", "function": "process_refund", "skip_if": skip_if }]
+                }
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+    };
+
+    write_pipeline("input.amount <= 0 || input.reason.is_none()");
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success();
+
+    write_pipeline("input.total > 0");
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E009] 'input.total' in skip_if of stage 'process' does not resolve: type 'RefundRequest' has no field 'total'",
+        ));
+
+    write_pipeline("input.amount >");
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("[E009] Cannot parse skip_if of stage 'process'"));
+}
+
+fn write_module(temp: &TempDir, id: &str, name: &str, path: &str, child_file: &str) {
+    fs::write(
+        temp.path().join(format!(".intent/model/{}.intent.json", name.to_lowercase())),
+        serde_json::to_string_pretty(&serde_json::json!({
+            "schema_version": "2.0",
+            "id": id,
+            "kind": "Module",
+            "name": name,
+            "spec": {
+                "path": path,
+                "children": [{ "name": "types", "file": child_file, "public": true }]
+            }
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_module_output_collisions() {
+    let temp = fixture_project();
+    write_module(&temp, "550e8400-e29b-41d4-a716-446655440020", "ApiModule", "src/api", "types.rs");
+    write_module(&temp, "550e8400-e29b-41d4-a716-446655440021", "WebModule", "src/web", "types.rs");
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success();
+
+    // A different path that resolves to the same file still collides
+    write_module(&temp, "550e8400-e29b-41d4-a716-446655440021", "WebModule", "src/./api/", "types.rs");
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--format", "json"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "Module 'WebModule' writes 'src/api/types.rs', which module 'ApiModule' also writes",
+        ))
+        .stdout(predicate::str::contains("$.spec.children[0].file"))
+        .stdout(predicate::str::contains("E010"));
+}
+
+#[test]
+fn test_module_path_traversal() {
+    let temp = fixture_project();
+    write_module(&temp, "550e8400-e29b-41d4-a716-446655440020", "ApiModule", "src/api", "../../build.rs");
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E009] Child module 'types' file '../../build.rs' must not contain '..'",
+        ));
+}
+
+#[test]
+fn test_template_output_file_traversal() {
+    let temp = fixture_project();
+    let template = serde_json::json!({
+        "id": "550e8400-e29b-41d4-a716-446655440030",
+        "kind": "Template",
+        "name": "EscapeTemplate",
+        "schema_version": "2.0",
+        "spec": {
+            "input": "Vec<IntentDocument>",
+            "output_file": "../escape.rs",
+            "template": ["// escaped"]
+        }
+    });
+    fs::write(
+        temp.path().join(".intent/model/escape.template.intent.json"),
+        serde_json::to_string_pretty(&template).unwrap(),
+    )
+    .unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E009] Output path '../escape.rs' of Template 'EscapeTemplate' must be relative and must not contain '..'",
+        ));
+}
+
+#[test]
+fn test_json_output_is_versioned() {
+    let temp = fixture_project();
+
+    // Outside a git repository the base is empty, so every intent is added
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["diff", "--base", "HEAD", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let diff: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(diff["schema"], "intent-diff/1");
+    assert!(!diff["result"]["changes"].as_array().unwrap().is_empty());
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--format", "json"])
+        .output()
+        .unwrap();
+    let validate: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(validate["schema"], "intent-validate/1");
+    assert!(validate["result"]["errors"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_diff_filters_by_category_and_severity() {
+    let temp = fixture_project();
+    let diff = |args: &[&str]| -> serde_json::Value {
+        let output = intent_cmd()
+            .current_dir(temp.path())
+            .args(["diff", "--base", "HEAD", "--format", "json"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()["result"].clone()
+    };
+    let counts = |result: &serde_json::Value| {
+        ["high_count", "medium_count", "low_count", "info_count"].map(|count| result[count].as_u64().unwrap())
+    };
+
+    // Every fixture intent is added against an empty base
+    let all = diff(&[]);
+    assert_eq!(counts(&all), [4, 2, 2, 1]);
+
+    let result = diff(&["--category", "DataSchema,ApiSurface"]);
+    assert_eq!(counts(&result), [1, 1, 2, 0]);
+    assert!(result["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|change| change["category"] == "DataSchema" || change["category"] == "ApiSurface"));
+
+    let result = diff(&["--min-severity", "medium"]);
+    assert_eq!(counts(&result), [4, 2, 0, 0]);
+
+    let result = diff(&["--category", "Effects", "--min-severity", "high"]);
+    assert_eq!(counts(&result), [3, 0, 0, 0]);
+    assert_eq!(result["changes"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn test_diff_markdown_format() {
+    let temp = fixture_project();
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["diff", "--base", "HEAD", "--format", "markdown", "--min-severity", "high"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("### Semantic diff (4 changes)"))
+        .stdout(predicate::str::contains("| Severity | Category | Intent | Description |"))
+        .stdout(predicate::str::contains("<summary>HIGH (4)</summary>"));
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--format", "markdown"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--format markdown is only supported by `intent diff`"));
+}
+
+#[test]
+fn test_type_without_fields_warns() {
+    let temp = fixture_project();
+    let write_type = |name: &str, spec: serde_json::Value| {
+        let doc = serde_json::json!({
+            "schema_version": "2.0",
+            "id": uuid::Uuid::new_v4(),
+            "kind": "Type",
+            "name": name,
+            "spec": spec
+        });
+        fs::write(
+            temp.path().join(format!(".intent/model/{}.intent.json", name.to_lowercase())),
+            serde_json::to_string_pretty(&doc).unwrap(),
+        )
+        .unwrap();
+    };
+
+    // The fixture types all have fields
+    intent_cmd()
+        .current_dir(temp.path())
+        .arg("validate")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("W017").not());
+
+    write_type("Blank", serde_json::json!({ "fields": {} }));
+    write_type("Marker", serde_json::json!({ "fields": {}, "unit": true }));
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--json-schema-validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[W017] Type 'Blank' has no fields"))
+        .stdout(predicate::str::contains("Type 'Marker' has no fields").not());
+}
+
+#[test]
+fn test_validate_baseline() {
+    let temp = fixture_project();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--baseline", "baseline.json", "--write-baseline"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Wrote baseline with 3 diagnostics"));
+
+    // The existing warning is accepted
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--baseline", "baseline.json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3 baselined diagnostics suppressed"))
+        .stdout(predicate::str::contains("W015").not());
+
+    // A newly introduced warning is still reported
+    let file = temp.path().join(".intent/model/refund-response.intent.json");
+    let mut doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    doc["spec"]["fields"]["customer_email"] = serde_json::json!({ "type": "string" });
+    fs::write(&file, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--baseline", "baseline.json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[W003] Field 'customer_email'"))
+        .stdout(predicate::str::contains("W015").not());
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--baseline", "missing.json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Baseline file not found"));
+}
+
+#[test]
+fn test_validate_info_diagnostics() {
+    let temp = fixture_project();
+
+    // An unreferenced Type with the same fields as RefundResponse
+    let source = temp.path().join(".intent/model/refund-response.intent.json");
+    let mut doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(&source).unwrap()).unwrap();
+    doc["id"] = serde_json::json!("550e8400-e29b-41d4-a716-446655440030");
+    doc["name"] = serde_json::json!("RefundSummary");
+    fs::write(
+        temp.path().join(".intent/model/refund-summary.intent.json"),
+        serde_json::to_string_pretty(&doc).unwrap(),
+    )
+    .unwrap();
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 info diagnostics hidden"))
+        .stdout(predicate::str::contains("I001").not());
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--show-info"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[I001] Type 'RefundSummary' is not referenced by any intent"))
+        .stdout(predicate::str::contains(
+            "[I002] Type 'RefundSummary' has exactly the same fields as 'RefundResponse'",
+        ));
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--fail-on-info"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("Validation failed with 2 info diagnostics"));
+}
+
+fn emit_step(name: &str) -> serde_json::Value {
+    serde_json::json!({
+        "kind": "Effect",
+        "name": name,
+        "effect": "EmitEvent",
+        "topic": "refund.created",
+        "input_mapping": {"id": "context.refund_id"},
+        "on_error": "abort"
+    })
+}
+
+#[test]
+fn test_emit_before_write_hazard() {
+    let temp = fixture_project();
+    edit_refund_workflow(&temp, |doc| {
+        name_refund_steps(doc);
+        let steps = doc["spec"]["steps"].as_array_mut().unwrap();
+        steps.insert(2, emit_step("announce_refund"));
+    });
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[W016] Workflow 'RefundWorkflow' emits event 'refund.created' in step 'announce_refund' before writing table 'refunds' in step 'record_refund'",
+        ));
+}
+
+#[test]
+fn test_benign_effect_ordering() {
+    let temp = fixture_project();
+    edit_refund_workflow(&temp, |doc| {
+        name_refund_steps(doc);
+        let steps = doc["spec"]["steps"].as_array_mut().unwrap();
+        steps.push(serde_json::json!({
+            "kind": "Effect",
+            "name": "reload_refund",
+            "effect": "DbRead",
+            "table": "refunds",
+            "input_mapping": {"id": "context.refund_id"},
+            "on_error": "abort"
+        }));
+        steps.push(emit_step("announce_refund"));
+    });
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--show-info"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("W016").not())
+        .stdout(predicate::str::contains(
+            "[I003] Workflow 'RefundWorkflow' reads table 'refunds' in step 'reload_refund' after writing it in step 'record_refund'",
+        ));
+}
+
+#[test]
+fn test_validate_junit_output() {
+    let temp = fixture_project();
+    let file = temp.path().join(".intent/model/refund-response.intent.json");
+    let content = fs::read_to_string(&file).unwrap();
+    fs::write(&file, content.replace("\"RefundResponse\"", "\"RefundResult\"")).unwrap();
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--format", "junit"])
+        .assert()
+        .code(2)
+        .get_output()
+        .stdout
+        .clone();
+    let xml = String::from_utf8(output).unwrap();
+
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+    assert_eq!(xml.matches("<testcase ").count(), 7);
+    assert!(xml.contains(r#"<testcase classname="Type" name="RefundResult"/>"#));
+    assert!(xml.contains(
+        r#"<failure type="E005" message="Unknown type reference: RefundResponse">"#
+    ));
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["list", "--format", "junit"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--format junit is only supported by `intent validate`"));
+}
+
+#[test]
+fn test_json_errors_carry_help_url() {
+    let temp = fixture_project();
+    let file = temp.path().join(".intent/model/refund-response.intent.json");
+    let content = fs::read_to_string(&file).unwrap();
+    fs::write(&file, content.replace("\"RefundResponse\"", "\"RefundResult\"")).unwrap();
+
+    let output = intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate", "--format", "json"])
+        .assert()
+        .code(2)
+        .get_output()
+        .stdout
+        .clone();
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    let error = &json["result"]["errors"][0];
+    assert_eq!(error["code"], "E005");
+    assert_eq!(
+        error["help_url"],
+        "https://github.com/mazharm/intent-engine/blob/main/docs/error-codes.md#e005-unknown-reference"
+    );
+}
+
+fn use_error_enum(temp: &TempDir, variants: &[&str]) {
+    let variants: Vec<_> = variants
+        .iter()
+        .map(|name| serde_json::json!({ "name": name }))
+        .collect();
+    fs::write(
+        temp.path().join(".intent/model/refund-error-code.intent.json"),
+        serde_json::to_string_pretty(&serde_json::json!({
+            "schema_version": "2.0",
+            "id": "550e8400-e29b-41d4-a716-446655440040",
+            "kind": "Enum",
+            "name": "RefundErrorCode",
+            "spec": { "variants": variants }
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+
+    let file = temp.path().join(".intent/model/create-refund-endpoint.intent.json");
+    let mut doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    doc["spec"]["error_enum"] = serde_json::json!("RefundErrorCode");
+    fs::write(&file, serde_json::to_string_pretty(&doc).unwrap()).unwrap();
+}
+
+#[test]
+fn test_error_codes_in_enum() {
+    let temp = fixture_project();
+    use_error_enum(&temp, &["INVALID_INPUT", "PAYMENT_FAILED", "NOT_FOUND"]);
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_error_code_not_in_enum() {
+    let temp = fixture_project();
+    use_error_enum(&temp, &["INVALID_INPUT"]);
+
+    intent_cmd()
+        .current_dir(temp.path())
+        .args(["validate"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "[E005] Error code 'PAYMENT_FAILED' is not a variant of enum 'RefundErrorCode'",
+        ))
+        .stdout(predicate::str::contains("$.spec.errors[1].code"));
+}
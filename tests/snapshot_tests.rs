@@ -1,17 +1,47 @@
 //! Snapshot tests for code generation
 
-use intent_engine::codegen::{generate_types, generate_endpoints, generate_workflows};
-use intent_engine::parser::IntentStore;
-use std::path::PathBuf;
+use intent_engine::codegen::{generate_types, generate_endpoints, generate_workflows, generate_main_rs, generate_commands, generate_cargo_toml, generate_errors, generate_functions, generate_effects, generate_service_clients, generate_contract_tests, generate_enums, generate_with_config, GenFilter, LockFiles, MemoryWriter};
+use intent_engine::model::{IntentDocument, IntentKind};
+use intent_engine::parser::{GenTarget, IntentConfig, IntentStore, ResponseFormat};
+use quote::ToTokens;
+use std::path::Path;
 
 fn load_fixtures() -> IntentStore {
     IntentStore::load_from_path("fixtures/valid").expect("Failed to load fixtures")
 }
 
+/// Generate the whole crate for `store` and `cargo check` it offline. Every
+/// generated crate shares one target directory, so the dependencies only
+/// build once.
+fn assert_generated_crate_compiles(store: &IntentStore, config: &IntentConfig) {
+    let mut writer = MemoryWriter::new();
+    generate_with_config(store, config, false, &GenFilter::default(), false, LockFiles::none(), &mut writer)
+        .expect("generation failed");
+
+    let dir = tempfile::tempdir().unwrap();
+    for (path, content) in &writer.files {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    let output = std::process::Command::new(env!("CARGO"))
+        .args(["check", "--offline", "--quiet", "--all-targets"])
+        .current_dir(dir.path().join("gen"))
+        .env("CARGO_TARGET_DIR", Path::new(env!("CARGO_TARGET_TMPDIR")).join("generated-crates"))
+        .output()
+        .expect("failed to run cargo");
+    assert!(
+        output.status.success(),
+        "generated crate does not compile:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
 #[test]
 fn test_types_generation() {
     let store = load_fixtures();
-    let content = generate_types(&store);
+    let content = generate_types(&store, &IntentConfig::default());
 
     insta::assert_snapshot!("types_rs", content);
 }
@@ -19,7 +49,7 @@ fn test_types_generation() {
 #[test]
 fn test_endpoints_generation() {
     let store = load_fixtures();
-    let output = generate_endpoints(&store);
+    let output = generate_endpoints(&store, &IntentConfig::default());
 
     insta::assert_snapshot!("endpoints_mod_rs", output.mod_rs);
 
@@ -39,3 +69,983 @@ fn test_workflows_generation() {
         insta::assert_snapshot!(format!("workflow_{}", file.name), &file.content);
     }
 }
+
+#[test]
+fn test_workflow_context_struct() {
+    let mut store = load_fixtures();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Workflow,
+            "LookupRefund".to_string(),
+            serde_json::json!({
+                "input": "RefundRequest",
+                "output": "RefundResponse",
+                "context": {
+                    "order_id": "uuid",
+                    "type": "string",
+                    "existing": "RefundResponse"
+                },
+                "steps": [
+                    {
+                        "kind": "Transform",
+                        "name": "capture_order",
+                        "assign": { "order_id": "input.order_id", "type": "input.reason" }
+                    },
+                    {
+                        "kind": "Effect",
+                        "name": "load_refund",
+                        "when": "context.type != 'test'",
+                        "effect": "DbRead",
+                        "table": "refunds",
+                        "input_mapping": { "order_id": "context.order_id" },
+                        "output_binding": "existing"
+                    }
+                ]
+            }),
+        ))
+        .unwrap();
+
+    let output = generate_workflows(&store);
+    let file = output
+        .files
+        .iter()
+        .find(|f| f.name == "lookup_refund.rs")
+        .expect("workflow file");
+    // A required uuid is wrapped once; the optional reason is already an
+    // `Option<Option<String>>`
+    assert!(file.content.contains("context.order_id = Some(context.input.order_id.clone());"));
+    assert!(file.content.contains("context.r#type = context.input.reason.clone().flatten();"));
+    insta::assert_snapshot!("workflow_lookup_refund_context", &file.content);
+    assert_generated_crate_compiles(&store, &IntentConfig::default());
+}
+
+#[test]
+fn test_conditional_workflow_steps() {
+    let mut store = load_fixtures();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Workflow,
+            "NotifyRefund".to_string(),
+            serde_json::json!({
+                "input": "RefundRequest",
+                "output": "RefundResponse",
+                "context": { "reason": "string" },
+                "steps": [
+                    {
+                        "kind": "Transform",
+                        "name": "capture_reason",
+                        "when": "input.reason != null",
+                        "assign": { "reason": "input.reason" }
+                    },
+                    {
+                        "kind": "Effect",
+                        "name": "notify",
+                        "when": "context.reason == 'fraud' || context.reason.len() > 100",
+                        "effect": "EmitEvent",
+                        "topic": "RefundFlagged"
+                    }
+                ]
+            }),
+        ))
+        .unwrap();
+
+    let output = generate_workflows(&store);
+    let file = output
+        .files
+        .iter()
+        .find(|f| f.name == "notify_refund.rs")
+        .expect("workflow file");
+    assert!(file.content.contains("if context.input.reason != None {"));
+    insta::assert_snapshot!("workflow_notify_refund_conditional", &file.content);
+    assert_generated_crate_compiles(&store, &IntentConfig::default());
+}
+
+#[test]
+fn test_tagged_enum_output_endpoint() {
+    let mut store = load_fixtures();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Enum,
+            "RefundOutcome".to_string(),
+            serde_json::json!({
+                "serde_tag": "outcome",
+                "variants": [
+                    { "name": "Succeeded", "data": { "refund_id": "uuid" }, "status": 201 },
+                    { "name": "Declined", "data": { "reason": "string" }, "status": 402 },
+                    { "name": "Pending" }
+                ]
+            }),
+        ))
+        .unwrap();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Endpoint,
+            "ReviewRefund".to_string(),
+            serde_json::json!({
+                "method": "POST",
+                "path": "/refund/review",
+                "input": "RefundRequest",
+                "output": "RefundOutcome",
+                "workflow": "DecideRefund"
+            }),
+        ))
+        .unwrap();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Workflow,
+            "DecideRefund".to_string(),
+            serde_json::json!({
+                "input": "RefundRequest",
+                "output": "RefundOutcome",
+                "context": { "previous": "optional<RefundOutcome>" },
+                "steps": []
+            }),
+        ))
+        .unwrap();
+
+    let output = generate_endpoints(&store, &IntentConfig::default());
+    let file = output
+        .files
+        .iter()
+        .find(|f| f.name == "review_refund.rs")
+        .expect("enum output endpoint file");
+    insta::assert_snapshot!("endpoint_review_refund_enum_output", &file.content);
+
+    let output = generate_workflows(&store);
+    let file = output
+        .files
+        .iter()
+        .find(|f| f.name == "decide_refund.rs")
+        .expect("enum output workflow file");
+    insta::assert_snapshot!("workflow_decide_refund_enum_output", &file.content);
+    assert_generated_crate_compiles(&store, &IntentConfig::default());
+}
+
+#[test]
+fn test_enums_generation() {
+    let mut store = load_fixtures();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Enum,
+            "RefundEvent".to_string(),
+            serde_json::json!({
+                "description": "Something that happened to a refund",
+                "derives": ["Debug", "Clone", "Serialize", "Deserialize"],
+                "generics": [{ "name": "M", "bounds": ["Clone", "std::fmt::Debug"] }],
+                "variants": [
+                    { "name": "Requested", "description": "Submitted by the customer" },
+                    { "name": "Amended", "data": ["uuid", "optional<string>"], "serde_rename": "amended" },
+                    { "name": "Settled", "data": { "refund": "RefundResponse", "type": "string", "metadata": "M" } }
+                ]
+            }),
+        ))
+        .unwrap();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Enum,
+            "RefundOutcome".to_string(),
+            serde_json::json!({
+                "serde_tag": "outcome",
+                "variants": [
+                    { "name": "Succeeded", "data": { "refund_id": "uuid", "amounts": "array<money>" } },
+                    { "name": "Pending" }
+                ]
+            }),
+        ))
+        .unwrap();
+
+    let output = generate_enums(&store).unwrap();
+    assert_eq!(output.intent_ids.len(), 2);
+    insta::assert_snapshot!("enums_rs", output.enums_rs);
+
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Enum,
+            "Lookup".to_string(),
+            serde_json::json!({ "variants": [{ "name": "Names", "data": ["Vec<String"] }] }),
+        ))
+        .unwrap();
+    let error = generate_enums(&store).err().expect("unparseable variant data is an error");
+    assert_eq!(error.to_string(), "Enum 'Lookup': 'Vec<String' is not a valid Rust type");
+}
+
+#[test]
+fn test_main_rs_generation() {
+    let mut config = IntentConfig::default();
+    config.project.name = "refund-service".to_string();
+    config.generation.binary = true;
+    let content = generate_main_rs(&config);
+    insta::assert_snapshot!("main_rs", content);
+}
+
+#[test]
+fn test_endpoint_retry_backoff() {
+    for backoff in ["constant", "linear", "exponential"] {
+        let mut store = load_fixtures();
+        store
+            .add(IntentDocument::with_spec(
+                IntentKind::Endpoint,
+                "RetryRefund".to_string(),
+                serde_json::json!({
+                    "method": "POST",
+                    "path": "/refund/retry",
+                    "input": "RefundRequest",
+                    "output": "RefundResponse",
+                    "workflow": "RefundWorkflow",
+                    "policies": { "timeout_ms": 2000, "retries": { "max": 2, "backoff": backoff } },
+                    "errors": [
+                        { "code": "INVALID_INPUT", "status": 400 },
+                        { "code": "UPSTREAM_BUSY", "status": 503, "retryable": true }
+                    ]
+                }),
+            ))
+            .unwrap();
+
+        let output = generate_endpoints(&store, &IntentConfig::default());
+        let file = output
+            .files
+            .iter()
+            .find(|f| f.name == "retry_refund.rs")
+            .expect("retry endpoint file");
+        assert!(file.content.contains("const MAX_RETRIES: u32 = 2;"));
+        assert!(file.content.contains("const BACKOFF_BASE_MS: u64 = 200;"));
+        insta::assert_snapshot!(format!("endpoint_retry_refund_{}.rs", backoff), &file.content);
+    }
+}
+
+#[test]
+fn test_patch_endpoint_generation() {
+    let mut store = load_fixtures();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Endpoint,
+            "UpdateRefund".to_string(),
+            serde_json::json!({
+                "method": "PATCH",
+                "path": "/refund/:order_id",
+                "input": "RefundRequest",
+                "output": "RefundResponse",
+                "workflow": "RefundWorkflow",
+                "partial": true
+            }),
+        ))
+        .unwrap();
+
+    insta::assert_snapshot!("types_rs_with_patch", generate_types(&store, &IntentConfig::default()));
+
+    let output = generate_endpoints(&store, &IntentConfig::default());
+    let file = output
+        .files
+        .iter()
+        .find(|f| f.name == "update_refund.rs")
+        .expect("patch endpoint file");
+    assert!(file.content.contains("Path(key): Path<HashMap<String, String>>,"));
+    assert!(file.content.contains("db::read(\"refund_request\", &key)"));
+    insta::assert_snapshot!("endpoint_update_refund.rs", &file.content);
+    assert_generated_crate_compiles(&store, &IntentConfig::default());
+}
+
+#[test]
+fn test_rust_type_override() {
+    let mut store = IntentStore::new();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Type,
+            "Account".to_string(),
+            serde_json::json!({
+                "fields": {
+                    "id": { "type": "int", "required": true, "rust_type_override": "u32" },
+                    "parent_id": { "type": "int", "rust_type_override": "u64" },
+                    "balance": { "type": "int", "required": true }
+                }
+            }),
+        ))
+        .unwrap();
+
+    let content = generate_types(&store, &IntentConfig::default());
+    assert!(content.contains("pub id: u32,"));
+    assert!(content.contains("pub parent_id: Option<u64>,"));
+    assert!(content.contains("pub balance: i64,"));
+}
+
+#[test]
+fn test_readonly_fields_left_out_of_input() {
+    let mut store = IntentStore::new();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Type,
+            "Order".to_string(),
+            serde_json::json!({
+                "fields": {
+                    "id": { "type": "uuid", "readonly": true },
+                    "created_at": { "type": "datetime", "readonly": true },
+                    "item": { "type": "string", "required": true }
+                }
+            }),
+        ))
+        .unwrap();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Endpoint,
+            "CreateOrder".to_string(),
+            serde_json::json!({
+                "method": "POST",
+                "path": "/orders",
+                "input": "Order",
+                "output": "Order",
+                "workflow": "OrderWorkflow"
+            }),
+        ))
+        .unwrap();
+
+    let content = generate_types(&store, &IntentConfig::default());
+    let input = &content[content.find("pub struct OrderInput").expect("input struct")..];
+    let input = &input[..input.find('}').unwrap()];
+    assert!(input.contains("pub item: String"));
+    assert!(!input.contains("pub id"));
+    assert!(!input.contains("pub created_at"));
+    insta::assert_snapshot!("types_rs_readonly", content);
+
+    let output = generate_endpoints(&store, &IntentConfig::default());
+    let file = output
+        .files
+        .iter()
+        .find(|f| f.name == "create_order.rs")
+        .expect("endpoint file");
+    assert!(file.content.contains("Json(input): Json<OrderInput>"));
+    insta::assert_snapshot!("endpoint_create_order_readonly", &file.content);
+}
+
+#[test]
+fn test_bytes_field_generation() {
+    let mut store = IntentStore::new();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Type,
+            "Attachment".to_string(),
+            serde_json::json!({
+                "fields": {
+                    "content": { "type": "bytes", "required": true },
+                    "thumbnail": { "type": "bytes", "required": false },
+                    "chunks": { "type": "array<bytes>", "required": true },
+                    "previews": { "type": "optional<array<bytes>>", "required": true },
+                    "name": { "type": "string", "required": true, "sensitive": false }
+                }
+            }),
+        ))
+        .unwrap();
+
+    let config = IntentConfig::default();
+    let content = generate_types(&store, &config);
+    assert!(content.contains(r#"#[serde(with = "crate::types::bytes_encoding")]"#));
+    assert!(!content.contains("bytes_encoding::option"));
+    assert!(generate_cargo_toml(&store, &config).contains("\nbase64 = "));
+    insta::assert_snapshot!("types_rs_bytes", content);
+    assert_generated_crate_compiles(&store, &config);
+
+    // The fixtures have no bytes fields, so no encoding crate
+    assert!(!generate_cargo_toml(&load_fixtures(), &config).contains("base64"));
+}
+
+#[test]
+fn test_sensitive_field_debug_redaction() {
+    let mut store = IntentStore::new();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Type,
+            "Customer".to_string(),
+            serde_json::json!({
+                "fields": {
+                    "id": { "type": "uuid", "required": true },
+                    "tax_id": { "type": "string", "required": true, "sensitive": true },
+                    "email": { "type": "string", "required": false },
+                    "display_name": { "type": "string", "required": true, "sensitive": false }
+                }
+            }),
+        ))
+        .unwrap();
+
+    let content = generate_types(&store, &IntentConfig::default());
+    assert!(!content.contains("#[derive(Debug"));
+    assert!(content.contains(r#".field("tax_id", &"***")"#));
+    assert!(content.contains(r#".field("display_name", &self.display_name)"#));
+    insta::assert_snapshot!("types_rs_sensitive", content);
+}
+
+#[test]
+fn test_field_default_generation() {
+    let mut store = IntentStore::new();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Type,
+            "ListOrders".to_string(),
+            serde_json::json!({
+                "fields": {
+                    "customer_id": { "type": "uuid", "required": true },
+                    "page_size": { "type": "int32", "required": false, "default": 50 },
+                    "sort": { "type": "string", "required": true, "default": "created_at" },
+                    "include_archived": { "type": "bool", "required": false, "default": false },
+                    "statuses": { "type": "array<string>", "required": false, "default": ["open"] }
+                }
+            }),
+        ))
+        .unwrap();
+
+    let content = generate_types(&store, &IntentConfig::default());
+    assert!(content.contains(r#"#[serde(default = "default_list_orders_page_size")]"#));
+    assert!(!content.contains("default_list_orders_customer_id"));
+    insta::assert_snapshot!("types_rs_field_defaults", content);
+}
+
+#[test]
+fn test_conditional_derives() {
+    let store = load_fixtures();
+    let mut config = IntentConfig::default();
+    config
+        .generation
+        .conditional_derives
+        .insert("sqlx".to_string(), vec!["sqlx::FromRow".to_string()]);
+
+    let content = generate_types(&store, &config);
+    assert!(content.contains(r#"#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]"#));
+    insta::assert_snapshot!("types_rs_conditional_derives", content);
+
+    let cargo_toml = generate_cargo_toml(&store, &config);
+    assert!(cargo_toml.contains("\n[features]\nsqlx = []\n"));
+}
+
+#[test]
+fn test_diesel_db_backend() {
+    let store = load_fixtures();
+    let mut config = IntentConfig::default();
+    let sqlx_db = generate_effects(&store, &config).db_rs;
+    assert!(sqlx_db.contains("use sqlx::PgPool;"));
+    assert!(generate_cargo_toml(&store, &config).contains("\nsqlx = "));
+
+    config.runtime.db_client = "diesel".to_string();
+    let db_rs = generate_effects(&store, &config).db_rs;
+    assert!(db_rs.contains("use diesel_async::AsyncPgConnection;"));
+    assert!(db_rs.contains("pub type DbPool = Pool<AsyncPgConnection>;"));
+    assert!(!db_rs.contains("sqlx"));
+
+    let cargo_toml = generate_cargo_toml(&store, &config);
+    assert!(cargo_toml.contains("\ndiesel = { version = \"2.1\", features = [\"postgres\"] }\n"));
+    assert!(cargo_toml.contains("\ndiesel-async = "));
+    assert!(!cargo_toml.contains("sqlx"));
+    // The HTTP client is unaffected
+    assert!(cargo_toml.contains("\nreqwest = "));
+}
+
+#[test]
+fn test_service_client_generation() {
+    let store = load_fixtures();
+    let content = generate_service_clients(&store, &IntentConfig::default()).unwrap().mod_rs;
+    assert!(content.contains("pub struct PaymentsClient {"));
+    assert!(content.contains("input: &RefundRequest,\n    ) -> Result<RefundResponse, HttpError> {"));
+    assert!(content.contains(r#"send("POST", &format!("{}/v1/refund", self.base_url), input).await"#));
+    insta::assert_snapshot!("services_rs", content);
+
+    // The default environment's override replaces the Service's base_url
+    let config: IntentConfig = toml::from_str(
+        "[environments]\ndefault = \"dev\"\n\n[environments.dev]\n\"Payments.base_url\" = \"http://localhost:8080\"\n",
+    )
+    .unwrap();
+    let content = generate_service_clients(&store, &config).unwrap().mod_rs;
+    assert!(content.contains(r#".unwrap_or_else(|_| "http://localhost:8080".to_string())"#));
+}
+
+#[test]
+fn test_contract_test_generation() {
+    let mut store = load_fixtures();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::ContractTest,
+            "PaymentsHeadersContract".to_string(),
+            serde_json::json!({
+                "service": "Payments",
+                "operation": "Refund",
+                "scenarios": [
+                    {
+                        "name": "json_response",
+                        "request": { "amount": 1000, "order_id": "order-123" },
+                        "response": {
+                            "status": 200,
+                            "headers": { "Content-Type": "application/json", "x-request-id": "abc" },
+                            "body": { "refund_id": "@uuid" }
+                        }
+                    },
+                    {
+                        "name": "rejected",
+                        "request": { "amount": 999999, "order_id": "order-456" },
+                        "response": [
+                            { "status": 400, "body": { "error": "insufficient_funds" } },
+                            { "status": 402, "body": { "error": "insufficient_funds" } }
+                        ]
+                    }
+                ]
+            }),
+        ))
+        .unwrap();
+
+    let output = generate_contract_tests(&store, &IntentConfig::default()).unwrap();
+    assert_eq!(output.intent_ids.len(), 2);
+    let content = output.contracts_rs;
+    // Header names are lowercased for `HeaderMap` lookups
+    assert!(content.contains(r#"("content-type", "application/json"),"#));
+    assert!(content.contains(r#"("x-request-id", "abc"),"#));
+    assert!(content.contains("status: 402,"));
+    assert!(content.contains("async fn payments_headers_contract_rejected() {"));
+    insta::assert_snapshot!("contracts_rs", content);
+
+    assert!(generate_cargo_toml(&store, &IntentConfig::default()).contains("\n[dev-dependencies]\nreqwest = "));
+    assert_generated_crate_compiles(&store, &IntentConfig::default());
+}
+
+#[test]
+fn test_wasm_target_generation() {
+    let mut store = load_fixtures();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Endpoint,
+            "RetryRefund".to_string(),
+            serde_json::json!({
+                "method": "POST",
+                "path": "/refund/retry",
+                "input": "RefundRequest",
+                "output": "RefundResponse",
+                "workflow": "RefundWorkflow",
+                "policies": { "timeout_ms": 2000, "retries": { "max": 2, "backoff": "constant" } },
+                "errors": [{ "code": "UPSTREAM_BUSY", "status": 503, "retryable": true }]
+            }),
+        ))
+        .unwrap();
+    let mut config = IntentConfig::default();
+    config.generation.target = GenTarget::Wasm;
+    config.generation.binary = true;
+
+    let effects = generate_effects(&store, &config);
+    assert!(effects.http_rs.contains("gloo_net::http::Request::post(url)"));
+    assert!(effects.http_rs.contains("#[cfg(not(target_arch = \"wasm32\"))]\n    #[error(\"HTTP request failed: {0}\")]\n    Request(#[from] reqwest::Error),"));
+    insta::assert_snapshot!("effects_http_rs_wasm", effects.http_rs);
+    assert!(effects.db_rs.contains("#[cfg(not(target_arch = \"wasm32\"))]\nuse sqlx::PgPool;"));
+    assert!(effects.db_rs.contains("#[cfg(not(target_arch = \"wasm32\"))]\npub type DbPool = PgPool;"));
+
+    // Retries sleep on a browser timer instead of tokio
+    let endpoints = generate_endpoints(&store, &config);
+    let retry = endpoints.files.iter().find(|f| f.name == "retry_refund.rs").unwrap();
+    assert!(retry.content.contains(
+        "#[cfg(not(target_arch = \"wasm32\"))]\n                tokio::time::sleep(backoff(attempt)).await;\n                #[cfg(target_arch = \"wasm32\")]\n                gloo_timers::future::sleep(backoff(attempt)).await;"
+    ));
+
+    let cargo_toml = generate_cargo_toml(&store, &config);
+    let (shared, target_specific) = cargo_toml.split_once("[target.").unwrap();
+    assert!(!shared.contains("tokio") && !shared.contains("reqwest") && !shared.contains("sqlx"));
+    assert!(shared.contains(r#"axum = { version = "0.7", default-features = false, features = ["json"] }"#));
+    assert!(target_specific.contains("gloo-net") && target_specific.contains("gloo-timers"));
+    assert!(!cargo_toml.contains("[[bin]]"));
+
+    // The native side of the cfg split still builds
+    assert_generated_crate_compiles(&store, &config);
+
+    let native = generate_effects(&store, &IntentConfig::default());
+    assert!(!native.http_rs.contains("gloo"));
+    assert!(!native.db_rs.contains("target_arch"));
+}
+
+#[test]
+fn test_shared_error_enum_generation() {
+    let mut store = load_fixtures();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Enum,
+            "RefundErrorCode".to_string(),
+            serde_json::json!({
+                "variants": [
+                    { "name": "INVALID_INPUT" },
+                    { "name": "PaymentFailed", "serde_rename": "PAYMENT_FAILED" },
+                    { "name": "NOT_FOUND" }
+                ]
+            }),
+        ))
+        .unwrap();
+    let endpoint = store.find_by_name("CreateRefund").unwrap().clone();
+    store.remove(&endpoint.id);
+    let mut endpoint = endpoint;
+    endpoint.spec["error_enum"] = serde_json::json!("RefundErrorCode");
+    store.add(endpoint).unwrap();
+
+    let content = generate_errors(&store, &IntentConfig::default());
+    assert!(content.contains("Code(#[from] RefundErrorCode)"));
+    assert!(content.contains("Self::Code(_) => axum::http::StatusCode::INTERNAL_SERVER_ERROR"));
+    insta::assert_snapshot!("errors_rs_shared_enum", content);
+}
+
+#[test]
+fn test_comparable_error_generation() {
+    let store = load_fixtures();
+    let mut config = IntentConfig::default();
+    config.generation.comparable_errors = true;
+
+    let content = generate_errors(&store, &config);
+    assert!(content.contains("#[derive(Debug, Clone, PartialEq, thiserror::Error)]"));
+    assert!(content.contains("impl From<anyhow::Error> for CreateRefundError"));
+    insta::assert_snapshot!("errors_rs_comparable", content);
+
+    // Every error enum derives the comparison traits and no variant holds a
+    // field type that would keep the derive from compiling
+    let file = syn::parse_file(&content).unwrap();
+    let enums: Vec<_> = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Enum(item) => Some(item),
+            _ => None,
+        })
+        .collect();
+    assert!(!enums.is_empty());
+    for item in enums {
+        let derives: Vec<String> = item
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("derive"))
+            .map(|attr| attr.meta.require_list().unwrap().tokens.to_string())
+            .collect();
+        assert!(
+            derives.iter().any(|d| d.contains("Clone") && d.contains("PartialEq")),
+            "{} derives {:?}",
+            item.ident,
+            derives
+        );
+        for variant in &item.variants {
+            for field in &variant.fields {
+                let ty = field.ty.to_token_stream().to_string();
+                assert!(!ty.contains("anyhow"), "{}::{} holds {}", item.ident, variant.ident, ty);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_command_registry_generation() {
+    let mut store = IntentStore::new();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Command,
+            "GenCommand".to_string(),
+            serde_json::json!({
+                "command": "gen",
+                "description": "Generate Rust code",
+                "handler": "GenPipeline",
+                "aliases": ["g"],
+                "args": [
+                    { "name": "check", "long": "check", "type": "bool", "description": "Check only" }
+                ]
+            }),
+        ))
+        .unwrap();
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Command,
+            "ShowCommand".to_string(),
+            serde_json::json!({
+                "command": "show",
+                "handler": "show_intent",
+                "args": [
+                    { "name": "name", "type": "string", "required": true, "positional": true }
+                ]
+            }),
+        ))
+        .unwrap();
+
+    let output = generate_commands(&store);
+    assert!(output.mod_rs.contains("pub mod registry;"));
+    let registry = output
+        .files
+        .iter()
+        .find(|f| f.name == "registry.rs")
+        .expect("registry.rs not generated");
+    insta::assert_snapshot!("command_registry_rs", registry.content);
+}
+
+#[test]
+fn test_response_negotiation_generation() {
+    let store = load_fixtures();
+    let mut config = IntentConfig::default();
+    config.generation.response_formats = vec![ResponseFormat::Json, ResponseFormat::Msgpack];
+
+    let output = generate_endpoints(&store, &config);
+    let file = output
+        .files
+        .iter()
+        .find(|f| f.name == "create_refund.rs")
+        .expect("create_refund.rs not generated");
+    assert!(file.content.contains("Negotiated<RefundResponse>"));
+    assert!(!output.mod_rs.contains("Cbor"));
+
+    insta::assert_snapshot!("endpoint_create_refund_negotiated", &file.content);
+    insta::assert_snapshot!("endpoints_mod_rs_negotiated", output.mod_rs);
+}
+
+fn add_function(store: &mut IntentStore, name: &str, spec: serde_json::Value) {
+    store
+        .add(IntentDocument::with_spec(IntentKind::Function, name.to_string(), spec))
+        .unwrap();
+}
+
+#[test]
+fn test_functions_generation() {
+    use serde_json::json;
+
+    let mut store = load_fixtures();
+    let var = |name: &str| json!({ "kind": "Variable", "name": name });
+    let lit = |value: serde_json::Value| json!({ "kind": "Literal", "value": value });
+    let bin = |op: &str, left: serde_json::Value, right: serde_json::Value| {
+        json!({ "kind": "Binary", "op": op, "left": left, "right": right })
+    };
+
+    // Let, UnwrapOr, If/else if, Binary (incl. concatenation), Unary,
+    // Method, and string literals where a String is expected
+    add_function(&mut store, "DescribeAmount", json!({
+        "description": "Describe an amount for display",
+        "parameters": [
+            { "name": "amount", "type": "int" },
+            { "name": "label", "type": "optional<string>" }
+        ],
+        "returns": { "type": "string" },
+        "body": {
+            "kind": "Let",
+            "bindings": [{
+                "name": "name",
+                "type_annotation": "string",
+                "value": { "kind": "UnwrapOr", "expr": var("label"), "default": lit(json!("unnamed")) }
+            }],
+            "body": {
+                "kind": "If",
+                "cond": bin("&&",
+                    bin(">", var("amount"), lit(json!(100))),
+                    json!({ "kind": "Unary", "op": "!", "expr": { "kind": "Method", "expr": var("name"), "name": "is_empty" } })),
+                "then_branch": bin("++", var("name"), lit(json!(" (large)"))),
+                "else_branch": {
+                    "kind": "If",
+                    "cond": bin("==", bin("%", var("amount"), lit(json!(2))), lit(json!(0))),
+                    "then_branch": lit(json!("even")),
+                    "else_branch": { "kind": "Method", "expr": var("name"), "name": "to_uppercase" }
+                }
+            }
+        }
+    }));
+
+    // Block, Raise with and without a message, Try converting a foreign
+    // error, Return
+    add_function(&mut store, "ParseAmount", json!({
+        "parameters": [{ "name": "text", "type": "string" }],
+        "returns": { "type": "int" },
+        "body": {
+            "kind": "Block",
+            "exprs": [
+                {
+                    "kind": "If",
+                    "cond": { "kind": "Method", "expr": var("text"), "name": "is_empty" },
+                    "then_branch": { "kind": "Raise", "error": "EMPTY_AMOUNT" },
+                    "else_branch": { "kind": "Block", "exprs": [] }
+                },
+                {
+                    "kind": "Let",
+                    "bindings": [{
+                        "name": "value",
+                        "type_annotation": "int",
+                        "value": { "kind": "Try", "expr": { "kind": "Method", "expr": var("text"), "name": "parse" } }
+                    }],
+                    "body": {
+                        "kind": "If",
+                        "cond": bin("<", var("value"), lit(json!(0))),
+                        "then_branch": {
+                            "kind": "Raise",
+                            "error": "NEGATIVE_AMOUNT",
+                            "message": bin("++", lit(json!("got ")), var("text"))
+                        },
+                        "else_branch": { "kind": "Return", "value": bin("*", var("value"), lit(json!(100))) }
+                    }
+                }
+            ]
+        }
+    }));
+
+    // Call of another Function, and Try without any Raise
+    add_function(&mut store, "CheckedTotal", json!({
+        "parameters": [{ "name": "extra", "type": "int" }],
+        "returns": { "type": "int" },
+        "body": bin("+",
+            json!({ "kind": "Try", "expr": { "kind": "Call", "function": "ParseAmount", "args": [lit(json!("100"))] } }),
+            var("extra"))
+    }));
+
+    // Format, with and without args
+    add_function(&mut store, "RefundLabel", json!({
+        "parameters": [{ "name": "request", "type": "RefundRequest" }],
+        "returns": { "type": "string" },
+        "body": {
+            "kind": "If",
+            "cond": { "kind": "Method", "expr": { "kind": "Field", "expr": var("request"), "name": "reason" }, "name": "is_none" },
+            "then_branch": { "kind": "Format", "template": "{{unexplained}}" },
+            "else_branch": {
+                "kind": "Format",
+                "template": "refund of {} for order {}",
+                "args": [
+                    { "kind": "Field", "expr": var("request"), "name": "amount" },
+                    { "kind": "Field", "expr": var("request"), "name": "order_id" }
+                ]
+            }
+        }
+    }));
+
+    // For, a mutated binding, Index, Field, Array, Tuple, Closure, Struct,
+    // literals and operator precedence
+    add_function(&mut store, "SplitFees", json!({
+        "parameters": [
+            { "name": "amounts", "type": "array<float>" },
+            { "name": "request", "type": "RefundRequest" }
+        ],
+        "returns": { "type": "(Vec<f64>, RefundResponse, Vec<String>, serde_json::Value, serde_json::Value)" },
+        "body": {
+            "kind": "Let",
+            "bindings": [
+                { "name": "fees", "value": { "kind": "Array", "elements": [] } },
+                { "name": "rates", "value": lit(json!([1, 2.5])) },
+                { "name": "labels", "value": lit(json!(["fee", "refund"])) },
+                { "name": "mixed", "value": lit(json!([1, true, null, "flat"])) },
+                { "name": "meta", "value": lit(json!({ "source": "intent" })) }
+            ],
+            "body": {
+                "kind": "Block",
+                "exprs": [
+                    {
+                        "kind": "For",
+                        "var": "amount",
+                        "iterable": var("amounts"),
+                        "body": {
+                            "kind": "Method",
+                            "expr": var("fees"),
+                            "name": "push",
+                            "args": [bin("/", bin("*", bin("+", var("amount"), lit(json!(1.5))), json!({ "kind": "Index", "expr": var("rates"), "index": lit(json!(0)) })), bin("-", lit(json!(100.5)), lit(json!(0.5))))]
+                        }
+                    },
+                    {
+                        "kind": "Tuple",
+                        "elements": [
+                            {
+                                "kind": "Method",
+                                "expr": {
+                                    "kind": "Method",
+                                    "expr": { "kind": "Method", "expr": var("fees"), "name": "iter" },
+                                    "name": "map",
+                                    "args": [{ "kind": "Closure", "params": ["fee"], "body": { "kind": "Unary", "op": "-", "expr": bin("-", var("fee"), lit(json!(1.5))) } }]
+                                },
+                                "name": "collect"
+                            },
+                            {
+                                "kind": "Struct",
+                                "name": "RefundResponse",
+                                "fields": {
+                                    "refund_id": { "kind": "Field", "expr": var("request"), "name": "order_id" },
+                                    "status": lit(json!("pending"))
+                                }
+                            },
+                            var("labels"),
+                            var("mixed"),
+                            var("meta")
+                        ]
+                    }
+                ]
+            }
+        }
+    }));
+
+    // Match patterns and guards
+    add_function(&mut store, "ClassifyReference", json!({
+        "parameters": [
+            { "name": "reference", "type": "string" },
+            { "name": "parts", "type": "array<string>" }
+        ],
+        "returns": { "type": "Result<String, FunctionError>" },
+        "body": {
+            "kind": "Match",
+            "on": { "kind": "Method", "expr": var("reference"), "name": "as_str" },
+            "arms": [
+                { "pattern": { "kind": "Literal", "value": "" }, "body": { "kind": "Raise", "error": "EMPTY_REFERENCE" } },
+                { "pattern": { "kind": "StartsWith", "prefix": "rf_" }, "guard": bin(">", json!({ "kind": "Method", "expr": var("parts"), "name": "len" }), lit(json!(1))), "body": { "kind": "Call", "function": "Ok", "args": [lit(json!("refund"))] } },
+                { "pattern": { "kind": "EndsWith", "suffix": "_test" }, "body": { "kind": "Call", "function": "Ok", "args": [lit(json!("test"))] } },
+                { "pattern": { "kind": "Or", "patterns": [{ "kind": "Literal", "value": "a" }, { "kind": "Literal", "value": "b" }] }, "body": { "kind": "Call", "function": "Ok", "args": [lit(json!("short"))] } },
+                { "pattern": { "kind": "Variable", "name": "other" }, "body": {
+                    "kind": "Match",
+                    "on": { "kind": "Tuple", "elements": [{ "kind": "Method", "expr": var("parts"), "name": "first" }, { "kind": "Method", "expr": var("parts"), "name": "as_slice" }] },
+                    "arms": [
+                        { "pattern": { "kind": "Tuple", "elements": [{ "kind": "None" }, { "kind": "Wildcard" }] }, "body": { "kind": "Call", "function": "Ok", "args": [{ "kind": "Method", "expr": var("other"), "name": "to_string" }] } },
+                        { "pattern": { "kind": "Tuple", "elements": [{ "kind": "Some", "binding": "first" }, { "kind": "Array", "elements": [{ "kind": "Wildcard" }], "rest": "_rest" }] }, "body": { "kind": "Call", "function": "Ok", "args": [{ "kind": "Method", "expr": var("first"), "name": "clone" }] } },
+                        { "pattern": { "kind": "Wildcard" }, "body": { "kind": "Call", "function": "Ok", "args": [lit(json!("unknown"))] } }
+                    ]
+                } }
+            ]
+        }
+    }));
+
+    // Variant, Struct, Ok and Err patterns
+    store
+        .add(IntentDocument::with_spec(
+            IntentKind::Enum,
+            "RefundHold".to_string(),
+            json!({
+                "variants": [
+                    { "name": "Rejected", "data": ["string"] },
+                    { "name": "Pending" }
+                ]
+            }),
+        ))
+        .unwrap();
+    add_function(&mut store, "StatusCode", json!({
+        "parameters": [{ "name": "outcome", "type": "Result<RefundResponse, RefundHold>" }],
+        "returns": { "type": "int" },
+        "body": {
+            "kind": "Match",
+            "on": var("outcome"),
+            "arms": [
+                { "pattern": { "kind": "Ok", "binding": "response" }, "body": {
+                    "kind": "Match",
+                    "on": var("response"),
+                    "arms": [
+                        { "pattern": { "kind": "Struct", "name": "RefundResponse", "fields": { "status": { "kind": "Variable", "name": "status" } } }, "guard": bin("==", var("status"), lit(json!("failed"))), "body": lit(json!(500)) },
+                        { "pattern": { "kind": "Wildcard" }, "body": lit(json!(200)) }
+                    ]
+                } },
+                { "pattern": { "kind": "Err", "binding": "hold" }, "body": {
+                    "kind": "Match",
+                    "on": var("hold"),
+                    "arms": [
+                        { "pattern": { "kind": "Variant", "enum_name": "RefundHold", "variant": "Rejected", "bindings": ["_reason"] }, "body": lit(json!(400)) },
+                        { "pattern": { "kind": "Variant", "enum_name": "RefundHold", "variant": "Pending" }, "body": lit(json!(202)) }
+                    ]
+                } }
+            ]
+        }
+    }));
+
+    let output = generate_functions(&store);
+    assert_eq!(output.intent_ids.len(), 7);
+    insta::assert_snapshot!("functions_rs", output.functions_rs);
+    assert_generated_crate_compiles(&store, &IntentConfig::default());
+}
+
+#[test]
+fn test_try_only_function_compiles() {
+    let mut store = load_fixtures();
+    add_function(&mut store, "ParseCount", serde_json::json!({
+        "parameters": [{ "name": "text", "type": "string" }],
+        "returns": { "type": "int" },
+        "body": { "kind": "Try", "expr": { "kind": "Method", "expr": { "kind": "Variable", "name": "text" }, "name": "parse" } }
+    }));
+
+    let output = generate_functions(&store);
+    assert!(output.functions_rs.contains("pub struct FunctionError"));
+    assert!(output.functions_rs.contains("-> Result<i64, FunctionError>"));
+    assert_generated_crate_compiles(&store, &IntentConfig::default());
+}